@@ -0,0 +1,78 @@
+//! Benchmarks of the inference of within-line edits, for blocks of changed lines that are
+//! expensive to align, such as minified code. Run with `cargo bench`.
+
+use std::time::{Duration, Instant};
+
+use git_delta::ConfigBuilder;
+
+const ITERATIONS: u32 = 5;
+
+fn main() {
+    let config = ConfigBuilder::new()
+        .option("width", "200")
+        .option("syntax-theme", "none")
+        // Lines are otherwise truncated before their edits are inferred.
+        .option("max-line-length", "0")
+        .build()
+        .unwrap();
+    let minified_line = |seed: usize, n: usize| {
+        (0..n)
+            .map(|i| format!("a{}=b[{}]+{};", i, (i * seed) % 97, i % 13))
+            .collect::<String>()
+    };
+    let cases = vec![
+        (
+            "short lines",
+            make_diff(
+                &(0..100)
+                    .map(|i| format!("let x{} = {};", i, i))
+                    .collect::<Vec<_>>(),
+                &(0..100)
+                    .map(|i| format!("let x{} = {};", i, i + 1))
+                    .collect::<Vec<_>>(),
+            ),
+        ),
+        (
+            "one long minified line",
+            make_diff(&[minified_line(3, 2000)], &[minified_line(5, 2000)]),
+        ),
+        (
+            "block of long minified lines",
+            make_diff(
+                &(0..20).map(|i| minified_line(i, 500)).collect::<Vec<_>>(),
+                &(0..20)
+                    .map(|i| minified_line(i + 50, 500))
+                    .collect::<Vec<_>>(),
+            ),
+        ),
+        (
+            "long lines of different lengths",
+            make_diff(&[minified_line(3, 3000)], &[minified_line(3, 300)]),
+        ),
+    ];
+    for (name, diff) in cases {
+        let mut elapsed = Duration::default();
+        for _ in 0..ITERATIONS {
+            let start = Instant::now();
+            git_delta::render_to_string(diff.as_bytes(), &config).unwrap();
+            elapsed += start.elapsed();
+        }
+        println!("{:<40} {:>12.2?}", name, elapsed / ITERATIONS);
+    }
+}
+
+fn make_diff(minus_lines: &[String], plus_lines: &[String]) -> String {
+    let mut diff = format!(
+        "diff --git a/a.js b/a.js\nindex 1111111..2222222 100644\n--- a/a.js\n+++ b/a.js\n\
+         @@ -1,{} +1,{} @@\n",
+        minus_lines.len(),
+        plus_lines.len()
+    );
+    for line in minus_lines {
+        diff.push_str(&format!("-{}\n", line));
+    }
+    for line in plus_lines {
+        diff.push_str(&format!("+{}\n", line));
+    }
+    diff
+}
@@ -1,4 +1,4 @@
-use std::cmp::max;
+use std::cmp::{max, min};
 use std::collections::VecDeque;
 
 const SUBSTITUTION_COST: usize = 1;
@@ -24,29 +24,58 @@ struct Cell {
     cost: usize,
 }
 
+/// Alignments of token sequences whose full table would have more than this many cells are
+/// computed in a band around the diagonal of the table (see Alignment::new).
+pub const MAX_FULL_TABLE_SIZE: usize = 1_000_000;
+
+/// The number of cells on either side of the line from the first to the last cell of the table
+/// that are computed in each row of a banded alignment (fewer when the sequence written down the
+/// side of the table is the longer one; see get_band_columns_of_row).
+const BAND_HALF_WIDTH: usize = 100;
+
 pub struct Alignment<'a> {
     pub x: Vec<&'a str>,
     pub y: Vec<&'a str>,
     table: Vec<Cell>,
     dim: [usize; 2],
+    band: Option<Band>,
+}
+
+/// The cells computed in a banded alignment. Row j of the table stores only the cells of the
+/// columns columns[j] (inclusive), starting at index offsets[j] of the table.
+struct Band {
+    columns: Vec<(usize, usize)>,
+    offsets: Vec<usize>,
 }
 
 impl<'a> Alignment<'a> {
-    /// Fill table for Levenshtein distance / alignment computation
+    /// Fill table for Levenshtein distance / alignment computation. If the table would be larger
+    /// than MAX_FULL_TABLE_SIZE, only the cells in a band around the line from its first to its
+    /// last cell are computed. For sequences of lengths n and m the band has
+    /// O(n + m + BAND_HALF_WIDTH * min(n, m)) cells, however unequal the lengths. The resulting
+    /// alignment is optimal among those that stay within the band, which is the case for
+    /// sequences that are similar.
     pub fn new(x: Vec<&'a str>, y: Vec<&'a str>) -> Self {
         // TODO: Something about the alignment algorithm requires that the first two items in the
         // token stream are ["", " "]. In practice this means that the line must have a leading
         // space, and that the tokenization regex cooperates.
         let dim = [y.len() + 1, x.len() + 1];
+        let band = get_band(x.len(), y.len());
         let table = vec![
             Cell {
                 parent: 0,
                 operation: NoOp,
                 cost: 0
             };
-            dim[0] * dim[1]
+            get_table_size(x.len(), y.len())
         ];
-        let mut alignment = Self { x, y, table, dim };
+        let mut alignment = Self {
+            x,
+            y,
+            table,
+            dim,
+            band,
+        };
         alignment.fill();
         alignment
     }
@@ -57,46 +86,58 @@ impl<'a> Alignment<'a> {
         // table. Also, we insert a 0 in cell (0, 0) of the table, so x and y are shifted by one
         // position. Therefore, the element corresponding to (x[i], y[j]) is in column (i + 1) and
         // row (j + 1); the index of this element is given by index(i, j).
+        let origin = self.index(0, 0);
         for i in 1..self.dim[1] {
-            self.table[i] = Cell {
-                parent: 0,
-                operation: Deletion,
-                cost: i,
-            };
+            if self.is_in_band(i, 0) {
+                let index = self.index(i, 0);
+                self.table[index] = Cell {
+                    parent: origin,
+                    operation: Deletion,
+                    cost: i,
+                };
+            }
         }
         for j in 1..self.dim[0] {
-            self.table[j * self.dim[1]] = Cell {
-                parent: 0,
-                operation: Insertion,
-                cost: j,
-            };
+            if self.is_in_band(0, j) {
+                let index = self.index(0, j);
+                self.table[index] = Cell {
+                    parent: origin,
+                    operation: Insertion,
+                    cost: j,
+                };
+            }
         }
 
-        for (i, x_i) in self.x.iter().enumerate() {
-            for (j, y_j) in self.y.iter().enumerate() {
-                let (left, diag, up) =
-                    (self.index(i, j + 1), self.index(i, j), self.index(i + 1, j));
+        for j in 0..self.y.len() {
+            for i in self.get_band_columns(j + 1) {
+                let (x_i, y_j) = (self.x[i], self.y[j]);
+                let (left, diag, up) = ((i, j + 1), (i, j), (i + 1, j));
+                // A neighbor outside the band is not a candidate.
+                let candidate = |(i, j), operation, cost| {
+                    if self.is_in_band(i, j) {
+                        let parent = self.index(i, j);
+                        Some(Cell {
+                            parent,
+                            operation,
+                            cost: self.table[parent].cost + cost,
+                        })
+                    } else {
+                        None
+                    }
+                };
                 let candidates = [
-                    Cell {
-                        parent: left,
-                        operation: Deletion,
-                        cost: self.table[left].cost + DELETION_COST,
-                    },
-                    Cell {
-                        parent: diag,
-                        operation: if x_i == y_j { NoOp } else { Substitution },
-                        cost: self.table[diag].cost
-                            + if x_i == y_j { 0 } else { SUBSTITUTION_COST },
-                    },
-                    Cell {
-                        parent: up,
-                        operation: Insertion,
-                        cost: self.table[up].cost + INSERTION_COST,
-                    },
+                    candidate(left, Deletion, DELETION_COST),
+                    candidate(
+                        diag,
+                        if x_i == y_j { NoOp } else { Substitution },
+                        if x_i == y_j { 0 } else { SUBSTITUTION_COST },
+                    ),
+                    candidate(up, Insertion, INSERTION_COST),
                 ];
                 let index = self.index(i + 1, j + 1);
                 self.table[index] = candidates
                     .iter()
+                    .flatten()
                     .min_by_key(|cell| cell.cost)
                     .unwrap()
                     .clone();
@@ -108,9 +149,10 @@ impl<'a> Alignment<'a> {
     pub fn operations(&self) -> Vec<Operation> {
         let mut ops = VecDeque::with_capacity(max(self.x.len(), self.y.len()));
         let mut cell = &self.table[self.index(self.x.len(), self.y.len())];
+        let origin = self.index(0, 0);
         loop {
             ops.push_front(cell.operation);
-            if cell.parent == 0 {
+            if cell.parent == origin {
                 break;
             }
             cell = &self.table[cell.parent];
@@ -151,9 +193,30 @@ impl<'a> Alignment<'a> {
         self.table[self.index(self.x.len(), self.y.len())].cost
     }
 
-    // Row-major storage of 2D array.
+    // Row-major storage of 2D array. In a banded alignment, each row stores the cells of the band.
     fn index(&self, i: usize, j: usize) -> usize {
-        j * self.dim[1] + i
+        match &self.band {
+            None => j * self.dim[1] + i,
+            Some(band) => band.offsets[j] + i - band.columns[j].0,
+        }
+    }
+
+    fn is_in_band(&self, i: usize, j: usize) -> bool {
+        match &self.band {
+            None => true,
+            Some(band) => (band.columns[j].0..=band.columns[j].1).contains(&i),
+        }
+    }
+
+    /// The range of i such that (i + 1, j) is in the band, for 0 <= i < x.len().
+    fn get_band_columns(&self, j: usize) -> std::ops::Range<usize> {
+        match &self.band {
+            None => 0..self.x.len(),
+            Some(band) => {
+                let (first, last) = band.columns[j];
+                (max(first, 1) - 1)..last
+            }
+        }
     }
 
     #[allow(dead_code)]
@@ -184,8 +247,12 @@ impl<'a> Alignment<'a> {
                 if j == 0 {
                     print!("{}     ", if i > 0 { self.y[i - 1] } else { " " })
                 }
-                let cell = &self.table[self.index(j, i)];
-                print!("{}   ", self.format_cell(cell));
+                if self.is_in_band(j, i) {
+                    let cell = &self.table[self.index(j, i)];
+                    print!("{}   ", self.format_cell(cell));
+                } else {
+                    print!("        ");
+                }
             }
             println!();
         }
@@ -193,6 +260,56 @@ impl<'a> Alignment<'a> {
     }
 }
 
+/// Whether an alignment of sequences of lengths n and m is computed in a band of its table.
+fn is_banded(n: usize, m: usize) -> bool {
+    (n + 1) * (m + 1) > MAX_FULL_TABLE_SIZE
+}
+
+/// The band of a banded alignment of sequences of lengths n and m, if one is needed.
+fn get_band(n: usize, m: usize) -> Option<Band> {
+    if !is_banded(n, m) {
+        return None;
+    }
+    let columns: Vec<_> = (0..=m).map(|j| get_band_columns_of_row(n, m, j)).collect();
+    let offsets = columns
+        .iter()
+        .scan(0, |offset, (first, last)| {
+            let row_offset = *offset;
+            *offset += last - first + 1;
+            Some(row_offset)
+        })
+        .collect();
+    Some(Band { columns, offsets })
+}
+
+/// The first and last columns of row j of the band of an alignment of sequences of lengths n and
+/// m: those of the line from (0, 0) to (n, m) in rows j and j + 1, widened on either side by
+/// BAND_HALF_WIDTH, scaled down by n / m when m is the greater length. Consecutive rows therefore
+/// overlap, so that the band contains a path from the first to the last cell of the table, and
+/// each row has at most n / m + 3 + 2 * max(1, BAND_HALF_WIDTH * min(n, m) / m) cells.
+fn get_band_columns_of_row(n: usize, m: usize, j: usize) -> (usize, usize) {
+    if m == 0 {
+        return (0, n);
+    }
+    let half_width = max(1, BAND_HALF_WIDTH * n / max(n, m));
+    let first = (j * n / m).saturating_sub(half_width);
+    let last = min(n, ((j + 1) * n).div_ceil(m) + half_width);
+    (first, last)
+}
+
+/// The number of cells in the table of an alignment of sequences of lengths n and m.
+pub fn get_table_size(n: usize, m: usize) -> usize {
+    if !is_banded(n, m) {
+        return (n + 1) * (m + 1);
+    }
+    (0..=m)
+        .map(|j| {
+            let (first, last) = get_band_columns_of_row(n, m, j);
+            last - first + 1
+        })
+        .sum()
+}
+
 fn run_length_encode<T>(sequence: Vec<T>) -> Vec<(T, usize)>
 where
     T: Copy,
@@ -292,6 +409,71 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_banded_alignment() {
+        let x = "abcdefghij".repeat(150);
+        // Three edits: a substitution, an insertion, and a deletion.
+        let y = format!(
+            "{}X{}Y{}{}",
+            &x[..200],
+            &x[201..700],
+            &x[700..1200],
+            &x[1201..]
+        );
+        let (x, y) = (
+            x.graphemes(true).collect::<Vec<&str>>(),
+            y.graphemes(true).collect::<Vec<&str>>(),
+        );
+        assert!(get_band(x.len(), y.len()).is_some());
+        assert!(get_table_size(x.len(), y.len()) < (x.len() + 1) * (y.len() + 1) / 5);
+        let alignment = Alignment::new(x, y);
+        assert_eq!(alignment.levenshtein_distance(), 3);
+        assert_eq!(alignment.distance_parts(), (3, 1501));
+        assert_eq!(
+            alignment.coalesced_operations(),
+            vec![
+                (NoOp, 200),
+                (Substitution, 1),
+                (NoOp, 499),
+                (Insertion, 1),
+                (NoOp, 500),
+                (Deletion, 1),
+                (NoOp, 299)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_banded_alignment_of_sequences_of_different_lengths() {
+        let x = "abcdefghij".repeat(200);
+        let y = "abcdefghij".repeat(60);
+        let (x, y) = (
+            x.graphemes(true).collect::<Vec<&str>>(),
+            y.graphemes(true).collect::<Vec<&str>>(),
+        );
+        assert!(get_band(x.len(), y.len()).is_some());
+        assert_eq!(Alignment::new(x, y).levenshtein_distance(), 1400);
+    }
+
+    #[test]
+    fn test_banded_alignment_of_sequences_of_very_different_lengths() {
+        let short = "abcdefghij".to_string();
+        let long = "abcdefghij".repeat(10_000);
+        for (x, y) in [(&short, &long), (&long, &short)] {
+            let (x, y) = (
+                x.graphemes(true).collect::<Vec<&str>>(),
+                y.graphemes(true).collect::<Vec<&str>>(),
+            );
+            assert!(get_band(x.len(), y.len()).is_some());
+            // Linear in the lengths of the sequences, rather than in their product.
+            let table_size = get_table_size(x.len(), y.len());
+            assert!(table_size < 10 * (x.len() + y.len()));
+            let alignment = Alignment::new(x, y);
+            assert_eq!(alignment.table.len(), table_size);
+            assert_eq!(alignment.levenshtein_distance(), 99_990);
+        }
+    }
+
     fn assert_string_distance_parts(s1: &str, s2: &str, parts: (usize, usize)) {
         let (numer, _) = parts;
         assert_string_levenshtein_distance(s1, s2, numer);
@@ -0,0 +1,653 @@
+use std::ops::Range;
+
+use syntect::highlighting::Style as SyntectStyle;
+use syntect::parsing::{SyntaxReference, SyntaxSet};
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::ansi;
+use crate::config::{self, delta_unreachable};
+use crate::delta::State;
+use crate::edits;
+use crate::enclosing_scope::EnclosingScopes;
+use crate::model::{ChangedLines, EditOperation, HunkHeader, Line, LineFormat};
+use crate::parse;
+use crate::syntax_highlighter::{FileSyntaxState, HighlightLines};
+
+/// Annotates the lines of a diff for rendering: syntax highlighting, and the edit operations
+/// relating removed and added lines. Removed and added lines are buffered until the end of the run
+/// of changed lines (or until config.line_buffer_size is exceeded), since edit inference considers
+/// them jointly.
+pub struct Annotator<'a> {
+    pub minus_lines: Vec<(String, State)>,
+    pub plus_lines: Vec<(String, State)>,
+    // The formats of the buffered minus and plus lines (see model::LineFormat).
+    pub minus_line_formats: Vec<LineFormat>,
+    pub plus_line_formats: Vec<LineFormat>,
+    // An unchanged line is held until the next line is seen, since that may be a "\ No newline at
+    // end of file" line that applies to it.
+    pub zero_line: Option<Line>,
+    pub syntax: &'a SyntaxReference,
+    pub highlighter: HighlightLines<'a>,
+    // Under --full-file-syntax-highlighting, minus lines are highlighted using a separate
+    // highlighter, since the pre-image and post-image of a file are parsed independently.
+    pub minus_highlighter: Option<HighlightLines<'a>>,
+    pub minus_file_syntax_state: Option<FileSyntaxState<'a>>,
+    pub plus_file_syntax_state: Option<FileSyntaxState<'a>>,
+    // Under --hunk-header-enclosing-scope, the definitions in the pre-image of the file.
+    pub minus_file_enclosing_scopes: Option<EnclosingScopes>,
+    pub config: &'a config::Config,
+}
+
+impl<'a> Annotator<'a> {
+    pub fn new(config: &'a config::Config) -> Self {
+        let default_syntax = Self::get_syntax(&config.syntax_set, None);
+        // TODO: Avoid doing this.
+        let dummy_highlighter = HighlightLines::new(default_syntax, &config.syntax_dummy_theme);
+        Self {
+            minus_lines: Vec::new(),
+            plus_lines: Vec::new(),
+            minus_line_formats: Vec::new(),
+            plus_line_formats: Vec::new(),
+            zero_line: None,
+            syntax: default_syntax,
+            highlighter: dummy_highlighter,
+            minus_highlighter: None,
+            minus_file_syntax_state: None,
+            plus_file_syntax_state: None,
+            minus_file_enclosing_scopes: None,
+            config,
+        }
+    }
+
+    pub fn set_syntax(&mut self, extension: Option<&str>) {
+        self.syntax = Self::get_syntax(&self.config.syntax_set, extension);
+    }
+
+    pub fn get_syntax(syntax_set: &'a SyntaxSet, extension: Option<&str>) -> &'a SyntaxReference {
+        if let Some(extension) = extension {
+            if let Some(syntax) = syntax_set.find_syntax_by_extension(extension) {
+                return syntax;
+            }
+        }
+        syntax_set
+            .find_syntax_by_extension("txt")
+            .unwrap_or_else(|| delta_unreachable("Failed to find any language syntax definitions."))
+    }
+
+    pub fn set_highlighter(&mut self) {
+        if let Some(ref syntax_theme) = self.config.syntax_theme {
+            self.highlighter = HighlightLines::new_with_cache(
+                self.syntax,
+                syntax_theme,
+                self.config.highlight_cache.as_ref(),
+            )
+        };
+        self.minus_highlighter = None;
+    }
+
+    /// Store the full contents of the pre-image and post-image of the current file, if available,
+    /// for use by subsequent calls to set_highlighters_from_file_syntax_states and
+    /// get_enclosing_scope.
+    pub fn set_file_syntax_states(
+        &mut self,
+        minus_file_contents: Option<&str>,
+        plus_file_contents: Option<&str>,
+    ) {
+        let (syntax, config) = (self.syntax, self.config);
+        let make_file_syntax_state = |contents: Option<&str>| match (contents, &config.syntax_theme)
+        {
+            (Some(contents), Some(syntax_theme)) if config.full_file_syntax_highlighting => {
+                Some(FileSyntaxState::new(contents, syntax, syntax_theme))
+            }
+            _ => None,
+        };
+        self.minus_file_syntax_state = make_file_syntax_state(minus_file_contents);
+        self.plus_file_syntax_state = make_file_syntax_state(plus_file_contents);
+        self.minus_file_enclosing_scopes = match minus_file_contents {
+            Some(contents) if config.hunk_header_enclosing_scope => {
+                Some(EnclosingScopes::new(contents, syntax))
+            }
+            _ => None,
+        };
+    }
+
+    /// Return a description of the definitions in the pre-image of the file that enclose the hunk
+    /// starting at `minus_line_number`, if the file is available and there are any.
+    pub fn get_enclosing_scope(&mut self, minus_line_number: usize) -> Option<String> {
+        let syntax_set = &self.config.syntax_set;
+        self.minus_file_enclosing_scopes
+            .as_mut()
+            .and_then(|enclosing_scopes| {
+                enclosing_scopes.get_breadcrumb(minus_line_number, syntax_set)
+            })
+    }
+
+    /// Set the highlighters for a hunk starting at the given minus and plus line numbers to the
+    /// state reached by parsing the preceding lines of the full files. Where the full file is not
+    /// available, the highlighters set by set_highlighter are left in place.
+    pub fn set_highlighters_from_file_syntax_states(&mut self, line_numbers: &[(usize, usize)]) {
+        // Merge commits (more than two sets of line numbers) are not handled.
+        if let [(minus_line_number, _), (plus_line_number, _)] = *line_numbers {
+            if let Some(ref mut file_syntax_state) = self.plus_file_syntax_state {
+                self.highlighter = file_syntax_state
+                    .get_highlighter_at_line(plus_line_number, &self.config.syntax_set);
+            }
+            if let Some(ref mut file_syntax_state) = self.minus_file_syntax_state {
+                self.minus_highlighter = Some(
+                    file_syntax_state
+                        .get_highlighter_at_line(minus_line_number, &self.config.syntax_set),
+                );
+            }
+        }
+    }
+
+    /// Replace initial -/+ character with ' ', expand tabs as spaces, and optionally terminate with
+    /// newline.
+    // Terminating with newline character is necessary for many of the sublime syntax definitions to
+    // highlight correctly.
+    // See https://docs.rs/syntect/3.2.0/syntect/parsing/struct.SyntaxSetBuilder.html#method.add_from_folder
+    pub fn prepare(&self, line: &str, append_newline: bool) -> String {
+        let terminator = if append_newline { "\n" } else { "" };
+        if !line.is_empty() {
+            let mut line = line.graphemes(true);
+
+            // The first column contains a -/+/space character, added by git. We substitute it for
+            // a space now, so that it is not present during syntax highlighting. When emitting the
+            // line in Painter::paint_line, we drop the space (unless --keep-plus-minus-markers is
+            // in effect in which case we replace it with the appropriate marker).
+            // TODO: Things should, but do not, work if this leading space is omitted at this stage.
+            // See comment in align::Alignment::new.
+            line.next();
+            format!(" {}{}", self.expand_tabs(line), terminator)
+        } else {
+            terminator.to_string()
+        }
+    }
+
+    /// Prepare a removed or added line, as prepare does. Under --show-whitespace-changes, each tab
+    /// is expanded as a tab followed by spaces, so that a change between a tab and spaces is
+    /// inferred as an edit; the tabs are replaced when the line is annotated.
+    pub fn prepare_changed_line(&self, line: &str) -> String {
+        if !self.config.show_whitespace_changes || self.config.tab_width == 0 || line.is_empty() {
+            return self.prepare(line, true);
+        }
+        let tab_replacement = format!("\t{}", " ".repeat(self.config.tab_width - 1));
+        let mut line = line.graphemes(true);
+        line.next();
+        let line: String = line
+            .map(|s| if s == "\t" { &tab_replacement } else { s })
+            .collect();
+        format!(" {}\n", line)
+    }
+
+    /// Remove the initial +/- character of a line that will be emitted unchanged, including any
+    /// ANSI escape sequences.
+    pub fn prepare_raw_line(&self, line: &str) -> String {
+        ansi::ansi_preserving_slice(
+            &self.expand_tabs(line.graphemes(true)),
+            if self.config.keep_plus_minus_markers {
+                0
+            } else {
+                1
+            },
+        )
+    }
+
+    /// Expand tabs as spaces.
+    /// tab_width = 0 is documented to mean do not replace tabs.
+    pub fn expand_tabs<'b, I>(&self, line: I) -> String
+    where
+        I: Iterator<Item = &'b str>,
+    {
+        if self.config.tab_width > 0 {
+            let tab_replacement = " ".repeat(self.config.tab_width);
+            line.map(|s| if s == "\t" { &tab_replacement } else { s })
+                .collect::<String>()
+        } else {
+            line.collect::<String>()
+        }
+    }
+
+    /// Annotate the buffered minus and plus lines, and clear the buffers. Return None if there are
+    /// no buffered lines.
+    pub fn annotate_buffered_minus_and_plus_lines(&mut self) -> Option<ChangedLines> {
+        if self.minus_lines.is_empty() && self.plus_lines.is_empty() {
+            return None;
+        }
+        let minus_lines = std::mem::take(&mut self.minus_lines);
+        let plus_lines = std::mem::take(&mut self.plus_lines);
+        let minus_line_formats = std::mem::take(&mut self.minus_line_formats);
+        let plus_line_formats = std::mem::take(&mut self.plus_line_formats);
+        let minus_line_syntax_style_sections = Self::get_syntax_style_sections_for_lines(
+            &minus_lines,
+            &State::HunkMinus(None),
+            self.minus_highlighter
+                .as_mut()
+                .unwrap_or(&mut self.highlighter),
+            self.config,
+        );
+        let plus_line_syntax_style_sections = Self::get_syntax_style_sections_for_lines(
+            &plus_lines,
+            &State::HunkPlus(None),
+            &mut self.highlighter,
+            self.config,
+        );
+        let (minus_line_edit_sections, plus_line_edit_sections, alignment) = edits::infer_edits(
+            minus_lines.iter().map(|(line, _)| line.as_str()).collect(),
+            plus_lines.iter().map(|(line, _)| line.as_str()).collect(),
+            vec![EditOperation::MinusNoop; minus_lines.len()],
+            EditOperation::Deletion,
+            vec![EditOperation::PlusNoop; plus_lines.len()],
+            EditOperation::Insertion,
+            &self.config.tokenization_regex,
+            self.config.max_line_distance,
+            self.config.max_line_distance_for_naively_paired_lines,
+            self.config.max_token_distance,
+            edits::MAX_ALIGNMENT_CELLS_PER_BLOCK,
+        );
+        let mut minus_line_whitespace_changes = vec![false; minus_lines.len()];
+        let mut plus_line_whitespace_changes = vec![false; plus_lines.len()];
+        for (minus_index, plus_index) in &alignment {
+            if let (Some(minus_index), Some(plus_index)) = (*minus_index, *plus_index) {
+                if is_whitespace_change(&minus_lines[minus_index].0, &plus_lines[plus_index].0) {
+                    minus_line_whitespace_changes[minus_index] = true;
+                    plus_line_whitespace_changes[plus_index] = true;
+                }
+            }
+        }
+        let config = self.config;
+        let make_lines = |lines: &[(String, State)],
+                          syntax_sections: Vec<Vec<(SyntectStyle, &str)>>,
+                          edit_sections: Vec<Vec<(EditOperation, &str)>>,
+                          whitespace_changes: Vec<bool>,
+                          formats: Vec<LineFormat>,
+                          noop: EditOperation| {
+            lines
+                .iter()
+                .zip(syntax_sections)
+                .zip(edit_sections)
+                .zip(whitespace_changes)
+                .zip(formats)
+                .map(
+                    |(
+                        (
+                            (((text, state), syntax_sections), mut edit_sections),
+                            is_whitespace_change,
+                        ),
+                        format,
+                    )| {
+                        let whitespace_edit_ranges = if config.show_whitespace_changes {
+                            get_whitespace_edit_ranges(&edit_sections)
+                        } else {
+                            Vec::new()
+                        };
+                        if config.whitespace_insensitive_emph {
+                            for section in edit_sections.iter_mut() {
+                                if is_whitespace_edit(section) {
+                                    section.0 = noop;
+                                }
+                            }
+                        }
+                        if config.show_whitespace_changes {
+                            let expanded_tabs = config.tab_width > 0;
+                            Line {
+                                state: state.clone(),
+                                text: show_whitespace_changes(
+                                    text,
+                                    0,
+                                    &whitespace_edit_ranges,
+                                    expanded_tabs,
+                                ),
+                                syntax_sections: show_whitespace_changes_in_sections(
+                                    syntax_sections,
+                                    &whitespace_edit_ranges,
+                                    expanded_tabs,
+                                ),
+                                edit_sections: show_whitespace_changes_in_sections(
+                                    edit_sections,
+                                    &whitespace_edit_ranges,
+                                    expanded_tabs,
+                                ),
+                                is_whitespace_change,
+                                format,
+                            }
+                        } else {
+                            Line {
+                                state: state.clone(),
+                                text: text.clone(),
+                                syntax_sections: to_owned_sections(syntax_sections),
+                                edit_sections: to_owned_sections(edit_sections),
+                                is_whitespace_change,
+                                format,
+                            }
+                        }
+                    },
+                )
+                .collect()
+        };
+        Some(ChangedLines {
+            minus_lines: make_lines(
+                &minus_lines,
+                minus_line_syntax_style_sections,
+                minus_line_edit_sections,
+                minus_line_whitespace_changes,
+                minus_line_formats,
+                EditOperation::MinusNoop,
+            ),
+            plus_lines: make_lines(
+                &plus_lines,
+                plus_line_syntax_style_sections,
+                plus_line_edit_sections,
+                plus_line_whitespace_changes,
+                plus_line_formats,
+                EditOperation::PlusNoop,
+            ),
+            alignment,
+        })
+    }
+
+    pub fn annotate_zero_line(&mut self, line: &str, format: LineFormat) -> Line {
+        let state = State::HunkZero;
+        let lines = vec![(self.prepare(line, true), state.clone())];
+        let syntax_style_sections = Self::get_syntax_style_sections_for_lines(
+            &lines,
+            &state,
+            &mut self.highlighter,
+            self.config,
+        );
+        if let Some(ref mut minus_highlighter) = self.minus_highlighter {
+            // Unchanged lines are highlighted using the post-image highlighter; keep the
+            // pre-image highlighter in step.
+            minus_highlighter.highlight(&lines[0].0[1..], &self.config.syntax_set);
+        }
+        let syntax_sections = to_owned_sections(syntax_style_sections.into_iter().next().unwrap());
+        let (text, state) = lines.into_iter().next().unwrap();
+        Line {
+            state,
+            text,
+            syntax_sections,
+            edit_sections: Vec::new(),
+            is_whitespace_change: false,
+            format,
+        }
+    }
+
+    /// Parse the hunk header `line`, and prepare and highlight the code fragment to be displayed
+    /// in it.
+    pub fn annotate_hunk_header(
+        &mut self,
+        line: &str,
+        raw_line: &str,
+        plus_file: &str,
+    ) -> HunkHeader {
+        let config = self.config;
+        let (raw_code_fragment, line_numbers) = parse::parse_hunk_header(line);
+        let code_fragment =
+            if config.hunk_header_style.is_raw || config.hunk_header_style.is_omitted {
+                String::new()
+            } else if config.color_only {
+                // In color_only mode, raw_line's structure shouldn't be changed, so the whole line is
+                // used.
+                format!(" {}", &line)
+            } else {
+                let raw_code_fragment = match line_numbers.first() {
+                    Some(&(minus_line_number, minus_len)) if config.hunk_header_enclosing_scope => {
+                        // An empty hunk starts after the given line.
+                        let minus_line_number = minus_line_number + (minus_len == 0) as usize;
+                        self.get_enclosing_scope(minus_line_number)
+                            .map(|enclosing_scope| format!(" {}", enclosing_scope))
+                            .unwrap_or(raw_code_fragment)
+                    }
+                    _ => raw_code_fragment,
+                };
+                match self.prepare(&raw_code_fragment, false) {
+                    s if !s.is_empty() => format!("{} ", s),
+                    s => s,
+                }
+            };
+        let syntax_sections = if code_fragment.is_empty() {
+            Vec::new()
+        } else {
+            let lines = vec![(code_fragment.clone(), State::HunkHeader)];
+            let syntax_style_sections = Self::get_syntax_style_sections_for_lines(
+                &lines,
+                &State::HunkHeader,
+                &mut self.highlighter,
+                config,
+            );
+            to_owned_sections(syntax_style_sections.into_iter().next().unwrap())
+        };
+        HunkHeader {
+            line: line.to_string(),
+            raw_line: raw_line.to_string(),
+            line_numbers,
+            plus_file: plus_file.to_string(),
+            code_fragment: Line {
+                state: State::HunkHeader,
+                text: code_fragment,
+                syntax_sections,
+                edit_sections: Vec::new(),
+                is_whitespace_change: false,
+                format: LineFormat::default(),
+            },
+        }
+    }
+
+    pub fn should_compute_syntax_highlighting(state: &State, config: &config::Config) -> bool {
+        if config.syntax_theme.is_none() {
+            return false;
+        }
+        match state {
+            State::HunkMinus(None) => {
+                config.minus_style.is_syntax_highlighted
+                    || config.minus_emph_style.is_syntax_highlighted
+            }
+            State::HunkZero => config.zero_style.is_syntax_highlighted,
+            State::HunkPlus(None) => {
+                config.plus_style.is_syntax_highlighted
+                    || config.plus_emph_style.is_syntax_highlighted
+            }
+            State::HunkHeader => true,
+            State::HunkMinus(Some(_)) | State::HunkPlus(Some(_)) => false,
+            _ => panic!(
+                "should_compute_syntax_highlighting is undefined for state {:?}",
+                state
+            ),
+        }
+    }
+
+    pub fn get_syntax_style_sections_for_lines<'s>(
+        lines: &'s [(String, State)],
+        state: &State,
+        highlighter: &mut HighlightLines,
+        config: &config::Config,
+    ) -> Vec<Vec<(SyntectStyle, &'s str)>> {
+        let fake = !Self::should_compute_syntax_highlighting(state, config);
+        let mut line_sections = Vec::new();
+        for (line, _) in lines.iter() {
+            if fake {
+                if config.full_file_syntax_highlighting && config.syntax_theme.is_some() {
+                    // The highlighter state must track every line of the file.
+                    highlighter.highlight(&line[1..], &config.syntax_set);
+                }
+                line_sections.push(vec![(config.null_syntect_style, line.as_str())])
+            } else {
+                // The first character is a space injected by delta. See comment in
+                // Annotator::prepare.
+                let mut this_line_sections = highlighter.highlight(&line[1..], &config.syntax_set);
+                this_line_sections.insert(0, (config.null_syntect_style, &line[..1]));
+                line_sections.push(this_line_sections);
+            }
+        }
+        line_sections
+    }
+}
+
+fn to_owned_sections<T>(sections: Vec<(T, &str)>) -> Vec<(T, String)> {
+    sections
+        .into_iter()
+        .map(|(t, s)| (t, s.to_string()))
+        .collect()
+}
+
+/// Do the two lines differ only in whitespace?
+fn is_whitespace_change(minus_line: &str, plus_line: &str) -> bool {
+    let non_whitespace = |line: &str| {
+        line.chars()
+            .filter(|c| !c.is_whitespace())
+            .collect::<String>()
+    };
+    non_whitespace(minus_line) == non_whitespace(plus_line)
+}
+
+/// Is the section an edit consisting of whitespace only?
+fn is_whitespace_edit((operation, s): &(EditOperation, &str)) -> bool {
+    matches!(
+        operation,
+        EditOperation::Deletion | EditOperation::Insertion
+    ) && s.trim().is_empty()
+}
+
+/// Return the byte ranges of the line occupied by its edits consisting of whitespace only.
+fn get_whitespace_edit_ranges(edit_sections: &[(EditOperation, &str)]) -> Vec<Range<usize>> {
+    let line: String = edit_sections.iter().map(|(_, s)| *s).collect();
+    let mut ranges = Vec::new();
+    let mut offset = 0;
+    for section in edit_sections {
+        if is_whitespace_edit(section) {
+            let mut range = offset..offset + section.1.len();
+            // The first character of the line is the space injected by Annotator::prepare, which
+            // is not displayed. An edit of spaces beginning there is equivalent to the same edit
+            // beginning one character later, if that character is also a space.
+            if range.start == 0
+                && section.1.chars().all(|c| c == ' ')
+                && line[range.end..].starts_with(' ')
+            {
+                range = 1..range.end + 1;
+            }
+            ranges.push(range);
+        }
+        offset += section.1.len();
+    }
+    ranges
+}
+
+/// Display the spaces and tabs of `s` that lie in `ranges` as '·' and '→', where `offset` is the
+/// byte offset of `s` in its line. If `expanded_tabs`, the other tabs are those inserted by
+/// Annotator::prepare_changed_line, and are displayed as spaces.
+fn show_whitespace_changes(
+    s: &str,
+    offset: usize,
+    ranges: &[Range<usize>],
+    expanded_tabs: bool,
+) -> String {
+    s.char_indices()
+        .map(|(i, c)| {
+            let is_changed = ranges.iter().any(|range| range.contains(&(offset + i)));
+            match c {
+                ' ' if is_changed => '·',
+                '\t' if is_changed => '→',
+                '\t' if expanded_tabs => ' ',
+                c => c,
+            }
+        })
+        .collect()
+}
+
+fn show_whitespace_changes_in_sections<T>(
+    sections: Vec<(T, &str)>,
+    ranges: &[Range<usize>],
+    expanded_tabs: bool,
+) -> Vec<(T, String)> {
+    let mut offset = 0;
+    sections
+        .into_iter()
+        .map(|(t, s)| {
+            let section = show_whitespace_changes(s, offset, ranges, expanded_tabs);
+            offset += s.len();
+            (t, section)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::integration_test_utils::integration_test_utils;
+
+    fn get_text(sections: &[(SyntectStyle, String)]) -> String {
+        sections.iter().map(|(_, s)| s.as_str()).collect()
+    }
+
+    #[test]
+    fn test_annotate_buffered_minus_and_plus_lines() {
+        let config = integration_test_utils::make_config_from_args(&[]);
+        let mut annotator = Annotator::new(&config);
+        annotator.set_syntax(Some("py"));
+        annotator.set_highlighter();
+        let line = annotator.prepare_changed_line("-x = 1");
+        annotator.minus_lines.push((line, State::HunkMinus(None)));
+        annotator.minus_line_formats.push(LineFormat {
+            has_crlf: true,
+            ..LineFormat::default()
+        });
+        for line in &["+x = 2", "+y = 3"] {
+            let line = annotator.prepare_changed_line(line);
+            annotator.plus_lines.push((line, State::HunkPlus(None)));
+            annotator.plus_line_formats.push(LineFormat::default());
+        }
+
+        let changed_lines = annotator.annotate_buffered_minus_and_plus_lines().unwrap();
+        assert_eq!(
+            changed_lines.alignment,
+            vec![(Some(0), Some(0)), (None, Some(1))]
+        );
+        let minus_line = &changed_lines.minus_lines[0];
+        assert_eq!(minus_line.text, " x = 1\n");
+        assert_eq!(get_text(&minus_line.syntax_sections), minus_line.text);
+        assert!(minus_line.format.has_crlf);
+        assert!(minus_line
+            .edit_sections
+            .contains(&(EditOperation::Deletion, "1".to_string())));
+        assert!(changed_lines.plus_lines[0]
+            .edit_sections
+            .contains(&(EditOperation::Insertion, "2".to_string())));
+        assert_eq!(changed_lines.plus_lines[1].text, " y = 3\n");
+
+        // The buffers have been cleared.
+        assert!(annotator.minus_lines.is_empty() && annotator.plus_line_formats.is_empty());
+        assert!(annotator.annotate_buffered_minus_and_plus_lines().is_none());
+    }
+
+    #[test]
+    fn test_annotate_zero_line() {
+        let config = integration_test_utils::make_config_from_args(&[]);
+        let mut annotator = Annotator::new(&config);
+        let format = LineFormat {
+            has_no_newline: true,
+            ..LineFormat::default()
+        };
+        let line = annotator.annotate_zero_line(" z\t= 0", format);
+        assert_eq!(line.state, State::HunkZero);
+        assert_eq!(line.text, " z    = 0\n");
+        assert_eq!(get_text(&line.syntax_sections), line.text);
+        assert!(line.edit_sections.is_empty());
+        assert_eq!(line.format, format);
+    }
+
+    #[test]
+    fn test_annotate_hunk_header() {
+        let config = integration_test_utils::make_config_from_args(&[]);
+        let mut annotator = Annotator::new(&config);
+        let line = "@@ -1,3 +1,4 @@ def f():";
+        let hunk_header = annotator.annotate_hunk_header(line, line, "a.py");
+        assert_eq!(hunk_header.line_numbers, vec![(1, 3), (1, 4)]);
+        assert_eq!(hunk_header.plus_file, "a.py");
+        assert_eq!(hunk_header.code_fragment.state, State::HunkHeader);
+        assert_eq!(hunk_header.code_fragment.text, " def f(): ");
+        assert_eq!(
+            get_text(&hunk_header.code_fragment.syntax_sections),
+            hunk_header.code_fragment.text
+        );
+    }
+}
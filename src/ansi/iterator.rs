@@ -304,7 +304,9 @@ mod tests {
             match element {
                 Element::CSI(style, _, _) => assert!(style::ansi_term_style_equality(
                     style,
-                    style::Style::from_git_str(git_style_string).ansi_term_style
+                    style::Style::from_git_str(git_style_string)
+                        .unwrap()
+                        .ansi_term_style
                 )),
                 _ => assert!(false),
             }
@@ -324,7 +326,9 @@ mod tests {
             match element {
                 Element::CSI(style, _, _) => assert!(style::ansi_term_style_equality(
                     style,
-                    style::Style::from_git_str(git_style_string).ansi_term_style
+                    style::Style::from_git_str(git_style_string)
+                        .unwrap()
+                        .ansi_term_style
                 )),
                 _ => assert!(false),
             }
@@ -0,0 +1,726 @@
+use std::convert::TryFrom;
+use std::ffi::OsString;
+use std::io::{self, BufRead, ErrorKind, Read, Write};
+use std::path::PathBuf;
+use std::process;
+use std::sync::Mutex;
+
+use itertools::Itertools;
+use structopt::StructOpt;
+
+use crate::bat_utils::assets::{list_languages, HighlightingAssets};
+use crate::bat_utils::output::{OutputType, PagingMode};
+use crate::config::delta_unreachable;
+use crate::delta::delta;
+use crate::features::navigate::NavigateTarget;
+use crate::options::theme::is_light_syntax_theme;
+use crate::{builtin_pager, cli, config, git_config, git_diff};
+
+/// Run delta as a command-line program.
+#[cfg(not(tarpaulin_include))]
+pub fn run() -> std::io::Result<()> {
+    let assets = HighlightingAssets::new();
+    let mut git_config = make_git_config();
+    let opt = cli::Opt::from_args_and_git_config(&mut git_config, assets);
+
+    if opt.list_languages {
+        list_languages()?;
+        process::exit(0);
+    } else if opt.list_syntax_themes {
+        list_syntax_themes()?;
+        process::exit(0);
+    } else if opt.show_syntax_themes {
+        show_syntax_themes()?;
+        process::exit(0);
+    }
+
+    let _show_config = opt.show_config;
+    let mut config = make_config(opt);
+    config.repo = git_config
+        .and_then(|git_config| git_config.repo)
+        .map(Mutex::new);
+
+    if _show_config {
+        let stdout = io::stdout();
+        let mut stdout = stdout.lock();
+        show_config(&config, &mut stdout)?;
+        process::exit(0);
+    }
+
+    // Under --rev and --staged, the diff is read from the repository rather than stdin.
+    let input = if config.rev.is_some() || config.staged {
+        Some(read_diff_from_repository(&config))
+    } else {
+        None
+    };
+
+    if config.paging_mode == PagingMode::Builtin
+        && (input.is_some() || !atty::is(atty::Stream::Stdin))
+        && atty::is(atty::Stream::Stdout)
+    {
+        let input = match input {
+            Some(input) => input,
+            None => {
+                let mut input = Vec::new();
+                io::stdin().lock().read_to_end(&mut input)?;
+                input
+            }
+        };
+        let side_by_side = config.side_by_side;
+        return builtin_pager::run(&input, config, &|| {
+            make_config_with_side_by_side(!side_by_side)
+        });
+    }
+
+    let mut output_type = OutputType::from_mode(config.paging_mode, None, &config).unwrap();
+    let mut writer = output_type.handle().unwrap();
+
+    let result = match input {
+        Some(input) => delta(
+            BufRead::split(input.as_slice(), b'\n'),
+            &mut writer,
+            &config,
+        ),
+        None if atty::is(atty::Stream::Stdin) => {
+            let exit_code = diff(
+                config.minus_file.as_ref(),
+                config.plus_file.as_ref(),
+                &config,
+                &mut writer,
+            );
+            save_highlight_cache(&config);
+            process::exit(exit_code)
+        }
+        None => delta(
+            io::BufReader::new(io::stdin()).split(b'\n'),
+            &mut writer,
+            &config,
+        ),
+    };
+    if let Err(error) = result {
+        match error.kind() {
+            ErrorKind::BrokenPipe => {
+                save_highlight_cache(&config);
+                process::exit(0)
+            }
+            _ => eprintln!("{}", error),
+        }
+    };
+    Ok(())
+}
+
+/// Write the lines highlighted so far to the highlight cache. This must be done before calling
+/// process::exit, which does not run the cache's destructor.
+fn save_highlight_cache(config: &config::Config) {
+    if let Some(highlight_cache) = &config.highlight_cache {
+        // Failing to write the cache is not an error: the lines will be highlighted again.
+        highlight_cache.save().ok();
+    }
+}
+
+/// Read the git config, exiting if it cannot be read.
+fn make_git_config() -> Option<git_config::GitConfig> {
+    git_config::GitConfig::try_create().unwrap_or_else(|error| {
+        eprintln!("Failed to read git config: {}", error);
+        process::exit(1);
+    })
+}
+
+/// Make the config from `opt`, exiting if an option value is invalid.
+fn make_config(opt: cli::Opt) -> config::Config {
+    config::Config::try_from(opt).unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        process::exit(1);
+    })
+}
+
+/// Read the diff requested by --rev and --staged from the repository, exiting if this fails.
+fn read_diff_from_repository(config: &config::Config) -> Vec<u8> {
+    let repo = config.repo.as_ref().unwrap_or_else(|| {
+        eprintln!("--rev and --staged can only be used in a git repository.");
+        process::exit(config.error_exit_code);
+    });
+    git_diff::read_diff(&repo.lock().unwrap(), config.rev.as_deref(), config.staged).unwrap_or_else(
+        |error| {
+            eprintln!(
+                "Failed to read the diff from the repository: {}",
+                error.message()
+            );
+            process::exit(config.error_exit_code);
+        },
+    )
+}
+
+/// Make the config specified by the command line and git config, but with side-by-side mode on or
+/// off as requested. This is used when the built-in pager switches layouts.
+fn make_config_with_side_by_side(side_by_side: bool) -> config::Config {
+    let mut git_config = make_git_config();
+    let mut args: Vec<OsString> = std::env::args_os().collect();
+    if side_by_side {
+        args.push("--side-by-side".into());
+    }
+    let mut opt = cli::Opt::from_iter_and_git_config(args, &mut git_config);
+    opt.side_by_side = side_by_side;
+    let mut config = make_config(opt);
+    config.repo = git_config
+        .and_then(|git_config| git_config.repo)
+        .map(Mutex::new);
+    config
+}
+
+/// Run `diff -u` on the files provided on the command line and display the output.
+fn diff(
+    minus_file: Option<&PathBuf>,
+    plus_file: Option<&PathBuf>,
+    config: &config::Config,
+    writer: &mut dyn Write,
+) -> i32 {
+    use std::io::BufReader;
+    let die = || {
+        eprintln!("Usage: delta minus_file plus_file");
+        process::exit(config.error_exit_code);
+    };
+    let diff_command = "diff";
+    let mut diff_process = process::Command::new(PathBuf::from(diff_command))
+        .arg("-u")
+        .args(&[
+            minus_file.unwrap_or_else(die),
+            plus_file.unwrap_or_else(die),
+        ])
+        .stdout(process::Stdio::piped())
+        .spawn()
+        .unwrap_or_else(|err| {
+            eprintln!("Failed to execute the command '{}': {}", diff_command, err);
+            process::exit(config.error_exit_code);
+        });
+    let exit_code = diff_process
+        .wait()
+        .unwrap_or_else(|_| {
+            delta_unreachable(&format!("'{}' process not running.", diff_command));
+        })
+        .code()
+        .unwrap_or_else(|| {
+            eprintln!("'{}' process terminated without exit status.", diff_command);
+            process::exit(config.error_exit_code);
+        });
+
+    if let Err(error) = delta(
+        BufReader::new(diff_process.stdout.unwrap()).split(b'\n'),
+        writer,
+        &config,
+    ) {
+        match error.kind() {
+            ErrorKind::BrokenPipe => {
+                save_highlight_cache(config);
+                process::exit(0)
+            }
+            _ => {
+                eprintln!("{}", error);
+                process::exit(config.error_exit_code);
+            }
+        }
+    };
+    exit_code
+}
+
+fn show_config(config: &config::Config, writer: &mut dyn Write) -> std::io::Result<()> {
+    // styles first
+    writeln!(
+        writer,
+        "    commit-style                  = {commit_style}
+    file-style                    = {file_style}
+    file-header-event-style       = {file_header_event_style}
+    file-header-path-style        = {file_header_path_style}
+    file-header-old-path-style    = {file_header_old_path_style}
+    file-header-mode-style        = {file_header_mode_style}
+    file-header-notes-style       = {file_header_notes_style}
+    file-header-added-style       = {file_header_added_style}
+    file-header-removed-style     = {file_header_removed_style}
+    file-header-language-style    = {file_header_language_style}
+    file-header-icon-style        = {file_header_icon_style}
+    hunk-header-style             = {hunk_header_style}
+    hunk-header-file-style        = {hunk_header_file_style}
+    hunk-header-line-number-style = {hunk_header_line_number_style}
+    hunk-header-minus-start-style = {hunk_header_minus_start_style}
+    hunk-header-minus-len-style   = {hunk_header_minus_len_style}
+    hunk-header-plus-start-style  = {hunk_header_plus_start_style}
+    hunk-header-plus-len-style    = {hunk_header_plus_len_style}
+    hunk-header-fragment-style    = {hunk_header_fragment_style}
+    minus-style                   = {minus_style}
+    minus-non-emph-style          = {minus_non_emph_style}
+    minus-emph-style              = {minus_emph_style}
+    minus-empty-line-marker-style = {minus_empty_line_marker_style}
+    zero-style                    = {zero_style}
+    plus-style                    = {plus_style}
+    plus-non-emph-style           = {plus_non_emph_style}
+    plus-emph-style               = {plus_emph_style}
+    plus-empty-line-marker-style  = {plus_empty_line_marker_style}
+    whitespace-change-style       = {whitespace_change_style}
+    whitespace-error-style        = {whitespace_error_style}
+    line-ending-style             = {line_ending_style}
+    bom-style                     = {bom_style}
+    no-newline-style              = {no_newline_style}",
+        commit_style = config.commit_style.to_painted_string(),
+        file_style = config.file_style.to_painted_string(),
+        file_header_event_style = config.file_header_event_style.to_painted_string(),
+        file_header_path_style = config.file_header_path_style.to_painted_string(),
+        file_header_old_path_style = config.file_header_old_path_style.to_painted_string(),
+        file_header_mode_style = config.file_header_mode_style.to_painted_string(),
+        file_header_notes_style = config.file_header_notes_style.to_painted_string(),
+        file_header_added_style = config.file_header_added_style.to_painted_string(),
+        file_header_removed_style = config.file_header_removed_style.to_painted_string(),
+        file_header_language_style = config.file_header_language_style.to_painted_string(),
+        file_header_icon_style = config.file_header_icon_style.to_painted_string(),
+        hunk_header_style = config.hunk_header_style.to_painted_string(),
+        hunk_header_file_style = config.hunk_header_file_style.to_painted_string(),
+        hunk_header_line_number_style = config.hunk_header_line_number_style.to_painted_string(),
+        hunk_header_minus_start_style = config.hunk_header_minus_start_style.to_painted_string(),
+        hunk_header_minus_len_style = config.hunk_header_minus_len_style.to_painted_string(),
+        hunk_header_plus_start_style = config.hunk_header_plus_start_style.to_painted_string(),
+        hunk_header_plus_len_style = config.hunk_header_plus_len_style.to_painted_string(),
+        hunk_header_fragment_style = config.hunk_header_fragment_style.to_painted_string(),
+        minus_emph_style = config.minus_emph_style.to_painted_string(),
+        minus_empty_line_marker_style = config.minus_empty_line_marker_style.to_painted_string(),
+        minus_non_emph_style = config.minus_non_emph_style.to_painted_string(),
+        minus_style = config.minus_style.to_painted_string(),
+        plus_emph_style = config.plus_emph_style.to_painted_string(),
+        plus_empty_line_marker_style = config.plus_empty_line_marker_style.to_painted_string(),
+        plus_non_emph_style = config.plus_non_emph_style.to_painted_string(),
+        plus_style = config.plus_style.to_painted_string(),
+        whitespace_change_style = config
+            .whitespace_change_style
+            .map(|style| style.to_painted_string().to_string())
+            .unwrap_or_default(),
+        whitespace_error_style = config.whitespace_error_style.to_painted_string(),
+        line_ending_style = config.line_ending_style.to_painted_string(),
+        bom_style = config.bom_style.to_painted_string(),
+        no_newline_style = config.no_newline_style.to_painted_string(),
+        zero_style = config.zero_style.to_painted_string(),
+    )?;
+    // Everything else
+    writeln!(
+        writer,
+        "    24-bit-color                  = {true_color}
+    file-added-label              = {file_added_label}
+    file-header-format            = {file_header_format}
+    file-modified-label           = {file_modified_label}
+    file-removed-label            = {file_removed_label}
+    file-rename-format            = {file_rename_format}
+    file-renamed-label            = {file_renamed_label}
+    hunk-label                    = {hunk_label}",
+        true_color = config.true_color,
+        file_added_label = format_option_value(&config.file_added_label),
+        file_header_format = format_option_value(&config.file_header_format),
+        file_modified_label = format_option_value(&config.file_modified_label),
+        file_removed_label = format_option_value(&config.file_removed_label),
+        file_rename_format = format_option_value(&config.file_rename_format),
+        file_renamed_label = format_option_value(&config.file_renamed_label),
+        hunk_label = format_option_value(&config.hunk_label),
+    )?;
+    writeln!(
+        writer,
+        "    hyperlinks                    = {hyperlinks}",
+        hyperlinks = config.hyperlinks
+    )?;
+    if config.hyperlinks {
+        writeln!(
+            writer,
+            "    hyperlinks-file-link-format   = {hyperlinks_file_link_format}",
+            hyperlinks_file_link_format = format_option_value(&config.hyperlinks_file_link_format),
+        )?
+    }
+    writeln!(
+        writer,
+        "    context-lines                 = {context_lines}
+    default-encoding              = {default_encoding}
+    full-file-syntax-highlighting = {full_file_syntax_highlighting}
+    hex-diff                      = {hex_diff}
+    hex-diff-max-size             = {hex_diff_max_size}
+    highlight-cache               = {highlight_cache}
+    highlight-cache-max-size      = {highlight_cache_max_size}
+    hunk-header-enclosing-scope   = {hunk_header_enclosing_scope}
+    hunk-header-format            = {hunk_header_format}
+    image-protocol                = {image_protocol}
+    image-thumbnail-height        = {image_thumbnail_height}
+    image-thumbnails              = {image_thumbnails}
+    inspect-raw-lines             = {inspect_raw_lines}
+    keep-plus-minus-markers       = {keep_plus_minus_markers}
+    line-ending-note              = {line_ending_note}",
+        context_lines = config.context_lines,
+        default_encoding = config.default_encoding.name(),
+        full_file_syntax_highlighting = config.full_file_syntax_highlighting,
+        hex_diff = config.hex_diff,
+        hex_diff_max_size = config.hex_diff_max_size,
+        highlight_cache = config.highlight_cache.is_some(),
+        highlight_cache_max_size = config.highlight_cache_max_size,
+        hunk_header_enclosing_scope = config.hunk_header_enclosing_scope,
+        hunk_header_format = format_option_value(&config.hunk_header_format),
+        image_protocol = match config.image_protocol {
+            cli::ImageProtocol::Kitty => "kitty",
+            cli::ImageProtocol::Iterm2 => "iterm2",
+            cli::ImageProtocol::Sixel => "sixel",
+            cli::ImageProtocol::None => "none",
+        },
+        image_thumbnail_height = config.image_thumbnail_height,
+        image_thumbnails = config.image_thumbnails,
+        inspect_raw_lines = match config.inspect_raw_lines {
+            cli::InspectRawLines::True => "true",
+            cli::InspectRawLines::False => "false",
+        },
+        keep_plus_minus_markers = config.keep_plus_minus_markers,
+        line_ending_note = config.line_ending_note,
+    )?;
+    writeln!(
+        writer,
+        "    line-numbers                  = {line_numbers}",
+        line_numbers = config.line_numbers
+    )?;
+    if config.line_numbers {
+        writeln!(
+            writer,
+            "    line-numbers-minus-style      = {line_numbers_minus_style}
+    line-numbers-zero-style       = {line_numbers_zero_style}
+    line-numbers-plus-style       = {line_numbers_plus_style}
+    line-numbers-left-style       = {line_numbers_left_style}
+    line-numbers-right-style      = {line_numbers_right_style}
+    line-numbers-left-format      = {line_numbers_left_format}
+    line-numbers-right-format     = {line_numbers_right_format}",
+            line_numbers_minus_style = config.line_numbers_minus_style.to_painted_string(),
+            line_numbers_zero_style = config.line_numbers_zero_style.to_painted_string(),
+            line_numbers_plus_style = config.line_numbers_plus_style.to_painted_string(),
+            line_numbers_left_style = config.line_numbers_left_style.to_painted_string(),
+            line_numbers_right_style = config.line_numbers_right_style.to_painted_string(),
+            line_numbers_left_format = format_option_value(&config.line_numbers_left_format),
+            line_numbers_right_format = format_option_value(&config.line_numbers_right_format),
+        )?
+    }
+    writeln!(
+        writer,
+        "    max-line-distance             = {max_line_distance}
+    max-line-length               = {max_line_length}
+    max-token-distance            = {max_token_distance}
+    navigate                      = {navigate}",
+        max_line_distance = config.max_line_distance,
+        max_line_length = config.max_line_length,
+        max_token_distance = config.max_token_distance,
+        navigate = config.navigate,
+    )?;
+    if config.navigate {
+        writeln!(
+            writer,
+            "    navigate-targets              = {navigate_targets}
+    navigate-regex                = {navigate_regex}",
+            navigate_targets = format_option_value(
+                config
+                    .navigate_targets
+                    .iter()
+                    .map(|target| match target {
+                        NavigateTarget::Commits => "commits",
+                        NavigateTarget::Files => "files",
+                        NavigateTarget::Hunks => "hunks",
+                    })
+                    .join(" ")
+            ),
+            navigate_regex = format_option_value(config.navigate_regex.as_deref().unwrap_or("")),
+        )?
+    }
+    writeln!(
+        writer,
+        "    paging                        = {paging_mode}
+    relative-paths                = {relative_paths}
+    show-whitespace-changes       = {show_whitespace_changes}
+    side-by-side                  = {side_by_side}
+    syntax-theme                  = {syntax_theme}
+    width                         = {width}
+    tabs                          = {tab_width}
+    threads                       = {threads}
+    whitespace-insensitive-emph   = {whitespace_insensitive_emph}
+    word-diff-regex               = {tokenization_regex}",
+        paging_mode = match config.paging_mode {
+            PagingMode::Always => "always",
+            PagingMode::Never => "never",
+            PagingMode::QuitIfOneScreen => "auto",
+            PagingMode::Builtin => "builtin",
+        },
+        relative_paths = config.relative_paths,
+        show_whitespace_changes = config.show_whitespace_changes,
+        side_by_side = config.side_by_side,
+        syntax_theme = config
+            .syntax_theme
+            .clone()
+            .map(|t| t.name.unwrap_or_else(|| "none".to_string()))
+            .unwrap_or_else(|| "none".to_string()),
+        width = match config.decorations_width {
+            cli::Width::Fixed(width) => width.to_string(),
+            cli::Width::Variable => "variable".to_string(),
+        },
+        tab_width = config.tab_width,
+        threads = config.threads,
+        whitespace_insensitive_emph = config.whitespace_insensitive_emph,
+        tokenization_regex = format_option_value(&config.tokenization_regex.to_string()),
+    )?;
+    Ok(())
+}
+
+// Heuristics determining whether to quote string option values when printing values intended for
+// git config.
+fn format_option_value<S>(s: S) -> String
+where
+    S: AsRef<str>,
+{
+    let s = s.as_ref();
+    if s.ends_with(' ')
+        || s.starts_with(' ')
+        || s.contains(&['\\', '{', '}', ':'][..])
+        || s.is_empty()
+    {
+        format!("'{}'", s)
+    } else {
+        s.to_string()
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+fn show_syntax_themes() -> std::io::Result<()> {
+    let mut opt = cli::Opt::from_args();
+    let assets = HighlightingAssets::new();
+    let mut output_type = OutputType::from_mode(
+        PagingMode::QuitIfOneScreen,
+        None,
+        &make_config(cli::Opt::default()),
+    )
+    .unwrap();
+    let mut writer = output_type.handle().unwrap();
+    opt.computed.syntax_set = assets.syntax_set;
+
+    if !(opt.dark || opt.light) {
+        _show_syntax_themes(opt.clone(), false, &mut writer)?;
+        _show_syntax_themes(opt, true, &mut writer)?;
+    } else if opt.light {
+        _show_syntax_themes(opt, true, &mut writer)?;
+    } else {
+        _show_syntax_themes(opt, false, &mut writer)?
+    };
+    Ok(())
+}
+
+fn _show_syntax_themes(
+    mut opt: cli::Opt,
+    is_light_mode: bool,
+    writer: &mut dyn Write,
+) -> std::io::Result<()> {
+    use std::io::BufReader;
+    let mut input = b"\
+diff --git a/example.rs b/example.rs
+index f38589a..0f1bb83 100644
+--- a/example.rs
++++ b/example.rs
+@@ -1,5 +1,5 @@
+-// Output the square of a number.
+-fn print_square(num: f64) {
+-    let result = f64::powf(num, 2.0);
+-    println!(\"The square of {:.2} is {:.2}.\", num, result);
++// Output the cube of a number.
++fn print_cube(num: f64) {
++    let result = f64::powf(num, 3.0);
++    println!(\"The cube of {:.2} is {:.2}.\", num, result);
+"
+    .to_vec();
+    if !atty::is(atty::Stream::Stdin) {
+        let mut buf = Vec::new();
+        io::stdin().lock().read_to_end(&mut buf)?;
+        if !buf.is_empty() {
+            input = buf;
+        }
+    };
+
+    opt.computed.is_light_mode = is_light_mode;
+    let mut config = make_config(opt);
+    let title_style = ansi_term::Style::new().bold();
+    let assets = HighlightingAssets::new();
+
+    for syntax_theme in assets
+        .theme_set
+        .themes
+        .iter()
+        .filter(|(t, _)| is_light_syntax_theme(t) == is_light_mode)
+        .map(|(t, _)| t)
+    {
+        writeln!(writer, "\n\nTheme: {}\n", title_style.paint(syntax_theme))?;
+        config.syntax_theme = Some(assets.theme_set.themes[syntax_theme.as_str()].clone());
+        if let Err(error) = delta(BufReader::new(&input[0..]).split(b'\n'), writer, &config) {
+            match error.kind() {
+                ErrorKind::BrokenPipe => process::exit(0),
+                _ => eprintln!("{}", error),
+            }
+        };
+    }
+    Ok(())
+}
+
+#[cfg(not(tarpaulin_include))]
+fn list_syntax_themes() -> std::io::Result<()> {
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+    if atty::is(atty::Stream::Stdout) {
+        _list_syntax_themes_for_humans(&mut stdout)
+    } else {
+        _list_syntax_themes_for_machines(&mut stdout)
+    }
+}
+
+fn _list_syntax_themes_for_humans(writer: &mut dyn Write) -> std::io::Result<()> {
+    let assets = HighlightingAssets::new();
+    let themes = &assets.theme_set.themes;
+
+    writeln!(writer, "Light themes:")?;
+    for (theme, _) in themes.iter().filter(|(t, _)| is_light_syntax_theme(*t)) {
+        writeln!(writer, "    {}", theme)?;
+    }
+    writeln!(writer, "\nDark themes:")?;
+    for (theme, _) in themes.iter().filter(|(t, _)| !is_light_syntax_theme(*t)) {
+        writeln!(writer, "    {}", theme)?;
+    }
+    writeln!(
+        writer,
+        "\nUse delta --show-syntax-themes to demo the themes."
+    )?;
+    Ok(())
+}
+
+fn _list_syntax_themes_for_machines(writer: &mut dyn Write) -> std::io::Result<()> {
+    let assets = HighlightingAssets::new();
+    let themes = &assets.theme_set.themes;
+    for (theme, _) in themes
+        .iter()
+        .sorted_by_key(|(t, _)| is_light_syntax_theme(*t))
+    {
+        writeln!(
+            writer,
+            "{}\t{}",
+            if is_light_syntax_theme(theme) {
+                "light"
+            } else {
+                "dark"
+            },
+            theme
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod main_tests {
+    use super::*;
+    use std::io::{Cursor, Seek, SeekFrom};
+
+    use crate::{ansi, ConfigBuilder};
+
+    #[test]
+    fn test_show_config() {
+        let config = ConfigBuilder::new().build().unwrap();
+        let mut writer = Cursor::new(vec![0; 1024]);
+        show_config(&config, &mut writer).unwrap();
+        let mut s = String::new();
+        writer.seek(SeekFrom::Start(0)).unwrap();
+        writer.read_to_string(&mut s).unwrap();
+        let s = ansi::strip_ansi_codes(&s);
+        assert!(s.contains("    commit-style                  = raw\n"));
+        assert!(s.contains(r"    word-diff-regex               = '\w+'"));
+    }
+
+    #[test]
+    #[ignore] // Not working (timing out) when run by tarpaulin, presumably due to stdin detection.
+    fn test_show_syntax_themes() {
+        let opt = cli::Opt::from_iter_and_git_config(&["delta"], &mut None);
+
+        let mut writer = Cursor::new(vec![0; 1024]);
+        _show_syntax_themes(opt, true, &mut writer).unwrap();
+        let mut s = String::new();
+        writer.seek(SeekFrom::Start(0)).unwrap();
+        writer.read_to_string(&mut s).unwrap();
+        let s = ansi::strip_ansi_codes(&s);
+        assert!(s.contains("\nTheme: gruvbox-white\n"));
+        println!("{}", s);
+        assert!(s.contains("\nfn print_cube(num: f64) {\n"));
+    }
+
+    #[test]
+    fn test_list_syntax_themes_for_humans() {
+        let mut writer = Cursor::new(vec![0; 512]);
+        _list_syntax_themes_for_humans(&mut writer).unwrap();
+        let mut s = String::new();
+        writer.seek(SeekFrom::Start(0)).unwrap();
+        writer.read_to_string(&mut s).unwrap();
+        assert!(s.contains("Light themes:\n"));
+        assert!(s.contains("    GitHub\n"));
+        assert!(s.contains("Dark themes:\n"));
+        assert!(s.contains("    Dracula\n"));
+    }
+
+    #[test]
+    fn test_list_syntax_themes_for_machines() {
+        let mut writer = Cursor::new(vec![0; 512]);
+        _list_syntax_themes_for_machines(&mut writer).unwrap();
+        let mut s = String::new();
+        writer.seek(SeekFrom::Start(0)).unwrap();
+        writer.read_to_string(&mut s).unwrap();
+        assert!(s.contains("light	GitHub\n"));
+        assert!(s.contains("dark	Dracula\n"));
+    }
+
+    #[test]
+    #[cfg_attr(target_os = "windows", ignore)]
+    fn test_diff_same_empty_file() {
+        let config = ConfigBuilder::new().build().unwrap();
+        let mut writer = Cursor::new(vec![]);
+        let exit_code = diff(
+            Some(&PathBuf::from("/dev/null")),
+            Some(&PathBuf::from("/dev/null")),
+            &config,
+            &mut writer,
+        );
+        assert_eq!(exit_code, 0);
+        let mut s = String::new();
+        writer.seek(SeekFrom::Start(0)).unwrap();
+        writer.read_to_string(&mut s).unwrap();
+        assert!(s.is_empty());
+    }
+
+    #[test]
+    #[cfg_attr(target_os = "windows", ignore)]
+    fn test_diff_same_non_empty_file() {
+        let config = ConfigBuilder::new().build().unwrap();
+        let mut writer = Cursor::new(vec![]);
+        let exit_code = diff(
+            Some(&PathBuf::from("/etc/passwd")),
+            Some(&PathBuf::from("/etc/passwd")),
+            &config,
+            &mut writer,
+        );
+        assert_eq!(exit_code, 0);
+        let mut s = String::new();
+        writer.seek(SeekFrom::Start(0)).unwrap();
+        writer.read_to_string(&mut s).unwrap();
+        assert!(s.is_empty());
+    }
+
+    #[test]
+    #[cfg_attr(target_os = "windows", ignore)]
+    fn test_diff_differing_files() {
+        let config = ConfigBuilder::new().build().unwrap();
+        let mut writer = Cursor::new(vec![]);
+        let exit_code = diff(
+            Some(&PathBuf::from("/dev/null")),
+            Some(&PathBuf::from("/etc/passwd")),
+            &config,
+            &mut writer,
+        );
+        assert_eq!(exit_code, 1);
+        let mut s = String::new();
+        writer.seek(SeekFrom::Start(0)).unwrap();
+        writer.read_to_string(&mut s).unwrap();
+        let s = ansi::strip_ansi_codes(&s);
+        assert!(s.contains("comparing: /dev/null ⟶   /etc/passwd\n"));
+    }
+}
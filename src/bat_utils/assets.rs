@@ -1,12 +1,15 @@
 // Based on code from https://github.com/sharkdp/bat a1b9334a44a2c652f52dddaa83dbacba57372468
 // See src/bat_utils/LICENSE
 
-use std::fs::File;
+use std::fs::{self, File};
+use std::hash::Hasher;
 use std::io::{self, BufReader, Write};
 use std::path::PathBuf;
+use std::time::UNIX_EPOCH;
 
 use ansi_term::Colour::Green;
 use ansi_term::Style;
+use fnv::FnvHasher;
 use syntect::dumps::{from_binary, from_reader};
 use syntect::highlighting::ThemeSet;
 use syntect::parsing::SyntaxSet;
@@ -19,6 +22,12 @@ pub struct HighlightingAssets {
     pub theme_set: ThemeSet,
 }
 
+impl Default for HighlightingAssets {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl HighlightingAssets {
     pub fn new() -> Self {
         Self::from_cache().unwrap_or_else(|_| Self::from_binary())
@@ -69,6 +78,26 @@ impl HighlightingAssets {
     }
 }
 
+/// Return a value that changes whenever the syntax and theme definitions change: that is, when
+/// delta is upgraded, or when the cached syntaxes.bin or themes.bin are rebuilt. It is stored on
+/// disk by the highlight cache, so it is computed with FNV rather than Rust's default hasher.
+pub fn get_assets_fingerprint() -> u64 {
+    let mut hasher = FnvHasher::default();
+    hasher.write(env!("CARGO_PKG_VERSION").as_bytes());
+    for path in &[syntax_set_path(), theme_set_path()] {
+        if let Ok(metadata) = fs::metadata(path) {
+            let modified = metadata
+                .modified()
+                .ok()
+                .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+                .map_or(0, |modified| modified.as_nanos() as u64);
+            hasher.write(&metadata.len().to_le_bytes());
+            hasher.write(&modified.to_le_bytes());
+        }
+    }
+    hasher.finish()
+}
+
 fn theme_set_path() -> PathBuf {
     PROJECT_DIRS.cache_dir().join("themes.bin")
 }
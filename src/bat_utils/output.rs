@@ -18,6 +18,7 @@ pub enum PagingMode {
     Always,
     QuitIfOneScreen,
     Never,
+    Builtin,
 }
 use crate::errors::*;
 
@@ -0,0 +1,795 @@
+use std::cell::Cell;
+use std::collections::HashSet;
+use std::io::{self, BufRead, Write};
+use std::rc::Rc;
+
+use console::{Key, Term};
+
+use crate::ansi;
+use crate::config::Config;
+use crate::delta;
+use crate::model::{self, FileHeader, Renderer};
+use crate::paint::Painter;
+
+mod terminal;
+
+use self::terminal::{Event, Terminal};
+
+/// The keys understood by the built-in pager, shown in its status line.
+const KEY_HELP: &str =
+    "q quit  ]/[ hunk  }/{ file  z/Z fold hunk/file  Tab files  / search  s side-by-side";
+
+/// A commit, file, or hunk header in delta's output, identified by the commit line, file path, or
+/// hunk header line.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Boundary {
+    Commit(String),
+    File(String),
+    Hunk(String),
+}
+
+/// Delta's output for a diff, together with the commit, file, and hunk headers it contains.
+pub struct Document {
+    lines: Vec<String>,
+    stripped_lines: Vec<String>,
+    // The boundaries in the order in which they occur, each with the index of the first line of
+    // its header.
+    boundaries: Vec<(usize, Boundary)>,
+}
+
+impl Document {
+    /// Render `input` (the raw diff) using `config`, recording the position of each boundary.
+    pub fn new(input: &[u8], config: &Config) -> io::Result<Self> {
+        let line_count = Rc::new(Cell::new(0));
+        let mut writer = LineCountingWriter {
+            buffer: Vec::new(),
+            line_count: line_count.clone(),
+        };
+        let mut boundary_recorder = BoundaryRecorder {
+            painter: Painter::new(&mut writer, config),
+            line_count,
+            boundaries: Vec::new(),
+        };
+        delta::parse_diff(BufRead::split(input, b'\n'), config, &mut boundary_recorder)?;
+        let boundaries = boundary_recorder.boundaries;
+        let lines: Vec<String> = String::from_utf8_lossy(&writer.buffer)
+            .lines()
+            .map(|line| line.to_string())
+            .collect();
+        let stripped_lines = lines
+            .iter()
+            .map(|line| ansi::strip_ansi_codes(line))
+            .collect();
+        Ok(Self {
+            lines,
+            stripped_lines,
+            boundaries,
+        })
+    }
+
+    fn start(&self, i: usize) -> usize {
+        self.boundaries[i].0
+    }
+
+    /// The index of the line following the section introduced by boundary `i`, i.e. the start of
+    /// the next boundary at the same or a higher level, or the end of the document.
+    fn end(&self, i: usize) -> usize {
+        let level = get_level(&self.boundaries[i].1);
+        self.boundaries[i + 1..]
+            .iter()
+            .find(|(_, boundary)| get_level(boundary) <= level)
+            .map_or(self.lines.len(), |(start, _)| *start)
+    }
+
+    fn title(&self, i: usize) -> &str {
+        match &self.boundaries[i].1 {
+            Boundary::Commit(title) | Boundary::File(title) | Boundary::Hunk(title) => title,
+        }
+    }
+}
+
+// Commits contain files, which contain hunks.
+fn get_level(boundary: &Boundary) -> usize {
+    match boundary {
+        Boundary::Commit(_) => 0,
+        Boundary::File(_) => 1,
+        Boundary::Hunk(_) => 2,
+    }
+}
+
+/// A renderer that records the position of each boundary in the painter's output.
+struct BoundaryRecorder<'a> {
+    painter: Painter<'a>,
+    line_count: Rc<Cell<usize>>,
+    boundaries: Vec<(usize, Boundary)>,
+}
+
+impl<'a> Renderer for BoundaryRecorder<'a> {
+    fn render(&mut self, event: model::Event) -> io::Result<()> {
+        let boundary = match &event {
+            model::Event::CommitHeader(commit_header) => {
+                Some(Boundary::Commit(commit_header.line.clone()))
+            }
+            model::Event::FileHeader(FileHeader::Paths {
+                minus_file,
+                plus_file,
+                ..
+            }) => Some(Boundary::File(if plus_file == "/dev/null" {
+                minus_file.clone()
+            } else {
+                plus_file.clone()
+            })),
+            // In color_only mode, the ---/+++ lines are both file headers.
+            model::Event::FileHeader(FileHeader::Line { line, .. })
+                if !self.painter.config.color_only =>
+            {
+                Some(Boundary::File(line.clone()))
+            }
+            model::Event::HunkHeader(hunk_header) => Some(Boundary::Hunk(hunk_header.line.clone())),
+            _ => None,
+        };
+        if let Some(boundary) = boundary {
+            // The painter writes each event's output before the next is rendered.
+            self.boundaries.push((self.line_count.get(), boundary));
+        }
+        self.painter.render(event)
+    }
+}
+
+struct LineCountingWriter {
+    buffer: Vec<u8>,
+    line_count: Rc<Cell<usize>>,
+}
+
+impl Write for LineCountingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        self.line_count
+            .set(self.line_count.get() + buf.iter().filter(|b| **b == b'\n').count());
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A line of the pager's display: either a line of the document, or a placeholder for a folded
+/// section (identified by the index of its boundary).
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Row {
+    Line(usize),
+    Folded(usize),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Action {
+    Continue,
+    Quit,
+    ToggleSideBySide,
+}
+
+// An incremental search that is being typed.
+struct Search {
+    query: String,
+    origin: usize, // The line at the top of the display when the search was started
+}
+
+pub struct Pager {
+    document: Document,
+    folded: HashSet<usize>,
+    rows: Vec<Row>,
+    top: usize, // The index of the row at the top of the display
+    show_file_list: bool,
+    search: Option<Search>,
+    last_query: Option<String>,
+}
+
+impl Pager {
+    pub fn new(document: Document) -> Self {
+        let mut pager = Self {
+            document,
+            folded: HashSet::new(),
+            rows: Vec::new(),
+            top: 0,
+            show_file_list: false,
+            search: None,
+            last_query: None,
+        };
+        pager.make_rows();
+        pager
+    }
+
+    /// Replace the document with a rendering of the same diff using different options, keeping
+    /// the position in the diff and the folded sections.
+    pub fn set_document(&mut self, document: Document) {
+        let line = self.top_line();
+        let position = self
+            .document
+            .boundaries
+            .iter()
+            .rposition(|(start, _)| *start <= line)
+            .map(|i| (i, line - self.document.start(i)));
+        if document.boundaries.len() != self.document.boundaries.len() {
+            self.folded.clear();
+        }
+        self.document = document;
+        self.make_rows();
+        let line = match position {
+            Some((i, offset)) if i < self.document.boundaries.len() => {
+                let (start, end) = (self.document.start(i), self.document.end(i));
+                (start + offset).min(end.max(start + 1) - 1)
+            }
+            _ => 0,
+        };
+        self.top = self.get_row(line);
+    }
+
+    pub fn handle_key(&mut self, key: Key, height: usize) -> Action {
+        if self.search.is_some() && key != Key::Char('\x03') {
+            self.handle_search_key(key);
+            return Action::Continue;
+        }
+        let page = height.saturating_sub(1).max(1);
+        match key {
+            Key::Char('q') | Key::Char('\x03') | Key::Escape => return Action::Quit,
+            Key::Char('s') => return Action::ToggleSideBySide,
+            Key::ArrowDown | Key::Enter | Key::Char('j') => self.scroll_to(self.top + 1, height),
+            Key::ArrowUp | Key::Char('k') => self.scroll_to(self.top.saturating_sub(1), height),
+            Key::PageDown | Key::Char(' ') | Key::Char('f') => {
+                self.scroll_to(self.top + page, height)
+            }
+            Key::PageUp | Key::Char('b') => self.scroll_to(self.top.saturating_sub(page), height),
+            Key::Home | Key::Char('g') => self.top = 0,
+            Key::End | Key::Char('G') => self.scroll_to(self.rows.len(), height),
+            Key::Char(']') => self.jump(2, true),
+            Key::Char('[') => self.jump(2, false),
+            Key::Char('}') => self.jump(1, true),
+            Key::Char('{') => self.jump(1, false),
+            Key::Char('z') => self.toggle_fold(2),
+            Key::Char('Z') => self.toggle_fold(1),
+            Key::Tab => self.show_file_list = !self.show_file_list,
+            Key::Char('/') => {
+                self.search = Some(Search {
+                    query: String::new(),
+                    origin: self.top_line(),
+                })
+            }
+            Key::Char('n') => self.search_again(true),
+            Key::Char('N') => self.search_again(false),
+            _ => {}
+        }
+        Action::Continue
+    }
+
+    fn handle_search_key(&mut self, key: Key) {
+        let search = self.search.as_mut().unwrap();
+        match key {
+            Key::Enter => {
+                if !search.query.is_empty() {
+                    self.last_query = Some(search.query.clone());
+                }
+                self.search = None;
+                return;
+            }
+            Key::Escape => {
+                let origin = search.origin;
+                self.search = None;
+                self.top = self.get_row(origin);
+                return;
+            }
+            Key::Backspace => {
+                search.query.pop();
+            }
+            Key::Char(c) if !c.is_control() => search.query.push(c),
+            _ => return,
+        }
+        let (query, origin) = (search.query.clone(), search.origin);
+        match self.find(&query, origin, true) {
+            Some(line) if !query.is_empty() => self.show_line(line),
+            _ => self.top = self.get_row(origin),
+        }
+    }
+
+    /// Render the display: `height` lines of width at most `width`, the last of which is a status
+    /// line.
+    pub fn render(&self, width: usize, height: usize) -> Vec<String> {
+        let n_rows = height.saturating_sub(1);
+        let files: Vec<usize> = (0..self.document.boundaries.len())
+            .filter(|i| get_level(&self.document.boundaries[*i].1) == 1)
+            .collect();
+        let current_file = self.get_current_section(1);
+        let file_list_width = if self.show_file_list {
+            files
+                .iter()
+                .map(|i| ansi::measure_text_width(self.document.title(*i)) + 2)
+                .max()
+                .unwrap_or(0)
+                .min(width / 3)
+        } else {
+            0
+        };
+        let first_file = files
+            .iter()
+            .position(|i| Some(*i) == current_file)
+            .unwrap_or(0)
+            .saturating_sub(n_rows.saturating_sub(1));
+
+        let mut display = Vec::new();
+        for n in 0..n_rows {
+            let mut line = String::new();
+            if file_list_width > 0 {
+                let entry = match files.get(first_file + n) {
+                    Some(i) => {
+                        let title = format!(" {}", self.document.title(*i));
+                        let title = ansi::truncate_str(&title, file_list_width, "");
+                        let padding =
+                            " ".repeat(file_list_width - ansi::measure_text_width(&title));
+                        if Some(*i) == current_file {
+                            format!("\x1b[7m{}{}\x1b[0m", title, padding)
+                        } else {
+                            format!("{}{}", title, padding)
+                        }
+                    }
+                    None => " ".repeat(file_list_width),
+                };
+                line.push_str(&entry);
+                line.push('│');
+            }
+            let available_width = width.saturating_sub(line_width(file_list_width));
+            if let Some(row) = self.rows.get(self.top + n) {
+                line.push_str(&ansi::truncate_str(
+                    &self.format_row(*row),
+                    available_width,
+                    "",
+                ));
+            }
+            display.push(line);
+        }
+
+        let status = match &self.search {
+            Some(search) => format!("/{}", search.query),
+            None => format!(
+                "{}  {}/{}  {}",
+                current_file.map_or("", |i| self.document.title(i)),
+                (self.top_line() + 1).min(self.document.lines.len()),
+                self.document.lines.len(),
+                KEY_HELP
+            ),
+        };
+        let status = ansi::truncate_str(&status, width, "").to_string();
+        let padding = " ".repeat(width.saturating_sub(ansi::measure_text_width(&status)));
+        display.push(format!("\x1b[7m{}{}\x1b[0m", status, padding));
+        display
+    }
+
+    fn format_row(&self, row: Row) -> String {
+        match row {
+            Row::Line(line) => self.document.lines[line].clone(),
+            Row::Folded(i) => format!(
+                "\x1b[2m▸ {} ({} lines)\x1b[0m",
+                self.document.title(i),
+                self.document.end(i) - self.document.start(i)
+            ),
+        }
+    }
+
+    fn make_rows(&mut self) {
+        let boundaries = &self.document.boundaries;
+        let mut rows = Vec::new();
+        let (mut line, mut b) = (0, 0);
+        while line < self.document.lines.len() {
+            while b < boundaries.len() && boundaries[b].0 < line {
+                b += 1;
+            }
+            match (b..boundaries.len())
+                .take_while(|i| boundaries[*i].0 == line)
+                .find(|i| self.folded.contains(i))
+            {
+                Some(i) => {
+                    rows.push(Row::Folded(i));
+                    line = self.document.end(i);
+                    b = i + 1;
+                }
+                None => {
+                    rows.push(Row::Line(line));
+                    line += 1;
+                }
+            }
+        }
+        self.rows = rows;
+        self.top = self.top.min(self.rows.len().saturating_sub(1));
+    }
+
+    fn get_row_line(&self, row: Row) -> usize {
+        match row {
+            Row::Line(line) => line,
+            Row::Folded(i) => self.document.start(i),
+        }
+    }
+
+    /// The row displaying `line` (the placeholder row, if it is folded).
+    fn get_row(&self, line: usize) -> usize {
+        self.rows
+            .partition_point(|row| self.get_row_line(*row) <= line)
+            .saturating_sub(1)
+    }
+
+    fn top_line(&self) -> usize {
+        self.rows
+            .get(self.top)
+            .map_or(0, |row| self.get_row_line(*row))
+    }
+
+    fn scroll_to(&mut self, top: usize, height: usize) {
+        let max_top = self
+            .rows
+            .len()
+            .saturating_sub(height.saturating_sub(1).max(1));
+        self.top = top.min(max_top);
+    }
+
+    /// The section at `level` containing the line at the top of the display.
+    fn get_current_section(&self, level: usize) -> Option<usize> {
+        let line = self.top_line();
+        let document = &self.document;
+        (0..document.boundaries.len()).rev().find(|i| {
+            get_level(&document.boundaries[*i].1) == level
+                && document.start(*i) <= line
+                && line < document.end(*i).max(document.start(*i) + 1)
+        })
+    }
+
+    /// Move to the next (or previous) boundary at `level` that is displayed on a different row.
+    fn jump(&mut self, level: usize, forwards: bool) {
+        let mut rows = (0..self.document.boundaries.len())
+            .filter(|i| get_level(&self.document.boundaries[*i].1) == level)
+            .map(|i| self.get_row(self.document.start(i)));
+        let row = if forwards {
+            rows.find(|row| *row > self.top)
+        } else {
+            rows.rev().find(|row| *row < self.top)
+        };
+        if let Some(row) = row {
+            self.top = row;
+        }
+    }
+
+    fn toggle_fold(&mut self, level: usize) {
+        if let Some(i) = self.get_current_section(level) {
+            if !self.folded.remove(&i) {
+                self.folded.insert(i);
+            }
+            self.make_rows();
+            self.top = self.get_row(self.document.start(i));
+        }
+    }
+
+    /// Find the first line at or after (or, if not `forwards`, at or before) `line` containing
+    /// `query`.
+    fn find(&self, query: &str, line: usize, forwards: bool) -> Option<usize> {
+        let matches = |n: &usize| self.document.stripped_lines[*n].contains(query);
+        if forwards {
+            (line..self.document.lines.len()).find(matches)
+        } else {
+            (0..=line.min(self.document.lines.len().saturating_sub(1)))
+                .rev()
+                .find(matches)
+        }
+    }
+
+    fn search_again(&mut self, forwards: bool) {
+        if let Some(query) = self.last_query.clone() {
+            let line = self.top_line();
+            let found = if forwards {
+                self.find(&query, line + 1, true)
+            } else if line > 0 {
+                self.find(&query, line - 1, false)
+            } else {
+                None
+            };
+            if let Some(line) = found {
+                self.show_line(line);
+            }
+        }
+    }
+
+    /// Unfold the sections containing `line` and move it to the top of the display.
+    fn show_line(&mut self, line: usize) {
+        let document = &self.document;
+        self.folded
+            .retain(|i| !(document.start(*i) <= line && line < document.end(*i)));
+        self.make_rows();
+        self.top = self.get_row(line);
+    }
+}
+
+// The width of the file list and its border.
+fn line_width(file_list_width: usize) -> usize {
+    if file_list_width > 0 {
+        file_list_width + 1
+    } else {
+        0
+    }
+}
+
+/// Display the diff `input` in the built-in pager until the user quits. `make_alternate_config`
+/// makes the config used when side-by-side mode is toggled. When the terminal is resized, the diff
+/// is rendered again using the new width.
+pub fn run(
+    input: &[u8],
+    config: Config,
+    make_alternate_config: &dyn Fn() -> Config,
+) -> io::Result<()> {
+    let term = Term::stdout();
+    let mut terminal = Terminal::new()?;
+    let mut stdout = io::stdout();
+    let mut configs = [Some(config), None];
+    let mut current = 0;
+    let mut pager = Pager::new(Document::new(input, configs[0].as_ref().unwrap())?);
+    let mut rendered_width = None;
+
+    write!(stdout, "\x1b[?1049h\x1b[?25l")?;
+    let result = (|| loop {
+        let (height, width) = term.size();
+        let (height, width) = (height as usize, width as usize);
+        if matches!(rendered_width.replace(width), Some(w) if w != width) {
+            for config in configs.iter_mut().flatten() {
+                config.set_terminal_width(width);
+            }
+            pager.set_document(Document::new(input, configs[current].as_ref().unwrap())?);
+        }
+        write!(
+            stdout,
+            "\x1b[H{}\x1b[0m",
+            pager.render(width, height).join("\x1b[0m\x1b[K\r\n")
+        )?;
+        stdout.flush()?;
+        let key = match terminal.read_event()? {
+            Event::Key(key) => key,
+            Event::Resize => continue,
+        };
+        match pager.handle_key(key, height) {
+            Action::Continue => {}
+            Action::Quit => return Ok(()),
+            Action::ToggleSideBySide => {
+                current = 1 - current;
+                let config = configs[current].get_or_insert_with(|| {
+                    let mut config = make_alternate_config();
+                    config.set_terminal_width(width);
+                    config
+                });
+                pager.set_document(Document::new(input, config)?);
+            }
+        }
+    })();
+    write!(stdout, "\x1b[?25h\x1b[?1049l")?;
+    stdout.flush()?;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::integration_test_utils::integration_test_utils;
+
+    fn make_pager(args: &[&str]) -> Pager {
+        let config = integration_test_utils::make_config_from_args(args);
+        Pager::new(Document::new(TWO_FILE_DIFF.as_bytes(), &config).unwrap())
+    }
+
+    const ARGS: &[&str] = &[
+        "--hunk-header-style",
+        "raw",
+        "--hunk-header-decoration-style",
+        "none",
+        "--keep-plus-minus-markers",
+    ];
+
+    // The first non-blank line at the top of the display.
+    fn top_text(pager: &Pager) -> String {
+        pager.rows[pager.top..]
+            .iter()
+            .map(|row| ansi::strip_ansi_codes(&pager.format_row(*row)))
+            .find(|text| !text.is_empty())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_boundaries() {
+        let pager = make_pager(&[]);
+        let boundaries: Vec<&Boundary> = pager
+            .document
+            .boundaries
+            .iter()
+            .map(|(_, boundary)| boundary)
+            .collect();
+        assert_eq!(
+            boundaries,
+            vec![
+                &Boundary::Commit("commit 94907c0f136f46dc46ffae2dc92dca9af7eb7c2e".to_string()),
+                &Boundary::File("a.py".to_string()),
+                &Boundary::Hunk("@@ -1,3 +1,3 @@".to_string()),
+                &Boundary::Hunk("@@ -10,3 +10,3 @@ def f():".to_string()),
+                &Boundary::File("b.py".to_string()),
+                &Boundary::Hunk("@@ -1 +1 @@".to_string()),
+            ]
+        );
+        for (start, boundary) in &pager.document.boundaries {
+            if let Boundary::Commit(title) = boundary {
+                assert!(pager.document.stripped_lines[*start].contains(title));
+            }
+        }
+    }
+
+    #[test]
+    fn test_jump_between_hunks_and_files() {
+        let mut pager = make_pager(ARGS);
+        pager.handle_key(Key::Char(']'), 100);
+        assert_eq!(top_text(&pager), "@@ -1,3 +1,3 @@");
+        pager.handle_key(Key::Char(']'), 100);
+        assert_eq!(top_text(&pager), "@@ -10,3 +10,3 @@ def f():");
+        pager.handle_key(Key::Char('}'), 100);
+        assert_eq!(top_text(&pager), "b.py");
+        pager.handle_key(Key::Char(']'), 100);
+        assert_eq!(top_text(&pager), "@@ -1 +1 @@");
+        pager.handle_key(Key::Char('{'), 100);
+        pager.handle_key(Key::Char('['), 100);
+        assert_eq!(top_text(&pager), "@@ -10,3 +10,3 @@ def f():");
+    }
+
+    #[test]
+    fn test_fold_hunk_and_file() {
+        let mut pager = make_pager(ARGS);
+        let n_rows = pager.rows.len();
+        pager.handle_key(Key::Char(']'), 100);
+        pager.handle_key(Key::Char('z'), 100);
+        assert_eq!(top_text(&pager), "▸ @@ -1,3 +1,3 @@ (5 lines)");
+        assert_eq!(pager.rows.len(), n_rows - 4);
+
+        pager.handle_key(Key::Char('Z'), 100);
+        assert!(top_text(&pager).starts_with("▸ a.py ("));
+        pager.handle_key(Key::Char(']'), 100);
+        assert_eq!(top_text(&pager), "@@ -1 +1 @@");
+
+        // Unfolding the file shows the hunk, which is still folded.
+        pager.handle_key(Key::Char('{'), 100);
+        pager.handle_key(Key::Char('{'), 100);
+        pager.handle_key(Key::Char('Z'), 100);
+        pager.handle_key(Key::Char(']'), 100);
+        assert_eq!(top_text(&pager), "▸ @@ -1,3 +1,3 @@ (5 lines)");
+        pager.handle_key(Key::Char('z'), 100);
+        assert_eq!(pager.rows.len(), n_rows);
+    }
+
+    #[test]
+    fn test_incremental_search() {
+        let mut pager = make_pager(ARGS);
+        pager.handle_key(Key::Char(']'), 100);
+        pager.handle_key(Key::Char('z'), 100);
+        pager.top = 0;
+        pager.handle_key(Key::Char('/'), 100);
+        for c in "return".chars() {
+            pager.handle_key(Key::Char(c), 100);
+        }
+        // The match is in a folded hunk, which is unfolded.
+        assert_eq!(top_text(&pager), "-    return 1");
+        pager.handle_key(Key::Enter, 100);
+        pager.handle_key(Key::Char('n'), 100);
+        assert_eq!(top_text(&pager), "+    return 2");
+        pager.handle_key(Key::Char('N'), 100);
+        assert_eq!(top_text(&pager), "-    return 1");
+
+        // Escape abandons the search, returning to where it started.
+        pager.handle_key(Key::Char('/'), 100);
+        pager.handle_key(Key::Char('y'), 100);
+        assert_eq!(top_text(&pager), "b.py");
+        pager.handle_key(Key::Char(' '), 100);
+        assert_eq!(top_text(&pager), "-y = 1");
+        pager.handle_key(Key::Escape, 100);
+        assert_eq!(top_text(&pager), "-    return 1");
+    }
+
+    #[test]
+    fn test_toggle_side_by_side_keeps_position() {
+        let mut pager = make_pager(ARGS);
+        assert_eq!(
+            pager.handle_key(Key::Char('s'), 100),
+            Action::ToggleSideBySide
+        );
+        pager.handle_key(Key::Char('}'), 100);
+        pager.handle_key(Key::Char('}'), 100);
+        pager.handle_key(Key::Char(']'), 100);
+        let config =
+            integration_test_utils::make_config_from_args(&[ARGS, &["--side-by-side"]].concat());
+        pager.set_document(Document::new(TWO_FILE_DIFF.as_bytes(), &config).unwrap());
+        assert_eq!(top_text(&pager), "@@ -1 +1 @@");
+        assert!(
+            ansi::strip_ansi_codes(&pager.document.lines[pager.document.lines.len() - 1])
+                .contains('│')
+        );
+    }
+
+    #[test]
+    fn test_set_terminal_width() {
+        // The width of the output for the files (excluding the commit metadata).
+        let max_width = |document: &Document| {
+            document.stripped_lines[document.start(1)..]
+                .iter()
+                .map(|line| ansi::measure_text_width(line))
+                .max()
+                .unwrap()
+        };
+        let mut config = integration_test_utils::make_config_from_args(&["--side-by-side"]);
+        config.set_terminal_width(61);
+        assert_eq!(config.side_by_side_data.left_panel.width, 30);
+        assert_eq!(
+            max_width(&Document::new(TWO_FILE_DIFF.as_bytes(), &config).unwrap()),
+            60
+        );
+        config.set_terminal_width(41);
+        assert_eq!(config.side_by_side_data.right_panel.width, 20);
+        assert_eq!(
+            max_width(&Document::new(TWO_FILE_DIFF.as_bytes(), &config).unwrap()),
+            40
+        );
+
+        // A width specified by --width is not affected.
+        let mut config =
+            integration_test_utils::make_config_from_args(&["--side-by-side", "--width", "50"]);
+        config.set_terminal_width(41);
+        assert_eq!(config.side_by_side_data.left_panel.width, 25);
+    }
+
+    #[test]
+    fn test_render_file_list() {
+        let mut pager = make_pager(&[]);
+        pager.handle_key(Key::Tab, 10);
+        pager.handle_key(Key::Char('}'), 10);
+        let display = pager.render(40, 10);
+        assert_eq!(display.len(), 10);
+        assert_eq!(
+            ansi::strip_ansi_codes(&display[0])
+                .chars()
+                .take(7)
+                .collect::<String>(),
+            " a.py │"
+        );
+        assert!(display[0].starts_with("\x1b[7m a.py"));
+        assert!(ansi::strip_ansi_codes(&display[1]).starts_with(" b.py │"));
+        for line in &display {
+            assert!(ansi::measure_text_width(line) <= 40);
+        }
+        assert!(ansi::strip_ansi_codes(&display[9]).starts_with("a.py  "));
+    }
+
+    const TWO_FILE_DIFF: &str = "\
+commit 94907c0f136f46dc46ffae2dc92dca9af7eb7c2e
+Author: Dan Davison <dandavison7@gmail.com>
+Date:   Wed Jul 8 11:07:59 2020 -0400
+
+    Change two files
+
+diff --git a/a.py b/a.py
+index 1111111..2222222 100644
+--- a/a.py
++++ b/a.py
+@@ -1,3 +1,3 @@
+ import os
+-x = 1
++x = 2
+ import sys
+@@ -10,3 +10,3 @@ def f():
+     pass
+-    return 1
++    return 2
+ # end
+diff --git a/b.py b/b.py
+index 3333333..4444444 100644
+--- a/b.py
++++ b/b.py
+@@ -1 +1 @@
+-y = 1
++y = 2
+";
+}
@@ -0,0 +1,227 @@
+use console::Key;
+
+pub enum Event {
+    Key(Key),
+    Resize,
+}
+
+#[cfg(unix)]
+pub use self::unix::Terminal;
+
+#[cfg(not(unix))]
+pub use self::other::Terminal;
+
+#[cfg(unix)]
+mod unix {
+    use std::collections::VecDeque;
+    use std::fs::File;
+    use std::io::{self, ErrorKind, Read};
+    use std::mem;
+    use std::os::unix::io::AsRawFd;
+    use std::ptr;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use super::{parse_key, Event};
+
+    static RESIZED: AtomicBool = AtomicBool::new(false);
+
+    extern "C" fn handle_sigwinch(_: libc::c_int) {
+        RESIZED.store(true, Ordering::SeqCst);
+    }
+
+    /// The controlling terminal, in raw mode, from which the built-in pager reads keys. Since
+    /// stdin is the diff, keys are read from /dev/tty.
+    ///
+    /// A SIGWINCH handler is installed so that a resize interrupts a blocking read; the original
+    /// terminal settings and signal handler are restored on drop.
+    pub struct Terminal {
+        tty: File,
+        original_termios: libc::termios,
+        original_sigwinch_action: libc::sigaction,
+        pending: VecDeque<u8>,
+    }
+
+    impl Terminal {
+        pub fn new() -> io::Result<Self> {
+            let tty = File::open("/dev/tty")?;
+            let fd = tty.as_raw_fd();
+            unsafe {
+                let mut termios = mem::zeroed();
+                if libc::tcgetattr(fd, &mut termios) != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                let original_termios = termios;
+                libc::cfmakeraw(&mut termios);
+                if libc::tcsetattr(fd, libc::TCSADRAIN, &termios) != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+
+                let mut action: libc::sigaction = mem::zeroed();
+                action.sa_sigaction =
+                    handle_sigwinch as extern "C" fn(libc::c_int) as libc::sighandler_t;
+                libc::sigemptyset(&mut action.sa_mask);
+                let mut original_sigwinch_action = mem::zeroed();
+                libc::sigaction(libc::SIGWINCH, &action, &mut original_sigwinch_action);
+
+                Ok(Self {
+                    tty,
+                    original_termios,
+                    original_sigwinch_action,
+                    pending: VecDeque::new(),
+                })
+            }
+        }
+
+        /// Wait for a key press or for the terminal to be resized.
+        pub fn read_event(&mut self) -> io::Result<Event> {
+            loop {
+                if RESIZED.swap(false, Ordering::SeqCst) {
+                    return Ok(Event::Resize);
+                }
+                if !self.pending.is_empty() {
+                    let bytes = self.pending.make_contiguous();
+                    let (key, n) = parse_key(bytes);
+                    self.pending.drain(..n);
+                    return Ok(Event::Key(key));
+                }
+                let mut pollfd = libc::pollfd {
+                    fd: self.tty.as_raw_fd(),
+                    events: libc::POLLIN,
+                    revents: 0,
+                };
+                // The timeout bounds the delay if SIGWINCH arrives just before poll is called.
+                if unsafe { libc::poll(&mut pollfd, 1, 200) } < 0 {
+                    match io::Error::last_os_error() {
+                        error if error.kind() == ErrorKind::Interrupted => continue,
+                        error => return Err(error),
+                    }
+                }
+                if pollfd.revents & libc::POLLIN != 0 {
+                    let mut buf = [0; 64];
+                    match self.tty.read(&mut buf)? {
+                        0 => return Err(io::Error::from(ErrorKind::UnexpectedEof)),
+                        n => self.pending.extend(&buf[..n]),
+                    }
+                }
+            }
+        }
+    }
+
+    impl Drop for Terminal {
+        fn drop(&mut self) {
+            unsafe {
+                libc::tcsetattr(
+                    self.tty.as_raw_fd(),
+                    libc::TCSADRAIN,
+                    &self.original_termios,
+                );
+                libc::sigaction(
+                    libc::SIGWINCH,
+                    &self.original_sigwinch_action,
+                    ptr::null_mut(),
+                );
+            }
+        }
+    }
+}
+
+#[cfg(not(unix))]
+mod other {
+    use std::io;
+
+    use console::Term;
+
+    use super::Event;
+
+    /// The terminal from which the built-in pager reads keys. Resizing is not signalled on this
+    /// platform; the pager notices a new size when it next redraws.
+    pub struct Terminal {
+        term: Term,
+    }
+
+    impl Terminal {
+        pub fn new() -> io::Result<Self> {
+            Ok(Self {
+                term: Term::stdout(),
+            })
+        }
+
+        pub fn read_event(&mut self) -> io::Result<Event> {
+            self.term.read_key().map(Event::Key)
+        }
+    }
+}
+
+/// Parse the key at the start of `bytes` (which must not be empty), returning the key and the
+/// number of bytes it occupies.
+#[cfg_attr(not(unix), allow(dead_code))]
+fn parse_key(bytes: &[u8]) -> (Key, usize) {
+    match bytes {
+        [b'\x1b', b'[', c, ..] | [b'\x1b', b'O', c, ..] if c.is_ascii_alphabetic() => {
+            let key = match c {
+                b'A' => Key::ArrowUp,
+                b'B' => Key::ArrowDown,
+                b'C' => Key::ArrowRight,
+                b'D' => Key::ArrowLeft,
+                b'H' => Key::Home,
+                b'F' => Key::End,
+                b'Z' => Key::BackTab,
+                _ => Key::UnknownEscSeq(vec![*c as char]),
+            };
+            (key, 3)
+        }
+        [b'\x1b', b'[', ..] => {
+            // E.g. "\x1b[5~" (PageUp): parameters terminated by a character in the range @ to ~.
+            let n = bytes[2..]
+                .iter()
+                .position(|b| (b'@'..=b'~').contains(b))
+                .map_or(bytes.len(), |i| i + 3);
+            let key = match &bytes[2..n] {
+                b"1~" | b"7~" => Key::Home,
+                b"2~" => Key::Insert,
+                b"3~" => Key::Del,
+                b"4~" | b"8~" => Key::End,
+                b"5~" => Key::PageUp,
+                b"6~" => Key::PageDown,
+                sequence => Key::UnknownEscSeq(sequence.iter().map(|b| *b as char).collect()),
+            };
+            (key, n)
+        }
+        [b'\x1b', ..] => (Key::Escape, 1),
+        [b'\r', ..] | [b'\n', ..] => (Key::Enter, 1),
+        [b'\x7f', ..] | [b'\x08', ..] => (Key::Backspace, 1),
+        [b'\t', ..] => (Key::Tab, 1),
+        _ => {
+            let n = match bytes[0] {
+                b if b & 0xe0 == 0xc0 => 2,
+                b if b & 0xf0 == 0xe0 => 3,
+                b if b & 0xf8 == 0xf0 => 4,
+                _ => 1,
+            }
+            .min(bytes.len());
+            match std::str::from_utf8(&bytes[..n]) {
+                Ok(s) => (Key::Char(s.chars().next().unwrap()), n),
+                Err(_) => (Key::Unknown, 1),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_key() {
+        assert_eq!(parse_key(b"q"), (Key::Char('q'), 1));
+        assert_eq!(parse_key(b"]]"), (Key::Char(']'), 1));
+        assert_eq!(parse_key("›x".as_bytes()), (Key::Char('›'), 3));
+        assert_eq!(parse_key(b"\x1b[Bj"), (Key::ArrowDown, 3));
+        assert_eq!(parse_key(b"\x1bOA"), (Key::ArrowUp, 3));
+        assert_eq!(parse_key(b"\x1b[6~"), (Key::PageDown, 4));
+        assert_eq!(parse_key(b"\x1b"), (Key::Escape, 1));
+        assert_eq!(parse_key(b"\r"), (Key::Enter, 1));
+        assert_eq!(parse_key(b"\x7f"), (Key::Backspace, 1));
+        assert_eq!(parse_key(b"\x03"), (Key::Char('\x03'), 1));
+    }
+}
@@ -1,7 +1,7 @@
 use std::collections::{HashMap, HashSet};
-#[cfg(test)]
 use std::ffi::OsString;
 use std::path::PathBuf;
+use std::process;
 
 use lazy_static::lazy_static;
 use structopt::clap::AppSettings::{ColorAlways, ColoredHelp, DeriveDisplayOrder};
@@ -11,6 +11,7 @@ use syntect::parsing::SyntaxSet;
 
 use crate::bat_utils::assets::HighlightingAssets;
 use crate::bat_utils::output::PagingMode;
+use crate::errors;
 use crate::git_config::GitConfig;
 use crate::git_config_entry::GitConfigEntry;
 use crate::options;
@@ -230,9 +231,22 @@ pub struct Opt {
     #[structopt(long = "navigate")]
     /// Activate diff navigation: use n to jump forwards and N to jump backwards. To change the
     /// file labels used see --file-modified-label, --file-removed-label, --file-added-label,
-    /// --file-renamed-label.
+    /// --file-renamed-label. To change where n and N jump to see --navigate-targets and
+    /// --navigate-regex.
     pub navigate: bool,
 
+    #[structopt(long = "navigate-targets", default_value = "commits files")]
+    /// The places that n and N jump to under --navigate (space-separated): any of "commits",
+    /// "files" (file headers, recognized by their labels), and "hunks" (hunk headers, recognized
+    /// by --hunk-label, which must then be set, or by their "@@" prefix under
+    /// --hunk-header-style=raw).
+    pub navigate_targets: String,
+
+    #[structopt(long = "navigate-regex")]
+    /// A regular expression (in the syntax understood by less) matching additional lines that n
+    /// and N jump to under --navigate.
+    pub navigate_regex: Option<String>,
+
     #[structopt(long = "hyperlinks")]
     /// Render commit hashes, file names, and line numbers as hyperlinks, according to the
     /// hyperlink spec for terminal emulators:
@@ -250,6 +264,14 @@ pub struct Opt {
     /// does not emit any prefix, so code can be copied directly from delta's output.
     pub keep_plus_minus_markers: bool,
 
+    #[structopt(long = "relative-paths")]
+    /// Display file paths in file headers and hunk headers relative to the current directory,
+    /// rather than to the root of the repository, when delta is run inside a git repository (e.g.
+    /// "../README.md" when running git diff in a subdirectory). Hyperlinks still link to the
+    /// files' absolute paths. Do not use this together with git diff --relative, whose paths are
+    /// already relative to the current directory.
+    pub relative_paths: bool,
+
     /// Display the active values for all Delta options. Style options are displayed with
     /// foreground and background colors. This can be used to experiment with colors by combining
     /// this option with other options such as --minus-style, --zero-style, --plus-style, --light,
@@ -271,6 +293,18 @@ pub struct Opt {
     #[structopt(long = "show-syntax-themes")]
     pub show_syntax_themes: bool,
 
+    /// Show the changes in a revision range such as HEAD~3..HEAD, with their commit metadata, as
+    /// `git log -p` would, reading them from the repository rather than from standard input. A
+    /// single revision shows the changes between it and the working tree, as `git diff REV` would.
+    #[structopt(long = "rev")]
+    pub rev: Option<String>,
+
+    /// Show the changes staged for the next commit, as `git diff --staged` would, reading them from
+    /// the repository rather than from standard input. Combined with a single revision given by
+    /// --rev, show the changes between that revision and the index.
+    #[structopt(long = "staged")]
+    pub staged: bool,
+
     #[structopt(long = "no-gitconfig")]
     /// Do not take any settings from git config. See GIT CONFIG section.
     pub no_gitconfig: bool,
@@ -298,6 +332,36 @@ pub struct Opt {
     /// --syntax-theme=none disables all syntax highlighting.
     pub syntax_theme: Option<String>,
 
+    #[structopt(long = "context-lines", default_value = "0")]
+    /// Extend each hunk with this many additional lines of unchanged context before and after the
+    /// context lines emitted by git, merging hunks whose context then overlaps. The lines are read
+    /// from the git repository using the blob ids in the "index" line of the diff (or from the
+    /// working tree file, if it is the post-image), so delta must be run inside the repository.
+    /// Hunks of files whose contents are not available are left unchanged.
+    pub context_lines: usize,
+
+    #[structopt(long = "full-file-syntax-highlighting")]
+    /// Syntax-highlight each hunk using the parser state reached by highlighting the complete file
+    /// up to the start of the hunk, so that, for example, code inside a multiline string or
+    /// comment that began before the hunk is highlighted correctly. This requires delta to be run
+    /// inside the git repository, from which the full files are read using the blob ids in the
+    /// "index" line of the diff. When they are not available, each hunk is highlighted in
+    /// isolation, as usual.
+    pub full_file_syntax_highlighting: bool,
+
+    #[structopt(long = "highlight-cache")]
+    /// Store the syntax highlighting of lines in an on-disk cache, so that lines that are
+    /// displayed repeatedly, such as those in the commits of `git log -p`, are highlighted only
+    /// once. The cache is kept in delta's cache directory (the one used for custom syntaxes and
+    /// themes, e.g. ~/.cache/bat), and is discarded when the syntax or theme definitions change.
+    /// See --highlight-cache-max-size.
+    pub highlight_cache: bool,
+
+    #[structopt(long = "highlight-cache-max-size", default_value = "64")]
+    /// The maximum size, in megabytes, of the cache of syntax highlighting used by
+    /// --highlight-cache. The entries that have not been used recently are removed first.
+    pub highlight_cache_max_size: usize,
+
     #[structopt(long = "minus-style", default_value = "normal auto")]
     /// Style (foreground, background, attributes) for removed lines. See STYLES section.
     pub minus_style: String,
@@ -368,12 +432,72 @@ pub struct Opt {
     /// style 'omit' can be used to remove the hunk header section from the output.
     pub hunk_header_style: String,
 
+    #[structopt(long = "hunk-header-enclosing-scope")]
+    /// Show the definitions (functions, classes, impls, modules, etc) enclosing each hunk in the
+    /// hunk header, as a breadcrumb such as "mod foo › impl Bar › fn baz", in place of the code
+    /// fragment supplied by git. The definitions are found by parsing the pre-image of the file
+    /// with the syntax definition for its language, so delta must be run inside the git
+    /// repository, from which the file is read using the blob id in the "index" line of the diff.
+    /// When it is not available, or no enclosing definition is found, git's code fragment is
+    /// shown.
+    pub hunk_header_enclosing_scope: bool,
+
     #[structopt(long = "hunk-header-decoration-style", default_value = "blue box")]
     /// Style (foreground, background, attributes) for the hunk-header decoration. See STYLES
     /// section. The style string should contain one of the special attributes 'box', 'ul'
     /// (underline), 'ol' (overline), or the combination 'ul ol'.
     pub hunk_header_decoration_style: String,
 
+    #[structopt(long = "hunk-header-format", default_value = "{fragment}")]
+    /// Format string for the hunk header. The placeholders {path}, {minus_start}, {minus_len},
+    /// {plus_start} and {plus_len} are replaced by the path of the file and the line numbers and
+    /// lengths of the hunk in the old (minus) and new (plus) versions of the file, and {fragment}
+    /// by the code fragment from the hunk header line. Each placeholder is styled using its own
+    /// option: --hunk-header-file-style for {path}, --hunk-header-minus-start-style for
+    /// {minus_start}, and so on. Everything else is styled using --hunk-header-style. When
+    /// --hyperlinks is in effect, each placeholder is a hyperlink to the file at the first line of
+    /// the hunk. For example, with --file-style=omit, the format
+    /// '{path}:{plus_start} {fragment}' makes each hunk header self-describing. If the format
+    /// contains a line number, then the first line number of the hunk is not also displayed below
+    /// the hunk header. This has no effect under --hunk-header-style=raw or omit.
+    pub hunk_header_format: String,
+
+    #[structopt(long = "hunk-header-file-style", default_value = "blue")]
+    /// Style (foreground, background, attributes) for the file path in the hunk header. See
+    /// --hunk-header-format and STYLES section.
+    pub hunk_header_file_style: String,
+
+    #[structopt(long = "hunk-header-line-number-style", default_value = "blue")]
+    /// Style (foreground, background, attributes) for the line numbers and lengths in the hunk
+    /// header, unless set by the option for each of them. See --hunk-header-format and STYLES
+    /// section.
+    pub hunk_header_line_number_style: String,
+
+    #[structopt(long = "hunk-header-minus-start-style", default_value = "")]
+    /// Style for the first line number of the hunk in the old version of the file
+    /// ({minus_start}). By default, --hunk-header-line-number-style is used.
+    pub hunk_header_minus_start_style: String,
+
+    #[structopt(long = "hunk-header-minus-len-style", default_value = "")]
+    /// Style for the length of the hunk in the old version of the file ({minus_len}). By default,
+    /// --hunk-header-line-number-style is used.
+    pub hunk_header_minus_len_style: String,
+
+    #[structopt(long = "hunk-header-plus-start-style", default_value = "")]
+    /// Style for the first line number of the hunk in the new version of the file ({plus_start}).
+    /// By default, --hunk-header-line-number-style is used.
+    pub hunk_header_plus_start_style: String,
+
+    #[structopt(long = "hunk-header-plus-len-style", default_value = "")]
+    /// Style for the length of the hunk in the new version of the file ({plus_len}). By default,
+    /// --hunk-header-line-number-style is used.
+    pub hunk_header_plus_len_style: String,
+
+    #[structopt(long = "hunk-header-fragment-style", default_value = "")]
+    /// Style for the code fragment in the hunk header ({fragment}). By default,
+    /// --hunk-header-style is used.
+    pub hunk_header_fragment_style: String,
+
     /// The regular expression used to decide what a word is for the within-line highlight
     /// algorithm. For less fine-grained matching than the default try --word-diff-regex="\S+"
     /// --max-line-distance=1.0 (this is more similar to `git --word-diff`).
@@ -386,6 +510,67 @@ pub struct Opt {
     #[structopt(long = "max-line-distance", default_value = "0.6")]
     pub max_line_distance: f64,
 
+    /// The maximum distance between a removed token and the token that replaced it for only the
+    /// characters that differ between them to be emphasized, rather than the whole token. The
+    /// distance is the proportion of characters (grapheme clusters) that must be deleted, inserted,
+    /// or substituted to transform one into the other. For example, with --max-token-distance=0.3
+    /// a one-character typo fix in an identifier emphasizes that character only. The default, 0,
+    /// always emphasizes whole tokens (see --word-diff-regex).
+    #[structopt(long = "max-token-distance", default_value = "0")]
+    pub max_token_distance: f64,
+
+    /// Do not emphasize changes to whitespace within lines. For example, a line whose indentation
+    /// has changed is not emphasized at all; see also --whitespace-change-style.
+    #[structopt(long = "whitespace-insensitive-emph")]
+    pub whitespace_insensitive_emph: bool,
+
+    /// Display the whitespace that differs between a removed line and the added line paired with
+    /// it visibly: spaces as '·' and tabs as '→'. Unchanged whitespace is displayed as usual.
+    #[structopt(long = "show-whitespace-changes")]
+    pub show_whitespace_changes: bool,
+
+    /// Add a note to the file header when the line endings of a file have changed, e.g.
+    /// "line endings: CRLF → LF". The lines of each file are read before its header is displayed.
+    #[structopt(long = "line-ending-note")]
+    pub line_ending_note: bool,
+
+    /// The encoding of files whose lines are not valid UTF-8, unless set by the
+    /// working-tree-encoding attribute in .gitattributes. UTF-16 files are detected by their byte
+    /// order mark. Supported encodings are utf-8, utf-16le, utf-16be, latin1 (iso-8859-1),
+    /// windows-1252 (cp1252), and shift_jis (sjis).
+    #[structopt(long = "default-encoding", default_value = "utf-8")]
+    pub default_encoding: String,
+
+    /// For binary files in a git repository, display the regions in which the two versions differ
+    /// as a side-by-side hex and ASCII dump, below a line giving their sizes. Changed bytes are
+    /// styled with minus-emph-style and plus-emph-style.
+    #[structopt(long = "hex-diff")]
+    pub hex_diff: bool,
+
+    /// Under --hex-diff, the size in bytes above which a binary file is not dumped; only its size
+    /// is displayed.
+    #[structopt(long = "hex-diff-max-size", default_value = "65536")]
+    pub hex_diff_max_size: usize,
+
+    /// For PNG, JPEG and GIF images in a git repository that git reports as binary files, display
+    /// the dimensions and sizes of the two versions, followed by thumbnails of them side by side if
+    /// the terminal supports graphics (see --image-protocol). The graphics escape
+    /// sequences must reach the terminal unaltered, e.g. with --paging=never. SVG images are not
+    /// supported: git diffs them as text, and displaying them would require rasterizing them.
+    #[structopt(long = "image-thumbnails")]
+    pub image_thumbnails: bool,
+
+    /// The terminal graphics protocol used to display images under --image-thumbnails: kitty
+    /// (PNG images only), iterm2 (PNG, JPEG and GIF images), sixel (PNG images only), or none to
+    /// display only their dimensions and sizes. The default, auto, chooses a protocol based on the
+    /// TERM and TERM_PROGRAM environment variables.
+    #[structopt(long = "image-protocol", default_value = "auto")]
+    pub image_protocol: String,
+
+    /// The height in terminal rows of the thumbnails displayed under --image-thumbnails.
+    #[structopt(long = "image-thumbnail-height", default_value = "8")]
+    pub image_thumbnail_height: usize,
+
     /// Style (foreground, background, attributes) for line numbers in the old (minus) version of
     /// the file. See STYLES and LINE NUMBERS sections.
     #[structopt(long = "line-numbers-minus-style", default_value = "auto")]
@@ -445,6 +630,90 @@ pub struct Opt {
     /// Text to display in front of a renamed file path.
     pub file_renamed_label: String,
 
+    #[structopt(long = "file-rename-format", default_value = "{diff}")]
+    /// Format string for the paths of a renamed or copied file in its file header. The
+    /// placeholders "{old}" and "{new}" are replaced by the old and new paths, and "{diff}" by a
+    /// compact form showing only the path components that changed, e.g.
+    /// "src/{features → plugins}/navigate.rs". The changed components are displayed using
+    /// minus-emph-style and plus-emph-style. For example, the format "{old} ⟶   {new}" displays
+    /// both paths in full.
+    pub file_rename_format: String,
+
+    #[structopt(long = "file-header-format", default_value = "")]
+    /// Format string for the file header. The placeholders {event}, {path}, {old_path}, {mode},
+    /// {notes}, {added}, {removed}, {language} and {icon} are replaced by the label of the change
+    /// to the file (see --file-modified-label etc), the path of the file (formatted according to
+    /// --file-rename-format for a renamed or copied file), the old path of a renamed or copied
+    /// file, the changes to the file's mode and symlink target, the other notes about the file
+    /// (similarity, emptiness, line endings), the numbers of lines added and removed, the name of
+    /// the language used to highlight the file, and the icon of the file from --file-icons. Each
+    /// placeholder is styled using the corresponding --file-header-*-style, and everything else
+    /// using --file-style. For example, '{icon} {path} ({language}) +{added} -{removed}'. Under
+    /// --navigate, the format should begin with {event}, by which file headers are found. By
+    /// default, the file header consists of the label and path, followed by any notes in
+    /// parentheses.
+    pub file_header_format: String,
+
+    #[structopt(long = "file-icons", default_value = "")]
+    /// Icons for the {icon} placeholder of --file-header-format, as a space-separated list of
+    /// entries of the form EXTENSION=ICON, e.g. "rs=🦀 py=🐍". Files without an extension are
+    /// matched by their name, e.g. "Makefile=M". A Nerd Font provides icons for many file types.
+    pub file_icons: String,
+
+    #[structopt(long = "file-header-event-style", default_value = "")]
+    /// Style (foreground, background, attributes) for the label of the change to the file ({event})
+    /// in the file header. See --file-header-format and STYLES section. By default, --file-style is
+    /// used.
+    pub file_header_event_style: String,
+
+    #[structopt(long = "file-header-path-style", default_value = "")]
+    /// Style (foreground, background, attributes) for the file path ({path}) in the file header.
+    /// See --file-header-format and STYLES section. By default, --file-style is used.
+    pub file_header_path_style: String,
+
+    #[structopt(long = "file-header-old-path-style", default_value = "")]
+    /// Style (foreground, background, attributes) for the old path of a renamed or copied file
+    /// ({old_path}) in the file header. See --file-header-format and STYLES section. By default,
+    /// --file-style is used.
+    pub file_header_old_path_style: String,
+
+    #[structopt(long = "file-header-mode-style", default_value = "")]
+    /// Style (foreground, background, attributes) for the mode of the file ({mode}) in the file
+    /// header. See --file-header-format and STYLES section. By default, --file-style is used.
+    pub file_header_mode_style: String,
+
+    #[structopt(long = "file-header-notes-style", default_value = "")]
+    /// Style (foreground, background, attributes) for the other notes about the file ({notes}) in
+    /// the file header. See --file-header-format and STYLES section. By default, --file-style is
+    /// used.
+    pub file_header_notes_style: String,
+
+    #[structopt(long = "file-header-added-style", default_value = "")]
+    /// Style (foreground, background, attributes) for the number of lines added ({added}) in the
+    /// file header. See --file-header-format and STYLES section. By default, --file-style is used.
+    pub file_header_added_style: String,
+
+    #[structopt(long = "file-header-removed-style", default_value = "")]
+    /// Style (foreground, background, attributes) for the number of lines removed ({removed}) in
+    /// the file header. See --file-header-format and STYLES section. By default, --file-style is
+    /// used.
+    pub file_header_removed_style: String,
+
+    #[structopt(long = "file-header-language-style", default_value = "")]
+    /// Style (foreground, background, attributes) for the language of the file ({language}) in the
+    /// file header. See --file-header-format and STYLES section. By default, --file-style is used.
+    pub file_header_language_style: String,
+
+    #[structopt(long = "file-header-icon-style", default_value = "")]
+    /// Style (foreground, background, attributes) for the icon of the file ({icon}) in the file
+    /// header. See --file-header-format and STYLES section. By default, --file-style is used.
+    pub file_header_icon_style: String,
+
+    #[structopt(long = "hunk-label", default_value = "")]
+    /// Text to display in front of the hunk header. Under --navigate, when --navigate-targets
+    /// includes "hunks", this is used to find hunk headers, and must therefore be set.
+    pub hunk_label: String,
+
     #[structopt(long = "max-line-length", default_value = "512")]
     /// Truncate lines longer than this. To prevent any truncation, set to zero. Note that
     /// syntax-highlighting very long lines (e.g. minified .js) will be very slow if they are not
@@ -464,6 +733,14 @@ pub struct Opt {
     #[structopt(long = "tabs", default_value = "4")]
     pub tab_width: usize,
 
+    /// The number of threads with which to parse and syntax-highlight the diff. 1 (the default)
+    /// means that the diff is processed on a single thread, one line at a time. With more threads,
+    /// the files of the diff are read ahead and highlighted in parallel, and are output in their
+    /// original order; each file is buffered whole, so output is not streamed as it is with
+    /// --line-buffer-size. 0 means one thread per CPU.
+    #[structopt(long = "threads", default_value = "1")]
+    pub threads: usize,
+
     /// Whether to emit 24-bit ("true color") RGB color codes. Options are auto, always, and never.
     /// "auto" means that delta will emit 24-bit color codes if the environment variable COLORTERM
     /// has the value "truecolor" or "24bit". If your terminal application (the application you use
@@ -478,9 +755,13 @@ pub struct Opt {
     #[structopt(long = "inspect-raw-lines", default_value = "true")]
     pub inspect_raw_lines: String,
 
-    /// Whether to use a pager when displaying output. Options are: auto, always, and never. The
-    /// default pager is `less`: this can be altered by setting the environment variables
-    /// DELTA_PAGER, BAT_PAGER, or PAGER (and that is their order of priority).
+    /// Whether to use a pager when displaying output. Options are: auto, always, never, and
+    /// builtin. The default pager is `less`: this can be altered by setting the environment
+    /// variables DELTA_PAGER, BAT_PAGER, or PAGER (and that is their order of priority). "builtin"
+    /// selects delta's own interactive pager, which understands the structure of the diff: use ]
+    /// and [ to jump between hunks, } and { to jump between files, z and Z to fold the current
+    /// hunk and file, Tab to show a list of files, / to search, s to toggle side-by-side mode,
+    /// and q to quit.
     #[structopt(long = "paging", default_value = "auto")]
     pub paging_mode: String,
 
@@ -509,6 +790,26 @@ pub struct Opt {
     #[structopt(long = "whitespace-error-style", default_value = "auto auto")]
     pub whitespace_error_style: String,
 
+    /// Style for pairs of removed and added lines that differ only in whitespace, such as lines
+    /// that have been re-indented. By default these are styled as other removed and added lines.
+    #[structopt(long = "whitespace-change-style", default_value = "")]
+    pub whitespace_change_style: String,
+
+    /// Style for the '␍' marker displayed at the end of a removed or added line whose CRLF line
+    /// ending differs from that of the line paired with it.
+    #[structopt(long = "line-ending-style", default_value = "bold yellow")]
+    pub line_ending_style: String,
+
+    /// Style for the 'BOM' marker displayed at the start of a removed or added line whose byte
+    /// order mark is absent from the line paired with it.
+    #[structopt(long = "bom-style", default_value = "bold yellow")]
+    pub bom_style: String,
+
+    /// Style for the marker displayed at the end of the last line of a file that has no newline at
+    /// the end, in place of the "\ No newline at end of file" line.
+    #[structopt(long = "no-newline-style", default_value = "yellow reverse")]
+    pub no_newline_style: String,
+
     #[structopt(long = "line-buffer-size", default_value = "32")]
     /// Size of internal line buffer. Delta compares the added and removed versions of nearby lines
     /// in order to detect and highlight changes at the level of individual words/tokens.
@@ -575,10 +876,8 @@ pub struct ComputedValues {
     pub available_terminal_width: usize,
     pub background_color_extends_to_terminal_width: bool,
     pub decorations_width: Width,
-    pub inspect_raw_lines: InspectRawLines,
     pub is_light_mode: bool,
     pub line_numbers_mode: LineNumbersMode,
-    pub paging_mode: PagingMode,
     pub syntax_dummy_theme: SyntaxTheme,
     pub syntax_set: SyntaxSet,
     pub syntax_theme: Option<SyntaxTheme>,
@@ -597,6 +896,15 @@ impl Default for Width {
     }
 }
 
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum ImageProtocol {
+    Kitty,
+    Iterm2,
+    Sixel,
+    #[default]
+    None,
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum InspectRawLines {
     True,
@@ -634,9 +942,9 @@ impl Opt {
         assets: HighlightingAssets,
     ) -> Self {
         Self::from_clap_and_git_config(Self::clap().get_matches(), git_config, assets)
+            .unwrap_or_else(exit_with_error)
     }
 
-    #[cfg(test)]
     pub fn from_iter_and_git_config<I>(iter: I, git_config: &mut Option<GitConfig>) -> Self
     where
         I: IntoIterator,
@@ -644,17 +952,36 @@ impl Opt {
     {
         let assets = HighlightingAssets::new();
         Self::from_clap_and_git_config(Self::clap().get_matches_from(iter), git_config, assets)
+            .unwrap_or_else(exit_with_error)
+    }
+
+    /// As from_iter_and_git_config, but return an error rather than exiting if the arguments or
+    /// option values are invalid.
+    pub fn from_iter_safe_and_git_config<I>(
+        iter: I,
+        git_config: &mut Option<GitConfig>,
+    ) -> errors::Result<Self>
+    where
+        I: IntoIterator,
+        I::Item: Into<OsString> + Clone,
+    {
+        let assets = HighlightingAssets::new();
+        Self::from_clap_and_git_config(
+            Self::clap().get_matches_from_safe(iter)?,
+            git_config,
+            assets,
+        )
     }
 
     fn from_clap_and_git_config(
         arg_matches: clap::ArgMatches,
         git_config: &mut Option<GitConfig>,
         assets: HighlightingAssets,
-    ) -> Self {
+    ) -> errors::Result<Self> {
         let mut opt = Opt::from_clap(&arg_matches);
-        options::rewrite::apply_rewrite_rules(&mut opt, &arg_matches);
-        options::set::set_options(&mut opt, git_config, &arg_matches, assets);
-        opt
+        options::rewrite::apply_rewrite_rules(&mut opt, &arg_matches)?;
+        options::set::set_options(&mut opt, git_config, &arg_matches, assets)?;
+        Ok(opt)
     }
 
     #[allow(dead_code)]
@@ -676,9 +1003,15 @@ impl Opt {
     }
 }
 
+fn exit_with_error(error: errors::Error) -> Opt {
+    eprintln!("{}", error);
+    process::exit(1);
+}
+
 // Option names to exclude when listing options to process for various purposes. These are
 // (1) Deprecated options
 // (2) Pseudo-flag commands such as --list-languages
+// (3) Options specifying the input, such as --rev
 lazy_static! {
     static ref IGNORED_OPTION_NAMES: HashSet<&'static str> = vec![
         "deprecated-file-color",
@@ -693,8 +1026,10 @@ lazy_static! {
         "deprecated-commit-color",
         "list-languages",
         "list-syntax-themes",
+        "rev",
         "show-config",
         "show-syntax-themes",
+        "staged",
     ]
     .into_iter()
     .collect();
@@ -1,5 +1,4 @@
 use std::collections::HashMap;
-use std::process;
 use std::str::FromStr;
 
 use ansi_term::Color;
@@ -7,26 +6,24 @@ use lazy_static::lazy_static;
 use syntect::highlighting::Color as SyntectColor;
 
 use crate::bat_utils::terminal::to_ansi_color;
+use crate::errors::*;
 use crate::syntect_color;
 
-pub fn parse_color(s: &str, true_color: bool) -> Option<Color> {
+/// Parse the color `s`, returning None for "normal" and an error if `s` is not a color.
+pub fn parse_color(s: &str, true_color: bool) -> Result<Option<Color>> {
     if s == "normal" {
-        return None;
+        return Ok(None);
     }
-    let die = || {
-        eprintln!("Invalid color or style attribute: {}", s);
-        process::exit(1);
-    };
     let syntect_color = if s.starts_with('#') {
-        SyntectColor::from_str(s).unwrap_or_else(|_| die())
+        SyntectColor::from_str(s).ok()
     } else {
         s.parse::<u8>()
             .ok()
             .and_then(syntect_color::syntect_color_from_ansi_number)
             .or_else(|| syntect_color::syntect_color_from_ansi_name(s))
-            .unwrap_or_else(die)
-    };
-    Some(to_ansi_color(syntect_color, true_color))
+    }
+    .ok_or_else(|| format!("Invalid color or style attribute: {}", s))?;
+    Ok(Some(to_ansi_color(syntect_color, true_color)))
 }
 
 pub fn color_to_string(color: Color) -> String {
@@ -1,6 +1,9 @@
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::convert::TryFrom;
+use std::path::{Path, PathBuf};
 use std::process;
+use std::str::FromStr;
+use std::sync::Mutex;
 
 use regex::Regex;
 use structopt::clap;
@@ -8,78 +11,153 @@ use syntect::highlighting::Style as SyntectStyle;
 use syntect::highlighting::Theme as SyntaxTheme;
 use syntect::parsing::SyntaxSet;
 
+use crate::bat_utils::assets;
+use crate::bat_utils::dirs::PROJECT_DIRS;
 use crate::bat_utils::output::PagingMode;
 use crate::cli;
 use crate::color;
 use crate::delta::State;
+use crate::encoding::Encoding;
 use crate::env;
+use crate::errors;
+use crate::features::navigate;
 use crate::features::side_by_side;
+use crate::format;
+use crate::git_config::GitConfig;
 use crate::git_config_entry::GitConfigEntry;
+use crate::highlight_cache::HighlightCache;
 use crate::style::{self, Style};
 
 pub struct Config {
-    pub available_terminal_width: usize,
-    pub background_color_extends_to_terminal_width: bool,
-    pub commit_style: Style,
-    pub color_only: bool,
-    pub decorations_width: cli::Width,
-    pub error_exit_code: i32,
-    pub file_added_label: String,
-    pub file_copied_label: String,
-    pub file_modified_label: String,
-    pub file_removed_label: String,
-    pub file_renamed_label: String,
-    pub file_style: Style,
-    pub git_config_entries: HashMap<String, GitConfigEntry>,
-    pub hunk_header_style: Style,
-    pub hyperlinks: bool,
-    pub hyperlinks_file_link_format: String,
-    pub inspect_raw_lines: cli::InspectRawLines,
-    pub keep_plus_minus_markers: bool,
-    pub line_numbers: bool,
-    pub line_numbers_left_format: String,
-    pub line_numbers_left_style: Style,
-    pub line_numbers_minus_style: Style,
-    pub line_numbers_plus_style: Style,
-    pub line_numbers_right_format: String,
-    pub line_numbers_right_style: Style,
-    pub line_numbers_show_first_line_number: bool,
-    pub line_numbers_zero_style: Style,
-    pub line_buffer_size: usize,
-    pub max_line_distance: f64,
-    pub max_line_distance_for_naively_paired_lines: f64,
-    pub max_line_length: usize,
-    pub minus_emph_style: Style,
-    pub minus_empty_line_marker_style: Style,
-    pub minus_file: Option<PathBuf>,
-    pub minus_non_emph_style: Style,
-    pub minus_style: Style,
-    pub navigate: bool,
-    pub null_style: Style,
-    pub null_syntect_style: SyntectStyle,
-    pub paging_mode: PagingMode,
-    pub plus_emph_style: Style,
-    pub plus_empty_line_marker_style: Style,
-    pub plus_file: Option<PathBuf>,
-    pub plus_non_emph_style: Style,
-    pub plus_style: Style,
-    pub git_minus_style: Style,
-    pub git_plus_style: Style,
-    pub side_by_side: bool,
-    pub side_by_side_data: side_by_side::SideBySideData,
-    pub syntax_dummy_theme: SyntaxTheme,
-    pub syntax_set: SyntaxSet,
-    pub syntax_theme: Option<SyntaxTheme>,
-    pub tab_width: usize,
-    pub tokenization_regex: Regex,
-    pub true_color: bool,
-    pub truncation_symbol: String,
-    pub whitespace_error_style: Style,
-    pub zero_style: Style,
+    pub(crate) available_terminal_width: usize,
+    pub(crate) background_color_extends_to_terminal_width: bool,
+    pub(crate) bom_style: Style,
+    pub(crate) commit_style: Style,
+    pub(crate) color_only: bool,
+    pub(crate) context_lines: usize,
+    // Under --relative-paths, the current directory relative to the root of the repository.
+    pub(crate) current_dir_in_repo: Option<PathBuf>,
+    pub(crate) decorations_width: cli::Width,
+    // Whether decorations_width is the terminal width, rather than being specified by --width.
+    pub(crate) decorations_width_is_terminal_width: bool,
+    pub(crate) default_encoding: Encoding,
+    pub(crate) error_exit_code: i32,
+    pub(crate) file_added_label: String,
+    pub(crate) file_copied_label: String,
+    pub(crate) file_header_added_style: Style,
+    pub(crate) file_header_event_style: Style,
+    pub(crate) file_header_format: String,
+    pub(crate) file_header_icon_style: Style,
+    pub(crate) file_header_language_style: Style,
+    pub(crate) file_header_mode_style: Style,
+    pub(crate) file_header_notes_style: Style,
+    pub(crate) file_header_old_path_style: Style,
+    pub(crate) file_header_path_style: Style,
+    pub(crate) file_header_removed_style: Style,
+    pub(crate) file_icons: HashMap<String, String>,
+    pub(crate) file_modified_label: String,
+    pub(crate) file_removed_label: String,
+    pub(crate) file_rename_format: String,
+    pub(crate) file_renamed_label: String,
+    pub(crate) file_style: Style,
+    pub(crate) full_file_syntax_highlighting: bool,
+    pub(crate) git_config_entries: HashMap<String, GitConfigEntry>,
+    pub(crate) hex_diff: bool,
+    pub(crate) hex_diff_max_size: usize,
+    pub(crate) highlight_cache: Option<HighlightCache>,
+    pub(crate) highlight_cache_max_size: usize,
+    pub(crate) hunk_header_enclosing_scope: bool,
+    pub(crate) hunk_header_file_style: Style,
+    pub(crate) hunk_header_format: String,
+    pub(crate) hunk_header_fragment_style: Style,
+    pub(crate) hunk_header_line_number_style: Style,
+    pub(crate) hunk_header_minus_len_style: Style,
+    pub(crate) hunk_header_minus_start_style: Style,
+    pub(crate) hunk_header_plus_len_style: Style,
+    pub(crate) hunk_header_plus_start_style: Style,
+    pub(crate) hunk_header_style: Style,
+    pub(crate) hunk_label: String,
+    pub(crate) hyperlinks: bool,
+    pub(crate) hyperlinks_file_link_format: String,
+    pub(crate) image_protocol: cli::ImageProtocol,
+    pub(crate) image_thumbnail_height: usize,
+    pub(crate) image_thumbnails: bool,
+    pub(crate) inspect_raw_lines: cli::InspectRawLines,
+    pub(crate) keep_plus_minus_markers: bool,
+    pub(crate) line_ending_note: bool,
+    pub(crate) line_ending_style: Style,
+    pub(crate) line_numbers: bool,
+    pub(crate) line_numbers_left_format: String,
+    pub(crate) line_numbers_left_style: Style,
+    pub(crate) line_numbers_minus_style: Style,
+    pub(crate) line_numbers_plus_style: Style,
+    pub(crate) line_numbers_right_format: String,
+    pub(crate) line_numbers_right_style: Style,
+    pub(crate) line_numbers_show_first_line_number: bool,
+    pub(crate) line_numbers_zero_style: Style,
+    pub(crate) line_buffer_size: usize,
+    pub(crate) max_line_distance: f64,
+    pub(crate) max_line_distance_for_naively_paired_lines: f64,
+    pub(crate) max_line_length: usize,
+    pub(crate) max_token_distance: f64,
+    pub(crate) minus_emph_style: Style,
+    pub(crate) minus_empty_line_marker_style: Style,
+    pub(crate) minus_file: Option<PathBuf>,
+    pub(crate) minus_non_emph_style: Style,
+    pub(crate) minus_style: Style,
+    pub(crate) navigate: bool,
+    pub(crate) no_newline_style: Style,
+    pub(crate) navigate_regex: Option<String>,
+    pub(crate) navigate_targets: Vec<navigate::NavigateTarget>,
+    pub(crate) null_style: Style,
+    pub(crate) null_syntect_style: SyntectStyle,
+    pub(crate) paging_mode: PagingMode,
+    pub(crate) plus_emph_style: Style,
+    pub(crate) plus_empty_line_marker_style: Style,
+    pub(crate) plus_file: Option<PathBuf>,
+    pub(crate) plus_non_emph_style: Style,
+    pub(crate) plus_style: Style,
+    pub(crate) relative_paths: bool,
+    // The repository is shared by the threads that process the diff (see parallel::parse_diff).
+    pub(crate) repo: Option<Mutex<git2::Repository>>,
+    pub(crate) rev: Option<String>,
+    pub(crate) git_minus_style: Style,
+    pub(crate) git_plus_style: Style,
+    pub(crate) show_whitespace_changes: bool,
+    pub(crate) side_by_side: bool,
+    pub(crate) side_by_side_data: side_by_side::SideBySideData,
+    pub(crate) staged: bool,
+    pub(crate) syntax_dummy_theme: SyntaxTheme,
+    pub(crate) syntax_set: SyntaxSet,
+    pub(crate) syntax_theme: Option<SyntaxTheme>,
+    pub(crate) tab_width: usize,
+    pub(crate) threads: usize,
+    pub(crate) tokenization_regex: Regex,
+    pub(crate) true_color: bool,
+    pub(crate) truncation_symbol: String,
+    pub(crate) whitespace_change_style: Option<Style>,
+    pub(crate) whitespace_error_style: Style,
+    pub(crate) whitespace_insensitive_emph: bool,
+    pub(crate) zero_style: Style,
 }
 
 impl Config {
-    pub fn get_style(&self, state: &State) -> &Style {
+    /// Whether the diff is displayed side by side.
+    pub fn side_by_side(&self) -> bool {
+        self.side_by_side
+    }
+
+    /// Whether line numbers are displayed.
+    pub fn line_numbers(&self) -> bool {
+        self.line_numbers
+    }
+
+    /// Whether colors are emitted as 24-bit RGB rather than 8-bit ANSI colors.
+    pub fn true_color(&self) -> bool {
+        self.true_color
+    }
+
+    pub(crate) fn get_style(&self, state: &State) -> &Style {
         match state {
             State::HunkMinus(_) => &self.minus_style,
             State::HunkPlus(_) => &self.plus_style,
@@ -89,10 +167,27 @@ impl Config {
             _ => delta_unreachable("Unreachable code reached in get_style."),
         }
     }
+
+    /// Recompute the widths that depend on the width of the terminal, e.g. after the terminal has
+    /// been resized.
+    pub fn set_terminal_width(&mut self, terminal_width: usize) {
+        // As in set_widths, allow one character in case e.g. `less --status-column` is in effect.
+        self.available_terminal_width = terminal_width.saturating_sub(1);
+        if self.decorations_width_is_terminal_width {
+            self.decorations_width = cli::Width::Fixed(self.available_terminal_width);
+        }
+        self.side_by_side_data = side_by_side::SideBySideData::new(
+            &self.decorations_width,
+            &self.available_terminal_width,
+        );
+    }
 }
 
-impl From<cli::Opt> for Config {
-    fn from(opt: cli::Opt) -> Self {
+impl TryFrom<cli::Opt> for Config {
+    type Error = errors::Error;
+
+    /// Make the config from `opt`, returning an error if an option value is invalid.
+    fn try_from(opt: cli::Opt) -> errors::Result<Self> {
         let (
             minus_style,
             minus_emph_style,
@@ -104,10 +199,44 @@ impl From<cli::Opt> for Config {
             plus_non_emph_style,
             plus_empty_line_marker_style,
             whitespace_error_style,
-        ) = make_hunk_styles(&opt);
+        ) = make_hunk_styles(&opt)?;
+
+        let whitespace_change_style = if opt.whitespace_change_style.is_empty() {
+            None
+        } else {
+            Some(Style::from_str(
+                &opt.whitespace_change_style,
+                None,
+                None,
+                opt.computed.true_color,
+                false,
+            )?)
+        };
+
+        let (line_ending_style, bom_style, no_newline_style) = make_line_format_styles(&opt)?;
 
         let (commit_style, file_style, hunk_header_style) =
-            make_commit_file_hunk_header_styles(&opt);
+            make_commit_file_hunk_header_styles(&opt)?;
+
+        let (
+            hunk_header_file_style,
+            hunk_header_line_number_style,
+            hunk_header_minus_start_style,
+            hunk_header_minus_len_style,
+            hunk_header_plus_start_style,
+            hunk_header_plus_len_style,
+        ) = make_hunk_header_component_styles(&opt)?;
+        let hunk_header_fragment_style = if opt.hunk_header_fragment_style.is_empty() {
+            hunk_header_style
+        } else {
+            Style::from_str(
+                &opt.hunk_header_fragment_style,
+                None,
+                None,
+                opt.computed.true_color,
+                false,
+            )?
+        };
 
         let (
             line_numbers_minus_style,
@@ -115,22 +244,49 @@ impl From<cli::Opt> for Config {
             line_numbers_plus_style,
             line_numbers_left_style,
             line_numbers_right_style,
-        ) = make_line_number_styles(&opt);
+        ) = make_line_number_styles(&opt)?;
 
         let max_line_distance_for_naively_paired_lines =
             env::get_env_var("DELTA_EXPERIMENTAL_MAX_LINE_DISTANCE_FOR_NAIVELY_PAIRED_LINES")
                 .map(|s| s.parse::<f64>().unwrap_or(0.0))
                 .unwrap_or(0.0);
 
-        let tokenization_regex = Regex::new(&opt.tokenization_regex).unwrap_or_else(|_| {
-            eprintln!(
+        let tokenization_regex = Regex::new(&opt.tokenization_regex).map_err(|_| {
+            format!(
                 "Invalid word-diff-regex: {}. \
                  The value must be a valid Rust regular expression. \
                  See https://docs.rs/regex.",
                 opt.tokenization_regex
-            );
-            process::exit(1);
-        });
+            )
+        })?;
+
+        let navigate_targets =
+            navigate::parse_navigate_targets(&opt.navigate_targets).map_err(|target| {
+                format!(
+                    "Invalid navigate-targets: {}. \
+                     Valid targets are: commits, files, hunks.",
+                    target
+                )
+            })?;
+
+        let true_color = opt.computed.true_color;
+        let make_style = |s: &str| Style::from_str(s, None, None, true_color, false);
+
+        let file_icons = format::parse_file_icons(&opt.file_icons).map_err(|entry| {
+            format!(
+                "Invalid file-icons entry: {}. \
+                 Entries must have the form EXTENSION=ICON, e.g. rs=🦀.",
+                entry
+            )
+        })?;
+
+        let default_encoding = Encoding::from_name(&opt.default_encoding).ok_or_else(|| {
+            format!(
+                "Invalid default-encoding: {}. \
+                 Valid encodings are: utf-8, utf-16le, utf-16be, latin1, windows-1252, shift_jis.",
+                opt.default_encoding
+            )
+        })?;
 
         let side_by_side_data = side_by_side::SideBySideData::new(
             &opt.computed.decorations_width,
@@ -138,35 +294,89 @@ impl From<cli::Opt> for Config {
         );
 
         let git_minus_style = match opt.git_config_entries.get("color.diff.old") {
-            Some(GitConfigEntry::Style(s)) => Style::from_git_str(s),
+            Some(GitConfigEntry::Style(s)) => Style::from_git_str(s)?,
             _ => *style::GIT_DEFAULT_MINUS_STYLE,
         };
         let git_plus_style = match opt.git_config_entries.get("color.diff.new") {
-            Some(GitConfigEntry::Style(s)) => Style::from_git_str(s),
+            Some(GitConfigEntry::Style(s)) => Style::from_git_str(s)?,
             _ => *style::GIT_DEFAULT_PLUS_STYLE,
         };
 
-        Self {
+        let current_dir_in_repo = match opt.git_config_entries.get("delta.__workdir__") {
+            Some(GitConfigEntry::Path(workdir)) if opt.relative_paths => {
+                get_current_dir_in_repo(workdir)
+            }
+            _ => None,
+        };
+
+        let highlight_cache = if opt.highlight_cache {
+            Some(HighlightCache::new(
+                PROJECT_DIRS.cache_dir(),
+                assets::get_assets_fingerprint(),
+                opt.highlight_cache_max_size as u64 * 1024 * 1024,
+            ))
+        } else {
+            None
+        };
+
+        Ok(Self {
             available_terminal_width: opt.computed.available_terminal_width,
             background_color_extends_to_terminal_width: opt
                 .computed
                 .background_color_extends_to_terminal_width,
+            bom_style,
             commit_style,
             color_only: opt.color_only,
+            context_lines: opt.context_lines,
+            current_dir_in_repo,
             decorations_width: opt.computed.decorations_width,
+            decorations_width_is_terminal_width: opt.width.is_none(),
+            default_encoding,
             error_exit_code: 2, // Use 2 for error because diff uses 0 and 1 for non-error.
             file_added_label: opt.file_added_label,
             file_copied_label: opt.file_copied_label,
+            file_header_added_style: make_style(&opt.file_header_added_style)?,
+            file_header_event_style: make_style(&opt.file_header_event_style)?,
+            file_header_format: opt.file_header_format,
+            file_header_icon_style: make_style(&opt.file_header_icon_style)?,
+            file_header_language_style: make_style(&opt.file_header_language_style)?,
+            file_header_mode_style: make_style(&opt.file_header_mode_style)?,
+            file_header_notes_style: make_style(&opt.file_header_notes_style)?,
+            file_header_old_path_style: make_style(&opt.file_header_old_path_style)?,
+            file_header_path_style: make_style(&opt.file_header_path_style)?,
+            file_header_removed_style: make_style(&opt.file_header_removed_style)?,
+            file_icons,
             file_modified_label: opt.file_modified_label,
             file_removed_label: opt.file_removed_label,
+            file_rename_format: opt.file_rename_format,
             file_renamed_label: opt.file_renamed_label,
             file_style,
+            full_file_syntax_highlighting: opt.full_file_syntax_highlighting,
             git_config_entries: opt.git_config_entries,
+            hex_diff: opt.hex_diff,
+            hex_diff_max_size: opt.hex_diff_max_size,
+            highlight_cache,
+            highlight_cache_max_size: opt.highlight_cache_max_size,
+            hunk_header_enclosing_scope: opt.hunk_header_enclosing_scope,
+            hunk_header_file_style,
+            hunk_header_format: opt.hunk_header_format,
+            hunk_header_fragment_style,
+            hunk_header_line_number_style,
+            hunk_header_minus_len_style,
+            hunk_header_minus_start_style,
+            hunk_header_plus_len_style,
+            hunk_header_plus_start_style,
             hunk_header_style,
+            hunk_label: opt.hunk_label,
             hyperlinks: opt.hyperlinks,
             hyperlinks_file_link_format: opt.hyperlinks_file_link_format,
-            inspect_raw_lines: opt.computed.inspect_raw_lines,
+            image_protocol: parse_image_protocol(&opt.image_protocol)?,
+            image_thumbnail_height: opt.image_thumbnail_height,
+            image_thumbnails: opt.image_thumbnails,
+            inspect_raw_lines: cli::InspectRawLines::from_str(&opt.inspect_raw_lines)?,
             keep_plus_minus_markers: opt.keep_plus_minus_markers,
+            line_ending_note: opt.line_ending_note,
+            line_ending_style,
             line_numbers: (opt.computed.line_numbers_mode == cli::LineNumbersMode::Full),
             line_numbers_left_format: opt.line_numbers_left_format,
             line_numbers_left_style,
@@ -181,40 +391,120 @@ impl From<cli::Opt> for Config {
             max_line_distance: opt.max_line_distance,
             max_line_distance_for_naively_paired_lines,
             max_line_length: opt.max_line_length,
+            max_token_distance: opt.max_token_distance,
             minus_emph_style,
             minus_empty_line_marker_style,
             minus_file: opt.minus_file,
             minus_non_emph_style,
             minus_style,
             navigate: opt.navigate,
+            no_newline_style,
+            navigate_regex: opt.navigate_regex,
+            navigate_targets,
             null_style: Style::new(),
             null_syntect_style: SyntectStyle::default(),
-            paging_mode: opt.computed.paging_mode,
+            paging_mode: parse_paging_mode(&opt.paging_mode)?,
             plus_emph_style,
             plus_empty_line_marker_style,
             plus_file: opt.plus_file,
             plus_non_emph_style,
             plus_style,
+            relative_paths: opt.relative_paths,
+            repo: None,
+            rev: opt.rev,
             git_minus_style,
             git_plus_style,
+            show_whitespace_changes: opt.show_whitespace_changes,
             side_by_side: opt.side_by_side,
             side_by_side_data,
+            staged: opt.staged,
             syntax_dummy_theme: SyntaxTheme::default(),
             syntax_set: opt.computed.syntax_set,
             syntax_theme: opt.computed.syntax_theme,
             tab_width: opt.tab_width,
+            threads: opt.threads,
             tokenization_regex,
             true_color: opt.computed.true_color,
             truncation_symbol: "→".to_string(),
+            whitespace_change_style,
             whitespace_error_style,
+            whitespace_insensitive_emph: opt.whitespace_insensitive_emph,
             zero_style,
-        }
+        })
     }
 }
 
+/// Builds a Config from delta's options, as if they had been given on the command line, without
+/// requiring git config.
+///
+/// ```
+/// let config = git_delta::ConfigBuilder::new()
+///     .option("features", "side-by-side line-numbers")
+///     .option("width", "100")
+///     .flag("keep-plus-minus-markers")
+///     .build()
+///     .unwrap();
+/// assert!(config.side_by_side());
+/// ```
+#[derive(Default)]
+pub struct ConfigBuilder {
+    args: Vec<String>,
+    git_config: Option<GitConfig>,
+    repo: Option<git2::Repository>,
+}
+
+impl ConfigBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the option `name` (its long form without leading dashes, e.g. "syntax-theme") to
+    /// `value`. The "features" option selects built-in features (see
+    /// features::make_builtin_features), as well as any custom features in the git config.
+    pub fn option(mut self, name: &str, value: &str) -> Self {
+        self.args.push(format!("--{}={}", name, value));
+        self
+    }
+
+    /// Turn on the boolean option `name`, e.g. "side-by-side".
+    pub fn flag(mut self, name: &str) -> Self {
+        self.args.push(format!("--{}", name));
+        self
+    }
+
+    /// Read option values and custom features from `git_config`, as delta does from the git
+    /// config of the current repository.
+    pub fn git_config(mut self, git_config: GitConfig) -> Self {
+        self.git_config = Some(git_config);
+        self
+    }
+
+    /// Use `repo` to read the files of the diff, as needed by e.g. --full-file-syntax-highlighting
+    /// and --context-lines.
+    pub fn repo(mut self, repo: git2::Repository) -> Self {
+        self.repo = Some(repo);
+        self
+    }
+
+    pub fn build(self) -> errors::Result<Config> {
+        let mut git_config = self.git_config;
+        let opt = cli::Opt::from_iter_safe_and_git_config(
+            std::iter::once("delta".to_string()).chain(self.args),
+            &mut git_config,
+        )?;
+        let mut config = Config::try_from(opt)?;
+        config.repo = self
+            .repo
+            .or_else(|| git_config.and_then(|git_config| git_config.repo))
+            .map(Mutex::new);
+        Ok(config)
+    }
+}
+
+#[allow(clippy::type_complexity)]
 fn make_hunk_styles(
     opt: &cli::Opt,
-) -> (
+) -> errors::Result<(
     Style,
     Style,
     Style,
@@ -225,7 +515,7 @@ fn make_hunk_styles(
     Style,
     Style,
     Style,
-) {
+)> {
     let is_light_mode = opt.computed.is_light_mode;
     let true_color = opt.computed.true_color;
     let minus_style = Style::from_str(
@@ -240,7 +530,7 @@ fn make_hunk_styles(
         None,
         true_color,
         false,
-    );
+    )?;
 
     let minus_emph_style = Style::from_str(
         &opt.minus_emph_style,
@@ -254,7 +544,7 @@ fn make_hunk_styles(
         None,
         true_color,
         true,
-    );
+    )?;
 
     let minus_non_emph_style = Style::from_str(
         &opt.minus_non_emph_style,
@@ -262,7 +552,7 @@ fn make_hunk_styles(
         None,
         true_color,
         false,
-    );
+    )?;
 
     // The style used to highlight a removed empty line when otherwise it would be invisible due to
     // lack of background color in minus-style.
@@ -278,9 +568,9 @@ fn make_hunk_styles(
         None,
         true_color,
         false,
-    );
+    )?;
 
-    let zero_style = Style::from_str(&opt.zero_style, None, None, true_color, false);
+    let zero_style = Style::from_str(&opt.zero_style, None, None, true_color, false)?;
 
     let plus_style = Style::from_str(
         &opt.plus_style,
@@ -294,7 +584,7 @@ fn make_hunk_styles(
         None,
         true_color,
         false,
-    );
+    )?;
 
     let plus_emph_style = Style::from_str(
         &opt.plus_emph_style,
@@ -308,7 +598,7 @@ fn make_hunk_styles(
         None,
         true_color,
         true,
-    );
+    )?;
 
     let plus_non_emph_style = Style::from_str(
         &opt.plus_non_emph_style,
@@ -316,7 +606,7 @@ fn make_hunk_styles(
         None,
         true_color,
         false,
-    );
+    )?;
 
     // The style used to highlight an added empty line when otherwise it would be invisible due to
     // lack of background color in plus-style.
@@ -332,12 +622,12 @@ fn make_hunk_styles(
         None,
         true_color,
         false,
-    );
+    )?;
 
     let whitespace_error_style =
-        Style::from_str(&opt.whitespace_error_style, None, None, true_color, false);
+        Style::from_str(&opt.whitespace_error_style, None, None, true_color, false)?;
 
-    (
+    Ok((
         minus_style,
         minus_emph_style,
         minus_non_emph_style,
@@ -348,38 +638,75 @@ fn make_hunk_styles(
         plus_non_emph_style,
         plus_empty_line_marker_style,
         whitespace_error_style,
-    )
+    ))
+}
+
+fn make_line_format_styles(opt: &cli::Opt) -> errors::Result<(Style, Style, Style)> {
+    let true_color = opt.computed.true_color;
+    let line_ending_style = Style::from_str(&opt.line_ending_style, None, None, true_color, false)?;
+    let bom_style = Style::from_str(&opt.bom_style, None, None, true_color, false)?;
+    let no_newline_style = Style::from_str(&opt.no_newline_style, None, None, true_color, false)?;
+    Ok((line_ending_style, bom_style, no_newline_style))
 }
 
-fn make_line_number_styles(opt: &cli::Opt) -> (Style, Style, Style, Style, Style) {
+fn make_line_number_styles(opt: &cli::Opt) -> errors::Result<(Style, Style, Style, Style, Style)> {
     let true_color = opt.computed.true_color;
     let line_numbers_left_style =
-        Style::from_str(&opt.line_numbers_left_style, None, None, true_color, false);
+        Style::from_str(&opt.line_numbers_left_style, None, None, true_color, false)?;
 
     let line_numbers_minus_style =
-        Style::from_str(&opt.line_numbers_minus_style, None, None, true_color, false);
+        Style::from_str(&opt.line_numbers_minus_style, None, None, true_color, false)?;
 
     let line_numbers_zero_style =
-        Style::from_str(&opt.line_numbers_zero_style, None, None, true_color, false);
+        Style::from_str(&opt.line_numbers_zero_style, None, None, true_color, false)?;
 
     let line_numbers_plus_style =
-        Style::from_str(&opt.line_numbers_plus_style, None, None, true_color, false);
+        Style::from_str(&opt.line_numbers_plus_style, None, None, true_color, false)?;
 
     let line_numbers_right_style =
-        Style::from_str(&opt.line_numbers_right_style, None, None, true_color, false);
+        Style::from_str(&opt.line_numbers_right_style, None, None, true_color, false)?;
 
-    (
+    Ok((
         line_numbers_minus_style,
         line_numbers_zero_style,
         line_numbers_plus_style,
         line_numbers_left_style,
         line_numbers_right_style,
-    )
+    ))
 }
 
-fn make_commit_file_hunk_header_styles(opt: &cli::Opt) -> (Style, Style, Style) {
+fn make_hunk_header_component_styles(
+    opt: &cli::Opt,
+) -> errors::Result<(Style, Style, Style, Style, Style, Style)> {
     let true_color = opt.computed.true_color;
-    (
+    let hunk_header_line_number_style = Style::from_str(
+        &opt.hunk_header_line_number_style,
+        None,
+        None,
+        true_color,
+        false,
+    )?;
+    // The styles of the line numbers and lengths default to --hunk-header-line-number-style.
+    let make_line_number_style = |s: &str| {
+        if s.is_empty() {
+            Ok(hunk_header_line_number_style)
+        } else {
+            Style::from_str(s, None, None, true_color, false)
+        }
+    };
+    Ok((
+        Style::from_str(&opt.hunk_header_file_style, None, None, true_color, false)?,
+        hunk_header_line_number_style,
+        make_line_number_style(&opt.hunk_header_minus_start_style)?,
+        make_line_number_style(&opt.hunk_header_minus_len_style)?,
+        make_line_number_style(&opt.hunk_header_plus_start_style)?,
+        make_line_number_style(&opt.hunk_header_plus_len_style)?,
+    ))
+}
+
+fn make_commit_file_hunk_header_styles(opt: &cli::Opt) -> errors::Result<(Style, Style, Style)> {
+    let true_color = opt.computed.true_color;
+    Ok((
         Style::from_str_with_handling_of_special_decoration_attributes_and_respecting_deprecated_foreground_color_arg(
             &opt.commit_style,
             None,
@@ -387,7 +714,7 @@ fn make_commit_file_hunk_header_styles(opt: &cli::Opt) -> (Style, Style, Style)
             opt.deprecated_commit_color.as_deref(),
             true_color,
             false,
-        ),
+        )?,
         Style::from_str_with_handling_of_special_decoration_attributes_and_respecting_deprecated_foreground_color_arg(
             &opt.file_style,
             None,
@@ -395,7 +722,7 @@ fn make_commit_file_hunk_header_styles(opt: &cli::Opt) -> (Style, Style, Style)
             opt.deprecated_file_color.as_deref(),
             true_color,
             false,
-        ),
+        )?,
         Style::from_str_with_handling_of_special_decoration_attributes_and_respecting_deprecated_foreground_color_arg(
             &opt.hunk_header_style,
             None,
@@ -403,8 +730,65 @@ fn make_commit_file_hunk_header_styles(opt: &cli::Opt) -> (Style, Style, Style)
             opt.deprecated_hunk_color.as_deref(),
             true_color,
             false,
-        ),
-    )
+        )?,
+    ))
+}
+
+fn parse_paging_mode(paging_mode_string: &str) -> errors::Result<PagingMode> {
+    match paging_mode_string.to_lowercase().as_str() {
+        "always" => Ok(PagingMode::Always),
+        "never" => Ok(PagingMode::Never),
+        "auto" => Ok(PagingMode::QuitIfOneScreen),
+        "builtin" => Ok(PagingMode::Builtin),
+        _ => Err(format!(
+            "Invalid value for --paging option: {} \
+             (valid values are \"always\", \"never\", \"auto\", and \"builtin\")",
+            paging_mode_string
+        )
+        .into()),
+    }
+}
+
+fn parse_image_protocol(image_protocol_string: &str) -> errors::Result<cli::ImageProtocol> {
+    match image_protocol_string.to_lowercase().as_str() {
+        "kitty" => Ok(cli::ImageProtocol::Kitty),
+        "iterm2" => Ok(cli::ImageProtocol::Iterm2),
+        "sixel" => Ok(cli::ImageProtocol::Sixel),
+        "none" => Ok(cli::ImageProtocol::None),
+        "auto" => Ok(get_terminal_image_protocol()),
+        _ => Err(format!(
+            "Invalid value for --image-protocol option: {} \
+             (valid values are \"auto\", \"kitty\", \"iterm2\", \"sixel\", and \"none\")",
+            image_protocol_string
+        )
+        .into()),
+    }
+}
+
+/// Guess the graphics protocol supported by the terminal from its TERM and TERM_PROGRAM.
+fn get_terminal_image_protocol() -> cli::ImageProtocol {
+    let term = env::get_env_var("TERM").unwrap_or_default();
+    let term_program = env::get_env_var("TERM_PROGRAM").unwrap_or_default();
+    if term.contains("kitty") || env::get_boolean_env_var("KITTY_WINDOW_ID") {
+        cli::ImageProtocol::Kitty
+    } else if term_program == "iTerm.app" || term_program == "WezTerm" {
+        cli::ImageProtocol::Iterm2
+    } else if term.contains("sixel") || term.starts_with("foot") || term.starts_with("mlterm") {
+        cli::ImageProtocol::Sixel
+    } else {
+        cli::ImageProtocol::None
+    }
+}
+
+/// Return the current directory relative to `workdir`, the root of the repository, if it is inside
+/// it.
+fn get_current_dir_in_repo(workdir: &Path) -> Option<PathBuf> {
+    let current_dir = std::env::current_dir().ok()?.canonicalize().ok()?;
+    let workdir = workdir.canonicalize().ok()?;
+    current_dir
+        .strip_prefix(workdir)
+        .ok()
+        .map(|dir| dir.to_path_buf())
 }
 
 /// Did the user supply `option` on the command line?
@@ -1,19 +1,31 @@
 use std::borrow::Cow;
-use std::io::BufRead;
-use std::io::Write;
+use std::io::{self, BufRead, Write};
 
-use bytelines::ByteLines;
 use unicode_segmentation::UnicodeSegmentation;
 
+use crate::annotate::Annotator;
 use crate::ansi;
 use crate::cli;
 use crate::config::Config;
-use crate::draw;
+use crate::encoding::{Encoding, FirstLines};
 use crate::features;
+use crate::file_header_amendment::FileHeaderAmender;
 use crate::format;
+use crate::git_blob;
+use crate::hex_diff;
+use crate::hunk_context::{self, HunkContextExpander};
+use crate::image;
+use crate::line_counts::LineCounts;
+use crate::line_endings::{self, LineEndingNote};
+use crate::model::{
+    BinaryFileDiff, CommitHeader, Event, FileHeader, ImageFileDiff, LineFormat, Renderer,
+    SubmoduleCommit, SubmoduleHeader,
+};
 use crate::paint::Painter;
-use crate::parse;
+use crate::parallel;
+use crate::parse::{self, DiffPrefixes, FileEvent, FileInfo};
 use crate::style::{self, DecorationStyle};
+use crate::submodule::{self, SubmoduleCommits};
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum State {
@@ -23,10 +35,11 @@ pub enum State {
     HunkZero, // In hunk; unchanged line
     HunkMinus(Option<String>), // In hunk; removed line (raw_line)
     HunkPlus(Option<String>), // In hunk; added line (raw_line)
+    SubmoduleLog, // In the list of commits of a submodule (git diff --submodule=log)
     Unknown,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Source {
     GitDiff,     // Coming from a `git diff` command
     DiffUnified, // Coming from a `diff -u` command
@@ -35,7 +48,10 @@ pub enum Source {
 
 impl State {
     fn is_in_hunk(&self) -> bool {
-        matches!(*self, State::HunkHeader | State::HunkZero | State::HunkMinus(_) | State::HunkPlus(_))
+        matches!(
+            *self,
+            State::HunkHeader | State::HunkZero | State::HunkMinus(_) | State::HunkPlus(_)
+        )
     }
 }
 
@@ -51,20 +67,83 @@ impl State {
 // | HunkMinus   | flush, emit | flush, emit | flush, emit | flush, emit | push        | push     |
 // | HunkPlus    | flush, emit | flush, emit | flush, emit | flush, emit | flush, push | push     |
 
-pub fn delta<I>(
-    mut lines: ByteLines<I>,
-    writer: &mut dyn Write,
+pub fn delta<I>(lines: io::Split<I>, writer: &mut dyn Write, config: &Config) -> std::io::Result<()>
+where
+    I: BufRead + Send,
+{
+    let mut painter = Painter::new(writer, config);
+    parse_diff(lines, config, &mut painter)
+}
+
+/// Parse the diff, passing it to `renderer` as a stream of events (see model::Event). Runs of
+/// changed lines are buffered for edit inference, but no more than config.line_buffer_size lines,
+/// so memory use does not grow with the size of the diff. If config.threads is not 1 (it is 1 by
+/// default), the files of the diff are parsed and highlighted in parallel (see
+/// parallel::parse_diff), in which case memory use is instead bounded by the size of the files.
+pub fn parse_diff<I>(
+    lines: io::Split<I>,
+    config: &Config,
+    renderer: &mut dyn Renderer,
+) -> std::io::Result<()>
+where
+    I: BufRead + Send,
+{
+    if format::file_header_format_contains_line_counts(&config.file_header_format) {
+        let mut line_counts = FileHeaderAmender::<LineCounts>::new(renderer);
+        parse_diff_with_line_ending_notes(lines, config, &mut line_counts)?;
+        line_counts.flush()
+    } else {
+        parse_diff_with_line_ending_notes(lines, config, renderer)
+    }
+}
+
+fn parse_diff_with_line_ending_notes<I>(
+    lines: io::Split<I>,
+    config: &Config,
+    renderer: &mut dyn Renderer,
+) -> std::io::Result<()>
+where
+    I: BufRead + Send,
+{
+    if config.line_ending_note {
+        let mut line_ending_notes = FileHeaderAmender::<LineEndingNote>::new(renderer);
+        parse_diff_with_threads(lines, config, &mut line_ending_notes)?;
+        line_ending_notes.flush()
+    } else {
+        parse_diff_with_threads(lines, config, renderer)
+    }
+}
+
+fn parse_diff_with_threads<I>(
+    lines: io::Split<I>,
+    config: &Config,
+    renderer: &mut dyn Renderer,
+) -> std::io::Result<()>
+where
+    I: BufRead + Send,
+{
+    match parallel::get_number_of_threads(config) {
+        1 => parse_diff_sequentially(lines, config, renderer, Source::Unknown),
+        n_threads => parallel::parse_diff(lines, config, renderer, n_threads),
+    }
+}
+
+/// Parse the diff on the current thread. `source` is the source of the input, if it has already
+/// been detected.
+pub fn parse_diff_sequentially<I>(
+    mut lines: io::Split<I>,
     config: &Config,
+    renderer: &mut dyn Renderer,
+    mut source: Source,
 ) -> std::io::Result<()>
 where
     I: BufRead,
 {
-    let mut painter = Painter::new(writer, config);
+    let mut annotator = Annotator::new(config);
     let mut minus_file = "".to_string();
     let mut plus_file = "".to_string();
     let mut file_event = parse::FileEvent::NoEvent;
     let mut state = State::Unknown;
-    let mut source = Source::Unknown;
 
     // When a file is modified, we use lines starting with '---' or '+++' to obtain the file name.
     // When a file is renamed without changes, we use lines starting with 'rename' to obtain the
@@ -74,54 +153,155 @@ where
     let mut current_file_pair;
     let mut handled_file_meta_header_line_file_pair = None;
 
-    while let Some(Ok(raw_line_bytes)) = lines.next() {
-        let raw_line = String::from_utf8_lossy(&raw_line_bytes);
-        let raw_line = if config.max_line_length > 0 && raw_line.len() > config.max_line_length {
-            ansi::truncate_str(&raw_line, config.max_line_length, &config.truncation_symbol)
-        } else {
+    // The "diff --git" line of the current file, and the prefixes of its paths (see
+    // parse::DiffPrefixes), which are removed from the paths in the ---/+++ lines.
+    let mut diff_git_line = String::new();
+    let mut diff_prefixes = None;
+
+    // Blob ids from the "index" line, used to read the full files under
+    // --full-file-syntax-highlighting.
+    let mut blob_ids = None;
+
+    // Under --context-lines, the hunks of each file are buffered, and then processed in their
+    // expanded form.
+    let mut hunk_context_expander = HunkContextExpander::default();
+    let mut hunk_context_unavailable = false;
+
+    // The encoding of the current file (see encoding::Encoding), and whether the next hunk lines
+    // are the first lines of its minus and plus files, which may begin with a byte order mark.
+    let mut encoding = config.default_encoding;
+    let mut first_lines = FirstLines::default();
+
+    // The extended header lines of a git diff (e.g. "new mode 100755") are collected in
+    // file_info. A file whose diff has no ---/+++ lines is given a header constructed from the
+    // path in its "diff --git" line, if there is anything to display; pending_file_path holds that
+    // path until a header is rendered for the file.
+    let mut file_info = FileInfo::default();
+    let mut pending_file_path = None;
+
+    // The commits of the current submodule, for git diff --submodule=log.
+    let mut submodule_commits = SubmoduleCommits::default();
+
+    loop {
+        let raw_line = if let Some(line) = hunk_context_expander.next_line() {
+            Cow::from(line)
+        } else if let Some(Ok(raw_line_bytes)) = lines.next() {
+            if let Some(bom_encoding) = first_lines.byte_order_mark(&raw_line_bytes) {
+                encoding = bom_encoding;
+            }
+            let raw_line = Cow::from(encoding.decode_line(raw_line_bytes));
+            if raw_line.starts_with("@@") {
+                first_lines.begin_hunk(&parse::parse_hunk_header(&raw_line).1);
+            }
+            if hunk_context_expander.is_active() {
+                hunk_context_expander.push_line(&raw_line, config.context_lines);
+                continue;
+            } else if config.context_lines > 0
+                && !config.color_only
+                && !hunk_context_unavailable
+                && ansi::strip_ansi_codes(&raw_line).starts_with("@@")
+            {
+                if begin_hunk_context_expansion(
+                    &mut hunk_context_expander,
+                    &raw_line,
+                    &blob_ids,
+                    &minus_file,
+                    &plus_file,
+                    encoding,
+                    config,
+                ) {
+                    continue;
+                }
+                hunk_context_unavailable = true;
+            }
             raw_line
+        } else if hunk_context_expander.is_active() {
+            hunk_context_expander.finish(config.context_lines);
+            continue;
+        } else {
+            break;
         };
+        let (raw_line, has_crlf) = line_endings::strip_carriage_return(raw_line);
+        let raw_line = truncate_raw_line(raw_line, config);
         let line = ansi::strip_ansi_codes(&raw_line).to_string();
         if source == Source::Unknown {
             source = detect_source(&line);
         }
         if line.starts_with("commit ") {
-            painter.paint_buffered_minus_and_plus_lines();
+            render_pending_file_header(
+                &mut pending_file_path,
+                &file_info,
+                &blob_ids,
+                config,
+                renderer,
+            )?;
+            render_buffered_minus_and_plus_lines(&mut annotator, renderer)?;
             state = State::CommitMeta;
-            if should_handle(&state, config) {
-                painter.emit()?;
-                handle_commit_meta_header_line(&mut painter, &line, &raw_line, config)?;
-                continue;
-            }
+            encoding = config.default_encoding;
+            renderer.render(Event::CommitHeader(CommitHeader {
+                line,
+                raw_line: raw_line.into_owned(),
+            }))?;
+            continue;
         } else if line.starts_with("diff ") {
-            painter.paint_buffered_minus_and_plus_lines();
+            render_pending_file_header(
+                &mut pending_file_path,
+                &file_info,
+                &blob_ids,
+                config,
+                renderer,
+            )?;
+            render_buffered_minus_and_plus_lines(&mut annotator, renderer)?;
             state = State::FileMeta;
             handled_file_meta_header_line_file_pair = None;
+            let diff_git_paths = parse::parse_diff_git_line(&line, None);
+            diff_prefixes = diff_git_paths
+                .as_ref()
+                .map(|(prefixes, ..)| prefixes.clone());
+            pending_file_path = diff_git_paths
+                .filter(|(_, minus_path, plus_path)| minus_path == plus_path)
+                .map(|(_, _, plus_path)| plus_path);
+            diff_git_line = line.to_string();
+            file_info = FileInfo::default();
+            blob_ids = None;
+            encoding = config.default_encoding;
+            hunk_context_unavailable = false;
+            annotator.set_file_syntax_states(None, None);
+        } else if state == State::FileMeta && line.starts_with("index ") {
+            blob_ids = parse::parse_index_line(&line)
+                .map(|(minus, plus)| (minus.to_string(), plus.to_string()));
         } else if (state == State::FileMeta || source == Source::DiffUnified)
             && (line.starts_with("--- ")
                 || line.starts_with("rename from ")
                 || line.starts_with("copy from "))
         {
-            let parsed_file_meta_line =
-                parse::parse_file_meta_line(&line, source == Source::GitDiff);
+            let parsed_file_meta_line = parse::parse_file_meta_line(
+                &line,
+                source == Source::GitDiff,
+                diff_prefixes.as_ref(),
+            );
             minus_file = parsed_file_meta_line.0;
             file_event = parsed_file_meta_line.1;
+            encoding = config.default_encoding;
 
             if source == Source::DiffUnified {
                 state = State::FileMeta;
-                painter.set_syntax(parse::get_file_extension_from_marker_line(&line));
+                annotator.set_syntax(parse::get_file_extension_from_marker_line(&line));
             } else {
-                painter.set_syntax(parse::get_file_extension_from_file_meta_line_file_path(
+                annotator.set_syntax(parse::get_file_extension_from_file_meta_line_file_path(
                     &minus_file,
                 ));
             }
 
             // In color_only mode, raw_line's structure shouldn't be changed.
-            // So it needs to avoid fn handle_file_meta_header_line
+            // So it needs to avoid a file header constructed from the file paths
             // (it connects the plus_file and minus_file),
-            // and to call fn handle_generic_file_meta_header_line directly.
+            // and to use the line as it stands.
             if config.color_only {
-                handle_generic_file_meta_header_line(&mut painter, &line, &raw_line, config)?;
+                renderer.render(Event::FileHeader(FileHeader::Line {
+                    line,
+                    raw_line: raw_line.into_owned(),
+                }))?;
                 continue;
             }
         } else if (state == State::FileMeta || source == Source::DiffUnified)
@@ -129,43 +309,98 @@ where
                 || line.starts_with("rename to ")
                 || line.starts_with("copy to "))
         {
-            let parsed_file_meta_line =
-                parse::parse_file_meta_line(&line, source == Source::GitDiff);
+            let parsed_file_meta_line = parse::parse_file_meta_line(
+                &line,
+                source == Source::GitDiff,
+                diff_prefixes.as_ref(),
+            );
             plus_file = parsed_file_meta_line.0;
-            painter.set_syntax(parse::get_file_extension_from_file_meta_line_file_path(
+            if !line.starts_with("+++ ") {
+                // The paths of a renamed or copied file determine the prefixes of its paths.
+                if let Some((prefixes, ..)) =
+                    parse::parse_diff_git_line(&diff_git_line, Some((&minus_file, &plus_file)))
+                {
+                    diff_prefixes = Some(prefixes);
+                }
+            }
+            if let Some(working_tree_encoding) = get_working_tree_encoding(
+                if plus_file == "/dev/null" {
+                    &minus_file
+                } else {
+                    &plus_file
+                },
+                config,
+            ) {
+                encoding = working_tree_encoding;
+            }
+            annotator.set_syntax(parse::get_file_extension_from_file_meta_line_file_path(
                 &plus_file,
             ));
             current_file_pair = Some((minus_file.clone(), plus_file.clone()));
 
             // In color_only mode, raw_line's structure shouldn't be changed.
-            // So it needs to avoid fn handle_file_meta_header_line
+            // So it needs to avoid a file header constructed from the file paths
             // (it connects the plus_file and minus_file),
-            // and to call fn handle_generic_file_meta_header_line directly.
+            // and to use the line as it stands.
             if config.color_only {
-                handle_generic_file_meta_header_line(&mut painter, &line, &raw_line, config)?;
+                renderer.render(Event::FileHeader(FileHeader::Line {
+                    line,
+                    raw_line: raw_line.into_owned(),
+                }))?;
                 continue;
             }
             if should_handle(&State::FileMeta, config)
                 && handled_file_meta_header_line_file_pair != current_file_pair
             {
-                painter.emit()?;
-                handle_file_meta_header_line(
-                    &mut painter,
-                    &minus_file,
-                    &plus_file,
-                    config,
-                    &file_event,
-                    source == Source::DiffUnified,
-                )?;
-                handled_file_meta_header_line_file_pair = current_file_pair
+                let mut file_info = file_info.clone();
+                set_symlink_targets(&mut file_info, &blob_ids, &minus_file, &plus_file, config);
+                renderer.render(Event::FileHeader(FileHeader::Paths {
+                    minus_file: minus_file.clone(),
+                    plus_file: plus_file.clone(),
+                    file_event: file_event.clone(),
+                    comparing: source == Source::DiffUnified,
+                    note: None,
+                    file_info,
+                    line_counts: None,
+                }))?;
+                handled_file_meta_header_line_file_pair = current_file_pair;
+                pending_file_path = None;
             }
         } else if line.starts_with("@@") {
-            painter.paint_buffered_minus_and_plus_lines();
+            render_buffered_minus_and_plus_lines(&mut annotator, renderer)?;
             state = State::HunkHeader;
-            painter.set_highlighter();
-            painter.emit()?;
-            handle_hunk_header_line(&mut painter, &line, &raw_line, &plus_file, config)?;
-            painter.set_highlighter();
+            if config.full_file_syntax_highlighting || config.hunk_header_enclosing_scope {
+                if let Some((minus_blob_id, plus_blob_id)) = blob_ids.take() {
+                    set_file_syntax_states(
+                        &mut annotator,
+                        &minus_blob_id,
+                        &plus_blob_id,
+                        &minus_file,
+                        &plus_file,
+                        encoding,
+                        config,
+                    );
+                }
+            }
+            annotator.set_highlighter();
+            let hunk_header = annotator.annotate_hunk_header(&line, &raw_line, &plus_file);
+            let line_numbers = hunk_header.line_numbers.clone();
+            renderer.render(Event::HunkHeader(hunk_header))?;
+            annotator.set_highlighter();
+            if config.full_file_syntax_highlighting {
+                annotator.set_highlighters_from_file_syntax_states(&line_numbers);
+            }
+            continue;
+        } else if let Some(submodule_header) = get_submodule_header(&line, config) {
+            render_buffered_minus_and_plus_lines(&mut annotator, renderer)?;
+            state = State::SubmoduleLog;
+            submodule_commits = match &config.repo {
+                Some(repo) => {
+                    SubmoduleCommits::from_superproject(&repo.lock().unwrap(), &submodule_header)
+                }
+                None => SubmoduleCommits::default(),
+            };
+            renderer.render(Event::SubmoduleHeader(submodule_header))?;
             continue;
         } else if source == Source::DiffUnified && line.starts_with("Only in ")
             || line.starts_with("Submodule ")
@@ -184,21 +419,60 @@ where
             // See https://github.com/dandavison/delta/issues/60#issuecomment-557485242 for a
             // proposal for more robust parsing logic.
 
-            painter.paint_buffered_minus_and_plus_lines();
+            render_buffered_minus_and_plus_lines(&mut annotator, renderer)?;
             state = State::FileMeta;
+            pending_file_path = None;
             if should_handle(&State::FileMeta, config) {
-                painter.emit()?;
-                handle_generic_file_meta_header_line(&mut painter, &line, &raw_line, config)?;
+                let binary_file_event =
+                    if (config.hex_diff || config.image_thumbnails) && !config.color_only {
+                        get_binary_file_event(&line, &blob_ids, diff_prefixes.as_ref(), config)
+                    } else {
+                        None
+                    };
+                renderer.render(Event::FileHeader(FileHeader::Line {
+                    line,
+                    raw_line: raw_line.into_owned(),
+                }))?;
+                if let Some(binary_file_event) = binary_file_event {
+                    renderer.render(binary_file_event)?;
+                }
+                continue;
+            }
+        } else if state == State::SubmoduleLog {
+            if let Some((is_plus, summary)) = submodule::parse_submodule_log_line(&line) {
+                let commit_id = submodule_commits.take_commit_id(is_plus, summary);
+                let url = match (&commit_id, &submodule_commits.github_repo) {
+                    (Some(commit_id), Some(github_repo)) if config.hyperlinks => Some(
+                        features::hyperlinks::format_github_commit_url(commit_id, github_repo),
+                    ),
+                    _ => None,
+                };
+                renderer.render(Event::SubmoduleCommit(SubmoduleCommit {
+                    is_plus,
+                    summary: summary.to_string(),
+                    commit_id,
+                    url,
+                }))?;
                 continue;
             }
         } else if state.is_in_hunk() {
             // A true hunk line should start with one of: '+', '-', ' '. However, handle_hunk_line
             // handles all lines until the state machine transitions away from the hunk states.
-            state = handle_hunk_line(&mut painter, &line, &raw_line, state, config);
-            painter.emit()?;
+            state = handle_hunk_line(
+                &mut annotator,
+                renderer,
+                &line,
+                &raw_line,
+                has_crlf,
+                state,
+                config,
+            )?;
             continue;
         }
 
+        if state == State::FileMeta {
+            file_info.update(&line);
+        }
         if state == State::FileMeta && should_handle(&State::FileMeta, config) && !config.color_only
         {
             // The file metadata section is 4 lines. Skip them under non-plain file-styles.
@@ -206,376 +480,314 @@ where
             // we won't skip because we can't change raw_line structure.
             continue;
         } else {
-            painter.emit()?;
-            writeln!(
-                painter.writer,
-                "{}",
-                format::format_raw_line(&raw_line, config)
-            )?;
+            renderer.render(Event::RawLine(raw_line.into_owned()))?;
         }
     }
 
-    painter.paint_buffered_minus_and_plus_lines();
-    painter.emit()?;
-    Ok(())
+    render_pending_file_header(
+        &mut pending_file_path,
+        &file_info,
+        &blob_ids,
+        config,
+        renderer,
+    )?;
+    render_buffered_minus_and_plus_lines(&mut annotator, renderer)
 }
 
-/// Should a handle_* function be called on this element?
-fn should_handle(state: &State, config: &Config) -> bool {
-    let style = config.get_style(state);
-    !(style.is_raw && style.decoration_style == DecorationStyle::NoDecoration)
+/// Render the header of a file whose diff has no ---/+++ lines, i.e. one whose mode changed or
+/// that was added or deleted with no content, if there is anything to display.
+fn render_pending_file_header(
+    pending_file_path: &mut Option<String>,
+    file_info: &FileInfo,
+    blob_ids: &Option<(String, String)>,
+    config: &Config,
+    renderer: &mut dyn Renderer,
+) -> std::io::Result<()> {
+    let path = match pending_file_path.take() {
+        Some(path) => path,
+        None => return Ok(()),
+    };
+    let mut file_info = file_info.clone();
+    file_info.is_empty = file_info.is_added() || file_info.is_deleted();
+    if !file_info.is_notable() || config.color_only || !should_handle(&State::FileMeta, config) {
+        return Ok(());
+    }
+    set_symlink_targets(&mut file_info, blob_ids, &path, &path, config);
+    renderer.render(Event::FileHeader(FileHeader::Paths {
+        minus_file: if file_info.is_added() {
+            "/dev/null".to_string()
+        } else {
+            path.clone()
+        },
+        plus_file: if file_info.is_deleted() {
+            "/dev/null".to_string()
+        } else {
+            path
+        },
+        file_event: FileEvent::Change,
+        comparing: false,
+        note: None,
+        file_info,
+        line_counts: None,
+    }))
 }
 
-/// Try to detect what is producing the input for delta.
-///
-/// Currently can detect:
-/// * git diff
-/// * diff -u
-fn detect_source(line: &str) -> Source {
-    if line.starts_with("commit ") || line.starts_with("diff --git ") {
-        Source::GitDiff
-    } else if line.starts_with("diff -u")
-        || line.starts_with("diff -ru")
-        || line.starts_with("diff -r -u")
-        || line.starts_with("diff -U")
-        || line.starts_with("--- ")
-        || line.starts_with("Only in ")
-    {
-        Source::DiffUnified
-    } else {
-        Source::Unknown
+/// Read the targets of the versions of the file that are symbolic links from the repository.
+fn set_symlink_targets(
+    file_info: &mut FileInfo,
+    blob_ids: &Option<(String, String)>,
+    minus_file: &str,
+    plus_file: &str,
+    config: &Config,
+) {
+    if let (Some(repo), Some((minus_blob_id, plus_blob_id))) = (&config.repo, blob_ids) {
+        let repo = repo.lock().unwrap();
+        let read = |blob_id, path| {
+            git_blob::get_blob_contents(&repo, blob_id, path)
+                .map(|contents| String::from_utf8_lossy(&contents).into_owned())
+        };
+        if file_info.is_minus_symlink() {
+            file_info.minus_symlink_target = read(minus_blob_id, minus_file);
+        }
+        if file_info.is_plus_symlink() {
+            file_info.plus_symlink_target = read(plus_blob_id, plus_file);
+        }
     }
 }
 
-fn handle_commit_meta_header_line(
-    painter: &mut Painter,
-    line: &str,
-    raw_line: &str,
-    config: &Config,
+fn render_buffered_minus_and_plus_lines(
+    annotator: &mut Annotator,
+    renderer: &mut dyn Renderer,
 ) -> std::io::Result<()> {
-    if config.commit_style.is_omitted {
-        return Ok(());
+    render_buffered_zero_line(annotator, renderer)?;
+    match annotator.annotate_buffered_minus_and_plus_lines() {
+        Some(changed_lines) => renderer.render(Event::ChangedLines(changed_lines)),
+        None => Ok(()),
     }
-    let decoration_ansi_term_style;
-    let mut pad = false;
-    let draw_fn = match config.commit_style.decoration_style {
-        DecorationStyle::Box(style) => {
-            pad = true;
-            decoration_ansi_term_style = style;
-            draw::write_boxed
-        }
-        DecorationStyle::BoxWithUnderline(style) => {
-            pad = true;
-            decoration_ansi_term_style = style;
-            draw::write_boxed_with_underline
-        }
-        DecorationStyle::BoxWithOverline(style) => {
-            pad = true;
-            decoration_ansi_term_style = style;
-            draw::write_boxed // TODO: not implemented
-        }
-        DecorationStyle::BoxWithUnderOverline(style) => {
-            pad = true;
-            decoration_ansi_term_style = style;
-            draw::write_boxed // TODO: not implemented
-        }
-        DecorationStyle::Underline(style) => {
-            decoration_ansi_term_style = style;
-            draw::write_underlined
-        }
-        DecorationStyle::Overline(style) => {
-            decoration_ansi_term_style = style;
-            draw::write_overlined
-        }
-        DecorationStyle::UnderOverline(style) => {
-            decoration_ansi_term_style = style;
-            draw::write_underoverlined
-        }
-        DecorationStyle::NoDecoration => {
-            decoration_ansi_term_style = ansi_term::Style::new();
-            draw::write_no_decoration
-        }
-    };
-    let (formatted_line, formatted_raw_line) = if config.hyperlinks {
-        (
-            features::hyperlinks::format_commit_line_with_osc8_commit_hyperlink(line, config),
-            features::hyperlinks::format_commit_line_with_osc8_commit_hyperlink(raw_line, config),
-        )
-    } else {
-        (Cow::from(line), Cow::from(raw_line))
-    };
+}
 
-    draw_fn(
-        painter.writer,
-        &format!("{}{}", formatted_line, if pad { " " } else { "" }),
-        &format!("{}{}", formatted_raw_line, if pad { " " } else { "" }),
-        &config.decorations_width,
-        config.commit_style,
-        decoration_ansi_term_style,
-    )?;
-    Ok(())
+fn render_buffered_zero_line(
+    annotator: &mut Annotator,
+    renderer: &mut dyn Renderer,
+) -> std::io::Result<()> {
+    match annotator.zero_line.take() {
+        Some(line) => renderer.render(Event::UnchangedLine(line)),
+        None => Ok(()),
+    }
 }
 
-/// Construct file change line from minus and plus file and write with FileMeta styling.
-fn handle_file_meta_header_line(
-    painter: &mut Painter,
+/// Read the full pre-image and post-image of the current file from the repository, for use by
+/// --full-file-syntax-highlighting and --hunk-header-enclosing-scope.
+fn set_file_syntax_states(
+    annotator: &mut Annotator,
+    minus_blob_id: &str,
+    plus_blob_id: &str,
     minus_file: &str,
     plus_file: &str,
+    encoding: Encoding,
     config: &Config,
-    file_event: &parse::FileEvent,
-    comparing: bool,
-) -> std::io::Result<()> {
-    let line = parse::get_file_change_description_from_file_paths(
-        minus_file, plus_file, comparing, file_event, config,
-    );
-    // FIXME: no support for 'raw'
-    handle_generic_file_meta_header_line(painter, &line, &line, config)
+) {
+    if let Some(repo) = &config.repo {
+        let (minus_file_contents, plus_file_contents) = {
+            let repo = repo.lock().unwrap();
+            let read = |blob_id, path| {
+                git_blob::get_blob_contents(&repo, blob_id, path)
+                    .map(|contents| encoding.decode(&contents))
+            };
+            (
+                read(minus_blob_id, minus_file),
+                read(plus_blob_id, plus_file),
+            )
+        };
+        annotator.set_file_syntax_states(
+            minus_file_contents.as_deref(),
+            plus_file_contents.as_deref(),
+        );
+    }
+}
+
+/// Parse a submodule header line such as "Submodule x/y 1234567..89abcde:", unless file headers
+/// are displayed raw.
+fn get_submodule_header(line: &str, config: &Config) -> Option<SubmoduleHeader> {
+    if line.starts_with("Submodule ")
+        && !config.color_only
+        && should_handle(&State::FileMeta, config)
+    {
+        submodule::parse_submodule_header_line(line)
+    } else {
+        None
+    }
 }
 
-/// Write `line` with FileMeta styling.
-fn handle_generic_file_meta_header_line(
-    painter: &mut Painter,
+/// Read both versions of the binary file described by the "Binary files ... differ" line `line`
+/// from the repository, and return the event displaying them under --image-thumbnails if either is
+/// an image, or else under --hex-diff.
+fn get_binary_file_event(
     line: &str,
-    raw_line: &str,
+    blob_ids: &Option<(String, String)>,
+    diff_prefixes: Option<&DiffPrefixes>,
     config: &Config,
-) -> std::io::Result<()> {
-    // If file_style is "omit", we'll skip the process and print nothing.
-    // However in the case of color_only mode,
-    // we won't skip because we can't change raw_line structure.
-    if config.file_style.is_omitted && !config.color_only {
-        return Ok(());
+) -> Option<Event> {
+    let (minus_file, plus_file) = parse::parse_binary_files_line(line, diff_prefixes)?;
+    let (minus_blob_id, plus_blob_id) = blob_ids.as_ref()?;
+    let (minus, plus) = {
+        let repo = config.repo.as_ref()?.lock().unwrap();
+        let read = |blob_id, path| git_blob::get_blob_contents(&repo, blob_id, path);
+        (
+            read(minus_blob_id, &minus_file),
+            read(plus_blob_id, &plus_file),
+        )
+    };
+    let is_image = |bytes: &Option<Vec<u8>>| {
+        bytes
+            .as_deref()
+            .and_then(image::ImageInfo::from_bytes)
+            .is_some()
+    };
+    if config.image_thumbnails && (is_image(&minus) || is_image(&plus)) {
+        Some(Event::ImageFileDiff(ImageFileDiff {
+            minus_image: minus,
+            plus_image: plus,
+        }))
+    } else if config.hex_diff {
+        Some(Event::BinaryFileDiff(get_binary_file_diff(
+            &minus.unwrap_or_default(),
+            &plus.unwrap_or_default(),
+            config,
+        )))
+    } else {
+        None
     }
-    let decoration_ansi_term_style;
-    let mut pad = false;
-    let draw_fn = match config.file_style.decoration_style {
-        DecorationStyle::Box(style) => {
-            pad = true;
-            decoration_ansi_term_style = style;
-            draw::write_boxed
-        }
-        DecorationStyle::BoxWithUnderline(style) => {
-            pad = true;
-            decoration_ansi_term_style = style;
-            draw::write_boxed_with_underline
-        }
-        DecorationStyle::BoxWithOverline(style) => {
-            pad = true;
-            decoration_ansi_term_style = style;
-            draw::write_boxed // TODO: not implemented
-        }
-        DecorationStyle::BoxWithUnderOverline(style) => {
-            pad = true;
-            decoration_ansi_term_style = style;
-            draw::write_boxed // TODO: not implemented
-        }
-        DecorationStyle::Underline(style) => {
-            decoration_ansi_term_style = style;
-            draw::write_underlined
-        }
-        DecorationStyle::Overline(style) => {
-            decoration_ansi_term_style = style;
-            draw::write_overlined
-        }
-        DecorationStyle::UnderOverline(style) => {
-            decoration_ansi_term_style = style;
-            draw::write_underoverlined
-        }
-        DecorationStyle::NoDecoration => {
-            decoration_ansi_term_style = ansi_term::Style::new();
-            draw::write_no_decoration
-        }
+}
+
+fn get_binary_file_diff(minus: &[u8], plus: &[u8], config: &Config) -> BinaryFileDiff {
+    let regions = if minus.len().max(plus.len()) <= config.hex_diff_max_size {
+        Some(hex_diff::get_differing_regions(minus, plus))
+    } else {
+        None
     };
-    // Prints the new line below file-meta-line.
-    // However in the case of color_only mode,
-    // we won't print it because we can't change raw_line structure.
-    if !config.color_only {
-        writeln!(painter.writer)?;
+    BinaryFileDiff {
+        minus_size: minus.len(),
+        plus_size: plus.len(),
+        regions,
     }
-    draw_fn(
-        painter.writer,
-        &format!("{}{}", line, if pad { " " } else { "" }),
-        &format!("{}{}", raw_line, if pad { " " } else { "" }),
-        &config.decorations_width,
-        config.file_style,
-        decoration_ansi_term_style,
-    )?;
-    Ok(())
 }
 
-fn handle_hunk_header_line(
-    painter: &mut Painter,
+/// Read the file to which the hunk with header `line` belongs from the repository, and start
+/// buffering its hunks for --context-lines. Return false if the file contents are not available.
+fn begin_hunk_context_expansion(
+    hunk_context_expander: &mut HunkContextExpander,
     line: &str,
-    raw_line: &str,
+    blob_ids: &Option<(String, String)>,
+    minus_file: &str,
     plus_file: &str,
+    encoding: Encoding,
     config: &Config,
-) -> std::io::Result<()> {
-    let decoration_ansi_term_style;
-    let mut pad = false;
-    let draw_fn = match config.hunk_header_style.decoration_style {
-        DecorationStyle::Box(style) => {
-            pad = true;
-            decoration_ansi_term_style = style;
-            draw::write_boxed
-        }
-        DecorationStyle::BoxWithUnderline(style) => {
-            pad = true;
-            decoration_ansi_term_style = style;
-            draw::write_boxed_with_underline
-        }
-        DecorationStyle::BoxWithOverline(style) => {
-            pad = true;
-            decoration_ansi_term_style = style;
-            draw::write_boxed // TODO: not implemented
-        }
-        DecorationStyle::BoxWithUnderOverline(style) => {
-            pad = true;
-            decoration_ansi_term_style = style;
-            draw::write_boxed // TODO: not implemented
-        }
-        DecorationStyle::Underline(style) => {
-            decoration_ansi_term_style = style;
-            draw::write_underlined
-        }
-        DecorationStyle::Overline(style) => {
-            decoration_ansi_term_style = style;
-            draw::write_overlined
-        }
-        DecorationStyle::UnderOverline(style) => {
-            decoration_ansi_term_style = style;
-            draw::write_underoverlined
-        }
-        DecorationStyle::NoDecoration => {
-            decoration_ansi_term_style = ansi_term::Style::new();
-            draw::write_no_decoration
-        }
-    };
-    let (raw_code_fragment, line_numbers) = parse::parse_hunk_header(&line);
-    // Emit the hunk header, with any requested decoration
-    if config.hunk_header_style.is_raw {
-        if config.hunk_header_style.decoration_style != DecorationStyle::NoDecoration {
-            writeln!(painter.writer)?;
+) -> bool {
+    let line = ansi::strip_ansi_codes(line);
+    match (&config.repo, blob_ids) {
+        (Some(repo), Some((minus_blob_id, plus_blob_id))) => {
+            match hunk_context::read_file_lines(
+                &repo.lock().unwrap(),
+                minus_blob_id,
+                plus_blob_id,
+                minus_file,
+                plus_file,
+                encoding,
+            ) {
+                Some((file_lines, is_plus_file)) => {
+                    hunk_context_expander.begin_file(file_lines, is_plus_file, &line)
+                }
+                None => false,
+            }
         }
-        draw_fn(
-            painter.writer,
-            &format!("{}{}", line, if pad { " " } else { "" }),
-            &format!("{}{}", raw_line, if pad { " " } else { "" }),
-            &config.decorations_width,
-            config.hunk_header_style,
-            decoration_ansi_term_style,
-        )?;
-    } else if config.hunk_header_style.is_omitted {
-        writeln!(painter.writer)?;
+        _ => false,
+    }
+}
+
+/// Return the encoding of the file at `path` set by the working-tree-encoding attribute, if any.
+fn get_working_tree_encoding(path: &str, config: &Config) -> Option<Encoding> {
+    let repo = config.repo.as_ref()?;
+    let name = git_blob::get_working_tree_encoding(&repo.lock().unwrap(), path)?;
+    Encoding::from_name(&name)
+}
+
+/// Truncate `raw_line` to config.max_line_length, if it is longer.
+pub fn truncate_raw_line<'a>(raw_line: Cow<'a, str>, config: &Config) -> Cow<'a, str> {
+    if config.max_line_length > 0 && raw_line.len() > config.max_line_length {
+        Cow::from(
+            ansi::truncate_str(&raw_line, config.max_line_length, &config.truncation_symbol)
+                .into_owned(),
+        )
     } else {
-        // Adjust the hunk-header-line before paint_lines.
-        // However in the case of color_only mode,
-        // we'll just use raw_line because we can't change raw_line structure.
-        let line = if config.color_only {
-            format!(" {}", &line)
-        } else {
-            match painter.prepare(&raw_code_fragment, false) {
-                s if !s.is_empty() => format!("{} ", s),
-                s => s,
-            }
-        };
+        raw_line
+    }
+}
 
-        // Prints the new line below hunk-header-line.
-        // However in the case of color_only mode,
-        // we won't print it because we can't change raw_line structure.
-        if !config.color_only {
-            writeln!(painter.writer)?;
-        }
-        if !line.is_empty() {
-            let lines = vec![(line, State::HunkHeader)];
-            let syntax_style_sections = Painter::get_syntax_style_sections_for_lines(
-                &lines,
-                &State::HunkHeader,
-                &mut painter.highlighter,
-                &painter.config,
-            );
-            Painter::paint_lines(
-                syntax_style_sections,
-                vec![vec![(config.hunk_header_style, &lines[0].0)]], // TODO: compute style from state
-                [State::HunkHeader].iter(),
-                &mut painter.output_buffer,
-                config,
-                &mut None,
-                None,
-                None,
-                Some(false),
-            );
-            painter.output_buffer.pop(); // trim newline
-            draw_fn(
-                painter.writer,
-                &painter.output_buffer,
-                &painter.output_buffer,
-                &config.decorations_width,
-                config.hunk_header_style,
-                decoration_ansi_term_style,
-            )?;
-            painter.output_buffer.clear();
-        }
-    };
+/// Should a handle_* function be called on this element?
+pub fn should_handle(state: &State, config: &Config) -> bool {
+    let style = config.get_style(state);
+    !(style.is_raw && style.decoration_style == DecorationStyle::NoDecoration)
+}
 
-    // Emit a full line-numbering
-    if config.line_numbers {
-        painter
-            .line_numbers_data
-            .initialize_hunk(line_numbers, plus_file.to_string());
-    // Emit a single line number.
-    // However with raw mode or color-only mode,
-    // we should prevent the output from creating new line for printing line number.
-    } else if config.line_numbers_show_first_line_number
-        && !config.hunk_header_style.is_raw
-        && !config.color_only
+/// Try to detect what is producing the input for delta.
+///
+/// Currently can detect:
+/// * git diff
+/// * diff -u
+pub fn detect_source(line: &str) -> Source {
+    if line.starts_with("commit ") || line.starts_with("diff --git ") {
+        Source::GitDiff
+    } else if line.starts_with("diff -u")
+        || line.starts_with("diff -ru")
+        || line.starts_with("diff -r -u")
+        || line.starts_with("diff -U")
+        || line.starts_with("--- ")
+        || line.starts_with("Only in ")
     {
-        let plus_line_number = line_numbers[line_numbers.len() - 1].0;
-        let formatted_plus_line_number = if config.hyperlinks {
-            features::hyperlinks::format_osc8_file_hyperlink(
-                plus_file,
-                Some(plus_line_number),
-                &format!("{}", plus_line_number),
-                config,
-            )
-        } else {
-            Cow::from(format!("{}", plus_line_number))
-        };
-        match config.hunk_header_style.decoration_ansi_term_style() {
-            Some(style) => writeln!(
-                painter.writer,
-                "{}",
-                style.paint(formatted_plus_line_number)
-            )?,
-            None => writeln!(painter.writer, "{}", formatted_plus_line_number)?,
-        }
+        Source::DiffUnified
+    } else {
+        Source::Unknown
     }
-    Ok(())
 }
 
 /// Handle a hunk line, i.e. a minus line, a plus line, or an unchanged line.
 // In the case of a minus or plus line, we store the line in a
 // buffer. When we exit the changed region we process the collected
-// minus and plus lines jointly, in order to paint detailed
-// highlighting according to inferred edit operations. In the case of
-// an unchanged line, we paint it immediately.
+// minus and plus lines jointly, in order to annotate them with the
+// inferred edit operations. In the case of an unchanged line, we
+// render it once the next line has been seen.
 fn handle_hunk_line(
-    painter: &mut Painter,
+    annotator: &mut Annotator,
+    renderer: &mut dyn Renderer,
     line: &str,
     raw_line: &str,
+    has_crlf: bool,
     state: State,
     config: &Config,
-) -> State {
+) -> std::io::Result<State> {
     // Don't let the line buffers become arbitrarily large -- if we
     // were to allow that, then for a large deleted/added file we
-    // would process the entire file before painting anything.
-    if painter.minus_lines.len() > config.line_buffer_size
-        || painter.plus_lines.len() > config.line_buffer_size
+    // would process the entire file before painting anything. A
+    // "\ No newline at end of file" line applies to the last buffered
+    // line, so the buffers are not flushed before it.
+    if (annotator.minus_lines.len() > config.line_buffer_size
+        || annotator.plus_lines.len() > config.line_buffer_size)
+        && !line.starts_with('\\')
     {
-        painter.paint_buffered_minus_and_plus_lines();
+        render_buffered_minus_and_plus_lines(annotator, renderer)?;
     }
+    let (line, has_bom) = line_endings::strip_byte_order_mark(line);
+    let format = LineFormat {
+        has_bom,
+        has_crlf,
+        has_no_newline: false,
+    };
     match line.chars().next() {
         Some('-') => {
+            render_buffered_zero_line(annotator, renderer)?;
             if let State::HunkPlus(_) = state {
-                painter.paint_buffered_minus_and_plus_lines();
+                render_buffered_minus_and_plus_lines(annotator, renderer)?;
             }
             let state = match config.inspect_raw_lines {
                 cli::InspectRawLines::True
@@ -584,16 +796,18 @@ fn handle_hunk_line(
                         [*style::GIT_DEFAULT_MINUS_STYLE, config.git_minus_style].iter(),
                     ) =>
                 {
-                    State::HunkMinus(Some(painter.prepare_raw_line(raw_line)))
+                    State::HunkMinus(Some(annotator.prepare_raw_line(raw_line)))
                 }
                 _ => State::HunkMinus(None),
             };
-            painter
+            annotator
                 .minus_lines
-                .push((painter.prepare(&line, true), state.clone()));
-            state
+                .push((annotator.prepare_changed_line(&line), state.clone()));
+            annotator.minus_line_formats.push(format);
+            Ok(state)
         }
         Some('+') => {
+            render_buffered_zero_line(annotator, renderer)?;
             let state = match config.inspect_raw_lines {
                 cli::InspectRawLines::True
                     if style::line_has_style_other_than(
@@ -601,30 +815,295 @@ fn handle_hunk_line(
                         [*style::GIT_DEFAULT_PLUS_STYLE, config.git_plus_style].iter(),
                     ) =>
                 {
-                    State::HunkPlus(Some(painter.prepare_raw_line(raw_line)))
+                    State::HunkPlus(Some(annotator.prepare_raw_line(raw_line)))
                 }
                 _ => State::HunkPlus(None),
             };
-            painter
+            annotator
                 .plus_lines
-                .push((painter.prepare(&line, true), state.clone()));
-            state
+                .push((annotator.prepare_changed_line(&line), state.clone()));
+            annotator.plus_line_formats.push(format);
+            Ok(state)
         }
         Some(' ') => {
-            painter.paint_buffered_minus_and_plus_lines();
-            painter.paint_zero_line(&line);
-            State::HunkZero
+            render_buffered_minus_and_plus_lines(annotator, renderer)?;
+            annotator.zero_line = Some(annotator.annotate_zero_line(&line, format));
+            Ok(State::HunkZero)
+        }
+        Some('\\') if !config.color_only => {
+            // "\ No newline at end of file" (possibly translated) applies to the preceding line,
+            // which is marked as such rather than the line being displayed. In color_only mode
+            // the line is displayed as it stands, since the structure of the input must be kept.
+            let format = match state {
+                State::HunkMinus(_) => annotator.minus_line_formats.last_mut(),
+                State::HunkPlus(_) => annotator.plus_line_formats.last_mut(),
+                _ => annotator.zero_line.as_mut().map(|line| &mut line.format),
+            };
+            if let Some(format) = format {
+                format.has_no_newline = true;
+            }
+            Ok(state)
         }
         _ => {
-            // The first character here could be e.g. '\' from '\ No newline at end of file'. This
-            // is not a hunk line, but the parser does not have a more accurate state corresponding
-            // to this.
-            painter.paint_buffered_minus_and_plus_lines();
-            painter
-                .output_buffer
-                .push_str(&painter.expand_tabs(raw_line.graphemes(true)));
-            painter.output_buffer.push('\n');
-            State::HunkZero
+            // The first character here could be e.g. '\' from '\ No newline at end of file' in
+            // color_only mode. This is not a hunk line, but the parser does not have a more
+            // accurate state corresponding to this.
+            render_buffered_minus_and_plus_lines(annotator, renderer)?;
+            renderer.render(Event::HunkMetaLine(
+                annotator.expand_tabs(raw_line.graphemes(true)),
+            ))?;
+            Ok(State::HunkZero)
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{EditOperation, Line};
+    use crate::tests::integration_test_utils::integration_test_utils;
+
+    fn parse(input: &str, args: &[&str]) -> Vec<Event> {
+        let config = integration_test_utils::make_config_from_args(args);
+        let mut events = Vec::new();
+        parse_diff(
+            BufRead::split(input.as_bytes(), b'\n'),
+            &config,
+            &mut events,
+        )
+        .unwrap();
+        events
+    }
+
+    fn get_edits(line: &Line) -> Vec<(EditOperation, &str)> {
+        line.edit_sections
+            .iter()
+            .map(|(operation, s)| (*operation, s.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_diff_events() {
+        let events = parse(DIFF, &[]);
+        let summary: Vec<String> = events
+            .iter()
+            .map(|event| match event {
+                Event::CommitHeader(commit_header) => format!("commit {}", commit_header.line),
+                Event::FileHeader(FileHeader::Paths { plus_file, .. }) => {
+                    format!("file {}", plus_file)
+                }
+                Event::FileHeader(FileHeader::Line { line, .. }) => format!("file {}", line),
+                Event::HunkHeader(hunk_header) => format!(
+                    "hunk {:?} {}",
+                    hunk_header.line_numbers, hunk_header.plus_file
+                ),
+                Event::ChangedLines(changed_lines) => format!(
+                    "changed {} {}",
+                    changed_lines.minus_lines.len(),
+                    changed_lines.plus_lines.len()
+                ),
+                Event::UnchangedLine(line) => format!("unchanged {:?}", line.text),
+                Event::BinaryFileDiff(binary_file_diff) => format!(
+                    "binary {} {}",
+                    binary_file_diff.minus_size, binary_file_diff.plus_size
+                ),
+                Event::ImageFileDiff(_) => "image".to_string(),
+                Event::SubmoduleHeader(header) => format!("submodule {}", header.path),
+                Event::SubmoduleCommit(commit) => format!("submodule commit {}", commit.summary),
+                Event::HunkMetaLine(line) => format!("meta {}", line),
+                Event::RawLine(line) => format!("raw {}", line),
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                "commit commit 94907c0f136f46dc46ffae2dc92dca9af7eb7c2e",
+                "raw Author: Dan Davison <dandavison7@gmail.com>",
+                "raw Date:   Sat Dec 12 12:00:00 2020 -0500",
+                "raw ",
+                "raw     Change a",
+                "raw ",
+                "file a.py",
+                "hunk [(1, 3), (1, 3)] a.py",
+                "unchanged \" x = 0\\n\"",
+                "changed 1 1",
+                "unchanged \" z = 2\\n\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_changed_lines_are_annotated_with_edit_operations() {
+        let events = parse(DIFF, &[]);
+        let changed_lines = events
+            .iter()
+            .find_map(|event| match event {
+                Event::ChangedLines(changed_lines) => Some(changed_lines),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(changed_lines.alignment, vec![(Some(0), Some(0))]);
+        let (minus_line, plus_line) = (&changed_lines.minus_lines[0], &changed_lines.plus_lines[0]);
+        assert_eq!(minus_line.state, State::HunkMinus(None));
+        assert_eq!(minus_line.text, " y = 1\n");
+        assert_eq!(
+            get_edits(minus_line),
+            vec![
+                (EditOperation::MinusNoop, " y = "),
+                (EditOperation::Deletion, "1"),
+                (EditOperation::Deletion, "\n"),
+            ]
+        );
+        assert_eq!(
+            get_edits(plus_line),
+            vec![
+                (EditOperation::PlusNoop, " y = "),
+                (EditOperation::Insertion, "111"),
+                (EditOperation::Insertion, "\n"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_hunk_lines_are_annotated_with_line_formats() {
+        let input = format!(
+            "{}{}",
+            HUNK_HEADER, "-\u{feff}x\r\n-y\r\n+x\n+y\n\\ No newline at end of file\n z\n"
+        );
+        let events = parse(&input, &[]);
+        let changed_lines = match &events[2] {
+            Event::ChangedLines(changed_lines) => changed_lines,
+            event => panic!("unexpected event: {:?}", event),
+        };
+        let formats = |lines: &[Line]| lines.iter().map(|line| line.format).collect::<Vec<_>>();
+        let format = |has_bom, has_crlf, has_no_newline| LineFormat {
+            has_bom,
+            has_crlf,
+            has_no_newline,
+        };
+        assert_eq!(
+            formats(&changed_lines.minus_lines),
+            vec![format(true, true, false), format(false, true, false)]
+        );
+        assert_eq!(
+            formats(&changed_lines.plus_lines),
+            vec![format(false, false, false), format(false, false, true)]
+        );
+        assert_eq!(changed_lines.minus_lines[0].text, " x\n");
+        assert!(matches!(&events[3], Event::UnchangedLine(line) if line.text == " z\n"));
+    }
+
+    #[test]
+    fn test_changed_lines_are_limited_by_line_buffer_size() {
+        let input = format!(
+            "{}{}",
+            HUNK_HEADER,
+            (0..8).map(|i| format!("-{}\n", i)).collect::<String>()
+        );
+        let events = parse(&input, &["--line-buffer-size", "2"]);
+        let sizes: Vec<usize> = events
+            .iter()
+            .filter_map(|event| match event {
+                Event::ChangedLines(changed_lines) => Some(changed_lines.minus_lines.len()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(sizes, vec![3, 3, 2]);
+    }
+
+    #[test]
+    fn test_byte_order_mark_is_recognized_only_at_the_start_of_a_file() {
+        let texts = |input: &[u8]| {
+            let config =
+                integration_test_utils::make_config_from_args(&["--default-encoding", "latin1"]);
+            let mut events = Vec::new();
+            parse_diff(BufRead::split(input, b'\n'), &config, &mut events).unwrap();
+            events
+                .into_iter()
+                .flat_map(|event| match event {
+                    Event::ChangedLines(changed_lines) => changed_lines
+                        .minus_lines
+                        .into_iter()
+                        .chain(changed_lines.plus_lines)
+                        .map(|line| line.text)
+                        .collect(),
+                    Event::UnchangedLine(line) => vec![line.text],
+                    _ => vec![],
+                })
+                .collect::<Vec<_>>()
+        };
+        // Lines in the middle of a file that begin with the bytes of a byte order mark.
+        assert_eq!(
+            texts(
+                b"\
+--- a/a.txt
++++ b/a.txt
+@@ -5,2 +5,2 @@
+ \xff\xfex
+-caf\xe9
++caf\xe9!
+"
+            ),
+            vec![" \u{ff}\u{fe}x\n", " caf\u{e9}\n", " caf\u{e9}!\n",]
+        );
+        // The first line of a UTF-16LE file ("a\nb\n").
+        assert_eq!(
+            texts(
+                b"\
+--- /dev/null
++++ b/a.txt
+@@ -0,0 +1,2 @@
++\xff\xfea\x00
++\x00b\x00
+"
+            ),
+            vec![" a\n", " b\n"]
+        );
+        // The encoding of a file does not apply to the next file.
+        assert_eq!(
+            texts(
+                b"\
+diff --git a/a.txt b/a.txt
+--- /dev/null
++++ b/a.txt
+@@ -0,0 +1 @@
++\xff\xfea\x00
+diff --git a/b.txt b/b.txt
+--- a/b.txt
++++ b/b.txt
+@@ -2 +2 @@
+-caf\xe9
++caf\xe9!
+"
+            )[1..],
+            [" caf\u{e9}\n", " caf\u{e9}!\n"]
+        );
+    }
+
+    const DIFF: &str = "\
+commit 94907c0f136f46dc46ffae2dc92dca9af7eb7c2e
+Author: Dan Davison <dandavison7@gmail.com>
+Date:   Sat Dec 12 12:00:00 2020 -0500
+
+    Change a
+
+diff --git a/a.py b/a.py
+index 1111111..2222222 100644
+--- a/a.py
++++ b/a.py
+@@ -1,3 +1,3 @@
+ x = 0
+-y = 1
++y = 111
+ z = 2
+\\ No newline at end of file
+";
+
+    const HUNK_HEADER: &str = "\
+diff --git a/a.txt b/a.txt
+index 1111111..2222222 100644
+--- a/a.txt
++++ b/a.txt
+@@ -1,8 +0,0 @@
+";
+}
@@ -3,7 +3,27 @@ use std::io::Write;
 
 use crate::ansi;
 use crate::cli::Width;
-use crate::style::Style;
+use crate::style::{DecorationStyle, Style};
+
+pub type DrawFunction =
+    fn(&mut dyn Write, &str, &str, &Width, Style, ansi_term::Style) -> std::io::Result<()>;
+
+/// Return the function that draws a header with the given decoration style, whether the header
+/// text should be padded, and the style of the decoration.
+pub fn get_draw_function(
+    decoration_style: DecorationStyle,
+) -> (DrawFunction, bool, ansi_term::Style) {
+    match decoration_style {
+        DecorationStyle::Box(style) => (write_boxed, true, style),
+        DecorationStyle::BoxWithUnderline(style) => (write_boxed_with_underline, true, style),
+        DecorationStyle::BoxWithOverline(style) => (write_boxed, true, style), // TODO: not implemented
+        DecorationStyle::BoxWithUnderOverline(style) => (write_boxed, true, style), // TODO: not implemented
+        DecorationStyle::Underline(style) => (write_underlined, false, style),
+        DecorationStyle::Overline(style) => (write_overlined, false, style),
+        DecorationStyle::UnderOverline(style) => (write_underoverlined, false, style),
+        DecorationStyle::NoDecoration => (write_no_decoration, false, ansi_term::Style::new()),
+    }
+}
 
 pub fn write_no_decoration(
     writer: &mut dyn Write,
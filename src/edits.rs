@@ -5,13 +5,23 @@ use unicode_width::UnicodeWidthStr;
 
 use crate::align;
 
+/// The maximum total size of the alignment tables computed when inferring the edits of a block of
+/// changed lines (see align::get_table_size). When pairing the remaining lines would exceed it,
+/// they are emitted without inferred edits, so that lines that are very long or numerous (such as
+/// minified code) cannot make delta unresponsive.
+pub const MAX_ALIGNMENT_CELLS_PER_BLOCK: usize = 20_000_000;
+
 /// Infer the edit operations responsible for the differences between a collection of old and new
 /// lines. A "line" is a string. An annotated line is a Vec of (op, &str) pairs, where the &str
 /// slices are slices of the line, and their concatenation equals the line. Return the input minus
 /// and plus lines, in annotated form. Also return a specification of the inferred alignment of
 /// minus and plus lines. `noop_deletions[i]` is the appropriate deletion operation tag to be used
 /// for `minus_lines[i]`; `noop_deletions` is guaranteed to be the same length as `minus_lines`.
-/// The equivalent statements hold for `plus_insertions` and `plus_lines`.
+/// The equivalent statements hold for `plus_insertions` and `plus_lines`. No more than
+/// `max_alignment_cells` cells of alignment tables are computed; once that budget would be
+/// exceeded, the remaining lines are emitted as unpaired. Substituted tokens whose distance is at
+/// most `max_token_distance` are annotated at the level of their characters (see
+/// refine_substitution).
 #[allow(clippy::too_many_arguments)]
 #[allow(clippy::type_complexity)]
 pub fn infer_edits<'a, EditOperation>(
@@ -24,6 +34,8 @@ pub fn infer_edits<'a, EditOperation>(
     tokenization_regex: &Regex,
     max_line_distance: f64,
     max_line_distance_for_naively_paired_lines: f64,
+    max_token_distance: f64,
+    max_alignment_cells: usize,
 ) -> (
     Vec<Vec<(EditOperation, &'a str)>>,  // annotated minus lines
     Vec<Vec<(EditOperation, &'a str)>>,  // annotated plus lines
@@ -38,14 +50,35 @@ where
     let mut line_alignment = Vec::<(Option<usize>, Option<usize>)>::new();
 
     let mut plus_index = 0; // plus lines emitted so far
+    let mut alignment_cells = 0; // size of the alignment tables computed so far
+
+    let minus_tokens: Vec<_> = minus_lines
+        .iter()
+        .map(|line| tokenize(line, tokenization_regex))
+        .collect();
+    let plus_tokens: Vec<_> = plus_lines
+        .iter()
+        .map(|line| tokenize(line, tokenization_regex))
+        .collect();
 
     'minus_lines_loop: for (minus_index, minus_line) in minus_lines.iter().enumerate() {
         let mut considered = 0; // plus lines considered so far as match for minus_line
-        for plus_line in &plus_lines[plus_index..] {
-            let alignment = align::Alignment::new(
-                tokenize(minus_line, tokenization_regex),
-                tokenize(plus_line, tokenization_regex),
-            );
+        for (plus_line, plus_line_tokens) in plus_lines[plus_index..]
+            .iter()
+            .zip(&plus_tokens[plus_index..])
+        {
+            alignment_cells +=
+                align::get_table_size(minus_tokens[minus_index].len(), plus_line_tokens.len());
+            if alignment_cells > max_alignment_cells {
+                // Emit this and the remaining minus lines as unpaired.
+                for (minus_index, minus_line) in minus_lines.iter().enumerate().skip(minus_index) {
+                    annotated_minus_lines.push(vec![(noop_deletions[minus_index], minus_line)]);
+                    line_alignment.push((Some(minus_index), None));
+                }
+                break 'minus_lines_loop;
+            }
+            let alignment =
+                align::Alignment::new(minus_tokens[minus_index].clone(), plus_line_tokens.clone());
             let (annotated_minus_line, annotated_plus_line, distance) = annotate(
                 alignment,
                 noop_deletions[minus_index],
@@ -54,6 +87,7 @@ where
                 insertion,
                 minus_line,
                 plus_line,
+                max_token_distance,
             );
             if minus_lines.len() == plus_lines.len()
                 && distance <= max_line_distance_for_naively_paired_lines
@@ -125,6 +159,7 @@ fn tokenize<'a>(line: &'a str, regex: &Regex) -> Vec<&'a str> {
 // This function doesn't return "coalesced" annotations: i.e. they're often are runs of consecutive
 // occurrences of the same operation. Since it is returning &strs pointing into the memory of the
 // original line, it's not possible to coalesce them in this function.
+#[allow(clippy::too_many_arguments)]
 #[allow(clippy::type_complexity)]
 fn annotate<'a, Annotation>(
     alignment: align::Alignment<'a>,
@@ -134,6 +169,7 @@ fn annotate<'a, Annotation>(
     insertion: Annotation,
     minus_line: &'a str,
     plus_line: &'a str,
+    max_token_distance: f64,
 ) -> (Vec<(Annotation, &'a str)>, Vec<(Annotation, &'a str)>, f64)
 where
     Annotation: Copy,
@@ -229,10 +265,29 @@ where
                 let n_d = distance_contribution(minus_section);
                 d_denom += n_d;
                 d_numer += n_d;
-                annotated_minus_line.push((deletion, minus_section));
-                annotated_plus_line.push((insertion, plus_section(n)));
-                minus_op_prev = deletion;
-                plus_op_prev = insertion;
+                let plus_section = plus_section(n);
+                match refine_substitution(
+                    minus_section,
+                    plus_section,
+                    noop_deletion,
+                    deletion,
+                    noop_insertion,
+                    insertion,
+                    max_token_distance,
+                ) {
+                    Some((minus_subsections, plus_subsections)) => {
+                        minus_op_prev = minus_subsections.last().unwrap().0;
+                        plus_op_prev = plus_subsections.last().unwrap().0;
+                        annotated_minus_line.extend(minus_subsections);
+                        annotated_plus_line.extend(plus_subsections);
+                    }
+                    None => {
+                        annotated_minus_line.push((deletion, minus_section));
+                        annotated_plus_line.push((insertion, plus_section));
+                        minus_op_prev = deletion;
+                        plus_op_prev = insertion;
+                    }
+                }
             }
             align::Operation::Insertion => {
                 let plus_section = plus_section(n);
@@ -251,6 +306,93 @@ where
     )
 }
 
+/// Align the characters (grapheme clusters) of a section of the minus line and the section of the
+/// plus line that was substituted for it. If the proportion of characters that differ is at most
+/// `max_token_distance`, return the sections annotated so that only those characters are
+/// emphasized; otherwise return None, and the sections are emphasized in their entirety.
+#[allow(clippy::type_complexity)]
+fn refine_substitution<'a, Annotation>(
+    minus_section: &'a str,
+    plus_section: &'a str,
+    noop_deletion: Annotation,
+    deletion: Annotation,
+    noop_insertion: Annotation,
+    insertion: Annotation,
+    max_token_distance: f64,
+) -> Option<(Vec<(Annotation, &'a str)>, Vec<(Annotation, &'a str)>)>
+where
+    Annotation: Copy,
+{
+    if max_token_distance <= 0.0 {
+        return None;
+    }
+    // A common empty first item satisfies the requirement of align::Alignment (see the TODO in
+    // align::Alignment::new) that alignments begin with a NoOp.
+    let graphemes = |section: &'a str| {
+        std::iter::once("")
+            .chain(section.graphemes(true))
+            .collect::<Vec<_>>()
+    };
+    let alignment = align::Alignment::new(graphemes(minus_section), graphemes(plus_section));
+    let (d_numer, d_denom) = alignment.distance_parts();
+    if compute_distance(d_numer as f64, (d_denom - 1) as f64) > max_token_distance {
+        return None;
+    }
+
+    let mut annotated_minus_section = Vec::new();
+    let mut annotated_plus_section = Vec::new();
+    let (mut x_offset, mut y_offset) = (0, 0);
+    let (mut minus_offset, mut plus_offset) = (0, 0);
+    let push_subsection = |annotated_section: &mut Vec<(Annotation, &'a str)>,
+                           annotation: Annotation,
+                           n: usize,
+                           offset: &mut usize,
+                           graphemes_offset: &mut usize,
+                           graphemes: &[&str],
+                           section: &'a str| {
+        let length = graphemes[*graphemes_offset..*graphemes_offset + n]
+            .iter()
+            .fold(0, |n, g| n + g.len());
+        *graphemes_offset += n;
+        // The first NoOp run may consist of the empty first item only.
+        if length > 0 {
+            annotated_section.push((annotation, &section[*offset..*offset + length]));
+            *offset += length;
+        }
+    };
+    for (op, n) in alignment.coalesced_operations() {
+        let (minus_annotation, plus_annotation) = match op {
+            align::Operation::NoOp => (Some(noop_deletion), Some(noop_insertion)),
+            align::Operation::Substitution => (Some(deletion), Some(insertion)),
+            align::Operation::Deletion => (Some(deletion), None),
+            align::Operation::Insertion => (None, Some(insertion)),
+        };
+        if let Some(annotation) = minus_annotation {
+            push_subsection(
+                &mut annotated_minus_section,
+                annotation,
+                n,
+                &mut minus_offset,
+                &mut x_offset,
+                &alignment.x,
+                minus_section,
+            );
+        }
+        if let Some(annotation) = plus_annotation {
+            push_subsection(
+                &mut annotated_plus_section,
+                annotation,
+                n,
+                &mut plus_offset,
+                &mut y_offset,
+                &alignment.y,
+                plus_section,
+            );
+        }
+    }
+    Some((annotated_minus_section, annotated_plus_section))
+}
+
 fn compute_distance(d_numer: f64, d_denom: f64) -> f64 {
     if d_denom > 0.0 {
         d_numer / d_denom
@@ -723,6 +865,151 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_infer_edits_stops_when_budget_is_exceeded() {
+        let minus_lines = vec![" a b c", " d e f"];
+        let plus_lines = vec![" a b x", " d e y"];
+        let tokens = |line| tokenize(line, &DEFAULT_TOKENIZATION_REGEXP).len();
+        // Enough for the first pair of lines only.
+        let max_alignment_cells =
+            align::get_table_size(tokens(minus_lines[0]), tokens(plus_lines[0]));
+        let (annotated_minus_lines, annotated_plus_lines, line_alignment) = infer_edits(
+            minus_lines,
+            plus_lines,
+            vec![MinusNoop; 2],
+            Deletion,
+            vec![PlusNoop; 2],
+            Insertion,
+            &DEFAULT_TOKENIZATION_REGEXP,
+            1.0,
+            1.0,
+            0.0,
+            max_alignment_cells,
+        );
+        assert_eq!(
+            annotated_minus_lines,
+            vec![
+                vec![(MinusNoop, " a b "), (Deletion, "c")],
+                vec![(MinusNoop, " d e f")]
+            ]
+        );
+        assert_eq!(
+            annotated_plus_lines,
+            vec![
+                vec![(PlusNoop, " a b "), (Insertion, "x")],
+                vec![(PlusNoop, " d e y")]
+            ]
+        );
+        assert_eq!(
+            line_alignment,
+            vec![(Some(0), Some(0)), (Some(1), None), (None, Some(1))]
+        );
+    }
+
+    #[test]
+    fn test_infer_edits_refines_substituted_tokens() {
+        // A one-character typo fix emphasizes the inserted character only.
+        assert_refined_edits(
+            "    let identifer = 1;",
+            "    let identifier = 1;",
+            (
+                vec![vec![
+                    (MinusNoop, "    let "),
+                    (MinusNoop, "identif"),
+                    (MinusNoop, "er"),
+                    (MinusNoop, " = 1;"),
+                ]],
+                vec![vec![
+                    (PlusNoop, "    let "),
+                    (PlusNoop, "identif"),
+                    (Insertion, "i"),
+                    (PlusNoop, "er"),
+                    (PlusNoop, " = 1;"),
+                ]],
+            ),
+            0.3,
+        );
+        // Tokens that differ by more than the maximum distance are emphasized in their entirety.
+        assert_refined_edits(
+            " let foo = 1;",
+            " let bar = 1;",
+            (
+                vec![vec![
+                    (MinusNoop, " let "),
+                    (Deletion, "foo"),
+                    (MinusNoop, " = 1;"),
+                ]],
+                vec![vec![
+                    (PlusNoop, " let "),
+                    (Insertion, "bar"),
+                    (PlusNoop, " = 1;"),
+                ]],
+            ),
+            0.3,
+        );
+        // As are all tokens when the maximum distance is zero.
+        assert_refined_edits(
+            "    let identifer = 1;",
+            "    let identifier = 1;",
+            (
+                vec![vec![
+                    (MinusNoop, "    let "),
+                    (Deletion, "identifer"),
+                    (MinusNoop, " = 1;"),
+                ]],
+                vec![vec![
+                    (PlusNoop, "    let "),
+                    (Insertion, "identifier"),
+                    (PlusNoop, " = 1;"),
+                ]],
+            ),
+            0.0,
+        );
+    }
+
+    #[test]
+    fn test_infer_edits_refines_substituted_tokens_by_grapheme() {
+        // A combining character is emphasized together with the character it modifies.
+        assert_refined_edits(
+            " nai\u{308}ve",
+            " naive",
+            (
+                vec![vec![
+                    (MinusNoop, " "),
+                    (MinusNoop, "na"),
+                    (Deletion, "i\u{308}"),
+                    (MinusNoop, "ve"),
+                ]],
+                vec![vec![
+                    (PlusNoop, " "),
+                    (PlusNoop, "na"),
+                    (Insertion, "i"),
+                    (PlusNoop, "ve"),
+                ]],
+            ),
+            0.3,
+        );
+        assert_refined_edits(
+            " 漢字を書く",
+            " 漢字が書く",
+            (
+                vec![vec![
+                    (MinusNoop, " "),
+                    (MinusNoop, "漢字"),
+                    (Deletion, "を"),
+                    (MinusNoop, "書く"),
+                ]],
+                vec![vec![
+                    (PlusNoop, " "),
+                    (PlusNoop, "漢字"),
+                    (Insertion, "が"),
+                    (PlusNoop, "書く"),
+                ]],
+            ),
+            0.3,
+        );
+    }
+
     fn assert_edits(
         minus_lines: Vec<&str>,
         plus_lines: Vec<&str>,
@@ -743,12 +1030,39 @@ mod tests {
             &*DEFAULT_TOKENIZATION_REGEXP,
             max_line_distance,
             0.0,
+            0.0,
+            MAX_ALIGNMENT_CELLS_PER_BLOCK,
         );
         // compare_annotated_lines(actual_edits, expected_edits);
         // TODO: test line alignment
         assert_eq!((actual_edits.0, actual_edits.1), expected_edits);
     }
 
+    // Assertions for a single pair of lines, with the supplied maximum distance for substituted
+    // tokens to be annotated at the level of their characters.
+    fn assert_refined_edits(
+        minus_line: &str,
+        plus_line: &str,
+        expected_edits: Edits,
+        max_token_distance: f64,
+    ) {
+        assert_consistent_pairs(&expected_edits);
+        let actual_edits = infer_edits(
+            vec![minus_line],
+            vec![plus_line],
+            vec![MinusNoop],
+            Deletion,
+            vec![PlusNoop],
+            Insertion,
+            &DEFAULT_TOKENIZATION_REGEXP,
+            1.0,
+            0.0,
+            max_token_distance,
+            MAX_ALIGNMENT_CELLS_PER_BLOCK,
+        );
+        assert_eq!((actual_edits.0, actual_edits.1), expected_edits);
+    }
+
     // Assert that no edits are inferred for the supplied minus and plus lines.
     fn assert_no_edits(minus_lines: Vec<&str>, plus_lines: Vec<&str>, max_line_distance: f64) {
         let expected_edits = (
@@ -0,0 +1,221 @@
+use syntect::parsing::{ParseState, Scope, ScopeStack, SyntaxReference, SyntaxSet};
+
+/// The kinds of definition whose names are shown in an enclosing scope breadcrumb. A definition
+/// is recognized by a token with scope entity.name.<kind>.
+const DEFINITION_KINDS: &[&str] = &[
+    "class",
+    "enum",
+    "function",
+    "impl",
+    "interface",
+    "module",
+    "namespace",
+    "struct",
+    "trait",
+];
+
+/// The separator between the definitions in an enclosing scope breadcrumb.
+const BREADCRUMB_SEPARATOR: &str = " › ";
+
+/// An outline of a file (the pre-image of a diff), from which the definitions (functions,
+/// classes, impls, modules, etc) enclosing a line can be obtained.
+///
+/// Definitions are recognized using the scopes assigned by the syntax definition of the language.
+/// Since many syntax definitions do not assign a scope to the body of a definition (e.g. Python),
+/// nesting is determined by indentation: a line is enclosed by the nearest preceding line with
+/// less indentation, which is enclosed in turn by the nearest line preceding that with less
+/// indentation, and so on.
+pub struct EnclosingScopes {
+    lines: Vec<String>,
+    parse_state: ParseState,
+    scope_stack: ScopeStack,
+    outline: Vec<OutlineEntry>,
+    next_line_number: usize, // 1-based number of the next line to be parsed
+}
+
+// A line containing code, i.e. a line that is neither blank, nor a continuation of a comment or
+// string.
+struct OutlineEntry {
+    line_number: usize,
+    indentation: usize,
+    definition: Option<String>,
+}
+
+impl EnclosingScopes {
+    pub fn new(contents: &str, syntax: &SyntaxReference) -> Self {
+        Self {
+            lines: contents.lines().map(|line| format!("{}\n", line)).collect(),
+            parse_state: ParseState::new(syntax),
+            scope_stack: ScopeStack::new(),
+            outline: Vec::new(),
+            next_line_number: 1,
+        }
+    }
+
+    /// Return a breadcrumb such as "mod foo › impl Bar › fn baz" describing the definitions
+    /// enclosing the first line of code at or after `line_number`, or None if there are none.
+    pub fn get_breadcrumb(&mut self, line_number: usize, syntax_set: &SyntaxSet) -> Option<String> {
+        while self.next_line_number <= self.lines.len()
+            && !matches!(self.outline.last(), Some(entry) if entry.line_number >= line_number)
+        {
+            self.parse_next_line(syntax_set);
+        }
+        let i = self
+            .outline
+            .iter()
+            .position(|entry| entry.line_number >= line_number)?;
+        let mut indentation = self.outline[i].indentation;
+        let mut definitions = Vec::new();
+        for entry in self.outline[..i].iter().rev() {
+            if entry.indentation < indentation {
+                indentation = entry.indentation;
+                if let Some(definition) = &entry.definition {
+                    definitions.push(definition.as_str());
+                }
+            }
+        }
+        if definitions.is_empty() {
+            None
+        } else {
+            definitions.reverse();
+            Some(definitions.join(BREADCRUMB_SEPARATOR))
+        }
+    }
+
+    fn parse_next_line(&mut self, syntax_set: &SyntaxSet) {
+        let line = &self.lines[self.next_line_number - 1];
+        let ops = self.parse_state.parse_line(line, syntax_set);
+        let code_start = line.len() - line.trim_start().len();
+
+        // The scope stack applying to each token of the line.
+        let mut tokens = Vec::new();
+        let mut token_start = 0;
+        for (offset, op) in ops {
+            if offset > token_start {
+                tokens.push((&line[token_start..offset], self.scope_stack.clone()));
+                token_start = offset;
+            }
+            self.scope_stack.apply(&op);
+        }
+        tokens.push((&line[token_start..], self.scope_stack.clone()));
+
+        let mut offset = 0;
+        let mut is_code = false;
+        let mut keyword = None;
+        let mut definition = None;
+        for (token, scope_stack) in tokens {
+            if offset <= code_start && code_start < offset + token.len() {
+                is_code = !scope_stack
+                    .as_slice()
+                    .iter()
+                    .any(|scope| has_atom(scope, "comment") || has_atom(scope, "string"));
+            }
+            offset += token.len();
+            let scopes = scope_stack.as_slice();
+            if scopes.iter().any(is_definition_keyword) {
+                keyword = Some(token.trim());
+            } else if definition.is_none() && scopes.iter().any(is_definition_name) {
+                definition = Some(match keyword {
+                    Some(keyword) => format!("{} {}", keyword, token.trim()),
+                    None => token.trim().to_string(),
+                });
+            }
+        }
+        if is_code {
+            self.outline.push(OutlineEntry {
+                line_number: self.next_line_number,
+                indentation: code_start,
+                definition,
+            });
+        }
+        self.next_line_number += 1;
+    }
+}
+
+/// E.g. entity.name.function.rust
+fn is_definition_name(scope: &Scope) -> bool {
+    let scope = scope.build_string();
+    let mut atoms = scope.split('.');
+    atoms.next() == Some("entity")
+        && atoms.next() == Some("name")
+        && matches!(atoms.next(), Some(kind) if DEFINITION_KINDS.contains(&kind))
+}
+
+/// E.g. storage.type.function.rust ("fn"), keyword.declaration.class.python ("class")
+fn is_definition_keyword(scope: &Scope) -> bool {
+    (has_atom(scope, "storage") || has_atom(scope, "keyword"))
+        && DEFINITION_KINDS.iter().any(|kind| has_atom(scope, kind))
+}
+
+fn has_atom(scope: &Scope, atom: &str) -> bool {
+    scope.build_string().split('.').any(|s| s == atom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bat_utils::assets::HighlightingAssets;
+
+    fn get_breadcrumbs(contents: &str, extension: &str) -> Vec<Option<String>> {
+        let assets = HighlightingAssets::new();
+        let syntax = assets
+            .syntax_set
+            .find_syntax_by_extension(extension)
+            .unwrap();
+        let mut enclosing_scopes = EnclosingScopes::new(contents, syntax);
+        (1..=contents.lines().count())
+            .map(|n| enclosing_scopes.get_breadcrumb(n, &assets.syntax_set))
+            .collect()
+    }
+
+    #[test]
+    fn test_rust() {
+        let breadcrumbs = get_breadcrumbs(
+            "\
+mod foo {
+    impl<'a> Bar<'a> {
+        pub fn baz(&self) {
+
+            // a comment
+            let x = 1;
+        }
+    }
+}
+fn f() {}
+",
+            "rs",
+        );
+        assert_eq!(breadcrumbs[0], None);
+        assert_eq!(breadcrumbs[1].as_deref(), Some("mod foo"));
+        assert_eq!(breadcrumbs[2].as_deref(), Some("mod foo › impl Bar"));
+        for breadcrumb in &breadcrumbs[3..6] {
+            assert_eq!(breadcrumb.as_deref(), Some("mod foo › impl Bar › fn baz"));
+        }
+        assert_eq!(breadcrumbs[6].as_deref(), Some("mod foo › impl Bar"));
+        assert_eq!(breadcrumbs[9], None);
+    }
+
+    #[test]
+    fn test_python() {
+        let breadcrumbs = get_breadcrumbs(
+            "\
+class A:
+    def f(self):
+        \"\"\"
+A docstring.
+        \"\"\"
+        x = 1
+y = 2
+if y:
+    z = 3
+",
+            "py",
+        );
+        assert_eq!(breadcrumbs[1].as_deref(), Some("class A"));
+        for breadcrumb in &breadcrumbs[2..6] {
+            assert_eq!(breadcrumb.as_deref(), Some("class A › def f"));
+        }
+        assert_eq!(breadcrumbs[6], None);
+        assert_eq!(breadcrumbs[8], None);
+    }
+}
@@ -0,0 +1,221 @@
+use std::char;
+
+/// The encodings in which delta can read the contents of files. Lines that are valid UTF-8 are
+/// read as UTF-8 whatever the encoding of the file, since the metadata of a diff is UTF-8 and git
+/// converts files with a working-tree-encoding to UTF-8 before diffing them.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Latin1,
+    Windows1252,
+    ShiftJis,
+}
+
+impl Encoding {
+    /// Return the encoding with the given name, as used by iconv and the working-tree-encoding
+    /// git attribute, e.g. "UTF-16LE" or "ISO-8859-1".
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().replace('_', "-").as_str() {
+            "utf-8" | "utf8" => Some(Self::Utf8),
+            "utf-16" | "utf16" | "utf-16le" | "utf16le" | "ucs-2" | "ucs-2le" => {
+                Some(Self::Utf16Le)
+            }
+            "utf-16be" | "utf16be" | "ucs-2be" => Some(Self::Utf16Be),
+            "latin1" | "latin-1" | "l1" | "iso-8859-1" | "iso8859-1" => Some(Self::Latin1),
+            "windows-1252" | "cp1252" => Some(Self::Windows1252),
+            "shift-jis" | "shiftjis" | "sjis" | "ms-kanji" | "cp932" => Some(Self::ShiftJis),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Utf8 => "utf-8",
+            Self::Utf16Le => "utf-16le",
+            Self::Utf16Be => "utf-16be",
+            Self::Latin1 => "latin1",
+            Self::Windows1252 => "windows-1252",
+            Self::ShiftJis => "shift_jis",
+        }
+    }
+
+    /// Return the UTF-16 encoding indicated by the byte order mark at the start of the hunk line
+    /// `line` (following its -/+/space character), if it has one.
+    pub fn from_byte_order_mark(line: &[u8]) -> Option<Self> {
+        match line.get(1..3) {
+            Some([0xff, 0xfe]) => Some(Self::Utf16Le),
+            Some([0xfe, 0xff]) => Some(Self::Utf16Be),
+            _ => None,
+        }
+    }
+
+    /// Decode the contents of a file.
+    pub fn decode(&self, bytes: &[u8]) -> String {
+        if self.is_utf16() && bytes.contains(&0) {
+            return self.decode_utf16(bytes);
+        }
+        match std::str::from_utf8(bytes) {
+            Ok(s) => s.to_string(),
+            Err(_) => self.decode_non_utf8(bytes),
+        }
+    }
+
+    /// Decode a line of a diff. A hunk line in a UTF-16 encoding has a single-byte -/+/space
+    /// character, and, since the input was divided into lines at '\n' bytes, the other byte of the
+    /// preceding newline at its start (little-endian) or of its own newline at its end
+    /// (big-endian).
+    pub fn decode_line(&self, bytes: Vec<u8>) -> String {
+        if self.is_utf16() && bytes.contains(&0) {
+            let (prefix, rest) = bytes.split_at(1);
+            let rest = match (self, rest.len() % 2) {
+                (Self::Utf16Le, 1) => &rest[1..],
+                (Self::Utf16Be, 1) => &rest[..rest.len() - 1],
+                _ => rest,
+            };
+            return format!("{}{}", prefix[0] as char, self.decode_utf16(rest));
+        }
+        match String::from_utf8(bytes) {
+            Ok(s) => s,
+            Err(error) => self.decode_non_utf8(error.as_bytes()),
+        }
+    }
+
+    fn is_utf16(&self) -> bool {
+        matches!(self, Self::Utf16Le | Self::Utf16Be)
+    }
+
+    fn decode_utf16(&self, bytes: &[u8]) -> String {
+        let units = bytes.chunks(2).map(|pair| match (self, pair) {
+            (Self::Utf16Be, [high, low]) => u16::from_be_bytes([*high, *low]),
+            (_, [low, high]) => u16::from_le_bytes([*low, *high]),
+            (_, _) => char::REPLACEMENT_CHARACTER as u16,
+        });
+        char::decode_utf16(units)
+            .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+            .collect()
+    }
+
+    /// Decode text that is not valid UTF-8 as Latin-1, Windows-1252 or Shift-JIS. In other
+    /// encodings it is decoded as String::from_utf8_lossy does. (The second byte of a Shift-JIS
+    /// character is never '\n', so dividing the input into lines does not split characters.)
+    /// encoding_rs follows the WHATWG Encoding Standard, in which ISO-8859-1 is a label of
+    /// Windows-1252, so Latin-1 is decoded byte for byte by encoding_rs::mem instead.
+    fn decode_non_utf8(&self, bytes: &[u8]) -> String {
+        match self {
+            Self::Latin1 => encoding_rs::mem::decode_latin1(bytes).into_owned(),
+            Self::Windows1252 => encoding_rs::WINDOWS_1252
+                .decode_without_bom_handling(bytes)
+                .0
+                .into_owned(),
+            Self::ShiftJis => encoding_rs::SHIFT_JIS
+                .decode_without_bom_handling(bytes)
+                .0
+                .into_owned(),
+            _ => String::from_utf8_lossy(bytes).into_owned(),
+        }
+    }
+}
+
+/// Whether the next hunk line of each side of a diff is the first line of the file, which is the
+/// only line at which a byte order mark is recognized.
+#[derive(Default)]
+pub struct FirstLines {
+    minus: bool,
+    plus: bool,
+}
+
+impl FirstLines {
+    /// Begin a hunk with the given (line_number, hunk_length) pairs of the minus and plus files.
+    pub fn begin_hunk(&mut self, line_numbers_and_hunk_lengths: &[(usize, usize)]) {
+        self.minus = matches!(line_numbers_and_hunk_lengths.first(), Some((1, n)) if *n > 0);
+        self.plus = matches!(line_numbers_and_hunk_lengths.last(), Some((1, n)) if *n > 0);
+    }
+
+    /// Return the encoding indicated by the byte order mark of `line`, if it is the first line of
+    /// a file.
+    pub fn byte_order_mark(&mut self, line: &[u8]) -> Option<Encoding> {
+        let (minus, plus) = match line.first() {
+            Some(b'-') => (true, false),
+            Some(b'+') => (false, true),
+            Some(b' ') => (true, true),
+            _ => (false, false),
+        };
+        let is_first_line = (minus && self.minus) || (plus && self.plus);
+        self.minus &= !minus;
+        self.plus &= !plus;
+        if is_first_line {
+            Encoding::from_byte_order_mark(line)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_name() {
+        assert_eq!(Encoding::from_name("UTF-16LE"), Some(Encoding::Utf16Le));
+        assert_eq!(Encoding::from_name("iso-8859-1"), Some(Encoding::Latin1));
+        assert_eq!(Encoding::from_name("CP1252"), Some(Encoding::Windows1252));
+        assert_eq!(Encoding::from_name("Shift_JIS"), Some(Encoding::ShiftJis));
+        assert_eq!(Encoding::from_name("sjis"), Some(Encoding::ShiftJis));
+        assert_eq!(Encoding::from_name("EBCDIC"), None);
+    }
+
+    #[test]
+    fn test_decode_line_single_byte() {
+        let line = b"+caf\xe9 \x80".to_vec();
+        assert_eq!(Encoding::Latin1.decode_line(line.clone()), "+café \u{80}");
+        assert_eq!(Encoding::Windows1252.decode_line(line.clone()), "+café €");
+        assert_eq!(Encoding::Utf8.decode_line(line), "+caf\u{fffd} \u{fffd}");
+        assert_eq!(Encoding::Latin1.decode_line("+café".into()), "+café");
+    }
+
+    #[test]
+    fn test_decode_line_shift_jis() {
+        // "日本語" in Shift-JIS.
+        let line = b"-\x93\xfa\x96\x7b\x8c\xea".to_vec();
+        assert_eq!(Encoding::ShiftJis.decode_line(line.clone()), "-日本語");
+        assert_eq!(Encoding::ShiftJis.decode(&line[1..]), "日本語");
+        assert_eq!(Encoding::Latin1.decode_line("+日本語".into()), "+日本語");
+    }
+
+    #[test]
+    fn test_decode_line_utf16() {
+        // "é\n" in UTF-16LE is e9 00 0a 00: the 00 following the newline begins the next line.
+        assert_eq!(
+            Encoding::Utf16Le.decode_line(b"-\xff\xfea\x00".to_vec()),
+            "-\u{feff}a"
+        );
+        assert_eq!(
+            Encoding::Utf16Le.decode_line(b"-\x00\xe9\x00".to_vec()),
+            "-é"
+        );
+        assert_eq!(
+            Encoding::Utf16Be.decode_line(b"+\x00\xe9\x00".to_vec()),
+            "+é"
+        );
+        assert_eq!(
+            Encoding::Utf16Le.decode_line(b"@@ -1 +1 @@".to_vec()),
+            "@@ -1 +1 @@"
+        );
+    }
+
+    #[test]
+    fn test_from_byte_order_mark() {
+        assert_eq!(
+            Encoding::from_byte_order_mark(b"+\xff\xfea\x00"),
+            Some(Encoding::Utf16Le)
+        );
+        assert_eq!(
+            Encoding::from_byte_order_mark(b" \xfe\xff\x00a"),
+            Some(Encoding::Utf16Be)
+        );
+        assert_eq!(Encoding::from_byte_order_mark(b"+a"), None);
+    }
+}
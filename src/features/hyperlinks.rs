@@ -51,18 +51,27 @@ pub fn format_osc8_file_hyperlink<'a>(
         } else {
             url = url.replace("{line}", "")
         };
-        Cow::from(format!(
-            "{osc}8;;{url}{st}{text}{osc}8;;{st}",
-            url = url,
-            text = text,
-            osc = "\x1b]",
-            st = "\x1b\\"
-        ))
+        Cow::from(format_osc8_hyperlink(&url, text))
     } else {
         Cow::from(relative_path)
     }
 }
 
+/// Create a file hyperlink to `path`, displaying `text`, or return `text` if the working directory
+/// is not known.
+pub fn format_osc8_file_hyperlink_or_text(
+    relative_path: &str,
+    line_number: Option<usize>,
+    text: &str,
+    config: &Config,
+) -> String {
+    if config.git_config_entries.contains_key("delta.__workdir__") {
+        format_osc8_file_hyperlink(relative_path, line_number, text, config).into_owned()
+    } else {
+        text.to_string()
+    }
+}
+
 lazy_static! {
     static ref COMMIT_LINE_REGEX: Regex = Regex::new("(.* )([0-9a-f]{40})(.*)").unwrap();
 }
@@ -73,16 +82,24 @@ fn format_commit_line_captures_with_osc8_commit_hyperlink(
 ) -> String {
     let commit = captures.get(2).unwrap().as_str();
     format!(
-        "{prefix}{osc}8;;{url}{st}{commit}{osc}8;;{st}{suffix}",
-        url = format_github_commit_url(commit, github_repo),
-        commit = commit,
+        "{prefix}{link}{suffix}",
+        link = format_osc8_hyperlink(&format_github_commit_url(commit, github_repo), commit),
         prefix = captures.get(1).unwrap().as_str(),
         suffix = captures.get(3).unwrap().as_str(),
+    )
+}
+
+/// Create an OSC 8 hyperlink to `url`, displaying `text`.
+pub fn format_osc8_hyperlink(url: &str, text: &str) -> String {
+    format!(
+        "{osc}8;;{url}{st}{text}{osc}8;;{st}",
+        url = url,
+        text = text,
         osc = "\x1b]",
         st = "\x1b\\"
     )
 }
 
-fn format_github_commit_url(commit: &str, github_repo: &str) -> String {
+pub fn format_github_commit_url(commit: &str, github_repo: &str) -> String {
     format!("https://github.com/{}/commit/{}", github_repo, commit)
 }
@@ -1,6 +1,13 @@
 use crate::config::Config;
 use crate::features::OptionValueFunction;
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NavigateTarget {
+    Commits,
+    Files,
+    Hunks,
+}
+
 pub fn make_feature() -> Vec<(String, OptionValueFunction)> {
     builtin_feature!([
         (
@@ -18,22 +25,140 @@ pub fn make_feature() -> Vec<(String, OptionValueFunction)> {
     ])
 }
 
+/// Parse the space-separated --navigate-targets value. Return the invalid target on error.
+pub fn parse_navigate_targets(s: &str) -> Result<Vec<NavigateTarget>, String> {
+    s.split_whitespace()
+        .map(|target| match target {
+            "commits" => Ok(NavigateTarget::Commits),
+            "files" => Ok(NavigateTarget::Files),
+            "hunks" => Ok(NavigateTarget::Hunks),
+            _ => Err(target.to_string()),
+        })
+        .collect()
+}
+
+/// Return the regular expression passed to less as its search pattern, matching the lines that
+/// are navigation targets. These are recognized by their beginning, since decorations (see
+/// draw.rs) are never drawn to the left of a commit, file, or hunk header.
 pub fn make_navigate_regexp(config: &Config) -> String {
-    format!(
-        "^(commit|{}|{}|{}|{})",
-        config.file_modified_label,
-        config.file_added_label,
-        config.file_removed_label,
-        config.file_renamed_label
-    )
+    let mut prefixes = Vec::new();
+    for target in &config.navigate_targets {
+        match target {
+            NavigateTarget::Commits => prefixes.push("commit"),
+            NavigateTarget::Files => prefixes.extend(&[
+                config.file_modified_label.as_str(),
+                config.file_added_label.as_str(),
+                config.file_removed_label.as_str(),
+                config.file_renamed_label.as_str(),
+            ]),
+            NavigateTarget::Hunks if config.hunk_header_style.is_raw => prefixes.push("@@"),
+            NavigateTarget::Hunks => prefixes.push(config.hunk_label.as_str()),
+        }
+    }
+    let prefixes: Vec<String> = prefixes
+        .iter()
+        .filter(|prefix| !prefix.is_empty())
+        .map(|prefix| escape_less_regex(prefix))
+        .collect();
+    let mut alternatives = Vec::new();
+    if !prefixes.is_empty() {
+        alternatives.push(format!("^({})", prefixes.join("|")));
+    }
+    if let Some(navigate_regex) = &config.navigate_regex {
+        alternatives.push(navigate_regex.to_string());
+    }
+    alternatives.join("|")
+}
+
+/// Escape the characters of `s` that are special in the regular expressions of less. Depending on
+/// how it was built, less uses POSIX extended regular expressions or PCRE; a backslash makes these
+/// characters literal in both, whereas escaping other characters (as regex::escape does) is
+/// undefined in POSIX regular expressions.
+fn escape_less_regex(s: &str) -> String {
+    let mut escaped = String::new();
+    for c in s.chars() {
+        if ".[\\()*+?{|^$".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
 }
 
 #[cfg(test)]
 mod tests {
     use std::fs::remove_file;
 
+    use crate::ansi::strip_ansi_codes;
     use crate::tests::integration_test_utils::integration_test_utils;
 
+    use super::*;
+
+    #[test]
+    fn test_navigate_regexp() {
+        let config = integration_test_utils::make_config_from_args(&["--navigate"]);
+        assert_eq!(
+            make_navigate_regexp(&config),
+            "^(commit|Δ|added:|removed:|renamed:)"
+        );
+    }
+
+    #[test]
+    fn test_escape_less_regex() {
+        assert_eq!(escape_less_regex("a.b*"), r"a\.b\*");
+        assert_eq!(escape_less_regex("(x|y)"), r"\(x\|y\)");
+        assert_eq!(escape_less_regex("#-~ ]}"), "#-~ ]}");
+    }
+
+    #[test]
+    fn test_navigate_regexp_with_targets_and_regex() {
+        let config = integration_test_utils::make_config_from_args(&[
+            "--navigate",
+            "--navigate-targets",
+            "hunks",
+            "--hunk-label",
+            "hunk*",
+            "--navigate-regex",
+            "TODO",
+        ]);
+        assert_eq!(make_navigate_regexp(&config), r"^(hunk\*)|TODO");
+
+        let config = integration_test_utils::make_config_from_args(&[
+            "--navigate",
+            "--navigate-targets",
+            "hunks",
+            "--hunk-header-style",
+            "raw",
+        ]);
+        assert_eq!(make_navigate_regexp(&config), "^(@@)");
+    }
+
+    #[test]
+    fn test_parse_navigate_targets() {
+        assert_eq!(
+            parse_navigate_targets(" commits  hunks"),
+            Ok(vec![NavigateTarget::Commits, NavigateTarget::Hunks])
+        );
+        assert_eq!(
+            parse_navigate_targets("files lines"),
+            Err("lines".to_string())
+        );
+    }
+
+    #[test]
+    fn test_navigate_hunk_label_precedes_box_decoration() {
+        let config = integration_test_utils::make_config_from_args(&[
+            "--navigate",
+            "--hunk-label",
+            "•",
+            "--hunk-header-decoration-style",
+            "box",
+        ]);
+        let output = integration_test_utils::run_delta(HUNK_WITHOUT_CODE_FRAGMENT_DIFF, &config);
+        let output = strip_ansi_codes(&output);
+        assert!(output.lines().any(|line| line == "• │"));
+    }
+
     #[test]
     fn test_navigate_with_overriden_key_in_main_section() {
         let git_config_contents = b"
@@ -148,4 +273,14 @@ mod tests {
 
         remove_file(git_config_path).unwrap();
     }
+
+    const HUNK_WITHOUT_CODE_FRAGMENT_DIFF: &str = "\
+diff --git a/a.py b/a.py
+index 223ca50..e69de29 100644
+--- a/a.py
++++ b/a.py
+@@ -1 +1 @@
+-a = 1
++a = 2
+";
 }
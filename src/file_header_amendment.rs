@@ -0,0 +1,58 @@
+use std::io;
+
+use crate::model::{Event, FileHeader, Renderer};
+
+/// An amendment to the header of a file that depends on the lines of the file, e.g. the numbers of
+/// lines added and removed. It is determined by observing the events of the file.
+pub trait FileHeaderAmendment: Default {
+    fn observe(&mut self, event: &Event);
+
+    fn amend(self, file_header: &mut FileHeader);
+}
+
+/// A renderer that amends the header of each file. Since the amendment is known only once the lines
+/// of the file have been seen, the events of each file are held until the next file begins, and
+/// then passed on to `renderer`.
+pub struct FileHeaderAmender<'a, A: FileHeaderAmendment> {
+    renderer: &'a mut dyn Renderer,
+    // The events of the current file, beginning with its header.
+    events: Vec<Event>,
+    amendment: A,
+}
+
+impl<'a, A: FileHeaderAmendment> FileHeaderAmender<'a, A> {
+    pub fn new(renderer: &'a mut dyn Renderer) -> Self {
+        Self {
+            renderer,
+            events: Vec::new(),
+            amendment: A::default(),
+        }
+    }
+
+    /// Pass the events of the current file on, with its header amended.
+    pub fn flush(&mut self) -> io::Result<()> {
+        let amendment = std::mem::take(&mut self.amendment);
+        if let Some(Event::FileHeader(file_header)) = self.events.first_mut() {
+            amendment.amend(file_header);
+        }
+        for event in self.events.drain(..) {
+            self.renderer.render(event)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a, A: FileHeaderAmendment> Renderer for FileHeaderAmender<'a, A> {
+    fn render(&mut self, event: Event) -> io::Result<()> {
+        if let Event::CommitHeader(_) | Event::FileHeader(_) = event {
+            self.flush()?;
+        }
+        self.amendment.observe(&event);
+        if self.events.is_empty() && !matches!(event, Event::FileHeader(FileHeader::Paths { .. })) {
+            self.renderer.render(event)
+        } else {
+            self.events.push(event);
+            Ok(())
+        }
+    }
+}
@@ -1,7 +1,16 @@
 use std::borrow::Cow;
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
 
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::annotate::Annotator;
 use crate::config::Config;
 use crate::features;
+use crate::model::FileHeader;
+use crate::parse;
+use crate::style::Style;
 
 /// If output is going to a tty, emit hyperlinks if requested.
 // Although raw output should basically be emitted unaltered, we do this.
@@ -12,3 +21,445 @@ pub fn format_raw_line<'a>(line: &'a str, config: &Config) -> Cow<'a, str> {
         Cow::from(line)
     }
 }
+
+/// Return `path`, which is relative to the root of the repository, for display: under
+/// --relative-paths it is made relative to the current directory.
+pub fn format_path<'a>(path: &'a str, config: &Config) -> Cow<'a, str> {
+    match &config.current_dir_in_repo {
+        Some(current_dir) if path != "/dev/null" => Cow::from(get_relative_path(path, current_dir)),
+        _ => Cow::from(path),
+    }
+}
+
+/// Return `path` relative to `dir`, where both are relative to the same directory, e.g.
+/// "../README.md" for "README.md" and "src".
+fn get_relative_path(path: &str, dir: &Path) -> String {
+    let mut path_components = Path::new(path).components().peekable();
+    let mut dir_components = dir.components().peekable();
+    while path_components.peek().is_some() && path_components.peek() == dir_components.peek() {
+        path_components.next();
+        dir_components.next();
+    }
+    let relative_path: PathBuf = dir_components
+        .map(|_| Component::ParentDir)
+        .chain(path_components)
+        .collect();
+    match relative_path.to_string_lossy() {
+        relative_path if relative_path.is_empty() => ".".to_string(),
+        relative_path => relative_path.into_owned(),
+    }
+}
+
+lazy_static! {
+    static ref FILE_RENAME_PLACEHOLDER_REGEX: Regex = Regex::new(r"\{(old|new|diff)\}").unwrap();
+}
+
+/// Format the paths of a renamed or copied file according to --file-rename-format. The path
+/// components that differ are painted in minus-emph-style and plus-emph-style.
+pub fn format_file_rename(
+    format_string: &str,
+    minus_file: &str,
+    plus_file: &str,
+    config: &Config,
+) -> String {
+    let (minus_path, plus_path) = (
+        format_path(minus_file, config),
+        format_path(plus_file, config),
+    );
+    let (prefix, minus_part, plus_part, suffix) =
+        split_paths_at_changed_components(&minus_path, &plus_path);
+    let (minus_part, plus_part) = (
+        paint_in_file_header(config.minus_emph_style, minus_part, config),
+        paint_in_file_header(config.plus_emph_style, plus_part, config),
+    );
+    let file_hyperlink = |file: &str, text: String| {
+        if config.hyperlinks {
+            features::hyperlinks::format_osc8_file_hyperlink_or_text(file, None, &text, config)
+        } else {
+            text
+        }
+    };
+
+    let mut formatted = String::new();
+    let mut offset = 0;
+    for captures in FILE_RENAME_PLACEHOLDER_REGEX.captures_iter(format_string) {
+        let _match = captures.get(0).unwrap();
+        formatted.push_str(&format_string[offset.._match.start()]);
+        formatted.push_str(&match &captures[1] {
+            "old" => file_hyperlink(minus_file, format!("{}{}{}", prefix, minus_part, suffix)),
+            "new" => file_hyperlink(plus_file, format!("{}{}{}", prefix, plus_part, suffix)),
+            "diff" if prefix.is_empty() && suffix.is_empty() => {
+                file_hyperlink(plus_file, format!("{} → {}", minus_part, plus_part))
+            }
+            "diff" => file_hyperlink(
+                plus_file,
+                format!("{}{{{} → {}}}{}", prefix, minus_part, plus_part, suffix),
+            ),
+            _ => unreachable!(),
+        });
+        offset = _match.end();
+    }
+    formatted.push_str(&format_string[offset..]);
+    formatted
+}
+
+/// Divide two paths into the leading components that they have in common (with a trailing '/'),
+/// the components that differ, and the trailing components that they have in common (with a
+/// leading '/'), e.g. ("src/", "features", "plugins", "/navigate.rs").
+fn split_paths_at_changed_components<'a>(
+    minus_path: &'a str,
+    plus_path: &'a str,
+) -> (&'a str, &'a str, &'a str, &'a str) {
+    let minus_components: Vec<&str> = minus_path.split('/').collect();
+    let plus_components: Vec<&str> = plus_path.split('/').collect();
+    let max_common = minus_components.len().min(plus_components.len());
+    let common_prefix_len = minus_components
+        .iter()
+        .zip(&plus_components)
+        .take(max_common - 1)
+        .take_while(|(m, p)| m == p)
+        .count();
+    // Without a common prefix, a common suffix covering the whole of one path would have no '/' to
+    // share with it.
+    let max_common_suffix = if common_prefix_len == 0 {
+        max_common - 1
+    } else {
+        max_common - common_prefix_len
+    };
+    let common_suffix_len = minus_components
+        .iter()
+        .rev()
+        .zip(plus_components.iter().rev())
+        .take(max_common_suffix)
+        .take_while(|(m, p)| m == p)
+        .count();
+    // The byte length of the first n components of the path, with the '/' following them.
+    let prefix_len = |n: usize| {
+        minus_components[..n]
+            .iter()
+            .map(|c| c.len() + 1)
+            .sum::<usize>()
+    };
+    let suffix_len = |components: &[&str]| {
+        components[components.len() - common_suffix_len..]
+            .iter()
+            .map(|c| c.len() + 1)
+            .sum::<usize>()
+    };
+    let prefix = &minus_path[..prefix_len(common_prefix_len)];
+    let suffix = &minus_path[minus_path.len() - suffix_len(&minus_components)..];
+    // Where the components that differ are absent from one path, its prefix and suffix share a '/'.
+    let changed_part = |path: &'a str, suffix_len: usize| {
+        &path[prefix.len()..(path.len() - suffix_len).max(prefix.len())]
+    };
+    (
+        prefix,
+        changed_part(minus_path, suffix.len()),
+        changed_part(plus_path, suffix_len(&plus_components)),
+        suffix,
+    )
+}
+
+/// Paint `text`, part of a file header, in `style`, after which file-style, in which the whole
+/// file header is painted, is restored. A plain style leaves `text` in file-style.
+fn paint_in_file_header(style: Style, text: &str, config: &Config) -> String {
+    if text.is_empty() || style.ansi_term_style.is_plain() {
+        text.to_string()
+    } else {
+        format!(
+            "{}{}",
+            style.paint(text),
+            config.file_style.ansi_term_style.prefix()
+        )
+    }
+}
+
+lazy_static! {
+    static ref FILE_HEADER_PLACEHOLDER_REGEX: Regex =
+        Regex::new(r"\{(event|path|old_path|mode|notes|added|removed|language|icon)\}").unwrap();
+}
+
+/// Does the --file-header-format string contain a placeholder for the numbers of lines added or
+/// removed?
+pub fn file_header_format_contains_line_counts(format_string: &str) -> bool {
+    FILE_HEADER_PLACEHOLDER_REGEX
+        .captures_iter(format_string)
+        .any(|captures| &captures[1] == "added" || &captures[1] == "removed")
+}
+
+/// Format a file header according to --file-header-format. Each placeholder is painted in its
+/// file-header-*-style, and everything else in file-style.
+pub fn format_file_header(
+    format_string: &str,
+    file_header: &FileHeader,
+    config: &Config,
+) -> String {
+    let (minus_file, plus_file, file_event, comparing, note, file_info, line_counts) =
+        match file_header {
+            FileHeader::Paths {
+                minus_file,
+                plus_file,
+                file_event,
+                comparing,
+                note,
+                file_info,
+                line_counts,
+            } => (
+                minus_file,
+                plus_file,
+                file_event,
+                comparing,
+                note,
+                file_info,
+                line_counts,
+            ),
+            FileHeader::Line { line, .. } => return line.clone(),
+        };
+    let file = if plus_file == "/dev/null" {
+        minus_file
+    } else {
+        plus_file
+    };
+    let extension = parse::get_file_extension_from_file_meta_line_file_path(file);
+
+    let mut formatted = String::new();
+    let mut offset = 0;
+    for captures in FILE_HEADER_PLACEHOLDER_REGEX.captures_iter(format_string) {
+        let _match = captures.get(0).unwrap();
+        formatted.push_str(&format_string[offset.._match.start()]);
+        let (style, text) = match &captures[1] {
+            "event" => (
+                config.file_header_event_style,
+                parse::get_file_change_label(minus_file, plus_file, *comparing, file_event, config)
+                    .to_string(),
+            ),
+            "path" => (
+                config.file_header_path_style,
+                parse::format_file_paths(minus_file, plus_file, config),
+            ),
+            "old_path" => (
+                config.file_header_old_path_style,
+                if minus_file != plus_file && minus_file != "/dev/null" && plus_file != "/dev/null"
+                {
+                    format_path(minus_file, config).into_owned()
+                } else {
+                    String::new()
+                },
+            ),
+            "mode" => (
+                config.file_header_mode_style,
+                file_info.describe_mode().join(", "),
+            ),
+            "notes" => (
+                config.file_header_notes_style,
+                file_info
+                    .describe_other_than_mode()
+                    .into_iter()
+                    .chain(note.clone())
+                    .collect::<Vec<String>>()
+                    .join(", "),
+            ),
+            "added" => (
+                config.file_header_added_style,
+                line_counts.map_or_else(String::new, |(added, _)| added.to_string()),
+            ),
+            "removed" => (
+                config.file_header_removed_style,
+                line_counts.map_or_else(String::new, |(_, removed)| removed.to_string()),
+            ),
+            "language" => (
+                config.file_header_language_style,
+                Annotator::get_syntax(&config.syntax_set, extension)
+                    .name
+                    .clone(),
+            ),
+            "icon" => (
+                config.file_header_icon_style,
+                extension
+                    .and_then(|extension| config.file_icons.get(extension))
+                    .cloned()
+                    .unwrap_or_default(),
+            ),
+            _ => unreachable!(),
+        };
+        formatted.push_str(&paint_in_file_header(style, &text, config));
+        offset = _match.end();
+    }
+    formatted.push_str(&format_string[offset..]);
+    formatted
+}
+
+/// Parse the value of --file-icons, e.g. "rs=🦀 py=🐍", into a map from file extension to icon.
+/// Return the first invalid entry in case of error.
+pub fn parse_file_icons(s: &str) -> Result<HashMap<String, String>, String> {
+    s.split_whitespace()
+        .map(|entry| match entry.split_once('=') {
+            Some((extension, icon)) if !extension.is_empty() && !icon.is_empty() => {
+                Ok((extension.to_string(), icon.to_string()))
+            }
+            _ => Err(entry.to_string()),
+        })
+        .collect()
+}
+
+lazy_static! {
+    static ref HUNK_HEADER_PLACEHOLDER_REGEX: Regex =
+        Regex::new(r"\{(path|minus_start|minus_len|plus_start|plus_len|fragment)\}").unwrap();
+}
+
+/// Does the --hunk-header-format string contain a placeholder for a line number?
+pub fn hunk_header_format_contains_line_number(format_string: &str) -> bool {
+    HUNK_HEADER_PLACEHOLDER_REGEX
+        .captures_iter(format_string)
+        .any(|captures| captures[1].ends_with("_start"))
+}
+
+/// Format a hunk header according to --hunk-header-format, preceded by --hunk-label, if any.
+/// `painted_fragment` is the code fragment, already syntax-highlighted and painted in
+/// hunk-header-fragment-style.
+pub fn format_hunk_header(
+    format_string: &str,
+    painted_fragment: &str,
+    plus_file: &str,
+    line_numbers: &[(usize, usize)],
+    config: &Config,
+) -> String {
+    // Merge commits have more than two sets of line numbers: the first is that of the first
+    // parent, and the last is that of the merge result.
+    let (minus_start, minus_len) = line_numbers[0];
+    let (plus_start, plus_len) = line_numbers[line_numbers.len() - 1];
+    // Each placeholder is a hyperlink to the first line of the hunk.
+    let hunk_hyperlink = |text: String| {
+        if config.hyperlinks {
+            features::hyperlinks::format_osc8_file_hyperlink_or_text(
+                plus_file,
+                Some(plus_start),
+                &text,
+                config,
+            )
+        } else {
+            text
+        }
+    };
+
+    let mut formatted = String::new();
+    if !config.hunk_label.is_empty() {
+        formatted.push_str(
+            &config
+                .hunk_header_style
+                .paint(format!("{} ", config.hunk_label))
+                .to_string(),
+        );
+    }
+    let mut offset = 0;
+    for captures in HUNK_HEADER_PLACEHOLDER_REGEX.captures_iter(format_string) {
+        let _match = captures.get(0).unwrap();
+        let literal = &format_string[offset.._match.start()];
+        if !literal.is_empty() {
+            formatted.push_str(&config.hunk_header_style.paint(literal).to_string());
+        }
+        let painted = match &captures[1] {
+            "path" => config
+                .hunk_header_file_style
+                .paint(format_path(plus_file, config))
+                .to_string(),
+            "minus_start" => config
+                .hunk_header_minus_start_style
+                .paint(minus_start.to_string())
+                .to_string(),
+            "minus_len" => config
+                .hunk_header_minus_len_style
+                .paint(minus_len.to_string())
+                .to_string(),
+            "plus_start" => config
+                .hunk_header_plus_start_style
+                .paint(plus_start.to_string())
+                .to_string(),
+            "plus_len" => config
+                .hunk_header_plus_len_style
+                .paint(plus_len.to_string())
+                .to_string(),
+            "fragment" => painted_fragment.to_string(),
+            _ => unreachable!(),
+        };
+        formatted.push_str(&hunk_hyperlink(painted));
+        offset = _match.end();
+    }
+    let literal = &format_string[offset..];
+    if !literal.is_empty() {
+        formatted.push_str(&config.hunk_header_style.paint(literal).to_string());
+    }
+    formatted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_relative_path() {
+        let dir = Path::new("src/features");
+        assert_eq!(
+            get_relative_path("src/features/navigate.rs", dir),
+            "navigate.rs"
+        );
+        assert_eq!(get_relative_path("src/delta.rs", dir), "../delta.rs");
+        assert_eq!(get_relative_path("README.md", dir), "../../README.md");
+        assert_eq!(get_relative_path("src/features", dir), ".");
+        assert_eq!(get_relative_path("README.md", Path::new("")), "README.md");
+    }
+
+    #[test]
+    fn test_parse_file_icons() {
+        let icons = parse_file_icons("rs=R  py=P\tMakefile=M").unwrap();
+        assert_eq!(icons.len(), 3);
+        assert_eq!(icons["rs"], "R");
+        assert_eq!(icons["Makefile"], "M");
+        assert!(parse_file_icons("").unwrap().is_empty());
+        assert_eq!(parse_file_icons("rs=R py"), Err("py".to_string()));
+        assert_eq!(parse_file_icons("=R"), Err("=R".to_string()));
+    }
+
+    #[test]
+    fn test_split_paths_at_changed_components() {
+        assert_eq!(
+            split_paths_at_changed_components(
+                "src/features/navigate.rs",
+                "src/plugins/navigate.rs"
+            ),
+            ("src/", "features", "plugins", "/navigate.rs")
+        );
+        assert_eq!(
+            split_paths_at_changed_components("a.py", "b.py"),
+            ("", "a.py", "b.py", "")
+        );
+        assert_eq!(
+            split_paths_at_changed_components("x/a.py", "x/y/a.py"),
+            ("x/", "", "y", "/a.py")
+        );
+        assert_eq!(
+            split_paths_at_changed_components("a/b", "a/b/c"),
+            ("a/", "b", "b/c", "")
+        );
+        assert_eq!(
+            split_paths_at_changed_components("x/y/a.py", "x/a.py"),
+            ("x/", "y", "", "/a.py")
+        );
+        assert_eq!(
+            split_paths_at_changed_components("src/a.rs", "a.rs"),
+            ("", "src/a.rs", "a.rs", "")
+        );
+        assert_eq!(
+            split_paths_at_changed_components("a.rs", "src/a.rs"),
+            ("", "a.rs", "src/a.rs", "")
+        );
+        assert_eq!(
+            split_paths_at_changed_components("a/x.rs", "b/a/x.rs"),
+            ("", "a", "b/a", "/x.rs")
+        );
+        assert_eq!(
+            split_paths_at_changed_components("../x/a.rs", "a.rs"),
+            ("", "../x/a.rs", "a.rs", "")
+        );
+    }
+}
@@ -0,0 +1,116 @@
+use std::fs;
+use std::path::Path;
+
+use git2::{AttrCheckFlags, ObjectType, Oid, Repository};
+
+/// Return the contents of the blob identified by `blob_id`, which is an (abbreviated) object id
+/// as found in the `index <minus>..<plus>` line of a git diff. If the blob is not in the object
+/// database, then fall back to the file at `path` in the working tree, but only if its contents
+/// hash to `blob_id` (this is the case for the post-image of `git diff` without `--cached`).
+/// Return None if the contents are not available, e.g. because `blob_id` is the null object id
+/// of an added or removed file.
+pub fn get_blob_contents(repo: &Repository, blob_id: &str, path: &str) -> Option<Vec<u8>> {
+    if blob_id.is_empty() || blob_id.chars().all(|c| c == '0') {
+        return None;
+    }
+    if let Ok(blob) = repo
+        .revparse_single(blob_id)
+        .and_then(|object| object.peel_to_blob())
+    {
+        return Some(blob.content().to_vec());
+    }
+    let workdir = repo.workdir()?;
+    let contents = fs::read(workdir.join(path)).ok()?;
+    match Oid::hash_object(ObjectType::Blob, &contents) {
+        Ok(oid) if oid.to_string().starts_with(blob_id) => Some(contents),
+        _ => None,
+    }
+}
+
+/// Return the value of the working-tree-encoding attribute of the file at `path`, as set in
+/// .gitattributes, if it has one.
+pub fn get_working_tree_encoding(repo: &Repository, path: &str) -> Option<String> {
+    repo.get_attr(
+        Path::new(path),
+        "working-tree-encoding",
+        AttrCheckFlags::FILE_THEN_INDEX,
+    )
+    .ok()
+    .flatten()
+    .map(|value| value.to_string())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::fs::write;
+    use std::ops::Deref;
+
+    use super::*;
+    use crate::tests::test_utils::test_utils::TempDir;
+
+    /// A git repository with a working tree in a temporary directory, which is removed when the
+    /// repository is dropped.
+    pub struct TestRepo {
+        repo: Repository,
+        pub dir: TempDir,
+    }
+
+    impl TestRepo {
+        /// Return another handle to the repository, e.g. for Config::repo.
+        pub fn open(&self) -> Repository {
+            Repository::open(self.dir.path()).unwrap()
+        }
+    }
+
+    impl Deref for TestRepo {
+        type Target = Repository;
+
+        fn deref(&self) -> &Repository {
+            &self.repo
+        }
+    }
+
+    /// Create a git repository with a working tree in a temporary directory named after `name`.
+    pub fn make_git_repo(name: &str) -> TestRepo {
+        let dir = TempDir::new(name);
+        let repo = Repository::init(dir.path()).unwrap();
+        TestRepo { repo, dir }
+    }
+
+    #[test]
+    fn test_get_blob_contents() {
+        let repo = make_git_repo("delta__test_get_blob_contents");
+        let blob_id = repo.blob(b"in object database\n").unwrap().to_string();
+        write(repo.dir.path().join("file.txt"), "in working tree only\n").unwrap();
+        let worktree_blob_id = Oid::hash_object(ObjectType::Blob, b"in working tree only\n")
+            .unwrap()
+            .to_string();
+
+        assert_eq!(
+            get_blob_contents(&repo, &blob_id[..7], "file.txt"),
+            Some(b"in object database\n".to_vec())
+        );
+        assert_eq!(
+            get_blob_contents(&repo, &worktree_blob_id[..7], "file.txt"),
+            Some(b"in working tree only\n".to_vec())
+        );
+        assert_eq!(get_blob_contents(&repo, "1234567", "file.txt"), None);
+        assert_eq!(get_blob_contents(&repo, "0000000", "file.txt"), None);
+    }
+
+    #[test]
+    fn test_get_working_tree_encoding() {
+        let repo = make_git_repo("delta__test_get_working_tree_encoding");
+        write(
+            repo.dir.path().join(".gitattributes"),
+            "*.txt working-tree-encoding=ISO-8859-1\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            get_working_tree_encoding(&repo, "a/file.txt"),
+            Some("ISO-8859-1".to_string())
+        );
+        assert_eq!(get_working_tree_encoding(&repo, "file.rs"), None);
+    }
+}
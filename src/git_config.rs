@@ -1,6 +1,5 @@
 #[cfg(test)]
 use std::path::Path;
-use std::process;
 
 pub struct GitConfig {
     config: git2::Config,
@@ -9,7 +8,10 @@ pub struct GitConfig {
 }
 
 impl GitConfig {
-    pub fn try_create() -> Option<Self> {
+    /// Read the git config of the repository containing the current directory, or the global git
+    /// config outside a repository. Return None if there is no git config, and an error if it
+    /// cannot be read.
+    pub fn try_create() -> Result<Option<Self>, git2::Error> {
         let repo = match std::env::current_dir() {
             Ok(dir) => git2::Repository::discover(dir).ok(),
             _ => None,
@@ -19,18 +21,12 @@ impl GitConfig {
             None => git2::Config::open_default().ok(),
         };
         match config {
-            Some(mut config) => {
-                let config = config.snapshot().unwrap_or_else(|err| {
-                    eprintln!("Failed to read git config: {}", err);
-                    process::exit(1)
-                });
-                Some(Self {
-                    config,
-                    repo,
-                    enabled: true,
-                })
-            }
-            None => None,
+            Some(mut config) => Ok(Some(Self {
+                config: config.snapshot()?,
+                repo,
+                enabled: true,
+            })),
+            None => Ok(None),
         }
     }
 
@@ -0,0 +1,324 @@
+use git2::{
+    Commit, Diff, DiffFindOptions, DiffOptions, Oid, Repository, RevparseMode, Sort, Time, Tree,
+};
+
+/// Return the patch text of `diff`, as it would be printed by git.
+pub fn git2_diff_to_text(diff: &Diff) -> Result<Vec<u8>, git2::Error> {
+    let mut text = Vec::new();
+    diff.print(git2::DiffFormat::Patch, |_, _, line| {
+        // The origin of added, removed, and unchanged lines is not included in their content.
+        if let origin @ '+' | origin @ '-' | origin @ ' ' = line.origin() {
+            text.push(origin as u8);
+        }
+        text.extend_from_slice(line.content());
+        true
+    })?;
+    Ok(text)
+}
+
+/// Read a diff from `repo`, as text in the form produced by git:
+///
+/// - For a revision range (`A..B` or `A...B`), the commits in the range with their changes, as
+///   git log -p would show them.
+/// - For a single revision, the changes between it and the working tree, as git diff would show
+///   them, or between it and the index if `staged`.
+/// - With no revision, the changes between HEAD and the index if `staged`, as git diff --staged
+///   would show them, and otherwise the changes between the index and the working tree.
+pub fn read_diff(
+    repo: &Repository,
+    rev: Option<&str>,
+    staged: bool,
+) -> Result<Vec<u8>, git2::Error> {
+    let tree = match rev {
+        Some(rev) => {
+            let revspec = repo.revparse(rev)?;
+            if !revspec.mode().contains(RevparseMode::SINGLE) {
+                if staged {
+                    return Err(git2::Error::from_str(
+                        "--staged cannot be used with a revision range",
+                    ));
+                }
+                return read_log(repo, rev);
+            }
+            let object = revspec
+                .from()
+                .ok_or_else(|| git2::Error::from_str("Invalid revision"))?;
+            Some(object.peel_to_tree()?)
+        }
+        None if staged => get_head_tree(repo)?,
+        None => None,
+    };
+    let mut diff = match (&tree, staged) {
+        (_, true) => repo.diff_tree_to_index(tree.as_ref(), None, Some(&mut diff_options()))?,
+        (Some(tree), false) => {
+            repo.diff_tree_to_workdir_with_index(Some(tree), Some(&mut diff_options()))?
+        }
+        (None, false) => repo.diff_index_to_workdir(None, Some(&mut diff_options()))?,
+    };
+    find_renames(&mut diff)?;
+    git2_diff_to_text(&diff)
+}
+
+/// Return the commits in the revision range `range` with their changes, as git log -p would.
+fn read_log(repo: &Repository, range: &str) -> Result<Vec<u8>, git2::Error> {
+    let mut revwalk = repo.revwalk()?;
+    revwalk.set_sorting(Sort::TIME)?;
+    let revspec = repo.revparse(range)?;
+    let (from, to) = match (revspec.from(), revspec.to()) {
+        (Some(from), Some(to)) => (from.id(), to.id()),
+        _ => return Err(git2::Error::from_str("Invalid revision range")),
+    };
+    if revspec.mode().contains(RevparseMode::MERGE_BASE) {
+        // A...B: the commits reachable from either but not both.
+        revwalk.push(from)?;
+        revwalk.push(to)?;
+        revwalk.hide(repo.merge_base(from, to)?)?;
+    } else {
+        revwalk.push(to)?;
+        revwalk.hide(from)?;
+    }
+
+    let mut text = Vec::new();
+    for (i, oid) in revwalk.enumerate() {
+        if i > 0 {
+            text.push(b'\n');
+        }
+        let commit = repo.find_commit(oid?)?;
+        text.extend(format_commit_header(&commit).into_bytes());
+        // As in git log, the changes of merge commits are not shown.
+        if commit.parent_count() <= 1 {
+            let parent_tree = match commit.parent_count() {
+                0 => None,
+                _ => Some(commit.parent(0)?.tree()?),
+            };
+            let mut diff = repo.diff_tree_to_tree(
+                parent_tree.as_ref(),
+                Some(&commit.tree()?),
+                Some(&mut diff_options()),
+            )?;
+            find_renames(&mut diff)?;
+            let diff_text = git2_diff_to_text(&diff)?;
+            if !diff_text.is_empty() {
+                text.push(b'\n');
+                text.extend(diff_text);
+            }
+        }
+    }
+    Ok(text)
+}
+
+fn get_head_tree(repo: &Repository) -> Result<Option<Tree<'_>>, git2::Error> {
+    match repo.head() {
+        Ok(head) => Ok(Some(head.peel_to_tree()?)),
+        // There is no HEAD commit in a repository with no commits.
+        Err(error) if error.code() == git2::ErrorCode::UnbornBranch => Ok(None),
+        Err(error) => Err(error),
+    }
+}
+
+/// The options with which git diff computes diffs by default.
+fn diff_options() -> DiffOptions {
+    let mut options = DiffOptions::new();
+    options.indent_heuristic(true);
+    options
+}
+
+fn find_renames(diff: &mut Diff) -> Result<(), git2::Error> {
+    diff.find_similar(Some(DiffFindOptions::new().renames(true)))
+}
+
+/// The commit metadata, as shown by git log in its default (medium) format.
+fn format_commit_header(commit: &Commit) -> String {
+    let mut header = format!("commit {}\n", commit.id());
+    if commit.parent_count() > 1 {
+        header.push_str(&format!(
+            "Merge: {}\n",
+            commit
+                .parent_ids()
+                .map(abbreviate)
+                .collect::<Vec<_>>()
+                .join(" ")
+        ));
+    }
+    let author = commit.author();
+    header.push_str(&format!(
+        "Author: {} <{}>\nDate:   {}\n\n",
+        String::from_utf8_lossy(author.name_bytes()),
+        String::from_utf8_lossy(author.email_bytes()),
+        format_time(author.when())
+    ));
+    let message = String::from_utf8_lossy(commit.message_bytes());
+    for line in message.trim_end().lines() {
+        if !line.trim().is_empty() {
+            header.push_str("    ");
+            header.push_str(line);
+        }
+        header.push('\n');
+    }
+    header
+}
+
+fn abbreviate(oid: Oid) -> String {
+    oid.to_string()[..7].to_string()
+}
+
+const WEEKDAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Format `time` as git does by default, e.g. "Sat Dec 12 12:00:00 2020 -0500".
+fn format_time(time: Time) -> String {
+    let offset_minutes = time.offset_minutes() as i64;
+    let seconds = time.seconds() + offset_minutes * 60;
+    let (days, seconds_of_day) = (seconds.div_euclid(86400), seconds.rem_euclid(86400));
+    let (year, month, day) = get_date_from_days_since_epoch(days);
+    format!(
+        "{} {} {} {:02}:{:02}:{:02} {} {}{:02}{:02}",
+        WEEKDAYS[(days + 4).rem_euclid(7) as usize], // 1970-01-01 was a Thursday.
+        MONTHS[month as usize - 1],
+        day,
+        seconds_of_day / 3600,
+        seconds_of_day % 3600 / 60,
+        seconds_of_day % 60,
+        year,
+        if offset_minutes < 0 { '-' } else { '+' },
+        offset_minutes.abs() / 60,
+        offset_minutes.abs() % 60,
+    )
+}
+
+/// Return the (year, month, day) of the proleptic Gregorian calendar date `days` after
+/// 1970-01-01. See http://howardhinnant.github.io/date_algorithms.html#civil_from_days.
+fn get_date_from_days_since_epoch(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468; // Days since 0000-03-01.
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_from_march + 2) / 5 + 1;
+    let month = if month_from_march < 10 {
+        month_from_march + 3
+    } else {
+        month_from_march - 9
+    };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::write;
+    use std::path::Path;
+
+    use git2::{IndexAddOption, Signature};
+
+    use super::*;
+    use crate::git_blob::tests::make_git_repo;
+
+    /// Stage the files in the working tree of `repo`, and commit them with `message`.
+    fn commit(repo: &Repository, message: &str, seconds: i64) -> Oid {
+        let mut index = repo.index().unwrap();
+        index.add_all(["*"], IndexAddOption::DEFAULT, None).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = Signature::new(
+            "Dan Davison",
+            "dandavison7@gmail.com",
+            &Time::new(seconds, -300),
+        )
+        .unwrap();
+        let parents = match repo.head() {
+            Ok(head) => vec![head.peel_to_commit().unwrap()],
+            Err(_) => vec![],
+        };
+        repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            message,
+            &tree,
+            &parents.iter().collect::<Vec<_>>(),
+        )
+        .unwrap()
+    }
+
+    fn read(repo: &Repository, rev: Option<&str>, staged: bool) -> String {
+        String::from_utf8(read_diff(repo, rev, staged).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_read_diff() {
+        let repo = make_git_repo("delta__test_read_diff");
+        let path = repo.dir.path().join("a.py");
+        write(&path, "x = 1\n").unwrap();
+        let oid_1 = commit(&repo, "Add a\n", 1607792400);
+        write(&path, "x = 2\n").unwrap();
+        let oid_2 = commit(&repo, "Change a\n\nIn detail.\n", 1607792460);
+
+        assert_eq!(
+            read(&repo, Some("HEAD~1..HEAD"), false),
+            format!(
+                "\
+commit {}
+Author: Dan Davison <dandavison7@gmail.com>
+Date:   Sat Dec 12 12:01:00 2020 -0500
+
+    Change a
+
+    In detail.
+
+diff --git a/a.py b/a.py
+index 7d4290a..407de30 100644
+--- a/a.py
++++ b/a.py
+@@ -1 +1 @@
+-x = 1
++x = 2
+",
+                oid_2
+            )
+        );
+        let log = read(&repo, Some(&format!("{}..{}", oid_1, oid_2)), false);
+        assert!(log.starts_with(&format!("commit {}\n", oid_2)));
+        let log = read(
+            &repo,
+            Some(&format!("{}..HEAD", repo.head().unwrap().target().unwrap())),
+            false,
+        );
+        assert_eq!(log, "");
+
+        // Changes in the working tree and the index.
+        write(&path, "x = 3\n").unwrap();
+        assert!(read(&repo, None, false).ends_with("-x = 2\n+x = 3\n"));
+        assert!(read(&repo, Some("HEAD~1"), false).ends_with("-x = 1\n+x = 3\n"));
+        assert_eq!(read(&repo, None, true), "");
+
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("a.py")).unwrap();
+        index.write().unwrap();
+        write(&path, "x = 4\n").unwrap();
+        assert!(read(&repo, None, true).ends_with("-x = 2\n+x = 3\n"));
+        assert!(read(&repo, Some("HEAD~1"), true).ends_with("-x = 1\n+x = 3\n"));
+        assert!(read_diff(&repo, Some("HEAD~1..HEAD"), true).is_err());
+    }
+
+    #[test]
+    fn test_format_time() {
+        assert_eq!(
+            format_time(Time::new(1607792400, -300)),
+            "Sat Dec 12 12:00:00 2020 -0500"
+        );
+        assert_eq!(
+            format_time(Time::new(0, 0)),
+            "Thu Jan 1 00:00:00 1970 +0000"
+        );
+        assert_eq!(
+            format_time(Time::new(951825600, 330)),
+            "Tue Feb 29 17:30:00 2000 +0530"
+        );
+    }
+}
@@ -0,0 +1,90 @@
+use crate::model::HexRow;
+
+/// The number of bytes of each version of a file in a row of the hex dump.
+pub const BYTES_PER_ROW: usize = 8;
+
+/// Return the runs of consecutive rows of a hex dump in which `minus` and `plus` differ. The
+/// common suffix of the two is excluded before the rows are compared, so that an insertion or
+/// deletion does not cause all subsequent rows to differ.
+pub fn get_differing_regions(minus: &[u8], plus: &[u8]) -> Vec<Vec<HexRow>> {
+    let prefix_len = minus.iter().zip(plus).take_while(|(m, p)| m == p).count();
+    let suffix_len = minus
+        .iter()
+        .rev()
+        .zip(plus.iter().rev())
+        .take(minus.len().min(plus.len()) - prefix_len)
+        .take_while(|(m, p)| m == p)
+        .count();
+    let (minus, plus) = (
+        &minus[..minus.len() - suffix_len],
+        &plus[..plus.len() - suffix_len],
+    );
+    let get_row_bytes = |bytes: &[u8], offset: usize| {
+        bytes[offset.min(bytes.len())..(offset + BYTES_PER_ROW).min(bytes.len())].to_vec()
+    };
+    let mut regions = Vec::new();
+    let mut region = Vec::new();
+    let mut offset = prefix_len - prefix_len % BYTES_PER_ROW;
+    while offset < minus.len().max(plus.len()) {
+        let row = HexRow {
+            offset,
+            minus_bytes: get_row_bytes(minus, offset),
+            plus_bytes: get_row_bytes(plus, offset),
+        };
+        if row.minus_bytes != row.plus_bytes {
+            region.push(row);
+        } else if !region.is_empty() {
+            regions.push(std::mem::take(&mut region));
+        }
+        offset += BYTES_PER_ROW;
+    }
+    if !region.is_empty() {
+        regions.push(region);
+    }
+    regions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(offset: usize, minus_bytes: &[u8], plus_bytes: &[u8]) -> HexRow {
+        HexRow {
+            offset,
+            minus_bytes: minus_bytes.to_vec(),
+            plus_bytes: plus_bytes.to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_get_differing_regions_of_same_length() {
+        let minus = b"0123456789abcdef0123456789abcdef";
+        let plus = b"0123456789abcdeF0123456789abcdeF";
+        assert_eq!(
+            get_differing_regions(minus, plus),
+            vec![
+                vec![row(8, b"89abcdef", b"89abcdeF")],
+                vec![row(24, b"89abcdef", b"89abcdeF")],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_get_differing_regions_with_insertion() {
+        let minus = b"0123456789abcdef0123456789abcdef";
+        let plus = b"0123456789XXabcdef0123456789abcdef";
+        assert_eq!(
+            get_differing_regions(minus, plus),
+            vec![vec![row(8, b"89", b"89XX")]]
+        );
+    }
+
+    #[test]
+    fn test_get_differing_regions_of_identical_files() {
+        assert!(get_differing_regions(b"0123", b"0123").is_empty());
+        assert_eq!(
+            get_differing_regions(b"", b"HI"),
+            vec![vec![row(0, b"", b"HI")]]
+        );
+    }
+}
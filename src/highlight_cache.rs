@@ -0,0 +1,354 @@
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::fs::{self, File, OpenOptions};
+use std::hash::Hasher;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use fnv::FnvHasher;
+use syntect::highlighting::{Color, FontStyle, HighlightState, Style as SyntectStyle, Theme};
+use syntect::parsing::{ParseState, SyntaxReference};
+
+/// The styles of the sections of a highlighted line, with the length in bytes of each section.
+pub type LineStyles = Vec<(SyntectStyle, usize)>;
+
+/// A cache entry: the styles of a highlighted line, and the key of the state of the highlighter
+/// after highlighting it.
+pub type CachedLine = (LineStyles, u64);
+
+const MAGIC: &[u8; 8] = b"DELTAHC2";
+
+/// An on-disk cache of the syntax highlighting of lines, used under --highlight-cache.
+///
+/// The highlighting of a line depends on the state the highlighter is in when it reaches the line,
+/// so entries are keyed by the syntax, the theme, that state, and the line (see line_key). Each
+/// entry also records the state after the line, so that a run of lines can be looked up without
+/// parsing them. Keys are computed with FNV, whose output does not change between releases of
+/// Rust. The cache is stored as two generations: new entries are appended to the current
+/// file, which becomes the previous generation once it exceeds half of the maximum size. Entries
+/// that are used from the previous generation are copied to the current one, so the entries that
+/// are still in use survive. Each file begins with a fingerprint of the syntax and theme
+/// definitions, and is ignored if they have changed.
+pub struct HighlightCache {
+    current_path: PathBuf,
+    previous_path: PathBuf,
+    fingerprint: u64,
+    max_size: u64,
+    state: Mutex<CacheState>,
+}
+
+#[derive(Default)]
+struct CacheState {
+    // The entries of both generations, read when first needed, and whether each entry is in the
+    // current generation.
+    entries: Option<HashMap<u64, (CachedLine, bool)>>,
+    // Whether the current file exists and has the expected fingerprint.
+    is_current_file_valid: bool,
+    // The encoded entries to be appended to the current file.
+    new_entries: Vec<u8>,
+}
+
+impl HighlightCache {
+    /// Create a cache stored in `dir`, of at most `max_size` bytes. `fingerprint` identifies the
+    /// syntax and theme definitions in use.
+    pub fn new(dir: &Path, fingerprint: u64, max_size: u64) -> Self {
+        Self {
+            current_path: dir.join("delta-highlights.bin"),
+            previous_path: dir.join("delta-highlights.old.bin"),
+            fingerprint,
+            max_size,
+            state: Mutex::new(CacheState::default()),
+        }
+    }
+
+    pub fn get(&self, key: u64) -> Option<CachedLine> {
+        let mut state = self.state.lock().unwrap();
+        self.read_entries(&mut state);
+        let state = &mut *state;
+        let (cached_line, is_current) = state.entries.as_mut().unwrap().get_mut(&key)?;
+        if !*is_current {
+            encode_entry(key, cached_line, &mut state.new_entries);
+            *is_current = true;
+        }
+        Some(cached_line.clone())
+    }
+
+    pub fn insert(&self, key: u64, cached_line: CachedLine) {
+        let mut state = self.state.lock().unwrap();
+        self.read_entries(&mut state);
+        let state = &mut *state;
+        if let Entry::Vacant(entry) = state.entries.as_mut().unwrap().entry(key) {
+            encode_entry(key, &cached_line, &mut state.new_entries);
+            entry.insert((cached_line, true));
+        }
+    }
+
+    /// Write the entries added since the cache was read to disk.
+    pub fn save(&self) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let state = &mut *state;
+        if state.new_entries.is_empty() {
+            return Ok(());
+        }
+        if let Some(dir) = self.current_path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let new_entries = std::mem::take(&mut state.new_entries);
+        let mut file = if state.is_current_file_valid {
+            OpenOptions::new().append(true).open(&self.current_path)?
+        } else {
+            let mut file = File::create(&self.current_path)?;
+            file.write_all(MAGIC)?;
+            file.write_all(&self.fingerprint.to_le_bytes())?;
+            state.is_current_file_valid = true;
+            file
+        };
+        file.write_all(&new_entries)?;
+        if file.metadata()?.len() > self.max_size / 2 {
+            fs::rename(&self.current_path, &self.previous_path)?;
+            state.is_current_file_valid = false;
+            for (_, is_current) in state
+                .entries
+                .iter_mut()
+                .flat_map(|entries| entries.values_mut())
+            {
+                *is_current = false;
+            }
+        }
+        Ok(())
+    }
+
+    /// Read the cache files, if they have not been read yet.
+    fn read_entries(&self, state: &mut CacheState) {
+        if state.entries.is_some() {
+            return;
+        }
+        let mut entries = HashMap::new();
+        self.read_file(&self.previous_path, false, &mut entries);
+        state.is_current_file_valid = self.read_file(&self.current_path, true, &mut entries);
+        if !state.is_current_file_valid {
+            // The entries read from a damaged current file are written again.
+            for (key, (cached_line, is_current)) in &entries {
+                if *is_current {
+                    encode_entry(*key, cached_line, &mut state.new_entries);
+                }
+            }
+        }
+        state.entries = Some(entries);
+    }
+
+    /// Read the entries in the file at `path` into `entries`. Return false if the file does not
+    /// exist, was made with different syntax and theme definitions, or is damaged.
+    fn read_file(
+        &self,
+        path: &Path,
+        is_current: bool,
+        entries: &mut HashMap<u64, (CachedLine, bool)>,
+    ) -> bool {
+        let mut contents = Vec::new();
+        if File::open(path)
+            .and_then(|mut file| file.read_to_end(&mut contents))
+            .is_err()
+            || contents.len() < 16
+            || &contents[..8] != MAGIC
+            || contents[8..16] != self.fingerprint.to_le_bytes()
+        {
+            return false;
+        }
+        let mut bytes = &contents[16..];
+        while let Some((key, cached_line)) = decode_entry(&mut bytes) {
+            entries.insert(key, (cached_line, is_current));
+        }
+        // Any remaining bytes are a truncated entry, e.g. from an interrupted write.
+        bytes.is_empty()
+    }
+}
+
+impl Drop for HighlightCache {
+    fn drop(&mut self) {
+        // Failing to write the cache is not an error: the lines will be highlighted again.
+        self.save().ok();
+    }
+}
+
+/// The key of the state of a highlighter of `syntax` with `theme`.
+pub fn state_key(
+    syntax: &SyntaxReference,
+    theme: &Theme,
+    parse_state: &ParseState,
+    highlight_state: &HighlightState,
+) -> u64 {
+    let mut hasher = FnvHasher::default();
+    write_str(&mut hasher, &syntax.name);
+    write_str(&mut hasher, theme.name.as_deref().unwrap_or_default());
+    // The syntect states implement neither Hash nor serialization, but their Debug representations
+    // are complete. (A parse state holding regex captures also shows addresses, so lines that
+    // follow one, as in a heredoc, are not found in the cache of a later run.)
+    write_str(
+        &mut hasher,
+        &format!("{:?}{:?}", parse_state, highlight_state),
+    );
+    hasher.finish()
+}
+
+/// The key of the highlighting of `line` by a highlighter in the state with key `state_key`.
+pub fn line_key(state_key: u64, line: &str) -> u64 {
+    let mut hasher = FnvHasher::default();
+    hasher.write(&state_key.to_le_bytes());
+    write_str(&mut hasher, line);
+    hasher.finish()
+}
+
+fn write_str(hasher: &mut FnvHasher, s: &str) {
+    hasher.write(&(s.len() as u64).to_le_bytes());
+    hasher.write(s.as_bytes());
+}
+
+// An entry is encoded as its key, the key of the following state, the number of sections, and for
+// each section the foreground and background colors, the font style, and the length.
+fn encode_entry(key: u64, (styles, next_state_key): &CachedLine, bytes: &mut Vec<u8>) {
+    bytes.extend_from_slice(&key.to_le_bytes());
+    bytes.extend_from_slice(&next_state_key.to_le_bytes());
+    bytes.extend_from_slice(&(styles.len() as u32).to_le_bytes());
+    for (style, len) in styles {
+        for color in &[style.foreground, style.background] {
+            bytes.extend_from_slice(&[color.r, color.g, color.b, color.a]);
+        }
+        bytes.push(style.font_style.bits());
+        bytes.extend_from_slice(&(*len as u32).to_le_bytes());
+    }
+}
+
+fn decode_entry(bytes: &mut &[u8]) -> Option<(u64, CachedLine)> {
+    let key = u64::from_le_bytes(take(bytes, 8)?.try_into().ok()?);
+    let next_state_key = u64::from_le_bytes(take(bytes, 8)?.try_into().ok()?);
+    let n_sections = u32::from_le_bytes(take(bytes, 4)?.try_into().ok()?);
+    let mut styles = Vec::new();
+    for _ in 0..n_sections {
+        let section = take(bytes, 13)?;
+        let color = |c: &[u8]| Color {
+            r: c[0],
+            g: c[1],
+            b: c[2],
+            a: c[3],
+        };
+        let style = SyntectStyle {
+            foreground: color(&section[0..4]),
+            background: color(&section[4..8]),
+            font_style: FontStyle::from_bits_truncate(section[8]),
+        };
+        let len = u32::from_le_bytes(section[9..13].try_into().ok()?) as usize;
+        styles.push((style, len));
+    }
+    Some((key, (styles, next_state_key)))
+}
+
+fn take<'a>(bytes: &mut &'a [u8], n: usize) -> Option<&'a [u8]> {
+    if bytes.len() < n {
+        return None;
+    }
+    let (taken, rest) = bytes.split_at(n);
+    *bytes = rest;
+    Some(taken)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::test_utils::test_utils::TempDir;
+
+    fn make_cached_line(n: usize) -> CachedLine {
+        let style = SyntectStyle {
+            foreground: Color {
+                r: 1,
+                g: 2,
+                b: 3,
+                a: 0xFF,
+            },
+            background: Color::WHITE,
+            font_style: FontStyle::BOLD | FontStyle::ITALIC,
+        };
+        (
+            vec![(style, n), (SyntectStyle::default(), n + 1)],
+            u64::MAX - n as u64,
+        )
+    }
+
+    #[test]
+    fn test_highlight_cache_entries_are_saved() {
+        let dir = TempDir::new("delta__test_highlight_cache_entries_are_saved");
+        let cache = HighlightCache::new(dir.path(), 1, 1_000_000);
+        assert_eq!(cache.get(7), None);
+        cache.insert(7, make_cached_line(3));
+        assert_eq!(cache.get(7), Some(make_cached_line(3)));
+        drop(cache);
+
+        let cache = HighlightCache::new(dir.path(), 1, 1_000_000);
+        assert_eq!(cache.get(7), Some(make_cached_line(3)));
+        cache.insert(8, make_cached_line(4));
+        drop(cache);
+
+        let cache = HighlightCache::new(dir.path(), 1, 1_000_000);
+        assert_eq!(cache.get(7), Some(make_cached_line(3)));
+        assert_eq!(cache.get(8), Some(make_cached_line(4)));
+
+        // The cache is invalidated if the syntax and theme definitions change.
+        let cache = HighlightCache::new(dir.path(), 2, 1_000_000);
+        assert_eq!(cache.get(7), None);
+    }
+
+    #[test]
+    fn test_highlight_cache_keeps_entries_in_use_when_full() {
+        let dir = TempDir::new("delta__test_highlight_cache_keeps_entries_in_use_when_full");
+        let max_size = 2 * 200;
+        let cache = HighlightCache::new(dir.path(), 1, max_size);
+        for key in 0..10 {
+            cache.insert(key, make_cached_line(1));
+        }
+        drop(cache);
+        // The current file exceeded half of the maximum size, and became the previous generation.
+        assert!(!dir.path().join("delta-highlights.bin").exists());
+
+        let cache = HighlightCache::new(dir.path(), 1, max_size);
+        assert_eq!(cache.get(0), Some(make_cached_line(1)));
+        cache.insert(10, make_cached_line(1));
+        drop(cache);
+
+        // Entry 0 was used, and so was copied to the current generation; the other old entries
+        // remain in the previous generation until it is replaced.
+        let cache = HighlightCache::new(dir.path(), 1, max_size);
+        for key in 11..20 {
+            cache.insert(key, make_cached_line(1));
+        }
+        drop(cache);
+        let cache = HighlightCache::new(dir.path(), 1, max_size);
+        assert_eq!(cache.get(0), Some(make_cached_line(1)));
+        assert_eq!(cache.get(10), Some(make_cached_line(1)));
+        assert_eq!(cache.get(1), None);
+    }
+
+    #[test]
+    fn test_highlight_cache_ignores_truncated_entry() {
+        let dir = TempDir::new("delta__test_highlight_cache_ignores_truncated_entry");
+        let cache = HighlightCache::new(dir.path(), 1, 1_000_000);
+        cache.insert(7, make_cached_line(3));
+        cache.insert(8, make_cached_line(4));
+        drop(cache);
+        let path = dir.path().join("delta-highlights.bin");
+        let contents = fs::read(&path).unwrap();
+        fs::write(&path, &contents[..contents.len() - 1]).unwrap();
+
+        let cache = HighlightCache::new(dir.path(), 1, 1_000_000);
+        assert_eq!(cache.get(7), Some(make_cached_line(3)));
+        assert_eq!(cache.get(8), None);
+        cache.insert(9, make_cached_line(5));
+        drop(cache);
+
+        // The damaged file was written again.
+        let cache = HighlightCache::new(dir.path(), 1, 1_000_000);
+        assert_eq!(cache.get(7), Some(make_cached_line(3)));
+        assert_eq!(cache.get(9), Some(make_cached_line(5)));
+    }
+}
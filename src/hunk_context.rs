@@ -0,0 +1,316 @@
+use std::collections::VecDeque;
+
+use git2::Repository;
+
+use crate::ansi;
+use crate::encoding::Encoding;
+use crate::git_blob;
+use crate::parse;
+
+/// Extends the hunks of a file with additional context lines taken from the full file (--context-lines).
+///
+/// Since hunks whose expanded context overlaps are merged, all hunks of a file are buffered before
+/// the rewritten hunks are made available, followed by the input line that ended them.
+#[derive(Default)]
+pub struct HunkContextExpander {
+    // The lines of the file from which context lines are taken, if the hunks of a file are
+    // currently being buffered.
+    file_lines: Option<Vec<String>>,
+    // Whether file_lines are the lines of the plus file (otherwise the minus file).
+    is_plus_file: bool,
+    hunks: Vec<Hunk>,
+    output_lines: VecDeque<String>,
+}
+
+struct Hunk {
+    minus_start: usize,
+    minus_len: usize,
+    plus_start: usize,
+    plus_len: usize,
+    code_fragment: String,
+    lines: Vec<String>,
+    minus_lines_remaining: usize,
+    plus_lines_remaining: usize,
+}
+
+impl HunkContextExpander {
+    /// Start buffering the hunks of a file, the first of which has header `line`. `file_lines` are
+    /// the lines of the plus file if `is_plus_file`, and of the minus file otherwise. Return false
+    /// (and do nothing) if the hunk header cannot be handled, e.g. because it is from a merge.
+    pub fn begin_file(&mut self, file_lines: Vec<String>, is_plus_file: bool, line: &str) -> bool {
+        match Hunk::from_header_line(line) {
+            Some(hunk) => {
+                self.file_lines = Some(file_lines);
+                self.is_plus_file = is_plus_file;
+                self.hunks.push(hunk);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Are the hunks of a file currently being buffered?
+    pub fn is_active(&self) -> bool {
+        self.file_lines.is_some()
+    }
+
+    /// Buffer an input line. If the line is not part of the hunks of the current file, then the
+    /// expanded hunks are output, followed by `raw_line`.
+    pub fn push_line(&mut self, raw_line: &str, context_lines: usize) {
+        let line = ansi::strip_ansi_codes(raw_line);
+        let hunk = self.hunks.last_mut().unwrap();
+        if hunk.minus_lines_remaining > 0 || hunk.plus_lines_remaining > 0 {
+            if line.starts_with('-') {
+                hunk.minus_lines_remaining = hunk.minus_lines_remaining.saturating_sub(1);
+            } else if line.starts_with('+') {
+                hunk.plus_lines_remaining = hunk.plus_lines_remaining.saturating_sub(1);
+            } else if !line.starts_with('\\') {
+                hunk.minus_lines_remaining = hunk.minus_lines_remaining.saturating_sub(1);
+                hunk.plus_lines_remaining = hunk.plus_lines_remaining.saturating_sub(1);
+            }
+            hunk.lines.push(raw_line.to_string());
+        } else if line.starts_with('\\') {
+            // "\ No newline at end of file"
+            hunk.lines.push(raw_line.to_string());
+        } else if let Some(hunk) = line
+            .starts_with("@@")
+            .then(|| Hunk::from_header_line(&line))
+            .flatten()
+        {
+            self.hunks.push(hunk);
+        } else {
+            self.finish(context_lines);
+            self.output_lines.push_back(raw_line.to_string());
+        }
+    }
+
+    /// Output the expanded hunks of the current file.
+    pub fn finish(&mut self, context_lines: usize) {
+        let file_lines = match self.file_lines.take() {
+            Some(file_lines) => file_lines,
+            None => return,
+        };
+        let hunks = std::mem::take(&mut self.hunks);
+        let file_line = |n: usize| format!(" {}", file_lines.get(n - 1).map_or("", |s| s.as_str()));
+        let n_file_lines = file_lines.len();
+
+        // Divide the hunks into groups, each of which is output as a single hunk.
+        let mut groups: Vec<Vec<&Hunk>> = Vec::new();
+        for hunk in &hunks {
+            match groups.last_mut() {
+                Some(group)
+                    if self.first_line(hunk)
+                        <= self.end_line(group.last().unwrap()) + 2 * context_lines =>
+                {
+                    group.push(hunk)
+                }
+                _ => groups.push(vec![hunk]),
+            }
+        }
+
+        for group in groups {
+            let (first_hunk, last_hunk) = (group[0], group[group.len() - 1]);
+            let n_leading = context_lines.min(self.first_line(first_hunk).saturating_sub(1));
+            let n_trailing =
+                context_lines.min((n_file_lines + 1).saturating_sub(self.end_line(last_hunk)));
+            let mut lines = Vec::new();
+            let (mut minus_len, mut plus_len) = (0, 0);
+            for n in self.first_line(first_hunk) - n_leading..self.first_line(first_hunk) {
+                lines.push(file_line(n));
+            }
+            for (i, hunk) in group.iter().enumerate() {
+                if i > 0 {
+                    for n in self.end_line(group[i - 1])..self.first_line(hunk) {
+                        lines.push(file_line(n));
+                    }
+                }
+                lines.extend(hunk.lines.iter().cloned());
+                minus_len += hunk.minus_len;
+                plus_len += hunk.plus_len;
+            }
+            for n in self.end_line(last_hunk)..self.end_line(last_hunk) + n_trailing {
+                lines.push(file_line(n));
+            }
+            let n_context_lines =
+                lines.len() - group.iter().map(|hunk| hunk.lines.len()).sum::<usize>();
+            minus_len += n_context_lines;
+            plus_len += n_context_lines;
+
+            self.output_lines.push_back(format!(
+                "@@ -{},{} +{},{} @@{}",
+                get_hunk_start(
+                    get_first_line(first_hunk.minus_start, first_hunk.minus_len) - n_leading,
+                    minus_len
+                ),
+                minus_len,
+                get_hunk_start(
+                    get_first_line(first_hunk.plus_start, first_hunk.plus_len) - n_leading,
+                    plus_len
+                ),
+                plus_len,
+                first_hunk.code_fragment,
+            ));
+            self.output_lines.extend(lines);
+        }
+    }
+
+    /// Return the next line to be processed in place of the buffered input lines.
+    pub fn next_line(&mut self) -> Option<String> {
+        self.output_lines.pop_front()
+    }
+
+    /// The line number, in file_lines, of the first line of the hunk.
+    fn first_line(&self, hunk: &Hunk) -> usize {
+        if self.is_plus_file {
+            get_first_line(hunk.plus_start, hunk.plus_len)
+        } else {
+            get_first_line(hunk.minus_start, hunk.minus_len)
+        }
+    }
+
+    /// The line number, in file_lines, of the first line after the hunk.
+    fn end_line(&self, hunk: &Hunk) -> usize {
+        if self.is_plus_file {
+            get_first_line(hunk.plus_start, hunk.plus_len) + hunk.plus_len
+        } else {
+            get_first_line(hunk.minus_start, hunk.minus_len) + hunk.minus_len
+        }
+    }
+}
+
+impl Hunk {
+    fn from_header_line(line: &str) -> Option<Self> {
+        match parse::parse_hunk_header(line) {
+            (code_fragment, line_numbers) if line_numbers.len() == 2 => {
+                let ((minus_start, minus_len), (plus_start, plus_len)) =
+                    (line_numbers[0], line_numbers[1]);
+                Some(Self {
+                    minus_start,
+                    minus_len,
+                    plus_start,
+                    plus_len,
+                    code_fragment,
+                    lines: Vec::new(),
+                    minus_lines_remaining: minus_len,
+                    plus_lines_remaining: plus_len,
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+// In a hunk header, the start of an empty range is the line after which it lies.
+fn get_first_line(start: usize, len: usize) -> usize {
+    if len == 0 {
+        start + 1
+    } else {
+        start
+    }
+}
+
+fn get_hunk_start(first_line: usize, len: usize) -> usize {
+    if len == 0 {
+        first_line - 1
+    } else {
+        first_line
+    }
+}
+
+/// Read the lines of the minus file, or, if that is not available, of the plus file, from the
+/// repository. Return the lines, and whether they are those of the plus file.
+pub fn read_file_lines(
+    repo: &Repository,
+    minus_blob_id: &str,
+    plus_blob_id: &str,
+    minus_file: &str,
+    plus_file: &str,
+    encoding: Encoding,
+) -> Option<(Vec<String>, bool)> {
+    let (contents, is_plus_file) = git_blob::get_blob_contents(repo, minus_blob_id, minus_file)
+        .map(|contents| (contents, false))
+        .or_else(|| {
+            git_blob::get_blob_contents(repo, plus_blob_id, plus_file)
+                .map(|contents| (contents, true))
+        })?;
+    let contents = encoding.decode(&contents);
+    let mut lines: Vec<String> = contents.split('\n').map(|s| s.to_string()).collect();
+    if contents.ends_with('\n') {
+        lines.pop();
+    }
+    Some((lines, is_plus_file))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand(file: &str, is_plus_file: bool, input: &str, context_lines: usize) -> String {
+        let file_lines = file.lines().map(|s| s.to_string()).collect();
+        let mut input_lines = input.lines();
+        let mut expander = HunkContextExpander::default();
+        assert!(expander.begin_file(file_lines, is_plus_file, input_lines.next().unwrap()));
+        for line in input_lines {
+            expander.push_line(line, context_lines);
+        }
+        expander.finish(context_lines);
+        let mut output = Vec::new();
+        while let Some(line) = expander.next_line() {
+            output.push(line);
+        }
+        output.join("\n")
+    }
+
+    const FILE: &str = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n11\n12\n";
+
+    #[test]
+    fn test_expand_single_hunk() {
+        assert_eq!(
+            expand(FILE, false, "@@ -5,1 +5,1 @@ x\n-5\n+five", 2),
+            "@@ -3,5 +3,5 @@ x\n 3\n 4\n-5\n+five\n 6\n 7"
+        );
+    }
+
+    #[test]
+    fn test_expand_hunk_at_start_and_end_of_file() {
+        assert_eq!(
+            expand(FILE, false, "@@ -2,1 +2,1 @@\n-2\n+two\n@@ -12 +12 @@\n-12\n+twelve", 3),
+            "@@ -1,5 +1,5 @@\n 1\n-2\n+two\n 3\n 4\n 5\n@@ -9,4 +9,4 @@\n 9\n 10\n 11\n-12\n+twelve"
+        );
+    }
+
+    #[test]
+    fn test_expand_merges_overlapping_hunks() {
+        assert_eq!(
+            expand(
+                FILE,
+                false,
+                "@@ -3 +2,0 @@\n-3\n@@ -8,0 +8 @@\n+eight-and-a-half",
+                3
+            ),
+            "@@ -1,11 +1,11 @@\n 1\n 2\n-3\n 4\n 5\n 6\n 7\n 8\n+eight-and-a-half\n 9\n 10\n 11"
+        );
+    }
+
+    #[test]
+    fn test_expand_using_plus_file() {
+        let plus_file = FILE.replace("5\n", "five\n");
+        assert_eq!(
+            expand(&plus_file, true, "@@ -5,1 +5,1 @@\n-5\n+five", 1),
+            "@@ -4,3 +4,3 @@\n 4\n-5\n+five\n 6"
+        );
+    }
+
+    #[test]
+    fn test_expand_outputs_line_ending_hunks() {
+        assert_eq!(
+            expand(
+                FILE,
+                false,
+                "@@ -5,1 +5,1 @@\n-5\n+five\ndiff --git a/b b/b",
+                1
+            ),
+            "@@ -4,3 +4,3 @@\n 4\n-5\n+five\n 6\ndiff --git a/b b/b"
+        );
+    }
+}
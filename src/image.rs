@@ -0,0 +1,542 @@
+use std::collections::BTreeSet;
+use std::convert::TryInto;
+
+use crate::cli::ImageProtocol;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ImageFormat {
+    Png,
+    Jpeg,
+    Gif,
+}
+
+impl ImageFormat {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Png => "PNG",
+            Self::Jpeg => "JPEG",
+            Self::Gif => "GIF",
+        }
+    }
+}
+
+/// The format and dimensions in pixels of an image, read from its header.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ImageInfo {
+    pub format: ImageFormat,
+    pub width: usize,
+    pub height: usize,
+}
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+// The size of a terminal cell in pixels, assumed when scaling images for sixel terminals.
+const SIXEL_CELL_WIDTH: usize = 10;
+const SIXEL_CELL_HEIGHT: usize = 20;
+
+// The largest PNG image, in pixels, that is decoded for display as a sixel image.
+const MAX_DECODED_PNG_PIXELS: usize = 16 * 1024 * 1024;
+
+// The number of columns between the old and new thumbnails.
+const THUMBNAIL_GAP: usize = 2;
+
+impl ImageInfo {
+    /// Return the format and dimensions of the image `bytes`, if it is a PNG, JPEG or GIF image.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.starts_with(PNG_SIGNATURE) {
+            Some(Self {
+                format: ImageFormat::Png,
+                width: read_u32_be(bytes, 16)? as usize,
+                height: read_u32_be(bytes, 20)? as usize,
+            })
+        } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+            Some(Self {
+                format: ImageFormat::Gif,
+                width: u16::from_le_bytes(bytes.get(6..8)?.try_into().ok()?) as usize,
+                height: u16::from_le_bytes(bytes.get(8..10)?.try_into().ok()?) as usize,
+            })
+        } else if bytes.starts_with(b"\xff\xd8") {
+            get_jpeg_info(bytes)
+        } else {
+            None
+        }
+    }
+}
+
+fn read_u16_be(bytes: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(
+        bytes.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+fn read_u32_be(bytes: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(
+        bytes.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+/// Read the dimensions of a JPEG image from its start-of-frame segment.
+fn get_jpeg_info(bytes: &[u8]) -> Option<ImageInfo> {
+    let mut offset = 2;
+    loop {
+        while *bytes.get(offset + 1)? == 0xff {
+            offset += 1;
+        }
+        match bytes.get(offset..offset + 2)? {
+            [0xff, 0xc0..=0xcf] if ![0xc4, 0xc8, 0xcc].contains(&bytes[offset + 1]) => {
+                return Some(ImageInfo {
+                    format: ImageFormat::Jpeg,
+                    width: read_u16_be(bytes, offset + 7)? as usize,
+                    height: read_u16_be(bytes, offset + 5)? as usize,
+                });
+            }
+            [0xff, _] => offset += 2 + read_u16_be(bytes, offset + 2)? as usize,
+            _ => return None,
+        }
+    }
+}
+
+/// Return a line describing the old and new versions of an image, e.g.
+/// "image: 16×16 PNG, 1.2 KiB → 32×32 PNG, 2.5 KiB".
+pub fn format_image_summary(minus: Option<&[u8]>, plus: Option<&[u8]>) -> String {
+    let describe = |bytes: Option<&[u8]>| match bytes {
+        Some(bytes) => match ImageInfo::from_bytes(bytes) {
+            Some(info) => format!(
+                "{}×{} {}, {}",
+                info.width,
+                info.height,
+                info.format.name(),
+                format_size(bytes.len())
+            ),
+            None => format!("not an image, {}", format_size(bytes.len())),
+        },
+        None => "none".to_string(),
+    };
+    format!("image: {} → {}", describe(minus), describe(plus))
+}
+
+fn format_size(size: usize) -> String {
+    match size {
+        size if size < 1024 => format!("{} B", size),
+        size if size < 1024 * 1024 => format!("{:.1} KiB", size as f64 / 1024.0),
+        size => format!("{:.1} MiB", size as f64 / (1024.0 * 1024.0)),
+    }
+}
+
+/// Return the escape sequences that display the old and new versions of an image side by side,
+/// each scaled to fit `rows` terminal rows and half of `max_columns` columns. Return None if
+/// neither version can be displayed using `protocol`. The space for the thumbnails is reserved
+/// with newlines before they are drawn, so that the saved cursor position remains valid if the
+/// terminal scrolls.
+pub fn format_thumbnails(
+    minus: Option<&[u8]>,
+    plus: Option<&[u8]>,
+    protocol: &ImageProtocol,
+    rows: usize,
+    max_columns: usize,
+) -> Option<String> {
+    let max_columns = (max_columns.saturating_sub(THUMBNAIL_GAP) / 2).max(1);
+    let (minus_thumbnail, minus_columns) = format_thumbnail(minus, protocol, rows, max_columns);
+    let (plus_thumbnail, _) = format_thumbnail(plus, protocol, rows, max_columns);
+    if minus_thumbnail.is_none() && plus_thumbnail.is_none() {
+        return None;
+    }
+    Some(format!(
+        "{newlines}\x1b[{rows}A\x1b7{minus}\x1b8\x1b[{offset}C{plus}\x1b8\x1b[{rows}B",
+        newlines = "\n".repeat(rows),
+        rows = rows,
+        minus = minus_thumbnail.unwrap_or_default(),
+        offset = minus_columns + THUMBNAIL_GAP,
+        plus = plus_thumbnail.unwrap_or_default(),
+    ))
+}
+
+/// Return the escape sequence displaying the image `bytes` in at most `rows` rows and
+/// `max_columns` columns, if it can be displayed using `protocol`, and the number of columns it
+/// occupies. Cells are assumed to be twice as high as they are wide.
+fn format_thumbnail(
+    bytes: Option<&[u8]>,
+    protocol: &ImageProtocol,
+    rows: usize,
+    max_columns: usize,
+) -> (Option<String>, usize) {
+    let (bytes, info) = match bytes.and_then(|bytes| Some((bytes, ImageInfo::from_bytes(bytes)?))) {
+        Some((bytes, info)) if info.width > 0 && info.height > 0 => (bytes, info),
+        _ => return (None, (2 * rows).min(max_columns)),
+    };
+    let columns = (2 * rows * info.width / info.height).max(1);
+    let (columns, rows) = if columns > max_columns {
+        (max_columns, (rows * max_columns / columns).max(1))
+    } else {
+        (columns, rows)
+    };
+    let thumbnail = match (protocol, info.format) {
+        (ImageProtocol::Kitty, ImageFormat::Png) => Some(format_kitty_image(bytes, columns, rows)),
+        (ImageProtocol::Iterm2, ImageFormat::Png)
+        | (ImageProtocol::Iterm2, ImageFormat::Jpeg)
+        | (ImageProtocol::Iterm2, ImageFormat::Gif) => {
+            Some(format_iterm2_image(bytes, columns, rows))
+        }
+        (ImageProtocol::Sixel, ImageFormat::Png) => decode_png(bytes).map(|pixels| {
+            format_sixel_image(
+                &pixels,
+                columns * SIXEL_CELL_WIDTH,
+                rows * SIXEL_CELL_HEIGHT,
+            )
+        }),
+        _ => None,
+    };
+    (thumbnail, columns)
+}
+
+/// Return the kitty graphics protocol escape sequences that display the PNG image `bytes` scaled
+/// to `columns` × `rows` cells, without moving the cursor. The image data is transmitted in chunks
+/// of at most 4096 bytes, as the protocol requires.
+pub fn format_kitty_image(bytes: &[u8], columns: usize, rows: usize) -> String {
+    let data = base64::encode(bytes);
+    let chunks: Vec<&[u8]> = data.as_bytes().chunks(4096).collect();
+    let mut sequences = String::new();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = if i + 1 < chunks.len() { 1 } else { 0 };
+        let control = if i == 0 {
+            format!("f=100,a=T,q=2,C=1,c={},r={},m={}", columns, rows, more)
+        } else {
+            format!("m={}", more)
+        };
+        sequences.push_str(&format!(
+            "\x1b_G{};{}\x1b\\",
+            control,
+            std::str::from_utf8(chunk).unwrap()
+        ));
+    }
+    sequences
+}
+
+/// Return the iTerm2 inline image escape sequence that displays the image `bytes` scaled to fit
+/// `columns` × `rows` cells.
+pub fn format_iterm2_image(bytes: &[u8], columns: usize, rows: usize) -> String {
+    format!(
+        "\x1b]1337;File=inline=1;size={};width={};height={};preserveAspectRatio=1:{}\x07",
+        bytes.len(),
+        columns,
+        rows,
+        base64::encode(bytes)
+    )
+}
+
+/// The pixels of a decoded image, as RGBA values in row-major order.
+#[derive(Debug, PartialEq)]
+pub struct Pixels {
+    pub width: usize,
+    pub height: usize,
+    pub rgba: Vec<[u8; 4]>,
+}
+
+/// Decode a PNG image. Return None for malformed images, and for images larger than
+/// MAX_DECODED_PNG_PIXELS.
+pub fn decode_png(bytes: &[u8]) -> Option<Pixels> {
+    let mut decoder = png::Decoder::new(bytes);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().ok()?;
+    let (width, height) = reader.info().size();
+    let (width, height) = (width as usize, height as usize);
+    if width.checked_mul(height)? > MAX_DECODED_PNG_PIXELS {
+        return None;
+    }
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer).ok()?;
+    let rgba = buffer
+        .chunks(info.color_type.samples())
+        .map(|pixel| match pixel {
+            [v] => [*v, *v, *v, 255],
+            [v, a] => [*v, *v, *v, *a],
+            [r, g, b] => [*r, *g, *b, 255],
+            [r, g, b, a, ..] => [*r, *g, *b, *a],
+            [] => unreachable!(),
+        })
+        .collect();
+    Some(Pixels {
+        width,
+        height,
+        rgba,
+    })
+}
+
+/// Return the sixel escape sequence that displays `pixels`, scaled down to fit `max_width` ×
+/// `max_height` pixels. Colors are reduced to a 6×6×6 color cube, and pixels that are mostly
+/// transparent are left undrawn.
+pub fn format_sixel_image(pixels: &Pixels, max_width: usize, max_height: usize) -> String {
+    let scale = f64::min(
+        1.0,
+        f64::min(
+            max_width as f64 / pixels.width as f64,
+            max_height as f64 / pixels.height as f64,
+        ),
+    );
+    let width = ((pixels.width as f64 * scale) as usize).max(1);
+    let height = ((pixels.height as f64 * scale) as usize).max(1);
+    let color_index = |x: usize, y: usize| {
+        let [r, g, b, a] =
+            pixels.rgba[(y * pixels.height / height) * pixels.width + x * pixels.width / width];
+        let level = |c: u8| c as usize * 6 / 256;
+        if a < 128 {
+            None
+        } else {
+            Some(36 * level(r) + 6 * level(g) + level(b))
+        }
+    };
+    let indices: Vec<Vec<Option<usize>>> = (0..height)
+        .map(|y| (0..width).map(|x| color_index(x, y)).collect())
+        .collect();
+
+    let mut sixel = format!("\x1bP0;1;0q\"1;1;{};{}", width, height);
+    let colors: BTreeSet<usize> = indices.iter().flatten().flatten().copied().collect();
+    for color in &colors {
+        let percent = |level: usize| level * 100 / 5;
+        sixel.push_str(&format!(
+            "#{};2;{};{};{}",
+            color,
+            percent(color / 36),
+            percent(color / 6 % 6),
+            percent(color % 6)
+        ));
+    }
+    for band in indices.chunks(6) {
+        let band_colors: BTreeSet<usize> = band.iter().flatten().flatten().copied().collect();
+        for color in band_colors {
+            sixel.push_str(&format!("#{}", color));
+            let sixels = (0..width).map(|x| {
+                let bits = band
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, row)| row[x] == Some(color))
+                    .fold(0, |bits, (i, _)| bits | 1 << i);
+                (63 + bits) as u8 as char
+            });
+            push_run_length_encoded(&mut sixel, sixels);
+            sixel.push('$');
+        }
+        sixel.push('-');
+    }
+    sixel.push_str("\x1b\\");
+    sixel
+}
+
+/// Append the sixel characters `sixels` to `sixel`, using the repeat introducer for runs of more
+/// than three.
+fn push_run_length_encoded(sixel: &mut String, sixels: impl Iterator<Item = char>) {
+    let mut sixels = sixels.peekable();
+    while let Some(c) = sixels.next() {
+        let mut count = 1;
+        while sixels.peek() == Some(&c) {
+            sixels.next();
+            count += 1;
+        }
+        if count > 3 {
+            sixel.push_str(&format!("!{}{}", count, c));
+        } else {
+            sixel.push_str(&c.to_string().repeat(count));
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    /// Return an RGB PNG image with the given rows of pixels, using the Sub filter for every
+    /// scanline.
+    pub fn make_png(width: usize, rows: &[&[[u8; 3]]]) -> Vec<u8> {
+        let mut data = Vec::new();
+        for row in rows {
+            data.push(1);
+            let mut previous = [0u8; 3];
+            for pixel in row.iter() {
+                data.extend(pixel.iter().zip(&previous).map(|(c, p)| c.wrapping_sub(*p)));
+                previous = *pixel;
+            }
+        }
+        make_png_from_data(width, rows.len(), &data)
+    }
+
+    /// Return an RGB PNG image of `width` × `height` pixels with the (filtered) image data `data`.
+    fn make_png_from_data(width: usize, height: usize, data: &[u8]) -> Vec<u8> {
+        let mut header = Vec::new();
+        header.extend_from_slice(&(width as u32).to_be_bytes());
+        header.extend_from_slice(&(height as u32).to_be_bytes());
+        header.extend_from_slice(&[8, 2, 0, 0, 0]);
+        make_png_from_chunks(&[
+            (b"IHDR", header),
+            (b"IDAT", miniz_oxide::deflate::compress_to_vec_zlib(data, 6)),
+            (b"IEND", vec![]),
+        ])
+    }
+
+    fn make_png_from_chunks(chunks: &[(&[u8; 4], Vec<u8>)]) -> Vec<u8> {
+        let mut png = PNG_SIGNATURE.to_vec();
+        for (chunk_type, chunk) in chunks {
+            png.extend_from_slice(&(chunk.len() as u32).to_be_bytes());
+            let start = png.len();
+            png.extend_from_slice(*chunk_type);
+            png.extend_from_slice(chunk);
+            let crc = crc32(&png[start..]);
+            png.extend_from_slice(&crc.to_be_bytes());
+        }
+        png
+    }
+
+    fn crc32(bytes: &[u8]) -> u32 {
+        let mut crc = !0u32;
+        for byte in bytes {
+            crc ^= *byte as u32;
+            for _ in 0..8 {
+                crc = if crc & 1 == 1 {
+                    (crc >> 1) ^ 0xedb8_8320
+                } else {
+                    crc >> 1
+                };
+            }
+        }
+        !crc
+    }
+
+    const RED: [u8; 3] = [255, 0, 0];
+    const BLUE: [u8; 3] = [0, 0, 255];
+
+    #[test]
+    fn test_image_info_from_bytes() {
+        assert_eq!(
+            ImageInfo::from_bytes(&make_png(3, &[&[RED, RED, BLUE], &[RED, RED, BLUE]])),
+            Some(ImageInfo {
+                format: ImageFormat::Png,
+                width: 3,
+                height: 2
+            })
+        );
+        assert_eq!(
+            ImageInfo::from_bytes(b"GIF89a\x20\x00\x10\x00"),
+            Some(ImageInfo {
+                format: ImageFormat::Gif,
+                width: 32,
+                height: 16
+            })
+        );
+        assert_eq!(
+            ImageInfo::from_bytes(
+                b"\xff\xd8\xff\xe0\x00\x04JF\xff\xc0\x00\x11\x08\x00\x30\x00\x40\x03"
+            ),
+            Some(ImageInfo {
+                format: ImageFormat::Jpeg,
+                width: 64,
+                height: 48
+            })
+        );
+        assert_eq!(ImageInfo::from_bytes(b"\x00\x01\x02"), None);
+    }
+
+    #[test]
+    fn test_format_image_summary() {
+        let png = make_png(2, &[&[RED, BLUE]]);
+        assert_eq!(
+            format_image_summary(None, Some(&png)),
+            format!("image: none → 2×1 PNG, {} B", png.len())
+        );
+        assert_eq!(format_size(2560), "2.5 KiB");
+    }
+
+    #[test]
+    fn test_decode_png() {
+        let pixels = decode_png(&make_png(2, &[&[RED, BLUE], &[BLUE, RED]])).unwrap();
+        assert_eq!(
+            pixels,
+            Pixels {
+                width: 2,
+                height: 2,
+                rgba: vec![
+                    [255, 0, 0, 255],
+                    [0, 0, 255, 255],
+                    [0, 0, 255, 255],
+                    [255, 0, 0, 255]
+                ]
+            }
+        );
+    }
+
+    #[test]
+    fn test_decode_malformed_png() {
+        let png = make_png(2, &[&[RED, BLUE], &[BLUE, RED]]);
+        // Truncated.
+        assert_eq!(decode_png(&png[..png.len() / 2]), None);
+        // A header chunk too short to hold the bit depth, color type and interlace method.
+        assert_eq!(
+            decode_png(&make_png_from_chunks(&[(
+                b"IHDR",
+                vec![0, 0, 0, 1, 0, 0, 0, 1]
+            )])),
+            None
+        );
+        // More image data than the dimensions allow for: only the pixels they allow for are read.
+        assert_eq!(
+            decode_png(&make_png_from_data(1, 1, &[0; 8])).map(|pixels| pixels.rgba),
+            Some(vec![[0, 0, 0, 255]])
+        );
+        // Less image data than the dimensions require.
+        assert_eq!(decode_png(&make_png_from_data(2, 2, &[0; 7])), None);
+        // Dimensions too large to decode, with little image data.
+        assert_eq!(
+            decode_png(&make_png_from_data(1 << 20, 1 << 20, &[0; 8])),
+            None
+        );
+        assert_eq!(
+            decode_png(&make_png_from_data(
+                u32::MAX as usize,
+                u32::MAX as usize,
+                &[0; 8]
+            )),
+            None
+        );
+    }
+
+    #[test]
+    fn test_format_sixel_image() {
+        let pixels = decode_png(&make_png(6, &[&[RED, RED, RED, RED, RED, BLUE]])).unwrap();
+        assert_eq!(
+            format_sixel_image(&pixels, 100, 100),
+            "\x1bP0;1;0q\"1;1;6;1#5;2;0;0;100#180;2;100;0;0#5!5?@$#180!5@?$-\x1b\\"
+        );
+        // Scaled down to fit.
+        assert!(format_sixel_image(&pixels, 3, 100).starts_with("\x1bP0;1;0q\"1;1;3;1"));
+    }
+
+    #[test]
+    fn test_format_kitty_image() {
+        let png = vec![0u8; 3073];
+        let sequences = format_kitty_image(&png, 8, 4);
+        assert!(sequences.starts_with("\x1b_Gf=100,a=T,q=2,C=1,c=8,r=4,m=1;AAAA"));
+        assert!(sequences.ends_with("\x1b_Gm=0;AA==\x1b\\"));
+    }
+
+    #[test]
+    fn test_format_iterm2_image() {
+        assert_eq!(
+            format_iterm2_image(b"GIF", 8, 4),
+            "\x1b]1337;File=inline=1;size=3;width=8;height=4;preserveAspectRatio=1:R0lG\x07"
+        );
+    }
+
+    #[test]
+    fn test_format_thumbnails() {
+        let png = make_png(2, &[&[RED, BLUE]]);
+        assert_eq!(
+            format_thumbnails(None, Some(&png), &ImageProtocol::Kitty, 2, 80),
+            Some(format!(
+                "\n\n\x1b[2A\x1b7\x1b8\x1b[6C{}\x1b8\x1b[2B",
+                format_kitty_image(&png, 8, 2)
+            ))
+        );
+        assert_eq!(
+            format_thumbnails(None, Some(&png), &ImageProtocol::None, 2, 80),
+            None
+        );
+    }
+}
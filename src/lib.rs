@@ -0,0 +1,232 @@
+//! Delta's rendering of git and diff output, for use by other programs.
+//!
+//! A [`Config`] is built from delta's options with [`ConfigBuilder`]. A diff, either as text (the
+//! output of `git diff`, `git log -p`, `git show`, or `diff -u`) or as a [`git2::Diff`], is then
+//! rendered as delta would display it, with ANSI escape sequences:
+//!
+//! ```
+//! let config = git_delta::ConfigBuilder::new()
+//!     .option("features", "line-numbers")
+//!     .option("width", "80")
+//!     .build()
+//!     .unwrap();
+//! let diff = "\
+//! diff --git a/a.py b/a.py
+//! index 1111111..2222222 100644
+//! --- a/a.py
+//! +++ b/a.py
+//! @@ -1 +1 @@
+//! -x = 1
+//! +x = 2
+//! ";
+//! let output = git_delta::render_to_string(diff.as_bytes(), &config).unwrap();
+//! assert!(output.contains("a.py"));
+//! ```
+//!
+//! To produce some other kind of output, implement [`model::Renderer`] and pass it to [`parse`]:
+//! the diff is parsed into a stream of commit, file, and hunk headers and lines annotated with
+//! their syntax highlighting and inferred edits.
+
+extern crate bitflags;
+
+#[macro_use]
+extern crate error_chain;
+
+mod align;
+mod annotate;
+mod ansi;
+mod app;
+#[cfg(not(tarpaulin_include))]
+mod bat_utils;
+mod builtin_pager;
+mod cli;
+mod color;
+mod config;
+mod delta;
+mod draw;
+mod edits;
+mod enclosing_scope;
+mod encoding;
+mod env;
+mod features;
+mod file_header_amendment;
+mod format;
+mod git_blob;
+mod git_config;
+mod git_config_entry;
+mod git_diff;
+mod hex_diff;
+mod highlight_cache;
+mod hunk_context;
+mod image;
+mod line_counts;
+mod line_endings;
+pub mod model;
+mod options;
+mod paint;
+mod parallel;
+mod parse;
+mod parse_style;
+mod style;
+mod submodule;
+mod syntax_highlighter;
+mod syntect_color;
+mod tests;
+
+use std::io::{self, BufRead, Write};
+
+#[doc(hidden)]
+pub use crate::app::run;
+pub use crate::config::{Config, ConfigBuilder};
+pub use crate::git_config::GitConfig;
+pub use crate::git_diff::git2_diff_to_text;
+
+pub mod errors {
+    error_chain! {
+        foreign_links {
+            Io(::std::io::Error);
+            SyntectError(::syntect::LoadingError);
+            ParseIntError(::std::num::ParseIntError);
+            Clap(::structopt::clap::Error);
+            Git(::git2::Error);
+        }
+    }
+}
+
+/// Write `input`, a diff as produced by git or diff -u, to `writer` as delta displays it.
+pub fn render(input: &[u8], config: &Config, writer: &mut dyn Write) -> io::Result<()> {
+    delta::delta(BufRead::split(input, b'\n'), writer, config)
+}
+
+/// Return `input`, a diff as produced by git or diff -u, as delta displays it.
+pub fn render_to_string(input: &[u8], config: &Config) -> io::Result<String> {
+    let mut output = Vec::new();
+    render(input, config, &mut output)?;
+    Ok(String::from_utf8_lossy(&output).into_owned())
+}
+
+/// Write `diff` to `writer` as delta displays it.
+pub fn render_git2_diff(
+    diff: &git2::Diff,
+    config: &Config,
+    writer: &mut dyn Write,
+) -> errors::Result<()> {
+    render(&git2_diff_to_text(diff)?, config, writer)?;
+    Ok(())
+}
+
+/// Parse `input`, a diff as produced by git or diff -u, passing it to `renderer` as a stream of
+/// events.
+pub fn parse(input: &[u8], config: &Config, renderer: &mut dyn model::Renderer) -> io::Result<()> {
+    delta::parse_diff(BufRead::split(input, b'\n'), config, renderer)
+}
+
+#[cfg(test)]
+mod lib_tests {
+    use super::*;
+    use crate::git_blob::tests::make_git_repo;
+
+    /// Return a diff of a.py between two trees containing `minus` and `plus` respectively.
+    fn make_diff<'a>(repo: &'a git2::Repository, minus: &str, plus: &str) -> git2::Diff<'a> {
+        let make_tree = |contents: &str| {
+            let blob_id = repo.blob(contents.as_bytes()).unwrap();
+            let mut tree_builder = repo.treebuilder(None).unwrap();
+            tree_builder.insert("a.py", blob_id, 0o100644).unwrap();
+            repo.find_tree(tree_builder.write().unwrap()).unwrap()
+        };
+        repo.diff_tree_to_tree(Some(&make_tree(minus)), Some(&make_tree(plus)), None)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_git2_diff_to_text_and_render_git2_diff() {
+        let repo = make_git_repo("delta__test_git2_diff_to_text_and_render_git2_diff");
+        let diff = make_diff(&repo, "x = 0\ny = 1\nz = 3", "x = 0\ny = 2\nz = 3");
+
+        let text = String::from_utf8(git2_diff_to_text(&diff).unwrap()).unwrap();
+        assert!(text.starts_with("diff --git a/a.py b/a.py\nindex "));
+        assert!(text.ends_with(
+            "\
+--- a/a.py
++++ b/a.py
+@@ -1,3 +1,3 @@
+ x = 0
+-y = 1
++y = 2
+ z = 3
+\\ No newline at end of file
+"
+        ));
+
+        let config = ConfigBuilder::new().build().unwrap();
+        let mut output = Vec::new();
+        render_git2_diff(&diff, &config, &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            render_to_string(text.as_bytes(), &config).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_config_builder_reports_invalid_options() {
+        assert!(ConfigBuilder::new()
+            .option("no-such-option", "1")
+            .build()
+            .is_err());
+        assert!(ConfigBuilder::new()
+            .option("navigate-targets", "bogus")
+            .build()
+            .is_err());
+        assert!(ConfigBuilder::new()
+            .option("default-encoding", "bogus")
+            .build()
+            .is_err());
+        assert!(ConfigBuilder::new()
+            .option("paging", "bogus")
+            .build()
+            .is_err());
+        assert!(ConfigBuilder::new()
+            .option("image-protocol", "bogus")
+            .build()
+            .is_err());
+        assert!(ConfigBuilder::new()
+            .option("inspect-raw-lines", "bogus")
+            .build()
+            .is_err());
+        assert!(ConfigBuilder::new()
+            .option("minus-style", "red green blue")
+            .build()
+            .is_err());
+        assert!(ConfigBuilder::new()
+            .option("plus-style", "bogus")
+            .build()
+            .is_err());
+        assert!(ConfigBuilder::new()
+            .option("hunk-header-decoration-style", "syntax box")
+            .build()
+            .is_err());
+        assert!(ConfigBuilder::new()
+            .option("zero-style", "normal syntax")
+            .build()
+            .is_err());
+        assert!(ConfigBuilder::new()
+            .option("width", "bogus")
+            .build()
+            .is_err());
+        assert!(ConfigBuilder::new()
+            .option("24-bit-color", "bogus")
+            .build()
+            .is_err());
+        assert!(ConfigBuilder::new()
+            .flag("light")
+            .flag("dark")
+            .build()
+            .is_err());
+        assert!(ConfigBuilder::new()
+            .option("hunk-style", "box")
+            .option("hunk-header-decoration-style", "red")
+            .build()
+            .is_err());
+        assert!(ConfigBuilder::new().option("width", "100").build().is_ok());
+    }
+}
@@ -0,0 +1,25 @@
+use crate::file_header_amendment::FileHeaderAmendment;
+use crate::model::{Event, FileHeader};
+
+/// The numbers of lines added to and removed from a file, recorded in its header when
+/// --file-header-format displays them (see file_header_amendment::FileHeaderAmender).
+#[derive(Default)]
+pub struct LineCounts {
+    added: usize,
+    removed: usize,
+}
+
+impl FileHeaderAmendment for LineCounts {
+    fn observe(&mut self, event: &Event) {
+        if let Event::ChangedLines(changed_lines) = event {
+            self.removed += changed_lines.minus_lines.len();
+            self.added += changed_lines.plus_lines.len();
+        }
+    }
+
+    fn amend(self, file_header: &mut FileHeader) {
+        if let FileHeader::Paths { line_counts, .. } = file_header {
+            *line_counts = Some((self.added, self.removed));
+        }
+    }
+}
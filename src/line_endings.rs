@@ -0,0 +1,158 @@
+use std::borrow::Cow;
+
+use crate::ansi;
+use crate::file_header_amendment::FileHeaderAmendment;
+use crate::model::{Event, FileHeader, Line, LineFormat};
+
+const BYTE_ORDER_MARK: char = '\u{feff}';
+
+/// The markers displayed for a CRLF line ending, a byte order mark, and a missing newline at the
+/// end of a file.
+pub const CRLF_MARKER: &str = "␍";
+pub const BOM_MARKER: &str = "[BOM]";
+pub const NO_NEWLINE_MARKER: &str = " no newline ";
+
+/// Remove the carriage return ending `raw_line`, if it has one (possibly followed by ANSI escape
+/// sequences). Return the line and whether it had a CRLF line ending.
+pub fn strip_carriage_return(raw_line: Cow<'_, str>) -> (Cow<'_, str>, bool) {
+    match raw_line.rfind('\r') {
+        Some(i) if ansi::strip_ansi_codes(&raw_line[i + 1..]).is_empty() => {
+            let mut raw_line = raw_line.into_owned();
+            raw_line.remove(i);
+            (Cow::from(raw_line), true)
+        }
+        _ => (raw_line, false),
+    }
+}
+
+/// Remove the byte order mark following the -/+/space character of the hunk line `line`, if it
+/// has one. Return the line and whether it had a byte order mark.
+pub fn strip_byte_order_mark(line: &str) -> (Cow<'_, str>, bool) {
+    let mut chars = line.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), Some(BYTE_ORDER_MARK)) => (Cow::from(format!("{}{}", c, chars.as_str())), true),
+        _ => (Cow::from(line), false),
+    }
+}
+
+/// Return the parts of the format of a line that are to be marked when it is displayed: those
+/// that differ from the format of the line with which it is paired, if any. An unpaired line is
+/// marked only if it lacks a newline at the end of the file.
+pub fn get_format_changes(format: LineFormat, paired_format: Option<LineFormat>) -> LineFormat {
+    match paired_format {
+        Some(paired_format) => LineFormat {
+            has_bom: format.has_bom && !paired_format.has_bom,
+            has_crlf: format.has_crlf && !paired_format.has_crlf,
+            has_no_newline: format.has_no_newline && !paired_format.has_no_newline,
+        },
+        None => LineFormat {
+            has_no_newline: format.has_no_newline,
+            ..LineFormat::default()
+        },
+    }
+}
+
+/// A change to the line endings of a file, noted in its header under --line-ending-note (see
+/// file_header_amendment::FileHeaderAmender).
+#[derive(Default)]
+pub struct LineEndingNote {
+    // The numbers of lines of the file with CRLF and LF line endings, in the minus file and the
+    // plus file.
+    minus_line_endings: LineEndingCounts,
+    plus_line_endings: LineEndingCounts,
+}
+
+#[derive(Default)]
+struct LineEndingCounts {
+    crlf: usize,
+    lf: usize,
+}
+
+impl FileHeaderAmendment for LineEndingNote {
+    fn observe(&mut self, event: &Event) {
+        match event {
+            Event::ChangedLines(changed_lines) => {
+                self.minus_line_endings.add(&changed_lines.minus_lines);
+                self.plus_line_endings.add(&changed_lines.plus_lines);
+            }
+            Event::UnchangedLine(line) => {
+                self.minus_line_endings.add(std::slice::from_ref(line));
+                self.plus_line_endings.add(std::slice::from_ref(line));
+            }
+            _ => {}
+        }
+    }
+
+    fn amend(self, file_header: &mut FileHeader) {
+        if let FileHeader::Paths { note, .. } = file_header {
+            *note = match (
+                self.minus_line_endings.describe(),
+                self.plus_line_endings.describe(),
+            ) {
+                (Some(minus), Some(plus)) if minus != plus => {
+                    Some(format!("line endings: {} → {}", minus, plus))
+                }
+                _ => None,
+            };
+        }
+    }
+}
+
+impl LineEndingCounts {
+    fn add(&mut self, lines: &[Line]) {
+        for line in lines {
+            match line.format {
+                LineFormat {
+                    has_no_newline: true,
+                    ..
+                } => {}
+                LineFormat { has_crlf: true, .. } => self.crlf += 1,
+                _ => self.lf += 1,
+            }
+        }
+    }
+
+    fn describe(&self) -> Option<&'static str> {
+        match (self.crlf, self.lf) {
+            (0, 0) => None,
+            (_, 0) => Some("CRLF"),
+            (0, _) => Some("LF"),
+            _ => Some("mixed"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_carriage_return() {
+        assert_eq!(
+            strip_carriage_return(Cow::from("-foo\r")),
+            (Cow::from("-foo"), true)
+        );
+        assert_eq!(
+            strip_carriage_return(Cow::from("\x1b[31m-foo\r\x1b[m")),
+            (Cow::from("\x1b[31m-foo\x1b[m"), true)
+        );
+        assert_eq!(
+            strip_carriage_return(Cow::from("-foo\rbar")),
+            (Cow::from("-foo\rbar"), false)
+        );
+        assert_eq!(
+            strip_carriage_return(Cow::from("-foo")),
+            (Cow::from("-foo"), false)
+        );
+    }
+
+    #[test]
+    fn test_strip_byte_order_mark() {
+        assert_eq!(
+            strip_byte_order_mark("+\u{feff}foo"),
+            (Cow::from("+foo"), true)
+        );
+        assert_eq!(strip_byte_order_mark("+foo"), (Cow::from("+foo"), false));
+        assert_eq!(strip_byte_order_mark("+"), (Cow::from("+"), false));
+    }
+}
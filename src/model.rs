@@ -0,0 +1,254 @@
+use std::io;
+
+use syntect::highlighting::Style as SyntectStyle;
+
+use crate::delta::State;
+use crate::parse::{FileEvent, FileInfo};
+
+/// The parsed form of a diff, produced by delta::parse_diff as a stream of events: a commit header
+/// is followed by the files changed in the commit, a file header by the hunks of the file, and a
+/// hunk header by the lines of the hunk. The lines are annotated for rendering with their syntax
+/// highlighting and, for changed lines, with the edit operations inferred between removed and added
+/// lines.
+#[derive(Clone, Debug)]
+pub enum Event {
+    CommitHeader(CommitHeader),
+    FileHeader(FileHeader),
+    HunkHeader(HunkHeader),
+    /// A run of removed and added lines.
+    ChangedLines(ChangedLines),
+    UnchangedLine(Line),
+    /// The differing regions of the two versions of a binary file, under --hex-diff.
+    BinaryFileDiff(BinaryFileDiff),
+    /// The two versions of an image that git reports as a binary file, under --image-thumbnails.
+    ImageFileDiff(ImageFileDiff),
+    /// A line such as "Submodule x/y 1234567..89abcde:", emitted by git diff --submodule.
+    SubmoduleHeader(SubmoduleHeader),
+    /// A commit listed under a submodule header by git diff --submodule=log.
+    SubmoduleCommit(SubmoduleCommit),
+    /// A line within a hunk that is not part of the file, e.g. "\ No newline at end of file".
+    HunkMetaLine(String),
+    /// A line that is passed through without interpretation, e.g. commit metadata.
+    RawLine(String),
+}
+
+/// Something that consumes the parsed form of a diff, e.g. paint::Painter, which writes it to the
+/// terminal.
+pub trait Renderer {
+    fn render(&mut self, event: Event) -> io::Result<()>;
+}
+
+/// Collects the events, e.g. those of one file of the diff under parallel::parse_diff.
+impl Renderer for Vec<Event> {
+    fn render(&mut self, event: Event) -> io::Result<()> {
+        self.push(event);
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CommitHeader {
+    pub line: String,
+    pub raw_line: String,
+}
+
+#[derive(Clone, Debug)]
+pub enum FileHeader {
+    /// A file identified by the paths in the ---/+++ (or rename/copy) lines.
+    Paths {
+        minus_file: String,
+        plus_file: String,
+        file_event: FileEvent,
+        comparing: bool, // Whether the diff is a comparison of files, i.e. from diff -u
+        /// A note on the changes to the file, e.g. to its line endings under --line-ending-note.
+        note: Option<String>,
+        /// The file's modes, similarity and symlink targets, from the extended header lines.
+        file_info: FileInfo,
+        /// The numbers of lines (added, removed), when --file-header-format displays them.
+        line_counts: Option<(usize, usize)>,
+    },
+    /// A line used as a file header as it stands, e.g. "Binary files a/x and b/x differ".
+    Line { line: String, raw_line: String },
+}
+
+#[derive(Clone, Debug)]
+pub struct HunkHeader {
+    pub line: String,
+    pub raw_line: String,
+    /// The (start, length) of the hunk in each file, the plus file last.
+    pub line_numbers: Vec<(usize, usize)>,
+    pub plus_file: String,
+    /// The code fragment that follows the line numbers (or, under --hunk-header-enclosing-scope,
+    /// the enclosing scope), to be displayed in the hunk header. Its text is empty if there is
+    /// nothing to display.
+    pub code_fragment: Line,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SubmoduleHeader {
+    pub path: String,
+    /// The abbreviated ids of the commits of the submodule recorded before and after the change;
+    /// all zeros for a submodule that was added or deleted.
+    pub minus_commit: String,
+    pub plus_commit: String,
+    /// The parenthesized remark that git may append, e.g. "rewind" or "new submodule".
+    pub note: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SubmoduleCommit {
+    /// Whether the commit was added ('>') rather than removed ('<').
+    pub is_plus: bool,
+    pub summary: String,
+    /// The id of the commit, if it could be found in the submodule's repository.
+    pub commit_id: Option<String>,
+    /// The URL of the commit on the submodule's GitHub remote, under --hyperlinks.
+    pub url: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct BinaryFileDiff {
+    pub minus_size: usize,
+    pub plus_size: usize,
+    /// Runs of consecutive rows of the hex dump in which the versions differ, or None if either
+    /// version is larger than config.hex_diff_max_size.
+    pub regions: Option<Vec<Vec<HexRow>>>,
+}
+
+/// The contents of each version of an image file, or None if the file is absent from that side of
+/// the diff.
+#[derive(Clone, Debug)]
+pub struct ImageFileDiff {
+    pub minus_image: Option<Vec<u8>>,
+    pub plus_image: Option<Vec<u8>>,
+}
+
+/// A row of a hex dump: the bytes of each version of a file at `offset` (fewer at the end of the
+/// file).
+#[derive(Clone, Debug, PartialEq)]
+pub struct HexRow {
+    pub offset: usize,
+    pub minus_bytes: Vec<u8>,
+    pub plus_bytes: Vec<u8>,
+}
+
+/// Removed lines and added lines, with the inferred alignment of homologous lines: each element
+/// of `alignment` is a pair of indices into `minus_lines` and `plus_lines`, either of which may be
+/// absent if a line has no counterpart.
+#[derive(Clone, Debug)]
+pub struct ChangedLines {
+    pub minus_lines: Vec<Line>,
+    pub plus_lines: Vec<Line>,
+    pub alignment: Vec<(Option<usize>, Option<usize>)>,
+}
+
+/// A line, annotated for rendering.
+#[derive(Clone, Debug)]
+pub struct Line {
+    pub state: State,
+    /// The line, with its initial -/+/space character replaced by a space, tabs expanded, and,
+    /// other than in a hunk header, terminated by a newline (see annotate::Annotator::prepare).
+    pub text: String,
+    /// The text divided into sections with their syntax highlighting style. This is a single
+    /// section with config.null_syntect_style if the line is not syntax highlighted.
+    pub syntax_sections: Vec<(SyntectStyle, String)>,
+    /// For a removed or added line, the text divided into sections with the edit operation
+    /// inferred for each; otherwise empty.
+    pub edit_sections: Vec<(EditOperation, String)>,
+    /// For a removed or added line, whether it is paired with a line from which it differs only in
+    /// whitespace; otherwise false.
+    pub is_whitespace_change: bool,
+    /// For a removed, added, or unchanged line, the parts of the line that are not in its text.
+    pub format: LineFormat,
+}
+
+/// The parts of a line of a file that are not displayed as text: a byte order mark at its start,
+/// its line ending, and, for the last line of a file, whether it lacks a newline.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LineFormat {
+    pub has_bom: bool,
+    pub has_crlf: bool,
+    pub has_no_newline: bool,
+}
+
+/// The edit operations, inferred by edits::infer_edits, relating a removed line to the added line
+/// with which it is paired.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EditOperation {
+    MinusNoop,
+    Deletion,
+    PlusNoop,
+    Insertion,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::BufRead;
+
+    use super::*;
+    use crate::delta;
+    use crate::tests::integration_test_utils::integration_test_utils;
+
+    /// Render the diff `input` to a list of events, summarizing each hunk event.
+    fn render_hunk_events(input: &str, args: &[&str]) -> Vec<String> {
+        let config = integration_test_utils::make_config_from_args(args);
+        let mut events = Vec::new();
+        delta::parse_diff(
+            BufRead::split(input.as_bytes(), b'\n'),
+            &config,
+            &mut events,
+        )
+        .unwrap();
+        events
+            .iter()
+            .filter_map(|event| match event {
+                Event::HunkHeader(hunk_header) => {
+                    Some(format!("@@ {:?}", hunk_header.line_numbers))
+                }
+                Event::ChangedLines(changed_lines) => Some(format!(
+                    "-{} +{}",
+                    changed_lines.minus_lines.len(),
+                    changed_lines.plus_lines.len()
+                )),
+                Event::UnchangedLine(line) => Some(format!("={}", line.text.trim())),
+                Event::HunkMetaLine(line) => Some(line.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_renderer_receives_hunk_events_in_order() {
+        let input = format!(
+            "{}{}",
+            HUNK_HEADER, "-a\n+b\n c\n-d\n e\n+f\n\\ No newline at end of file\n"
+        );
+        assert_eq!(
+            render_hunk_events(&input, &[]),
+            vec!["@@ [(1, 4), (1, 4)]", "-1 +1", "=c", "-1 +0", "=e", "-0 +1",]
+        );
+    }
+
+    #[test]
+    fn test_renderer_receives_changed_lines_flushed_at_line_buffer_size() {
+        let input = format!("{}{}", HUNK_HEADER, "-a\n-b\n-c\n+d\n+e\n+f\n+g\n z\n");
+        // The buffers are flushed before a line is added to a buffer holding more than
+        // --line-buffer-size lines.
+        assert_eq!(
+            render_hunk_events(&input, &["--line-buffer-size", "2"]),
+            vec!["@@ [(1, 4), (1, 4)]", "-3 +0", "-0 +3", "-0 +1", "=z"]
+        );
+        assert_eq!(
+            render_hunk_events(&input, &[]),
+            vec!["@@ [(1, 4), (1, 4)]", "-3 +4", "=z"]
+        );
+    }
+
+    const HUNK_HEADER: &str = "\
+diff --git a/a.txt b/a.txt
+index 1111111..2222222 100644
+--- a/a.txt
++++ b/a.txt
+@@ -1,4 +1,4 @@
+";
+}
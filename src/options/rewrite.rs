@@ -2,18 +2,18 @@
 /// 1. Express deprecated usages in the new non-deprecated form
 /// 2. Implement options such as --raw which are defined to be equivalent to some set of
 ///    other options.
-use std::process;
-
 use structopt::clap;
 
 use crate::cli;
 use crate::config::user_supplied_option;
+use crate::errors::*;
 
-pub fn apply_rewrite_rules(opt: &mut cli::Opt, arg_matches: &clap::ArgMatches) {
-    rewrite_style_strings_to_honor_deprecated_minus_plus_options(opt);
+pub fn apply_rewrite_rules(opt: &mut cli::Opt, arg_matches: &clap::ArgMatches) -> Result<()> {
+    rewrite_style_strings_to_honor_deprecated_minus_plus_options(opt)?;
     rewrite_options_to_implement_deprecated_commit_and_file_style_box_option(opt);
-    rewrite_options_to_implement_deprecated_hunk_style_option(opt);
+    rewrite_options_to_implement_deprecated_hunk_style_option(opt)?;
     rewrite_options_to_implement_deprecated_theme_option(opt, arg_matches);
+    Ok(())
 }
 
 /// Honor deprecated --theme
@@ -31,7 +31,7 @@ fn rewrite_options_to_implement_deprecated_theme_option(
 /// Honor deprecated arguments by rewriting the canonical --*-style arguments if appropriate.
 // TODO: How to avoid repeating the default values for style options here and in
 // the structopt definition?
-fn rewrite_style_strings_to_honor_deprecated_minus_plus_options(opt: &mut cli::Opt) {
+fn rewrite_style_strings_to_honor_deprecated_minus_plus_options(opt: &mut cli::Opt) -> Result<()> {
     // If --highlight-removed was passed then we should set minus and minus emph foreground to
     // "syntax", if they are still at their default values.
     let deprecated_minus_foreground_arg = if opt.deprecated_highlight_minus_lines {
@@ -48,7 +48,7 @@ fn rewrite_style_strings_to_honor_deprecated_minus_plus_options(opt: &mut cli::O
             opt.deprecated_minus_background_color.as_deref(),
         ),
         "minus",
-    ) {
+    )? {
         opt.minus_style = rewritten;
     }
     if let Some(rewritten) = _get_rewritten_minus_plus_style_string(
@@ -59,7 +59,7 @@ fn rewrite_style_strings_to_honor_deprecated_minus_plus_options(opt: &mut cli::O
             opt.deprecated_minus_emph_background_color.as_deref(),
         ),
         "minus-emph",
-    ) {
+    )? {
         opt.minus_emph_style = rewritten;
     }
     if let Some(rewritten) = _get_rewritten_minus_plus_style_string(
@@ -67,7 +67,7 @@ fn rewrite_style_strings_to_honor_deprecated_minus_plus_options(opt: &mut cli::O
         ("syntax", "auto"),
         (None, opt.deprecated_plus_background_color.as_deref()),
         "plus",
-    ) {
+    )? {
         opt.plus_style = rewritten;
     }
     if let Some(rewritten) = _get_rewritten_minus_plus_style_string(
@@ -75,9 +75,10 @@ fn rewrite_style_strings_to_honor_deprecated_minus_plus_options(opt: &mut cli::O
         ("syntax", "auto"),
         (None, opt.deprecated_plus_emph_background_color.as_deref()),
         "plus-emph",
-    ) {
+    )? {
         opt.plus_emph_style = rewritten;
     }
+    Ok(())
 }
 
 /// For backwards-compatibility, --{commit,file}-style box means --element-decoration-style 'box ul'.
@@ -92,7 +93,7 @@ fn rewrite_options_to_implement_deprecated_commit_and_file_style_box_option(opt:
     }
 }
 
-fn rewrite_options_to_implement_deprecated_hunk_style_option(opt: &mut cli::Opt) {
+fn rewrite_options_to_implement_deprecated_hunk_style_option(opt: &mut cli::Opt) -> Result<()> {
     // Examples of how --hunk-style was originally used are
     // --hunk-style box       => --hunk-header-decoration-style box
     // --hunk-style underline => --hunk-header-decoration-style underline
@@ -102,10 +103,11 @@ fn rewrite_options_to_implement_deprecated_hunk_style_option(opt: &mut cli::Opt)
         // apparently been left at its default value.
         let hunk_header_decoration_default = "blue box";
         if opt.hunk_header_decoration_style != hunk_header_decoration_default {
-            eprintln!(
+            return Err(
                 "Deprecated option --hunk-style cannot be used with --hunk-header-decoration-style. \
-                 Use --hunk-header-decoration-style.");
-            process::exit(1);
+                 Use --hunk-header-decoration-style."
+                    .into(),
+            );
         }
         match opt.deprecated_hunk_style.as_deref().map(str::to_lowercase) {
             Some(attr) if attr == "plain" => opt.hunk_header_decoration_style = "".to_string(),
@@ -115,6 +117,7 @@ fn rewrite_options_to_implement_deprecated_hunk_style_option(opt: &mut cli::Opt)
         }
         opt.deprecated_hunk_style = None;
     }
+    Ok(())
 }
 
 fn _get_rewritten_commit_file_hunk_header_style_string(
@@ -148,41 +151,37 @@ fn _get_rewritten_minus_plus_style_string(
     style_default_pair: (&str, &str),
     deprecated_args_style_pair: (Option<&str>, Option<&str>),
     element_name: &str,
-) -> Option<String> {
+) -> Result<Option<String>> {
     let format_style = |pair: (&str, &str)| format!("{} {}", pair.0, pair.1);
     match (style, deprecated_args_style_pair) {
-        (_, (None, None)) => None, // no rewrite
+        (_, (None, None)) => Ok(None), // no rewrite
         (style, deprecated_args_style_pair) if style == format_style(style_default_pair) => {
             // TODO: We allow the deprecated argument values to have effect if
             // the style argument value is equal to its default value. This is
             // non-ideal, because the user may have explicitly supplied the
             // style argument (i.e. it might just happen to equal the default).
-            Some(format_style((
+            Ok(Some(format_style((
                 deprecated_args_style_pair.0.unwrap_or(style_default_pair.0),
                 deprecated_args_style_pair.1.unwrap_or(style_default_pair.1),
-            )))
+            ))))
         }
-        (_, (_, Some(_))) => {
-            eprintln!(
-                "--{name}-color cannot be used with --{name}-style. \
+        (_, (_, Some(_))) => Err(format!(
+            "--{name}-color cannot be used with --{name}-style. \
                  Use --{name}-style=\"fg bg attr1 attr2 ...\" to set \
                  foreground color, background color, and style attributes. \
                  --{name}-color can only be used to set the background color. \
                  (It is still available for backwards-compatibility.)",
-                name = element_name,
-            );
-            process::exit(1);
-        }
-        (_, (Some(_), None)) => {
-            eprintln!(
-                "Deprecated option --highlight-removed cannot be used with \
+            name = element_name,
+        )
+        .into()),
+        (_, (Some(_), None)) => Err(format!(
+            "Deprecated option --highlight-removed cannot be used with \
                  --{name}-style. Use --{name}-style=\"fg bg attr1 attr2 ...\" \
                  to set foreground color, background color, and style \
                  attributes.",
-                name = element_name,
-            );
-            process::exit(1);
-        }
+            name = element_name,
+        )
+        .into()),
     }
 }
 
@@ -203,7 +202,7 @@ mod tests {
         opt.deprecated_hunk_style = Some("underline".to_string());
         let default = "blue box";
         assert_eq!(opt.hunk_header_decoration_style, default);
-        apply_rewrite_rules(&mut opt, &clap::ArgMatches::new());
+        apply_rewrite_rules(&mut opt, &clap::ArgMatches::new()).unwrap();
         assert_eq!(opt.deprecated_hunk_style, None);
         assert_eq!(opt.hunk_header_decoration_style, "underline");
     }
@@ -214,7 +213,7 @@ mod tests {
         opt.deprecated_hunk_style = Some("".to_string());
         let default = "blue box";
         assert_eq!(opt.hunk_header_decoration_style, default);
-        apply_rewrite_rules(&mut opt, &clap::ArgMatches::new());
+        apply_rewrite_rules(&mut opt, &clap::ArgMatches::new()).unwrap();
         assert_eq!(opt.deprecated_hunk_style, None);
         assert_eq!(opt.hunk_header_decoration_style, default);
     }
@@ -1,13 +1,10 @@
 use std::collections::{HashMap, HashSet, VecDeque};
-use std::process;
-use std::result::Result;
 use std::str::FromStr;
 
 use console::Term;
 use structopt::clap;
 
 use crate::bat_utils::assets::HighlightingAssets;
-use crate::bat_utils::output::PagingMode;
 use crate::cli;
 use crate::config;
 use crate::env;
@@ -67,7 +64,7 @@ pub fn set_options(
     git_config: &mut Option<git_config::GitConfig>,
     arg_matches: &clap::ArgMatches,
     assets: HighlightingAssets,
-) {
+) -> Result<()> {
     if let Some(git_config) = git_config {
         if opt.no_gitconfig {
             git_config.enabled = false;
@@ -90,11 +87,11 @@ pub fn set_options(
     let features = gather_features(opt, &builtin_features, git_config);
     opt.features = features.join(" ");
 
-    set_widths(opt, git_config, arg_matches, &option_names);
+    set_widths(opt, git_config, arg_matches, &option_names)?;
 
     // Set light, dark, and syntax-theme.
-    set_true_color(opt);
-    set__light__dark__syntax_theme__options(opt, git_config, arg_matches, &option_names);
+    set_true_color(opt)?;
+    set__light__dark__syntax_theme__options(opt, git_config, arg_matches, &option_names)?;
     theme::set__is_light_mode__syntax_theme__syntax_set(opt, assets);
 
     // HACK: make minus-line styles have syntax-highlighting iff side-by-side.
@@ -125,25 +122,61 @@ pub fn set_options(
 
     set_options!(
         [
+            bom_style,
             color_only,
             commit_decoration_style,
             commit_style,
+            context_lines,
+            default_encoding,
             file_added_label,
             file_copied_label,
             file_decoration_style,
+            file_header_added_style,
+            file_header_event_style,
+            file_header_format,
+            file_header_icon_style,
+            file_header_language_style,
+            file_header_mode_style,
+            file_header_notes_style,
+            file_header_old_path_style,
+            file_header_path_style,
+            file_header_removed_style,
+            file_icons,
             file_modified_label,
             file_removed_label,
+            file_rename_format,
             file_renamed_label,
             file_style,
+            full_file_syntax_highlighting,
+            hex_diff,
+            hex_diff_max_size,
+            highlight_cache,
+            highlight_cache_max_size,
             hunk_header_decoration_style,
+            hunk_header_enclosing_scope,
+            hunk_header_file_style,
+            hunk_header_format,
+            hunk_header_fragment_style,
+            hunk_header_line_number_style,
+            hunk_header_minus_len_style,
+            hunk_header_minus_start_style,
+            hunk_header_plus_len_style,
+            hunk_header_plus_start_style,
             hunk_header_style,
+            hunk_label,
             hyperlinks,
             hyperlinks_file_link_format,
+            image_protocol,
+            image_thumbnail_height,
+            image_thumbnails,
             inspect_raw_lines,
             keep_plus_minus_markers,
             line_buffer_size,
+            line_ending_note,
+            line_ending_style,
             max_line_distance,
             max_line_length,
+            max_token_distance,
             // Hack: minus-style must come before minus-*emph-style because the latter default
             // dynamically to the value of the former.
             minus_style,
@@ -152,6 +185,9 @@ pub fn set_options(
             minus_non_emph_style,
             minus_non_emph_style,
             navigate,
+            navigate_regex,
+            navigate_targets,
+            no_newline_style,
             line_numbers,
             line_numbers_left_format,
             line_numbers_left_style,
@@ -168,11 +204,16 @@ pub fn set_options(
             plus_empty_line_marker_style,
             plus_non_emph_style,
             raw,
+            relative_paths,
+            show_whitespace_changes,
             side_by_side,
             tab_width,
+            threads,
             tokenization_regex,
             true_color,
+            whitespace_change_style,
             whitespace_error_style,
+            whitespace_insensitive_emph,
             width,
             zero_style
         ],
@@ -184,11 +225,8 @@ pub fn set_options(
         true
     );
 
-    opt.computed.inspect_raw_lines =
-        cli::InspectRawLines::from_str(&opt.inspect_raw_lines).unwrap();
     opt.computed.line_numbers_mode =
         compute_line_numbers_mode(opt, &builtin_features, git_config, &option_names);
-    opt.computed.paging_mode = parse_paging_mode(&opt.paging_mode);
 
     // --color-only is used for interactive.diffFilter (git add -p). side-by-side, and
     // **-decoration-style cannot be used there (does not emit lines in 1-1 correspondence with raw git output).
@@ -199,6 +237,7 @@ pub fn set_options(
         opt.commit_decoration_style = "none".to_string();
         opt.hunk_header_decoration_style = "none".to_string();
     }
+    Ok(())
 }
 
 fn compute_line_numbers_mode(
@@ -232,15 +271,16 @@ fn set__light__dark__syntax_theme__options(
     git_config: &mut Option<git_config::GitConfig>,
     arg_matches: &clap::ArgMatches,
     option_names: &HashMap<&str, &str>,
-) {
+) -> Result<()> {
     let validate_light_and_dark = |opt: &cli::Opt| {
         if opt.light && opt.dark {
-            eprintln!("--light and --dark cannot be used together.");
-            process::exit(1);
+            Err("--light and --dark cannot be used together.")
+        } else {
+            Ok(())
         }
     };
     let empty_builtin_features = HashMap::new();
-    validate_light_and_dark(&opt);
+    validate_light_and_dark(&opt)?;
     if !(opt.light || opt.dark) {
         set_options!(
             [dark, light],
@@ -252,7 +292,7 @@ fn set__light__dark__syntax_theme__options(
             false
         );
     }
-    validate_light_and_dark(&opt);
+    validate_light_and_dark(&opt)?;
     set_options!(
         [syntax_theme],
         opt,
@@ -262,6 +302,7 @@ fn set__light__dark__syntax_theme__options(
         option_names,
         false
     );
+    Ok(())
 }
 
 /// Features are processed differently from all other options. The role of this function is to
@@ -503,32 +544,15 @@ fn split_feature_string(features: &str) -> impl Iterator<Item = &str> {
 
 impl FromStr for cli::InspectRawLines {
     type Err = Error;
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
+    fn from_str(s: &str) -> Result<Self> {
         match s.to_lowercase().as_str() {
             "true" => Ok(Self::True),
             "false" => Ok(Self::False),
-            _ => {
-                eprintln!(
-                    r#"Invalid value for inspect-raw-lines option: {}. Valid values are "true", and "false"."#,
-                    s
-                );
-                process::exit(1);
-            }
-        }
-    }
-}
-
-fn parse_paging_mode(paging_mode_string: &str) -> PagingMode {
-    match paging_mode_string.to_lowercase().as_str() {
-        "always" => PagingMode::Always,
-        "never" => PagingMode::Never,
-        "auto" => PagingMode::QuitIfOneScreen,
-        _ => {
-            eprintln!(
-                "Invalid value for --paging option: {} (valid values are \"always\", \"never\", and \"auto\")",
-                paging_mode_string
-            );
-            process::exit(1);
+            _ => Err(format!(
+                r#"Invalid value for inspect-raw-lines option: {}. Valid values are "true", and "false"."#,
+                s
+            )
+            .into()),
         }
     }
 }
@@ -538,7 +562,7 @@ fn set_widths(
     git_config: &mut Option<git_config::GitConfig>,
     arg_matches: &clap::ArgMatches,
     option_names: &HashMap<&str, &str>,
-) {
+) -> Result<()> {
     // Allow one character in case e.g. `less --status-column` is in effect. See #41 and #10.
     opt.computed.available_terminal_width = (Term::stdout().size().1 - 1) as usize;
 
@@ -559,10 +583,9 @@ fn set_widths(
     {
         Some("variable") => (cli::Width::Variable, false),
         Some(width) => {
-            let width = width.parse().unwrap_or_else(|_| {
-                eprintln!("Could not parse width as a positive integer: {:?}", width);
-                process::exit(1);
-            });
+            let width = width
+                .parse()
+                .map_err(|_| format!("Could not parse width as a positive integer: {:?}", width))?;
             (cli::Width::Fixed(width), true)
         }
         None => (
@@ -573,21 +596,23 @@ fn set_widths(
     opt.computed.decorations_width = decorations_width;
     opt.computed.background_color_extends_to_terminal_width =
         background_color_extends_to_terminal_width;
+    Ok(())
 }
 
-fn set_true_color(opt: &mut cli::Opt) {
+fn set_true_color(opt: &mut cli::Opt) -> Result<()> {
     opt.computed.true_color = match opt.true_color.as_ref() {
         "always" => true,
         "never" => false,
         "auto" => is_truecolor_terminal(),
         _ => {
-            eprintln!(
+            return Err(format!(
                 "Invalid value for --24-bit-color option: {} (valid values are \"always\", \"never\", and \"auto\")",
                 opt.true_color
-            );
-            process::exit(1);
+            )
+            .into());
         }
     };
+    Ok(())
 }
 
 fn is_truecolor_terminal() -> bool {
@@ -640,23 +665,36 @@ pub mod tests {
         let git_config_contents = b"
 [delta]
     24-bit-color = never
+    bom-style = black black
     color-only = false
     commit-decoration-style = black black
     commit-style = black black
     dark = false
+    default-encoding = latin1
     diff-highlight = true
     diff-so-fancy = true
     features = xxxyyyzzz
     file-added-label = xxxyyyzzz
     file-decoration-style = black black
+    file-header-format = xxxyyyzzz
+    file-header-path-style = black black
+    file-icons = xxxyyyzzz
     file-modified-label = xxxyyyzzz
     file-removed-label = xxxyyyzzz
+    file-rename-format = xxxyyyzzz
     file-renamed-label = xxxyyyzzz
     file-style = black black
+    hex-diff = true
+    hex-diff-max-size = 77
     hunk-header-decoration-style = black black
     hunk-header-style = black black
+    image-protocol = sixel
+    image-thumbnail-height = 77
+    image-thumbnails = true
     keep-plus-minus-markers = true
     light = true
+    line-ending-note = true
+    line-ending-style = black black
     line-numbers = true
     line-numbers-left-format = xxxyyyzzz
     line-numbers-left-style = black black
@@ -667,21 +705,27 @@ pub mod tests {
     line-numbers-zero-style = black black
     max-line-distance = 77
     max-line-length = 77
+    max-token-distance = 0.5
     minus-emph-style = black black
     minus-empty-line-marker-style = black black
     minus-non-emph-style = black black
     minus-style = black black
     navigate = true
+    no-newline-style = black black
     paging = never
     plus-emph-style = black black
     plus-empty-line-marker-style = black black
     plus-non-emph-style = black black
     plus-style = black black
     raw = true
+    relative-paths = true
+    show-whitespace-changes = true
     side-by-side = true
     syntax-theme = xxxyyyzzz
     tabs = 77
+    whitespace-change-style = black black
     whitespace-error-style = black black
+    whitespace-insensitive-emph = true
     width = 77
     word-diff-regex = xxxyyyzzz
     zero-style = black black
@@ -699,21 +743,34 @@ pub mod tests {
         assert_eq!(opt.color_only, false);
         assert_eq!(opt.commit_decoration_style, "black black");
         assert_eq!(opt.commit_style, "black black");
+        assert_eq!(opt.bom_style, "black black");
         assert_eq!(opt.dark, false);
+        assert_eq!(opt.default_encoding, "latin1");
         // TODO: should set_options not be called on any feature flags?
         // assert_eq!(opt.diff_highlight, true);
         // assert_eq!(opt.diff_so_fancy, true);
         assert!(opt.features.split_whitespace().any(|s| s == "xxxyyyzzz"));
         assert_eq!(opt.file_added_label, "xxxyyyzzz");
         assert_eq!(opt.file_decoration_style, "black black");
+        assert_eq!(opt.file_header_format, "xxxyyyzzz");
+        assert_eq!(opt.file_header_path_style, "black black");
+        assert_eq!(opt.file_icons, "xxxyyyzzz");
         assert_eq!(opt.file_modified_label, "xxxyyyzzz");
         assert_eq!(opt.file_removed_label, "xxxyyyzzz");
+        assert_eq!(opt.file_rename_format, "xxxyyyzzz");
         assert_eq!(opt.file_renamed_label, "xxxyyyzzz");
         assert_eq!(opt.file_style, "black black");
+        assert!(opt.hex_diff);
+        assert_eq!(opt.hex_diff_max_size, 77);
         assert_eq!(opt.hunk_header_decoration_style, "black black");
         assert_eq!(opt.hunk_header_style, "black black");
+        assert_eq!(opt.image_protocol, "sixel");
+        assert_eq!(opt.image_thumbnail_height, 77);
+        assert!(opt.image_thumbnails);
         assert_eq!(opt.keep_plus_minus_markers, true);
         assert_eq!(opt.light, true);
+        assert!(opt.line_ending_note);
+        assert_eq!(opt.line_ending_style, "black black");
         assert_eq!(opt.line_numbers, true);
         assert_eq!(opt.line_numbers_left_format, "xxxyyyzzz");
         assert_eq!(opt.line_numbers_left_style, "black black");
@@ -724,27 +781,31 @@ pub mod tests {
         assert_eq!(opt.line_numbers_zero_style, "black black");
         assert_eq!(opt.max_line_distance, 77 as f64);
         assert_eq!(opt.max_line_length, 77);
+        assert_eq!(opt.max_token_distance, 0.5);
         assert_eq!(opt.minus_emph_style, "black black");
         assert_eq!(opt.minus_empty_line_marker_style, "black black");
         assert_eq!(opt.minus_non_emph_style, "black black");
         assert_eq!(opt.minus_style, "black black");
         assert_eq!(opt.navigate, true);
+        assert_eq!(opt.no_newline_style, "black black");
         assert_eq!(opt.paging_mode, "never");
         assert_eq!(opt.plus_emph_style, "black black");
         assert_eq!(opt.plus_empty_line_marker_style, "black black");
         assert_eq!(opt.plus_non_emph_style, "black black");
         assert_eq!(opt.plus_style, "black black");
         assert_eq!(opt.raw, true);
+        assert!(opt.relative_paths);
+        assert!(opt.show_whitespace_changes);
         assert_eq!(opt.side_by_side, true);
         assert_eq!(opt.syntax_theme, Some("xxxyyyzzz".to_string()));
         assert_eq!(opt.tab_width, 77);
+        assert_eq!(opt.whitespace_change_style, "black black");
         assert_eq!(opt.whitespace_error_style, "black black");
+        assert!(opt.whitespace_insensitive_emph);
         assert_eq!(opt.width, Some("77".to_string()));
         assert_eq!(opt.tokenization_regex, "xxxyyyzzz");
         assert_eq!(opt.zero_style, "black black");
 
-        assert_eq!(opt.computed.paging_mode, PagingMode::Never);
-
         remove_file(git_config_path).unwrap();
     }
 
@@ -769,4 +830,23 @@ pub mod tests {
 
         remove_file(git_config_path).unwrap();
     }
+
+    #[test]
+    fn test_paging_in_git_config_is_honored() {
+        let git_config_contents = b"
+[delta]
+    paging = never
+";
+        let git_config_path = "delta__test_paging_in_git_config_is_honored.gitconfig";
+
+        let config = integration_test_utils::make_config_from_args_and_git_config(
+            &[],
+            Some(git_config_contents),
+            Some(git_config_path),
+        );
+
+        assert_eq!(config.paging_mode, PagingMode::Never);
+
+        remove_file(git_config_path).unwrap();
+    }
 }
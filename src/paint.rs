@@ -1,26 +1,30 @@
+use std::borrow::Cow;
 use std::io::Write;
 
 use itertools::Itertools;
-use syntect::easy::HighlightLines;
 use syntect::highlighting::Style as SyntectStyle;
-use syntect::parsing::{SyntaxReference, SyntaxSet};
-use unicode_segmentation::UnicodeSegmentation;
 
 use crate::ansi;
-use crate::config::{self, delta_unreachable};
-use crate::delta::State;
-use crate::edits;
+use crate::config;
+use crate::delta::{self, State};
+use crate::draw;
+use crate::features;
 use crate::features::line_numbers;
 use crate::features::side_by_side;
+use crate::format;
+use crate::hex_diff;
+use crate::image;
+use crate::line_endings;
+use crate::model::{
+    BinaryFileDiff, ChangedLines, CommitHeader, EditOperation, Event, FileHeader, HunkHeader,
+    ImageFileDiff, Line, LineFormat, Renderer, SubmoduleCommit, SubmoduleHeader,
+};
 use crate::paint::superimpose_style_sections::superimpose_style_sections;
-use crate::style::Style;
+use crate::parse;
+use crate::style::{DecorationStyle, Style};
 
 pub struct Painter<'a> {
-    pub minus_lines: Vec<(String, State)>,
-    pub plus_lines: Vec<(String, State)>,
     pub writer: &'a mut dyn Write,
-    pub syntax: &'a SyntaxReference,
-    pub highlighter: HighlightLines<'a>,
     pub config: &'a config::Config,
     pub output_buffer: String,
     pub line_numbers_data: line_numbers::LineNumbersData<'a>,
@@ -28,10 +32,6 @@ pub struct Painter<'a> {
 
 impl<'a> Painter<'a> {
     pub fn new(writer: &'a mut dyn Write, config: &'a config::Config) -> Self {
-        let default_syntax = Self::get_syntax(&config.syntax_set, None);
-        // TODO: Avoid doing this.
-        let dummy_highlighter = HighlightLines::new(default_syntax, &config.syntax_dummy_theme);
-
         let line_numbers_data = if config.line_numbers {
             line_numbers::LineNumbersData::from_format_strings(
                 &config.line_numbers_left_format,
@@ -41,125 +41,321 @@ impl<'a> Painter<'a> {
             line_numbers::LineNumbersData::default()
         };
         Self {
-            minus_lines: Vec::new(),
-            plus_lines: Vec::new(),
             output_buffer: String::new(),
-            syntax: default_syntax,
-            highlighter: dummy_highlighter,
             writer,
             config,
             line_numbers_data,
         }
     }
 
-    pub fn set_syntax(&mut self, extension: Option<&str>) {
-        self.syntax = Painter::get_syntax(&self.config.syntax_set, extension);
+    pub fn paint_commit_header(&mut self, commit_header: &CommitHeader) -> std::io::Result<()> {
+        let config = self.config;
+        let (line, raw_line) = (&commit_header.line, &commit_header.raw_line);
+        if !delta::should_handle(&State::CommitMeta, config) {
+            writeln!(self.writer, "{}", format::format_raw_line(raw_line, config))?;
+            return Ok(());
+        }
+        if config.commit_style.is_omitted {
+            return Ok(());
+        }
+        let (draw_fn, pad, decoration_ansi_term_style) =
+            draw::get_draw_function(config.commit_style.decoration_style);
+        let (formatted_line, formatted_raw_line) = if config.hyperlinks {
+            (
+                features::hyperlinks::format_commit_line_with_osc8_commit_hyperlink(line, config),
+                features::hyperlinks::format_commit_line_with_osc8_commit_hyperlink(
+                    raw_line, config,
+                ),
+            )
+        } else {
+            (Cow::from(line), Cow::from(raw_line))
+        };
+
+        draw_fn(
+            self.writer,
+            &format!("{}{}", formatted_line, if pad { " " } else { "" }),
+            &format!("{}{}", formatted_raw_line, if pad { " " } else { "" }),
+            &config.decorations_width,
+            config.commit_style,
+            decoration_ansi_term_style,
+        )?;
+        Ok(())
     }
 
-    fn get_syntax(syntax_set: &'a SyntaxSet, extension: Option<&str>) -> &'a SyntaxReference {
-        if let Some(extension) = extension {
-            if let Some(syntax) = syntax_set.find_syntax_by_extension(extension) {
-                return syntax;
+    /// Write the file header with FileMeta styling, constructing the file change line from the
+    /// minus and plus file if necessary.
+    pub fn paint_file_header(&mut self, file_header: &FileHeader) -> std::io::Result<()> {
+        match file_header {
+            FileHeader::Paths { .. } if !self.config.file_header_format.is_empty() => {
+                let line = format::format_file_header(
+                    &self.config.file_header_format,
+                    file_header,
+                    self.config,
+                );
+                self.paint_generic_file_header(&line, &line)
+            }
+            FileHeader::Paths {
+                minus_file,
+                plus_file,
+                file_event,
+                comparing,
+                note,
+                file_info,
+                ..
+            } => {
+                let mut line = parse::get_file_change_description_from_file_paths(
+                    minus_file,
+                    plus_file,
+                    *comparing,
+                    file_event,
+                    self.config,
+                );
+                let mut parts = file_info.describe();
+                parts.extend(note.clone());
+                if !parts.is_empty() {
+                    line = format!("{} ({})", line, parts.join(", "));
+                }
+                // FIXME: no support for 'raw'
+                self.paint_generic_file_header(&line, &line)
             }
+            FileHeader::Line { line, raw_line } => self.paint_generic_file_header(line, raw_line),
         }
-        syntax_set
-            .find_syntax_by_extension("txt")
-            .unwrap_or_else(|| delta_unreachable("Failed to find any language syntax definitions."))
     }
 
-    pub fn set_highlighter(&mut self) {
-        if let Some(ref syntax_theme) = self.config.syntax_theme {
-            self.highlighter = HighlightLines::new(self.syntax, &syntax_theme)
-        };
+    /// Write the header of a submodule with FileMeta styling, e.g. "submodule x/y:
+    /// 1234567..89abcde (rewind)".
+    pub fn paint_submodule_header(
+        &mut self,
+        submodule_header: &SubmoduleHeader,
+    ) -> std::io::Result<()> {
+        let mut line = format!(
+            "submodule {}: {}..{}",
+            format::format_path(&submodule_header.path, self.config),
+            submodule_header.minus_commit,
+            submodule_header.plus_commit
+        );
+        if let Some(note) = &submodule_header.note {
+            line = format!("{} ({})", line, note);
+        }
+        self.paint_generic_file_header(&line, &line)
     }
 
-    /// Replace initial -/+ character with ' ', expand tabs as spaces, and optionally terminate with
-    /// newline.
-    // Terminating with newline character is necessary for many of the sublime syntax definitions to
-    // highlight correctly.
-    // See https://docs.rs/syntect/3.2.0/syntect/parsing/struct.SyntaxSetBuilder.html#method.add_from_folder
-    pub fn prepare(&self, line: &str, append_newline: bool) -> String {
-        let terminator = if append_newline { "\n" } else { "" };
-        if !line.is_empty() {
-            let mut line = line.graphemes(true);
-
-            // The first column contains a -/+/space character, added by git. We substitute it for
-            // a space now, so that it is not present during syntax highlighting. When emitting the
-            // line in Painter::paint_line, we drop the space (unless --keep-plus-minus-markers is
-            // in effect in which case we replace it with the appropriate marker).
-            // TODO: Things should, but do not, work if this leading space is omitted at this stage.
-            // See comment in align::Alignment::new.
-            line.next();
-            format!(" {}{}", self.expand_tabs(line), terminator)
+    /// Write a commit listed under a submodule header: its '>' or '<' marker in plus-style or
+    /// minus-style, its abbreviated id if known (hyperlinked under --hyperlinks), and its summary.
+    pub fn paint_submodule_commit(
+        &mut self,
+        submodule_commit: &SubmoduleCommit,
+    ) -> std::io::Result<()> {
+        let config = self.config;
+        let marker = if submodule_commit.is_plus {
+            config.plus_style.paint(">")
         } else {
-            terminator.to_string()
-        }
+            config.minus_style.paint("<")
+        };
+        let commit_id = match (&submodule_commit.commit_id, &submodule_commit.url) {
+            (Some(commit_id), Some(url)) => format!(
+                "{} ",
+                features::hyperlinks::format_osc8_hyperlink(url, &commit_id[..7])
+            ),
+            (Some(commit_id), None) => format!("{} ", &commit_id[..7]),
+            (None, _) => "".to_string(),
+        };
+        writeln!(
+            self.writer,
+            "  {} {}{}",
+            marker, commit_id, submodule_commit.summary
+        )
     }
 
-    /// Remove the initial +/- character of a line that will be emitted unchanged, including any
-    /// ANSI escape sequences.
-    pub fn prepare_raw_line(&self, line: &str) -> String {
-        ansi::ansi_preserving_slice(
-            &self.expand_tabs(line.graphemes(true)),
-            if self.config.keep_plus_minus_markers {
-                0
-            } else {
-                1
-            },
-        )
+    /// Write `line` with FileMeta styling.
+    fn paint_generic_file_header(&mut self, line: &str, raw_line: &str) -> std::io::Result<()> {
+        let config = self.config;
+        // If file_style is "omit", we'll skip the process and print nothing.
+        // However in the case of color_only mode,
+        // we won't skip because we can't change raw_line structure.
+        if config.file_style.is_omitted && !config.color_only {
+            return Ok(());
+        }
+        let (draw_fn, pad, decoration_ansi_term_style) =
+            draw::get_draw_function(config.file_style.decoration_style);
+        // Prints the new line below file-meta-line.
+        // However in the case of color_only mode,
+        // we won't print it because we can't change raw_line structure.
+        if !config.color_only {
+            writeln!(self.writer)?;
+        }
+        draw_fn(
+            self.writer,
+            &format!("{}{}", line, if pad { " " } else { "" }),
+            &format!("{}{}", raw_line, if pad { " " } else { "" }),
+            &config.decorations_width,
+            config.file_style,
+            decoration_ansi_term_style,
+        )?;
+        Ok(())
     }
 
-    /// Expand tabs as spaces.
-    /// tab_width = 0 is documented to mean do not replace tabs.
-    pub fn expand_tabs<'b, I>(&self, line: I) -> String
-    where
-        I: Iterator<Item = &'b str>,
-    {
-        if self.config.tab_width > 0 {
-            let tab_replacement = " ".repeat(self.config.tab_width);
-            line.map(|s| if s == "\t" { &tab_replacement } else { s })
-                .collect::<String>()
+    pub fn paint_hunk_header(&mut self, hunk_header: HunkHeader) -> std::io::Result<()> {
+        let config = self.config;
+        let HunkHeader {
+            line,
+            raw_line,
+            line_numbers,
+            plus_file,
+            code_fragment,
+        } = hunk_header;
+        let (draw_fn, pad, decoration_ansi_term_style) =
+            draw::get_draw_function(config.hunk_header_style.decoration_style);
+        // Emit the hunk header, with any requested decoration
+        if config.hunk_header_style.is_raw {
+            if config.hunk_header_style.decoration_style != DecorationStyle::NoDecoration {
+                writeln!(self.writer)?;
+            }
+            draw_fn(
+                self.writer,
+                &format!("{}{}", line, if pad { " " } else { "" }),
+                &format!("{}{}", raw_line, if pad { " " } else { "" }),
+                &config.decorations_width,
+                config.hunk_header_style,
+                decoration_ansi_term_style,
+            )?;
+        } else if config.hunk_header_style.is_omitted {
+            writeln!(self.writer)?;
         } else {
-            line.collect::<String>()
+            // Prints the new line below hunk-header-line.
+            // However in the case of color_only mode,
+            // we won't print it because we can't change raw_line structure.
+            if !config.color_only {
+                writeln!(self.writer)?;
+            }
+            let mut painted_code_fragment = String::new();
+            if !code_fragment.text.is_empty() {
+                Painter::paint_lines(
+                    vec![borrow_sections(&code_fragment.syntax_sections)],
+                    vec![vec![(
+                        config.hunk_header_fragment_style,
+                        &code_fragment.text,
+                    )]], // TODO: compute style from state
+                    [State::HunkHeader].iter(),
+                    &mut painted_code_fragment,
+                    config,
+                    &mut None,
+                    None,
+                    None,
+                    Some(false),
+                );
+                painted_code_fragment.pop(); // trim newline
+            }
+            let hunk_header = if config.color_only {
+                painted_code_fragment
+            } else {
+                format::format_hunk_header(
+                    &config.hunk_header_format,
+                    &painted_code_fragment,
+                    &plus_file,
+                    &line_numbers,
+                    config,
+                )
+            };
+            if !hunk_header.is_empty() {
+                draw_fn(
+                    self.writer,
+                    &hunk_header,
+                    &hunk_header,
+                    &config.decorations_width,
+                    config.hunk_header_style,
+                    decoration_ansi_term_style,
+                )?;
+            }
+        };
+
+        // Emit a full line-numbering
+        if config.line_numbers {
+            self.line_numbers_data
+                .initialize_hunk(line_numbers, plus_file);
+        // Emit a single line number.
+        // However with raw mode or color-only mode,
+        // we should prevent the output from creating new line for printing line number.
+        // Nor is it needed if the hunk header already contains the line number.
+        } else if config.line_numbers_show_first_line_number
+            && !config.hunk_header_style.is_raw
+            && !config.color_only
+            && !format::hunk_header_format_contains_line_number(&config.hunk_header_format)
+        {
+            let plus_line_number = line_numbers[line_numbers.len() - 1].0;
+            let formatted_plus_line_number = if config.hyperlinks {
+                features::hyperlinks::format_osc8_file_hyperlink(
+                    &plus_file,
+                    Some(plus_line_number),
+                    &format!("{}", plus_line_number),
+                    config,
+                )
+            } else {
+                Cow::from(format!("{}", plus_line_number))
+            };
+            match config.hunk_header_style.decoration_ansi_term_style() {
+                Some(style) => {
+                    writeln!(self.writer, "{}", style.paint(formatted_plus_line_number))?
+                }
+                None => writeln!(self.writer, "{}", formatted_plus_line_number)?,
+            }
         }
+        Ok(())
     }
 
-    pub fn paint_buffered_minus_and_plus_lines(&mut self) {
-        let minus_line_syntax_style_sections = Self::get_syntax_style_sections_for_lines(
-            &self.minus_lines,
-            &State::HunkMinus(None),
-            &mut self.highlighter,
-            self.config,
-        );
-        let plus_line_syntax_style_sections = Self::get_syntax_style_sections_for_lines(
-            &self.plus_lines,
-            &State::HunkPlus(None),
-            &mut self.highlighter,
-            self.config,
-        );
-        let (minus_line_diff_style_sections, plus_line_diff_style_sections, line_alignment) =
-            Self::get_diff_style_sections(&self.minus_lines, &self.plus_lines, self.config);
+    pub fn paint_changed_lines(&mut self, changed_lines: &ChangedLines) {
+        let (minus_lines, plus_lines) = (&changed_lines.minus_lines, &changed_lines.plus_lines);
+        let mut minus_line_syntax_style_sections = get_syntax_style_sections(minus_lines);
+        let mut plus_line_syntax_style_sections = get_syntax_style_sections(plus_lines);
+        let (mut minus_line_diff_style_sections, mut plus_line_diff_style_sections) =
+            Self::get_diff_style_sections(minus_lines, plus_lines, self.config);
+
+        // The format of a line is marked where it differs from that of the line paired with it.
+        let mut minus_paired_formats = vec![None; minus_lines.len()];
+        let mut plus_paired_formats = vec![None; plus_lines.len()];
+        for (minus_index, plus_index) in &changed_lines.alignment {
+            if let (Some(minus_index), Some(plus_index)) = (*minus_index, *plus_index) {
+                minus_paired_formats[minus_index] = Some(plus_lines[plus_index].format);
+                plus_paired_formats[plus_index] = Some(minus_lines[minus_index].format);
+            }
+        }
+        for (i, line) in minus_lines.iter().enumerate() {
+            insert_format_markers(
+                &mut minus_line_syntax_style_sections[i],
+                &mut minus_line_diff_style_sections[i],
+                line_endings::get_format_changes(line.format, minus_paired_formats[i]),
+                self.config,
+            );
+        }
+        for (i, line) in plus_lines.iter().enumerate() {
+            insert_format_markers(
+                &mut plus_line_syntax_style_sections[i],
+                &mut plus_line_diff_style_sections[i],
+                line_endings::get_format_changes(line.format, plus_paired_formats[i]),
+                self.config,
+            );
+        }
 
         if self.config.side_by_side {
             side_by_side::paint_minus_and_plus_lines_side_by_side(
                 minus_line_syntax_style_sections,
                 minus_line_diff_style_sections,
-                self.minus_lines.iter().map(|(_, state)| state).collect(),
+                minus_lines.iter().map(|line| &line.state).collect(),
                 plus_line_syntax_style_sections,
                 plus_line_diff_style_sections,
-                self.plus_lines.iter().map(|(_, state)| state).collect(),
-                line_alignment,
+                plus_lines.iter().map(|line| &line.state).collect(),
+                changed_lines.alignment.clone(),
                 &mut self.output_buffer,
                 self.config,
                 &mut Some(&mut self.line_numbers_data),
                 None,
             );
         } else {
-            if !self.minus_lines.is_empty() {
+            if !minus_lines.is_empty() {
                 Painter::paint_lines(
                     minus_line_syntax_style_sections,
                     minus_line_diff_style_sections,
-                    self.minus_lines.iter().map(|(_, state)| state),
+                    minus_lines.iter().map(|line| &line.state),
                     &mut self.output_buffer,
                     self.config,
                     &mut Some(&mut self.line_numbers_data),
@@ -172,11 +368,11 @@ impl<'a> Painter<'a> {
                     None,
                 );
             }
-            if !self.plus_lines.is_empty() {
+            if !plus_lines.is_empty() {
                 Painter::paint_lines(
                     plus_line_syntax_style_sections,
                     plus_line_diff_style_sections,
-                    self.plus_lines.iter().map(|(_, state)| state),
+                    plus_lines.iter().map(|line| &line.state),
                     &mut self.output_buffer,
                     self.config,
                     &mut Some(&mut self.line_numbers_data),
@@ -190,25 +386,79 @@ impl<'a> Painter<'a> {
                 );
             }
         }
-        self.minus_lines.clear();
-        self.plus_lines.clear();
     }
 
-    pub fn paint_zero_line(&mut self, line: &str) {
-        let state = State::HunkZero;
-        let painted_prefix = if self.config.keep_plus_minus_markers && !line.is_empty() {
-            Some(self.config.zero_style.paint(&line[..1]))
+    /// Write the sizes of the two versions of a binary file, followed by the regions in which they
+    /// differ as a hex dump with the versions side by side.
+    pub fn paint_binary_file_diff(
+        &mut self,
+        binary_file_diff: &BinaryFileDiff,
+    ) -> std::io::Result<()> {
+        let config = self.config;
+        let summary = format!(
+            "binary: {} → {} bytes",
+            binary_file_diff.minus_size, binary_file_diff.plus_size
+        );
+        let regions = match &binary_file_diff.regions {
+            Some(regions) => regions,
+            None => return writeln!(self.writer, "{} (too large for a hex diff)", summary),
+        };
+        writeln!(self.writer, "{}", summary)?;
+        for (i, region) in regions.iter().enumerate() {
+            if i > 0 {
+                writeln!(self.writer, "⋮")?;
+            }
+            for row in region {
+                writeln!(
+                    self.writer,
+                    "{:08x}  {} │ {}",
+                    row.offset,
+                    paint_hex_row_bytes(&row.minus_bytes, &row.plus_bytes, config.minus_emph_style),
+                    paint_hex_row_bytes(&row.plus_bytes, &row.minus_bytes, config.plus_emph_style),
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the dimensions and sizes of the two versions of an image, followed by thumbnails of
+    /// them if the terminal supports graphics.
+    pub fn paint_image_file_diff(
+        &mut self,
+        image_file_diff: &ImageFileDiff,
+    ) -> std::io::Result<()> {
+        let config = self.config;
+        let (minus, plus) = (
+            image_file_diff.minus_image.as_deref(),
+            image_file_diff.plus_image.as_deref(),
+        );
+        writeln!(self.writer, "{}", image::format_image_summary(minus, plus))?;
+        if let Some(thumbnails) = image::format_thumbnails(
+            minus,
+            plus,
+            &config.image_protocol,
+            config.image_thumbnail_height,
+            config.available_terminal_width,
+        ) {
+            write!(self.writer, "{}", thumbnails)?;
+        }
+        Ok(())
+    }
+
+    pub fn paint_zero_line(&mut self, line: &Line) {
+        let painted_prefix = if self.config.keep_plus_minus_markers && !line.text.is_empty() {
+            Some(self.config.zero_style.paint(&line.text[..1]))
         } else {
             None
         };
-        let lines = vec![(self.prepare(line, true), state.clone())];
-        let syntax_style_sections = Painter::get_syntax_style_sections_for_lines(
-            &lines,
-            &state,
-            &mut self.highlighter,
-            &self.config,
+        let mut syntax_style_sections = vec![borrow_sections(&line.syntax_sections)];
+        let mut diff_style_sections = vec![(self.config.zero_style, line.text.as_str())]; // TODO: compute style from state
+        insert_format_markers(
+            &mut syntax_style_sections[0],
+            &mut diff_style_sections,
+            line_endings::get_format_changes(line.format, None),
+            self.config,
         );
-        let diff_style_sections = vec![(self.config.zero_style, lines[0].0.as_str())]; // TODO: compute style from state
 
         if self.config.side_by_side {
             side_by_side::paint_zero_lines_side_by_side(
@@ -225,7 +475,7 @@ impl<'a> Painter<'a> {
             Painter::paint_lines(
                 syntax_style_sections,
                 vec![diff_style_sections],
-                [state].iter(),
+                [State::HunkZero].iter(),
                 &mut self.output_buffer,
                 self.config,
                 &mut Some(&mut self.line_numbers_data),
@@ -439,80 +689,46 @@ impl<'a> Painter<'a> {
         Ok(())
     }
 
-    pub fn should_compute_syntax_highlighting(state: &State, config: &config::Config) -> bool {
-        if config.syntax_theme.is_none() {
-            return false;
-        }
-        match state {
-            State::HunkMinus(None) => {
-                config.minus_style.is_syntax_highlighted
-                    || config.minus_emph_style.is_syntax_highlighted
-            }
-            State::HunkZero => config.zero_style.is_syntax_highlighted,
-            State::HunkPlus(None) => {
-                config.plus_style.is_syntax_highlighted
-                    || config.plus_emph_style.is_syntax_highlighted
-            }
-            State::HunkHeader => true,
-            State::HunkMinus(Some(_)) | State::HunkPlus(Some(_)) => false,
-            _ => panic!(
-                "should_compute_syntax_highlighting is undefined for state {:?}",
-                state
-            ),
-        }
-    }
-
-    pub fn get_syntax_style_sections_for_lines<'s>(
-        lines: &'s [(String, State)],
-        state: &State,
-        highlighter: &mut HighlightLines,
-        config: &config::Config,
-    ) -> Vec<Vec<(SyntectStyle, &'s str)>> {
-        let fake = !Painter::should_compute_syntax_highlighting(state, config);
-        let mut line_sections = Vec::new();
-        for (line, _) in lines.iter() {
-            if fake {
-                line_sections.push(vec![(config.null_syntect_style, line.as_str())])
-            } else {
-                // The first character is a space injected by delta. See comment in
-                // Painter:::prepare.
-                let mut this_line_sections = highlighter.highlight(&line[1..], &config.syntax_set);
-                this_line_sections.insert(0, (config.null_syntect_style, &line[..1]));
-                line_sections.push(this_line_sections);
-            }
-        }
-        line_sections
-    }
-
-    /// Set background styles to represent diff for minus and plus lines in buffer.
+    /// Set background styles to represent diff for minus and plus lines in buffer, according to
+    /// the edit operations with which they are annotated.
     #[allow(clippy::type_complexity)]
     fn get_diff_style_sections<'b>(
-        minus_lines: &'b [(String, State)],
-        plus_lines: &'b [(String, State)],
+        minus_lines: &'b [Line],
+        plus_lines: &'b [Line],
         config: &config::Config,
-    ) -> (
-        Vec<Vec<(Style, &'b str)>>,
-        Vec<Vec<(Style, &'b str)>>,
-        Vec<(Option<usize>, Option<usize>)>,
-    ) {
-        let (minus_lines, minus_styles): (Vec<&str>, Vec<Style>) = minus_lines
-            .iter()
-            .map(|(s, t)| (s.as_str(), *config.get_style(&t)))
-            .unzip();
-        let (plus_lines, plus_styles): (Vec<&str>, Vec<Style>) = plus_lines
-            .iter()
-            .map(|(s, t)| (s.as_str(), *config.get_style(&t)))
-            .unzip();
-        let mut diff_sections = edits::infer_edits(
-            minus_lines,
-            plus_lines,
-            minus_styles,
-            config.minus_emph_style, // FIXME
-            plus_styles,
-            config.plus_emph_style, // FIXME
-            &config.tokenization_regex,
-            config.max_line_distance,
-            config.max_line_distance_for_naively_paired_lines,
+    ) -> (Vec<Vec<(Style, &'b str)>>, Vec<Vec<(Style, &'b str)>>) {
+        let get_style_sections = |lines: &'b [Line], emph_style: Style| {
+            lines
+                .iter()
+                .map(|line| {
+                    let style = *config.get_style(&line.state);
+                    match config.whitespace_change_style {
+                        Some(whitespace_change_style) if line.is_whitespace_change => {
+                            return line
+                                .edit_sections
+                                .iter()
+                                .map(|(_, s)| (whitespace_change_style, s.as_str()))
+                                .collect();
+                        }
+                        _ => {}
+                    }
+                    line.edit_sections
+                        .iter()
+                        .map(|(operation, s)| match operation {
+                            EditOperation::Deletion | EditOperation::Insertion => {
+                                (emph_style, s.as_str())
+                            }
+                            EditOperation::MinusNoop | EditOperation::PlusNoop => {
+                                (style, s.as_str())
+                            }
+                        })
+                        .collect()
+                })
+                .collect()
+        };
+        let mut diff_sections = (
+            get_style_sections(minus_lines, config.minus_emph_style),
+            get_style_sections(plus_lines, config.plus_emph_style),
         );
 
         let minus_non_emph_style = if config.minus_non_emph_style != config.minus_emph_style {
@@ -572,6 +788,144 @@ impl<'a> Painter<'a> {
     }
 }
 
+impl<'a> Renderer for Painter<'a> {
+    fn render(&mut self, event: Event) -> std::io::Result<()> {
+        match event {
+            Event::CommitHeader(commit_header) => self.paint_commit_header(&commit_header)?,
+            Event::FileHeader(file_header) => self.paint_file_header(&file_header)?,
+            Event::HunkHeader(hunk_header) => self.paint_hunk_header(hunk_header)?,
+            Event::ChangedLines(changed_lines) => self.paint_changed_lines(&changed_lines),
+            Event::UnchangedLine(line) => self.paint_zero_line(&line),
+            Event::BinaryFileDiff(binary_file_diff) => {
+                self.paint_binary_file_diff(&binary_file_diff)?
+            }
+            Event::ImageFileDiff(image_file_diff) => {
+                self.paint_image_file_diff(&image_file_diff)?
+            }
+            Event::SubmoduleHeader(submodule_header) => {
+                self.paint_submodule_header(&submodule_header)?
+            }
+            Event::SubmoduleCommit(submodule_commit) => {
+                self.paint_submodule_commit(&submodule_commit)?
+            }
+            Event::HunkMetaLine(line) => {
+                self.output_buffer.push_str(&line);
+                self.output_buffer.push('\n');
+            }
+            Event::RawLine(raw_line) => writeln!(
+                self.writer,
+                "{}",
+                format::format_raw_line(&raw_line, self.config)
+            )?,
+        }
+        self.emit()
+    }
+}
+
+/// Return the hex and ASCII columns of one version of a row of a hex dump, with the bytes that
+/// differ from the other version, `other_bytes`, styled with `emph_style`.
+fn paint_hex_row_bytes(bytes: &[u8], other_bytes: &[u8], emph_style: Style) -> String {
+    let paint_byte = |i: usize, text: String| match bytes.get(i) {
+        Some(byte) if other_bytes.get(i) != Some(byte) => emph_style.paint(text).to_string(),
+        Some(_) => text,
+        None => " ".repeat(text.len()),
+    };
+    let hex = (0..hex_diff::BYTES_PER_ROW)
+        .map(|i| paint_byte(i, format!("{:02x}", bytes.get(i).unwrap_or(&0))))
+        .join(" ");
+    let ascii: String = (0..hex_diff::BYTES_PER_ROW)
+        .map(|i| match bytes.get(i) {
+            Some(byte) if byte.is_ascii_graphic() || *byte == b' ' => {
+                paint_byte(i, (*byte as char).to_string())
+            }
+            _ => paint_byte(i, ".".to_string()),
+        })
+        .collect();
+    format!("{}  {}", hex, ascii)
+}
+
+fn get_syntax_style_sections(lines: &[Line]) -> Vec<Vec<(SyntectStyle, &str)>> {
+    lines
+        .iter()
+        .map(|line| borrow_sections(&line.syntax_sections))
+        .collect()
+}
+
+fn borrow_sections<T: Copy>(sections: &[(T, String)]) -> Vec<(T, &str)> {
+    sections.iter().map(|(t, s)| (*t, s.as_str())).collect()
+}
+
+/// Insert markers for the given changes to the format of a line (see
+/// line_endings::get_format_changes) into its style sections: a byte order mark is marked at the
+/// start of the line, and a CRLF line ending and a missing newline at its end.
+fn insert_format_markers<'b>(
+    syntax_sections: &mut Vec<(SyntectStyle, &'b str)>,
+    diff_sections: &mut Vec<(Style, &'b str)>,
+    format_changes: LineFormat,
+    config: &config::Config,
+) {
+    let mut start_markers = Vec::new();
+    let mut end_markers = Vec::new();
+    if format_changes.has_bom {
+        start_markers.push((config.bom_style, line_endings::BOM_MARKER));
+    }
+    if format_changes.has_crlf {
+        end_markers.push((config.line_ending_style, line_endings::CRLF_MARKER));
+    }
+    if format_changes.has_no_newline {
+        if let Some(&(style, _)) = diff_sections.last() {
+            end_markers.push((style, " "));
+        }
+        end_markers.push((config.no_newline_style, line_endings::NO_NEWLINE_MARKER));
+    }
+    let unstyled = |markers: &[(Style, &'b str)]| {
+        markers
+            .iter()
+            .map(|(_, s)| (config.null_syntect_style, *s))
+            .collect::<Vec<_>>()
+    };
+    insert_markers(
+        syntax_sections,
+        &unstyled(&start_markers),
+        &unstyled(&end_markers),
+    );
+    insert_markers(diff_sections, &start_markers, &end_markers);
+}
+
+/// Insert `start_markers` after the initial space of the line represented by `sections` (which
+/// replaces its -/+/space character), and `end_markers` before its terminating newline.
+fn insert_markers<'b, T: Copy>(
+    sections: &mut Vec<(T, &'b str)>,
+    start_markers: &[(T, &'b str)],
+    end_markers: &[(T, &'b str)],
+) {
+    if !end_markers.is_empty() {
+        match sections.pop() {
+            Some((t, s)) if s.ends_with('\n') => {
+                if s.len() > 1 {
+                    sections.push((t, &s[..s.len() - 1]));
+                }
+                sections.extend_from_slice(end_markers);
+                sections.push((t, "\n"));
+            }
+            section => {
+                sections.extend(section);
+                sections.extend_from_slice(end_markers);
+            }
+        }
+    }
+    if !start_markers.is_empty() && !sections.is_empty() {
+        let (t, s) = sections.remove(0);
+        let i = s.char_indices().nth(1).map_or(s.len(), |(i, _)| i);
+        let mut initial_sections = vec![(t, &s[..i])];
+        initial_sections.extend_from_slice(start_markers);
+        if i < s.len() {
+            initial_sections.push((t, &s[i..]));
+        }
+        sections.splice(0..0, initial_sections);
+    }
+}
+
 // edits::annotate doesn't return "coalesced" annotations (see comment there), so we can't assume
 // that `sections.len() > 1 <=> (multiple styles)`.
 fn style_sections_contain_more_than_one_style(sections: &[(Style, &str)]) -> bool {
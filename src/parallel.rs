@@ -0,0 +1,242 @@
+use std::io::{self, BufRead};
+use std::sync::mpsc::{self, Receiver, Sender, SyncSender};
+use std::sync::Mutex;
+use std::thread;
+
+use crate::ansi;
+use crate::config::Config;
+use crate::delta::{self, Source};
+use crate::model::{Event, Renderer};
+
+/// The events of one file of the diff, or an error if the file could not be processed.
+type FileEvents = io::Result<Vec<Event>>;
+
+/// A file of the diff to be processed by a worker thread: its lines, the source detected in the
+/// input preceding it, and the channel on which its events are to be sent.
+type Job = (Vec<u8>, Source, Sender<FileEvents>);
+
+/// Return the number of threads with which to process the diff.
+pub fn get_number_of_threads(config: &Config) -> usize {
+    match config.threads {
+        0 => thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1),
+        n => n,
+    }
+}
+
+/// Parse the diff using `n_threads` worker threads, passing it to `renderer` on the current
+/// thread in the same stream of events as delta::parse_diff_sequentially.
+///
+/// Syntax highlighting dominates the time taken to process a large diff, and the highlighting of
+/// each file is independent of the others. So a reader thread splits the input into files (i.e.
+/// at "commit" and "diff" lines), and the files are parsed and highlighted concurrently by the
+/// worker threads. The events of each file are rendered as soon as they and those of all
+/// preceding files are available. At most 2 * n_threads files are read ahead of the output, so
+/// output is streamed as the input arrives and memory use is bounded by the size of the files.
+pub fn parse_diff<I>(
+    lines: io::Split<I>,
+    config: &Config,
+    renderer: &mut dyn Renderer,
+    n_threads: usize,
+) -> io::Result<()>
+where
+    I: BufRead + Send,
+{
+    let (job_sender, job_receiver) = mpsc::channel();
+    let job_receiver = Mutex::new(job_receiver);
+    // The receivers of the events of each file, in the order of the files in the input.
+    let (file_events_sender, file_events_receiver) = mpsc::sync_channel(2 * n_threads);
+    thread::scope(|scope| {
+        scope.spawn(move || read_files(lines, config, job_sender, file_events_sender));
+        for _ in 0..n_threads {
+            scope.spawn(|| process_files(&job_receiver, config));
+        }
+        // If rendering fails, returning drops file_events_receiver, which stops the reader
+        // thread, and hence the worker threads.
+        for receiver in file_events_receiver {
+            // The worker cannot have gone away without sending the events, since a panic in a
+            // worker thread is propagated by the scope.
+            if let Ok(file_events) = receiver.recv() {
+                for event in file_events? {
+                    renderer.render(event)?;
+                }
+            }
+        }
+        Ok(())
+    })
+}
+
+/// Read the input, sending each file to the worker threads as a job, and the receiver of its
+/// events to the rendering thread.
+fn read_files<I>(
+    mut lines: io::Split<I>,
+    config: &Config,
+    job_sender: Sender<Job>,
+    file_events_sender: SyncSender<Receiver<FileEvents>>,
+) where
+    I: BufRead,
+{
+    let mut source = Source::Unknown;
+    let mut file = Vec::new();
+    let mut file_source = Source::Unknown;
+    let send_file = |file: Vec<u8>, file_source: Source| {
+        let (sender, receiver) = mpsc::channel();
+        file_events_sender.send(receiver).is_ok()
+            && job_sender.send((file, file_source, sender)).is_ok()
+    };
+    // As in delta::parse_diff_sequentially, input is read until the first read error.
+    while let Some(Ok(raw_line)) = lines.next() {
+        let line = delta::truncate_raw_line(String::from_utf8_lossy(&raw_line), config);
+        let line = ansi::strip_ansi_codes(&line);
+        if !file.is_empty() && is_file_boundary(&line) {
+            let file_source = std::mem::replace(&mut file_source, source.clone());
+            if !send_file(std::mem::take(&mut file), file_source) {
+                return;
+            }
+        }
+        if source == Source::Unknown {
+            source = delta::detect_source(&line);
+        }
+        file.extend_from_slice(&raw_line);
+        file.push(b'\n');
+    }
+    if !file.is_empty() {
+        send_file(file, file_source);
+    }
+}
+
+/// Parse and highlight files received from the reader thread until it finishes.
+fn process_files(job_receiver: &Mutex<Receiver<Job>>, config: &Config) {
+    loop {
+        // The lock is released as soon as a job has been received.
+        let job = job_receiver.lock().unwrap().recv();
+        let (file, source, sender) = match job {
+            Ok(job) => job,
+            Err(_) => return,
+        };
+        let mut events = Vec::new();
+        let result = delta::parse_diff_sequentially(
+            BufRead::split(&file[..], b'\n'),
+            config,
+            &mut events,
+            source,
+        )
+        .map(|_| events);
+        // The rendering thread may have stopped, in which case the events are not needed.
+        sender.send(result).ok();
+    }
+}
+
+/// Does the line begin the next file of the diff? At these lines, the parsing state that carries
+/// over from the preceding lines is reset, other than the detected source of the input.
+fn is_file_boundary(line: &str) -> bool {
+    line.starts_with("commit ") || line.starts_with("diff ")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::integration_test_utils::integration_test_utils;
+
+    #[test]
+    fn test_parallel_output_is_identical_to_sequential_output() {
+        for args in &[
+            vec![],
+            vec!["--side-by-side"],
+            vec!["--line-numbers", "--color-only"],
+            vec!["--max-line-length", "20"],
+        ] {
+            let make_config = |threads: &str| {
+                let mut args = args.clone();
+                args.extend(&["--threads", threads]);
+                integration_test_utils::make_config_from_args(&args)
+            };
+            let sequential_config = make_config("1");
+            let sequential_output =
+                integration_test_utils::run_delta(GIT_LOG_OUTPUT, &sequential_config);
+            for threads in &["2", "8"] {
+                let config = make_config(threads);
+                assert_eq!(
+                    integration_test_utils::run_delta(GIT_LOG_OUTPUT, &config),
+                    sequential_output
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_parallel_output_of_diff_unified_input() {
+        let sequential_config = integration_test_utils::make_config_from_args(&["--threads", "1"]);
+        let config = integration_test_utils::make_config_from_args(&["--threads", "4"]);
+        assert_eq!(
+            integration_test_utils::run_delta(DIFF_UNIFIED_OUTPUT, &config),
+            integration_test_utils::run_delta(DIFF_UNIFIED_OUTPUT, &sequential_config)
+        );
+    }
+
+    const GIT_LOG_OUTPUT: &str = r#"
+commit 94907c0f136f46dc46ffae2dc92dca9af7eb7c2e
+Author: Dan Davison <dandavison7@gmail.com>
+Date:   Wed Dec 9 11:14:41 2020 -0500
+
+    Change two files
+
+diff --git a/src/align.rs b/src/align.rs
+index 8e37a9e..6ce4863 100644
+--- a/src/align.rs
++++ b/src/align.rs
+@@ -1,3 +1,3 @@
+-use std::cmp::max;
++use std::cmp::{max, min};
+ use std::collections::VecDeque;
+
+@@ -50,2 +50,3 @@ impl Alignment {
+     fn new(x: Vec<&str>) -> Self {
++        let n = x.len();
+         Self { x }
+diff --git a/README.md b/README.md
+index 9c6c96e..b2c2fd1 100644
+--- a/README.md
++++ b/README.md
+@@ -1,2 +1,2 @@
+-# delta
++# Delta
+ A viewer for git and diff output
+
+commit 2d8d5d6ccb3d3ec1dc8cc8a0b0e9b3c8a1ba1f04
+Author: Dan Davison <dandavison7@gmail.com>
+Date:   Tue Dec 8 10:00:00 2020 -0500
+
+    Add a file and rename another
+
+diff --git a/a.py b/a.py
+new file mode 100644
+index 0000000..b4f8b72
+--- /dev/null
++++ b/a.py
+@@ -0,0 +1,3 @@
++def f(x):
++    """commit or diff"""
++    return x
+diff --git a/b.py b/c.py
+similarity index 100%
+rename from b.py
+rename to c.py
+"#;
+
+    const DIFF_UNIFIED_OUTPUT: &str = "\
+diff -u a/one.rs b/one.rs
+--- a/one.rs	2020-12-08 10:00:00.000000000 -0500
++++ b/one.rs	2020-12-08 10:00:01.000000000 -0500
+@@ -1 +1 @@
+-fn one() {}
++fn one() -> usize { 1 }
+diff -u a/two.rs b/two.rs
+--- a/two.rs	2020-12-08 10:00:00.000000000 -0500
++++ b/two.rs	2020-12-08 10:00:01.000000000 -0500
+@@ -1 +1 @@
+-fn two() {}
++fn two() -> usize { 2 }
+Only in b: three.rs
+";
+}
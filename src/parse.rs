@@ -5,6 +5,7 @@ use std::path::Path;
 
 use crate::config::Config;
 use crate::features;
+use crate::format;
 
 // https://git-scm.com/docs/git-config#Documentation/git-config.txt-diffmnemonicPrefix
 const DIFF_PREFIXES: [&str; 6] = ["a/", "b/", "c/", "i/", "o/", "w/"];
@@ -20,7 +21,7 @@ pub fn get_file_extension_from_marker_line(line: &str) -> Option<&str> {
         .and_then(|file| file.split('.').last())
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum FileEvent {
     Change,
     Copy,
@@ -28,32 +29,333 @@ pub enum FileEvent {
     NoEvent,
 }
 
-pub fn parse_file_meta_line(line: &str, git_diff_name: bool) -> (String, FileEvent) {
+const SYMLINK_MODE: &str = "120000";
+
+/// Information about a file from the extended header lines of a git diff, e.g. "new mode 100755"
+/// (see git-diff(1)), other than its paths.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FileInfo {
+    /// The modes of the file before and after the change, e.g. "100644". Only the plus mode is
+    /// present for an added file, and only the minus mode for a deleted file.
+    pub minus_mode: Option<String>,
+    pub plus_mode: Option<String>,
+    /// The similarity index of a renamed or copied file, as a percentage.
+    pub similarity: Option<usize>,
+    /// The target of each version of a symbolic link, if known.
+    pub minus_symlink_target: Option<String>,
+    pub plus_symlink_target: Option<String>,
+    /// Whether the file was added or deleted with no content, so that the diff has no hunks.
+    pub is_empty: bool,
+}
+
+impl FileInfo {
+    /// Update the information from the extended header line `line`. Other lines are ignored.
+    pub fn update(&mut self, line: &str) {
+        let mut words = line.split(' ');
+        match (words.next(), words.next(), words.next(), words.next()) {
+            (Some("old"), Some("mode"), Some(mode), None) => self.minus_mode = Some(mode.into()),
+            (Some("new"), Some("mode"), Some(mode), None) => self.plus_mode = Some(mode.into()),
+            (Some("new"), Some("file"), Some("mode"), Some(mode)) => {
+                self.plus_mode = Some(mode.into())
+            }
+            (Some("deleted"), Some("file"), Some("mode"), Some(mode)) => {
+                self.minus_mode = Some(mode.into())
+            }
+            // "index 3ec6c3f..f5f8c8e 100644" gives the mode of a file whose mode is unchanged.
+            (Some("index"), Some(_), Some(mode), None)
+                if self.minus_mode.is_none() && self.plus_mode.is_none() =>
+            {
+                self.minus_mode = Some(mode.into());
+                self.plus_mode = Some(mode.into());
+            }
+            (Some("similarity"), Some("index"), Some(percentage), None) => {
+                self.similarity = percentage.trim_end_matches('%').parse().ok()
+            }
+            _ => {}
+        }
+    }
+
+    pub fn is_added(&self) -> bool {
+        self.minus_mode.is_none() && self.plus_mode.is_some()
+    }
+
+    pub fn is_deleted(&self) -> bool {
+        self.minus_mode.is_some() && self.plus_mode.is_none()
+    }
+
+    pub fn is_minus_symlink(&self) -> bool {
+        self.minus_mode.as_deref() == Some(SYMLINK_MODE)
+    }
+
+    pub fn is_plus_symlink(&self) -> bool {
+        self.plus_mode.as_deref() == Some(SYMLINK_MODE)
+    }
+
+    /// Whether there is anything to display in the file header other than the file paths.
+    pub fn is_notable(&self) -> bool {
+        !self.describe().is_empty()
+    }
+
+    /// Return phrases describing the information to display in the file header, e.g. "87%
+    /// similar", "mode +x", or "symlink → target".
+    pub fn describe(&self) -> Vec<String> {
+        let mut descriptions = Vec::new();
+        descriptions.extend(self.describe_similarity());
+        descriptions.extend(self.describe_mode());
+        descriptions.extend(self.describe_emptiness());
+        descriptions
+    }
+
+    /// Return the phrases of `describe` other than those describing the file's mode.
+    pub fn describe_other_than_mode(&self) -> Vec<String> {
+        self.describe_similarity()
+            .into_iter()
+            .chain(self.describe_emptiness())
+            .collect()
+    }
+
+    fn describe_similarity(&self) -> Option<String> {
+        // A renamed or copied file that is 100% similar is unchanged, which the header shows.
+        self.similarity
+            .filter(|similarity| *similarity < 100)
+            .map(|similarity| format!("{}% similar", similarity))
+    }
+
+    fn describe_emptiness(&self) -> Option<String> {
+        if self.is_empty {
+            Some("empty".to_string())
+        } else {
+            None
+        }
+    }
+
+    /// Return phrases describing the file's mode and symlink target, e.g. "mode +x".
+    pub fn describe_mode(&self) -> Vec<String> {
+        let mut descriptions = Vec::new();
+        let describe_target = |target: &Option<String>| match target {
+            Some(target) => format!("symlink → {}", target),
+            None => "symlink".to_string(),
+        };
+        match (self.is_minus_symlink(), self.is_plus_symlink()) {
+            (true, true) => match (&self.minus_symlink_target, &self.plus_symlink_target) {
+                (Some(minus_target), Some(plus_target)) if minus_target != plus_target => {
+                    descriptions.push(format!("symlink {} → {}", minus_target, plus_target))
+                }
+                _ => {}
+            },
+            (false, true) => descriptions.push(describe_target(&self.plus_symlink_target)),
+            (true, false) => descriptions.push(describe_target(&self.minus_symlink_target)),
+            (false, false) => {}
+        }
+        match (self.minus_mode.as_deref(), self.plus_mode.as_deref()) {
+            (Some("100644"), Some("100755")) => descriptions.push("mode +x".to_string()),
+            (Some("100755"), Some("100644")) => descriptions.push("mode -x".to_string()),
+            (Some(minus_mode), Some(plus_mode))
+                if minus_mode != plus_mode
+                    && !self.is_minus_symlink()
+                    && !self.is_plus_symlink() =>
+            {
+                descriptions.push(format!("mode {} → {}", minus_mode, plus_mode))
+            }
+            (None, Some("100755")) | (Some("100755"), None) => {
+                descriptions.push("executable".to_string())
+            }
+            _ => {}
+        }
+        descriptions
+    }
+}
+
+/// The prefixes of the paths in the "diff --git", ---/+++ and "Binary files" lines of a git diff:
+/// "a/" and "b/" by default, none under diff.noprefix, e.g. "i/" and "w/" under
+/// diff.mnemonicPrefix, or those given by --src-prefix and --dst-prefix.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DiffPrefixes {
+    pub minus: String,
+    pub plus: String,
+}
+
+impl DiffPrefixes {
+    /// Given the paths of a file with their prefixes, return the prefixes, determined as those
+    /// that leave the longest path common to both, e.g. ("a/", "b/") for ("a/x/y", "b/x/y"), and
+    /// the path.
+    fn infer<'a>(minus_path: &str, plus_path: &'a str) -> Option<(Self, &'a str)> {
+        let common_suffix_len = minus_path
+            .chars()
+            .rev()
+            .zip(plus_path.chars().rev())
+            .take_while(|(m, p)| m == p)
+            .map(|(c, _)| c.len_utf8())
+            .sum::<usize>();
+        let common_suffix = &plus_path[plus_path.len() - common_suffix_len..];
+        common_suffix.char_indices().find_map(|(i, _)| {
+            let path = &common_suffix[i..];
+            let minus = &minus_path[..minus_path.len() - path.len()];
+            let plus = &plus_path[..plus_path.len() - path.len()];
+            if [minus, plus]
+                .iter()
+                .all(|prefix| prefix.is_empty() || prefix.ends_with('/'))
+            {
+                let prefixes = Self {
+                    minus: minus.to_string(),
+                    plus: plus.to_string(),
+                };
+                Some((prefixes, path))
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Remove the minus or plus prefix from `path`, if it has it.
+    pub fn strip<'a>(&self, path: &'a str, is_plus: bool) -> &'a str {
+        let prefix = if is_plus { &self.plus } else { &self.minus };
+        path.strip_prefix(prefix.as_str()).unwrap_or(path)
+    }
+}
+
+/// Given input like
+/// "diff --git a/src/delta.rs b/src/delta.rs"
+/// Return the prefixes of its paths, ("a/", "b/"), and the paths without them: ("src/delta.rs",
+/// "src/delta.rs"). The paths of a renamed or copied file are given by its rename or copy lines,
+/// which follow this line: pass them as `files` to determine its prefixes, since otherwise the
+/// paths are assumed to be the same. Where unquoted paths contain spaces, the division between
+/// them is chosen in the same way.
+pub fn parse_diff_git_line(
+    line: &str,
+    files: Option<(&str, &str)>,
+) -> Option<(DiffPrefixes, String, String)> {
+    let paths = line.strip_prefix("diff --git ")?;
+    let path_pairs = if paths.starts_with('"') {
+        let (minus_path, rest) = split_quoted_path(paths)?;
+        vec![(minus_path, rest.strip_prefix(' ')?)]
+    } else if let (Some(i), true) = (paths.find(" \""), paths.ends_with('"')) {
+        vec![(&paths[..i], &paths[i + 1..])]
+    } else {
+        paths
+            .match_indices(' ')
+            .map(|(i, _)| (&paths[..i], &paths[i + 1..]))
+            .collect()
+    };
+    let path_pairs = path_pairs
+        .into_iter()
+        .map(|(minus_path, plus_path)| (unquote_path(minus_path), unquote_path(plus_path)));
+    match files {
+        Some((minus_file, plus_file)) => path_pairs
+            .filter_map(|(minus_path, plus_path)| {
+                let prefixes = DiffPrefixes {
+                    minus: minus_path.strip_suffix(minus_file)?.to_string(),
+                    plus: plus_path.strip_suffix(plus_file)?.to_string(),
+                };
+                Some((prefixes, minus_file.to_string(), plus_file.to_string()))
+            })
+            .next(),
+        None => path_pairs
+            .filter_map(|(minus_path, plus_path)| {
+                let (prefixes, path) = DiffPrefixes::infer(&minus_path, &plus_path)?;
+                Some((prefixes, path.to_string(), path.to_string()))
+            })
+            .max_by_key(|(_, path, _)| path.len()),
+    }
+}
+
+/// Given input like
+/// "\"caf\\303\\251.txt\""
+/// Return the path with git's C-style quoting removed: "café.txt". git quotes paths that contain
+/// double quotes, backslashes or control characters, and, under core.quotePath (the default),
+/// those that contain non-ASCII characters, which it writes as octal escapes of their UTF-8 bytes.
+/// Unquoted paths are returned as they are.
+pub fn unquote_path(path: &str) -> Cow<'_, str> {
+    let quoted = match path.strip_prefix('"').and_then(|p| p.strip_suffix('"')) {
+        Some(quoted) => quoted.as_bytes(),
+        None => return Cow::from(path),
+    };
+    let mut bytes = Vec::with_capacity(quoted.len());
+    let mut i = 0;
+    while i < quoted.len() {
+        if quoted[i] != b'\\' || i + 1 == quoted.len() {
+            bytes.push(quoted[i]);
+            i += 1;
+            continue;
+        }
+        let escaped = quoted[i + 1];
+        i += 2;
+        bytes.push(match escaped {
+            b'a' => 0x07,
+            b'b' => 0x08,
+            b't' => b'\t',
+            b'n' => b'\n',
+            b'v' => 0x0b,
+            b'f' => 0x0c,
+            b'r' => b'\r',
+            b'0'..=b'7' => {
+                let mut byte = escaped - b'0';
+                for _ in 0..2 {
+                    if let Some(digit @ b'0'..=b'7') = quoted.get(i) {
+                        byte = byte.wrapping_mul(8).wrapping_add(digit - b'0');
+                        i += 1;
+                    }
+                }
+                byte
+            }
+            other => other,
+        });
+    }
+    Cow::from(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Divide `s`, which starts with a quoted path, into the quoted path and the rest.
+fn split_quoted_path(s: &str) -> Option<(&str, &str)> {
+    let mut escaped = false;
+    for (i, c) in s.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return Some(s.split_at(i + 1)),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Given input like
+/// "+++ b/src/delta.rs"
+/// Return the path, "src/delta.rs", and the event. `prefixes` are the prefixes of the paths of a
+/// git diff if they are known: otherwise the usual prefixes are removed.
+pub fn parse_file_meta_line(
+    line: &str,
+    git_diff_name: bool,
+    prefixes: Option<&DiffPrefixes>,
+) -> (String, FileEvent) {
     match line {
         line if line.starts_with("--- ") || line.starts_with("+++ ") => {
             let offset = 4;
             let file = match &line[offset..] {
-                path if path == "/dev/null" => "/dev/null",
-                path if git_diff_name && DIFF_PREFIXES.iter().any(|s| path.starts_with(s)) => {
-                    &path[2..]
+                path if path == "/dev/null" => "/dev/null".to_string(),
+                // git appends a tab to paths that contain spaces.
+                path if git_diff_name => {
+                    let path = unquote_path(path.trim_end_matches('\t'));
+                    match prefixes {
+                        Some(prefixes) => prefixes.strip(&path, line.starts_with("+++ ")),
+                        None => strip_diff_prefix(&path),
+                    }
+                    .to_string()
                 }
-                path if git_diff_name => &path,
-                path => path.split('\t').next().unwrap_or(""),
-            }
-            .to_string();
+                path => path.split('\t').next().unwrap_or("").to_string(),
+            };
             (file, FileEvent::Change)
         }
         line if line.starts_with("rename from ") => {
-            (line[12..].to_string(), FileEvent::Rename) // "rename from ".len()
+            (unquote_path(&line[12..]).into_owned(), FileEvent::Rename) // "rename from ".len()
         }
         line if line.starts_with("rename to ") => {
-            (line[10..].to_string(), FileEvent::Rename) // "rename to ".len()
+            (unquote_path(&line[10..]).into_owned(), FileEvent::Rename) // "rename to ".len()
         }
         line if line.starts_with("copy from ") => {
-            (line[10..].to_string(), FileEvent::Copy) // "copy from ".len()
+            (unquote_path(&line[10..]).into_owned(), FileEvent::Copy) // "copy from ".len()
         }
         line if line.starts_with("copy to ") => {
-            (line[8..].to_string(), FileEvent::Copy) // "copy to ".len()
+            (unquote_path(&line[8..]).into_owned(), FileEvent::Copy) // "copy to ".len()
         }
         _ => ("".to_string(), FileEvent::NoEvent),
     }
@@ -77,50 +379,113 @@ pub fn get_file_change_description_from_file_paths(
     if comparing {
         format!("comparing: {} ⟶   {}", minus_file, plus_file)
     } else {
-        let format_label = |label: &str| {
-            if !label.is_empty() {
-                format!("{} ", label)
-            } else {
-                "".to_string()
-            }
-        };
-        let format_file = |file| {
-            if config.hyperlinks {
-                features::hyperlinks::format_osc8_file_hyperlink(file, None, file, config)
-            } else {
-                Cow::from(file)
-            }
-        };
-        match (minus_file, plus_file) {
-            (minus_file, plus_file) if minus_file == plus_file => format!(
-                "{}{}",
-                format_label(&config.file_modified_label),
-                format_file(minus_file)
-            ),
-            (minus_file, "/dev/null") => format!(
-                "{}{}",
-                format_label(&config.file_removed_label),
-                format_file(minus_file)
-            ),
-            ("/dev/null", plus_file) => format!(
-                "{}{}",
-                format_label(&config.file_added_label),
-                format_file(plus_file)
-            ),
-            (minus_file, plus_file) => format!(
-                "{}{} ⟶   {}",
-                format_label(match file_event {
-                    FileEvent::Rename => &config.file_renamed_label,
-                    FileEvent::Copy => &config.file_copied_label,
-                    _ => "",
-                }),
-                format_file(minus_file),
-                format_file(plus_file)
+        match get_file_change_label(minus_file, plus_file, comparing, file_event, config) {
+            "" => format_file_paths(minus_file, plus_file, config),
+            label => format!(
+                "{} {}",
+                label,
+                format_file_paths(minus_file, plus_file, config)
             ),
         }
     }
 }
 
+/// Return the label describing the change to the file, e.g. "renamed:", from the file-*-label
+/// options.
+pub fn get_file_change_label<'a>(
+    minus_file: &str,
+    plus_file: &str,
+    comparing: bool,
+    file_event: &FileEvent,
+    config: &'a Config,
+) -> &'a str {
+    match (minus_file, plus_file) {
+        _ if comparing => "comparing:",
+        (minus_file, plus_file) if minus_file == plus_file => &config.file_modified_label,
+        (_, "/dev/null") => &config.file_removed_label,
+        ("/dev/null", _) => &config.file_added_label,
+        _ => match file_event {
+            FileEvent::Rename => &config.file_renamed_label,
+            FileEvent::Copy => &config.file_copied_label,
+            _ => "",
+        },
+    }
+}
+
+/// Return the path of the file for display in its header, or the paths of a renamed or copied
+/// file formatted according to --file-rename-format.
+pub fn format_file_paths(minus_file: &str, plus_file: &str, config: &Config) -> String {
+    let format_file = |file| {
+        let text = format::format_path(file, config);
+        if config.hyperlinks {
+            Cow::from(features::hyperlinks::format_osc8_file_hyperlink_or_text(
+                file, None, &text, config,
+            ))
+        } else {
+            text
+        }
+    };
+    match (minus_file, plus_file) {
+        (minus_file, plus_file) if minus_file == plus_file => format_file(minus_file).into_owned(),
+        (minus_file, "/dev/null") => format_file(minus_file).into_owned(),
+        ("/dev/null", plus_file) => format_file(plus_file).into_owned(),
+        (minus_file, plus_file) => {
+            format::format_file_rename(&config.file_rename_format, minus_file, plus_file, config)
+        }
+    }
+}
+
+/// Given input like
+/// "index 3ec6c3f..f5f8c8e 100644"
+/// Return the (abbreviated) blob ids of the pre-image and post-image: ("3ec6c3f", "f5f8c8e").
+/// Return None for the index lines of merge commits, which have more than one pre-image.
+pub fn parse_index_line(line: &str) -> Option<(&str, &str)> {
+    let blob_ids = line.strip_prefix("index ")?.split(' ').next()?;
+    match blob_ids.split("..").collect::<Vec<&str>>()[..] {
+        [minus_blob_id, plus_blob_id] if !minus_blob_id.contains(',') => {
+            Some((minus_blob_id, plus_blob_id))
+        }
+        _ => None,
+    }
+}
+
+/// Given input like
+/// "Binary files a/image.png and b/image.png differ"
+/// Return the paths of the pre-image and post-image: ("image.png", "image.png"). `prefixes` are
+/// as for parse_file_meta_line.
+pub fn parse_binary_files_line(
+    line: &str,
+    prefixes: Option<&DiffPrefixes>,
+) -> Option<(String, String)> {
+    let paths = line
+        .strip_prefix("Binary files ")?
+        .strip_suffix(" differ")?;
+    let (minus_path, plus_path) = if paths.starts_with('"') {
+        let (minus_path, rest) = split_quoted_path(paths)?;
+        (minus_path, rest.strip_prefix(" and ")?)
+    } else if let (Some(i), true) = (paths.find(" and \""), paths.ends_with('"')) {
+        (&paths[..i], &paths[i + 5..])
+    } else {
+        paths.split_once(" and ")?
+    };
+    let strip = |path: &str, is_plus| match unquote_path(path) {
+        path if path == "/dev/null" => path.into_owned(),
+        path => match prefixes {
+            Some(prefixes) => prefixes.strip(&path, is_plus),
+            None => strip_diff_prefix(&path),
+        }
+        .to_string(),
+    };
+    Some((strip(minus_path, false), strip(plus_path, true)))
+}
+
+fn strip_diff_prefix(path: &str) -> &str {
+    match path {
+        path if DIFF_PREFIXES.iter().any(|s| path.starts_with(s)) => &path[2..],
+        path => path,
+    }
+}
+
 lazy_static! {
     static ref HUNK_HEADER_REGEX: Regex = Regex::new(r"@+ ([^@]+)@+(.*\s?)").unwrap();
 }
@@ -228,38 +593,27 @@ mod tests {
         );
     }
 
-    // We should only strip the prefixes if they are "a/" or "b/". This will be correct except for
-    // the case of a user with `diff.noprefix = true` who has directories named "a" or "b", which
-    // is an irresolvable ambiguity. Ideally one would only strip the prefixes if we have confirmed
-    // that we are looking at something like
-    //
-    // --- a/src/parse.rs
-    // +++ b/src/parse.rs
-    //
-    // as opposed to something like
-    //
-    // --- a/src/parse.rs
-    // +++ sibling_of_a/src/parse.rs
-    //
-    // but we don't attempt that currently.
+    // Without the prefixes determined from the "diff --git" line (see parse_diff_git_line), we
+    // strip the usual prefixes. This will be correct except for the case of a user with
+    // `diff.noprefix = true` who has directories named "a" or "b".
     #[test]
     fn test_get_file_path_from_git_file_meta_line() {
         assert_eq!(
-            parse_file_meta_line("--- /dev/null", true),
+            parse_file_meta_line("--- /dev/null", true, None),
             ("/dev/null".to_string(), FileEvent::Change)
         );
         for prefix in &DIFF_PREFIXES {
             assert_eq!(
-                parse_file_meta_line(&format!("--- {}src/delta.rs", prefix), true),
+                parse_file_meta_line(&format!("--- {}src/delta.rs", prefix), true, None),
                 ("src/delta.rs".to_string(), FileEvent::Change)
             );
         }
         assert_eq!(
-            parse_file_meta_line("--- src/delta.rs", true),
+            parse_file_meta_line("--- src/delta.rs", true, None),
             ("src/delta.rs".to_string(), FileEvent::Change)
         );
         assert_eq!(
-            parse_file_meta_line("+++ src/delta.rs", true),
+            parse_file_meta_line("+++ src/delta.rs", true, None),
             ("src/delta.rs".to_string(), FileEvent::Change)
         );
     }
@@ -267,23 +621,23 @@ mod tests {
     #[test]
     fn test_get_file_path_from_git_file_meta_line_containing_spaces() {
         assert_eq!(
-            parse_file_meta_line("+++ a/my src/delta.rs", true),
+            parse_file_meta_line("+++ a/my src/delta.rs", true, None),
             ("my src/delta.rs".to_string(), FileEvent::Change)
         );
         assert_eq!(
-            parse_file_meta_line("+++ my src/delta.rs", true),
+            parse_file_meta_line("+++ my src/delta.rs", true, None),
             ("my src/delta.rs".to_string(), FileEvent::Change)
         );
         assert_eq!(
-            parse_file_meta_line("+++ a/src/my delta.rs", true),
+            parse_file_meta_line("+++ a/src/my delta.rs", true, None),
             ("src/my delta.rs".to_string(), FileEvent::Change)
         );
         assert_eq!(
-            parse_file_meta_line("+++ a/my src/my delta.rs", true),
+            parse_file_meta_line("+++ a/my src/my delta.rs", true, None),
             ("my src/my delta.rs".to_string(), FileEvent::Change)
         );
         assert_eq!(
-            parse_file_meta_line("+++ b/my src/my enough/my delta.rs", true),
+            parse_file_meta_line("+++ b/my src/my enough/my delta.rs", true, None),
             (
                 "my src/my enough/my delta.rs".to_string(),
                 FileEvent::Change
@@ -294,7 +648,7 @@ mod tests {
     #[test]
     fn test_get_file_path_from_git_file_meta_line_rename() {
         assert_eq!(
-            parse_file_meta_line("rename from nospace/file2.el", true),
+            parse_file_meta_line("rename from nospace/file2.el", true, None),
             ("nospace/file2.el".to_string(), FileEvent::Rename)
         );
     }
@@ -302,7 +656,7 @@ mod tests {
     #[test]
     fn test_get_file_path_from_git_file_meta_line_rename_containing_spaces() {
         assert_eq!(
-            parse_file_meta_line("rename from with space/file1.el", true),
+            parse_file_meta_line("rename from with space/file1.el", true, None),
             ("with space/file1.el".to_string(), FileEvent::Rename)
         );
     }
@@ -310,15 +664,60 @@ mod tests {
     #[test]
     fn test_parse_file_meta_line() {
         assert_eq!(
-            parse_file_meta_line("--- src/delta.rs", false),
+            parse_file_meta_line("--- src/delta.rs", false, None),
             ("src/delta.rs".to_string(), FileEvent::Change)
         );
         assert_eq!(
-            parse_file_meta_line("+++ src/delta.rs", false),
+            parse_file_meta_line("+++ src/delta.rs", false, None),
             ("src/delta.rs".to_string(), FileEvent::Change)
         );
     }
 
+    #[test]
+    fn test_parse_index_line() {
+        assert_eq!(
+            parse_index_line("index 3ec6c3f..f5f8c8e 100644"),
+            Some(("3ec6c3f", "f5f8c8e"))
+        );
+        assert_eq!(
+            parse_index_line("index 0000000..f5f8c8e"),
+            Some(("0000000", "f5f8c8e"))
+        );
+        assert_eq!(parse_index_line("index 3ec6c3f,7a5a1b2..f5f8c8e"), None);
+        assert_eq!(parse_index_line("--- a/src/delta.rs"), None);
+    }
+
+    #[test]
+    fn test_parse_binary_files_line() {
+        assert_eq!(
+            parse_binary_files_line("Binary files a/image.png and b/image.png differ", None),
+            Some(("image.png".to_string(), "image.png".to_string()))
+        );
+        assert_eq!(
+            parse_binary_files_line("Binary files /dev/null and b/image.png differ", None),
+            Some(("/dev/null".to_string(), "image.png".to_string()))
+        );
+        assert_eq!(parse_binary_files_line("Binary files differ", None), None);
+        let prefixes = DiffPrefixes {
+            minus: "".to_string(),
+            plus: "".to_string(),
+        };
+        assert_eq!(
+            parse_binary_files_line(
+                r#"Binary files "a/caf\303\251.png" and "a/caf\303\251.png" differ"#,
+                Some(&prefixes)
+            ),
+            Some(("a/café.png".to_string(), "a/café.png".to_string()))
+        );
+        assert_eq!(
+            parse_binary_files_line(
+                r#"Binary files /dev/null and "w/caf\303\251.png" differ"#,
+                None
+            ),
+            Some(("/dev/null".to_string(), "café.png".to_string()))
+        );
+    }
+
     #[test]
     fn test_parse_hunk_header() {
         let parsed = parse_hunk_header("@@ -74,15 +75,14 @@ pub fn delta(\n");
@@ -369,4 +768,126 @@ mod tests {
         assert_eq!(line_numbers_and_hunk_lengths[1], (358, 15),);
         assert_eq!(line_numbers_and_hunk_lengths[2], (358, 16),);
     }
+
+    #[test]
+    fn test_file_info_update() {
+        let mut file_info = FileInfo::default();
+        for line in &[
+            "old mode 100644",
+            "new mode 100755",
+            "similarity index 87%",
+            "index 0ff3bbb..fe94667",
+        ] {
+            file_info.update(line);
+        }
+        assert_eq!(file_info.minus_mode, Some("100644".to_string()));
+        assert_eq!(file_info.plus_mode, Some("100755".to_string()));
+        assert_eq!(file_info.similarity, Some(87));
+        assert_eq!(file_info.describe(), vec!["87% similar", "mode +x"]);
+
+        let mut file_info = FileInfo::default();
+        file_info.update("index 2e65efe..35d5d0e 120000");
+        assert!(file_info.is_minus_symlink() && file_info.is_plus_symlink());
+        assert!(!file_info.is_notable());
+        file_info.minus_symlink_target = Some("a".to_string());
+        file_info.plus_symlink_target = Some("b".to_string());
+        assert_eq!(file_info.describe(), vec!["symlink a → b"]);
+
+        let mut file_info = FileInfo::default();
+        file_info.update("new file mode 100644");
+        assert!(file_info.is_added() && !file_info.is_notable());
+        file_info.is_empty = true;
+        assert_eq!(file_info.describe(), vec!["empty"]);
+
+        let mut file_info = FileInfo::default();
+        file_info.update("deleted file mode 100755");
+        assert!(file_info.is_deleted());
+        assert_eq!(file_info.describe(), vec!["executable"]);
+    }
+
+    fn make_prefixes(minus: &str, plus: &str) -> DiffPrefixes {
+        DiffPrefixes {
+            minus: minus.to_string(),
+            plus: plus.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse_diff_git_line() {
+        let parse = |line| parse_diff_git_line(line, None);
+        let path = |path: &str| path.to_string();
+        assert_eq!(
+            parse("diff --git a/src/delta.rs b/src/delta.rs"),
+            Some((
+                make_prefixes("a/", "b/"),
+                path("src/delta.rs"),
+                path("src/delta.rs")
+            ))
+        );
+        // diff.noprefix, with a directory named "a".
+        assert_eq!(
+            parse("diff --git a/x a/x"),
+            Some((make_prefixes("", ""), path("a/x"), path("a/x")))
+        );
+        // diff.mnemonicPrefix, and --src-prefix and --dst-prefix.
+        assert_eq!(
+            parse("diff --git i/my src/my x w/my src/my x"),
+            Some((
+                make_prefixes("i/", "w/"),
+                path("my src/my x"),
+                path("my src/my x")
+            ))
+        );
+        assert_eq!(
+            parse("diff --git old/a b/x new/a b/x"),
+            Some((make_prefixes("old/", "new/"), path("a b/x"), path("a b/x")))
+        );
+        assert_eq!(
+            parse(r#"diff --git "a/caf\303\251" "b/caf\303\251""#),
+            Some((make_prefixes("a/", "b/"), path("café"), path("café")))
+        );
+        assert_eq!(parse(r#"diff --git a/x y "b/\"q\"""#), None);
+        assert_eq!(parse("diff -u a b"), None);
+        assert_eq!(
+            parse_diff_git_line("diff --git a/src/x b/lib/y", Some(("src/x", "lib/y"))),
+            Some((make_prefixes("a/", "b/"), path("src/x"), path("lib/y")))
+        );
+        assert_eq!(
+            parse_diff_git_line("diff --git plain new name", Some(("plain", "new name"))),
+            Some((make_prefixes("", ""), path("plain"), path("new name")))
+        );
+    }
+
+    #[test]
+    fn test_unquote_path() {
+        assert_eq!(unquote_path("src/delta.rs"), "src/delta.rs");
+        assert_eq!(unquote_path(r#""a/caf\303\251""#), "a/café");
+        assert_eq!(
+            unquote_path(r#""tab\there \"q\" \\""#),
+            "tab\there \"q\" \\"
+        );
+        assert_eq!(unquote_path(r#""\346\227\245\346\234\254""#), "日本");
+    }
+
+    #[test]
+    fn test_parse_file_meta_line_with_prefixes() {
+        let prefixes = make_prefixes("", "");
+        assert_eq!(
+            parse_file_meta_line("--- a/x", true, Some(&prefixes)),
+            ("a/x".to_string(), FileEvent::Change)
+        );
+        let prefixes = make_prefixes("SRC/", "dest/");
+        assert_eq!(
+            parse_file_meta_line("+++ dest/with space/f 1\t", true, Some(&prefixes)),
+            ("with space/f 1".to_string(), FileEvent::Change)
+        );
+        assert_eq!(
+            parse_file_meta_line(r#"--- "SRC/caf\303\251""#, true, Some(&prefixes)),
+            ("café".to_string(), FileEvent::Change)
+        );
+        assert_eq!(
+            parse_file_meta_line(r#"rename to "caf\303\251""#, true, Some(&prefixes)),
+            ("café".to_string(), FileEvent::Rename)
+        );
+    }
 }
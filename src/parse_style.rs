@@ -1,38 +1,37 @@
-use std::process;
-
 use bitflags::bitflags;
 
 use crate::color;
 use crate::config::delta_unreachable;
+use crate::errors::*;
 use crate::style::{DecorationStyle, Style};
 
 impl Style {
     /// Construct Style from style and decoration-style strings supplied on command line, together
     /// with defaults. A style string is a space-separated string containing 0, 1, or 2 colors
     /// (foreground and then background) and an arbitrary number of style attributes. See `delta
-    /// --help` for more precise spec.
+    /// --help` for more precise spec. Return an error if either string is invalid.
     pub fn from_str(
         style_string: &str,
         default: Option<Self>,
         decoration_style_string: Option<&str>,
         true_color: bool,
         is_emph: bool,
-    ) -> Self {
+    ) -> Result<Self> {
         let (ansi_term_style, is_omitted, is_raw, is_syntax_highlighted) =
-            parse_ansi_term_style(&style_string, default, true_color);
+            parse_ansi_term_style(&style_string, default, true_color)?;
         let decoration_style =
-            DecorationStyle::from_str(decoration_style_string.unwrap_or(""), true_color);
-        Self {
+            DecorationStyle::from_str(decoration_style_string.unwrap_or(""), true_color)?;
+        Ok(Self {
             ansi_term_style,
             is_emph,
             is_omitted,
             is_raw,
             is_syntax_highlighted,
             decoration_style,
-        }
+        })
     }
 
-    pub fn from_git_str(git_style_string: &str) -> Self {
+    pub fn from_git_str(git_style_string: &str) -> Result<Self> {
         Self::from_str(git_style_string, None, None, true, false)
     }
 
@@ -43,7 +42,7 @@ impl Style {
         decoration_style_string: Option<&str>,
         true_color: bool,
         is_emph: bool,
-    ) -> Self {
+    ) -> Result<Self> {
         let (special_attributes_from_style_string, style_string) =
             extract_special_decoration_attributes_from_non_decoration_style_string(style_string);
         let mut style = Style::from_str(
@@ -52,13 +51,13 @@ impl Style {
             decoration_style_string.as_deref(),
             true_color,
             is_emph,
-        );
+        )?;
         // TODO: box in this context resulted in box-with-underline for commit and file
         style.decoration_style = DecorationStyle::apply_special_decoration_attributes(
             &mut style,
             special_attributes_from_style_string,
         );
-        style
+        Ok(style)
     }
 
     /// As from_str_with_handling_of_special_decoration_attributes but respecting an optional
@@ -70,19 +69,19 @@ impl Style {
         deprecated_foreground_color_arg: Option<&str>,
         true_color: bool,
         is_emph: bool,
-    ) -> Self {
+    ) -> Result<Self> {
         let mut style = Self::from_str_with_handling_of_special_decoration_attributes(
             style_string,
             default,
             decoration_style_string,
             true_color,
             is_emph,
-        );
+        )?;
         if let Some(s) = deprecated_foreground_color_arg {
             // The deprecated --{commit,file,hunk}-color args functioned to set the decoration
             // foreground color. In the case of file, it set the text foreground color also.
             let foreground_from_deprecated_arg =
-                parse_ansi_term_style(s, None, true_color).0.foreground;
+                parse_ansi_term_style(s, None, true_color)?.0.foreground;
             style.ansi_term_style.foreground = foreground_from_deprecated_arg;
             style.decoration_style = match style.decoration_style {
                 DecorationStyle::Box(mut ansi_term_style) => {
@@ -116,7 +115,7 @@ impl Style {
                 DecorationStyle::NoDecoration => style.decoration_style,
             };
         }
-        style
+        Ok(style)
     }
 }
 
@@ -130,18 +129,16 @@ bitflags! {
 }
 
 impl DecorationStyle {
-    pub fn from_str(style_string: &str, true_color: bool) -> Self {
+    pub fn from_str(style_string: &str, true_color: bool) -> Result<Self> {
         let (special_attributes, style_string) =
             extract_special_decoration_attributes(&style_string);
         let (style, is_omitted, is_raw, is_syntax_highlighted) =
-            parse_ansi_term_style(&style_string, None, true_color);
+            parse_ansi_term_style(&style_string, None, true_color)?;
         if is_raw {
-            eprintln!("'raw' may not be used in a decoration style.");
-            process::exit(1);
+            return Err("'raw' may not be used in a decoration style.".into());
         };
         if is_syntax_highlighted {
-            eprintln!("'syntax' may not be used in a decoration style.");
-            process::exit(1);
+            return Err("'syntax' may not be used in a decoration style.".into());
         };
         #[allow(non_snake_case)]
         let (BOX, UL, OL, EMPTY) = (
@@ -150,7 +147,7 @@ impl DecorationStyle {
             DecorationAttributes::OVERLINE,
             DecorationAttributes::EMPTY,
         );
-        match special_attributes {
+        Ok(match special_attributes {
             bits if bits == EMPTY => DecorationStyle::NoDecoration,
             bits if bits == BOX => DecorationStyle::Box(style),
             bits if bits == UL => DecorationStyle::Underline(style),
@@ -161,7 +158,7 @@ impl DecorationStyle {
             bits if bits == BOX | UL | OL => DecorationStyle::BoxWithUnderOverline(style),
             _ if is_omitted => DecorationStyle::NoDecoration,
             _ => delta_unreachable("Unreachable code path reached in parse_decoration_style."),
-        }
+        })
     }
 
     fn apply_special_decoration_attributes(
@@ -203,7 +200,7 @@ fn parse_ansi_term_style(
     s: &str,
     default: Option<Style>,
     true_color: bool,
-) -> (ansi_term::Style, bool, bool, bool) {
+) -> Result<(ansi_term::Style, bool, bool, bool)> {
     let mut style = ansi_term::Style::new();
     let mut seen_foreground = false;
     let mut seen_background = false;
@@ -249,30 +246,30 @@ fn parse_ansi_term_style(
                 style.foreground = default.and_then(|s| s.ansi_term_style.foreground);
                 is_syntax_highlighted = default.map(|s| s.is_syntax_highlighted).unwrap_or(false);
             } else {
-                style.foreground = color::parse_color(word, true_color);
+                style.foreground = color::parse_color(word, true_color)?;
             }
             seen_foreground = true;
         } else if !seen_background {
             if word == "syntax" {
-                eprintln!(
+                return Err(
                     "You have used the special color 'syntax' as a background color \
-                     (second color in a style string). It may only be used as a foreground \
-                     color (first color in a style string)."
+                            (second color in a style string). It may only be used as a \
+                            foreground color (first color in a style string)."
+                        .into(),
                 );
-                process::exit(1);
             } else if word == "auto" {
                 background_is_auto = true;
                 style.background = default.and_then(|s| s.ansi_term_style.background);
             } else {
-                style.background = color::parse_color(word, true_color);
+                style.background = color::parse_color(word, true_color)?;
             }
             seen_background = true;
         } else {
-            eprintln!(
+            return Err(format!(
                 "Invalid style string: {}. See the STYLES section of delta --help.",
                 s
-            );
-            process::exit(1);
+            )
+            .into());
         }
     }
     if foreground_is_auto && background_is_auto {
@@ -283,7 +280,7 @@ fn parse_ansi_term_style(
             is_raw = default.map(|s| s.is_raw).unwrap_or(false);
         }
     }
-    (style, is_omitted, is_raw, is_syntax_highlighted)
+    Ok((style, is_omitted, is_raw, is_syntax_highlighted))
 }
 
 /// Extract set of 'special decoration attributes' and return it along with modified style string.
@@ -337,11 +334,11 @@ mod tests {
     #[test]
     fn test_parse_ansi_term_style() {
         assert_eq!(
-            parse_ansi_term_style("", None, false),
+            parse_ansi_term_style("", None, false).unwrap(),
             (ansi_term::Style::new(), false, false, false)
         );
         assert_eq!(
-            parse_ansi_term_style("red", None, false),
+            parse_ansi_term_style("red", None, false).unwrap(),
             (
                 ansi_term::Style {
                     foreground: Some(ansi_term::Color::Fixed(
@@ -355,7 +352,7 @@ mod tests {
             )
         );
         assert_eq!(
-            parse_ansi_term_style("red green", None, false),
+            parse_ansi_term_style("red green", None, false).unwrap(),
             (
                 ansi_term::Style {
                     foreground: Some(ansi_term::Color::Fixed(
@@ -372,7 +369,7 @@ mod tests {
             )
         );
         assert_eq!(
-            parse_ansi_term_style("bold red underline green blink", None, false),
+            parse_ansi_term_style("bold red underline green blink", None, false).unwrap(),
             (
                 ansi_term::Style {
                     foreground: Some(ansi_term::Color::Fixed(
@@ -396,11 +393,11 @@ mod tests {
     #[test]
     fn test_parse_ansi_term_style_with_special_syntax_color() {
         assert_eq!(
-            parse_ansi_term_style("syntax", None, false),
+            parse_ansi_term_style("syntax", None, false).unwrap(),
             (ansi_term::Style::new(), false, false, true)
         );
         assert_eq!(
-            parse_ansi_term_style("syntax italic white hidden", None, false),
+            parse_ansi_term_style("syntax italic white hidden", None, false).unwrap(),
             (
                 ansi_term::Style {
                     background: Some(ansi_term::Color::Fixed(
@@ -416,7 +413,7 @@ mod tests {
             )
         );
         assert_eq!(
-            parse_ansi_term_style("bold syntax italic white hidden", None, false),
+            parse_ansi_term_style("bold syntax italic white hidden", None, false).unwrap(),
             (
                 ansi_term::Style {
                     background: Some(ansi_term::Color::Fixed(
@@ -437,12 +434,12 @@ mod tests {
     #[test]
     fn test_parse_ansi_term_style_with_special_omit_attribute() {
         assert_eq!(
-            parse_ansi_term_style("omit", None, false),
+            parse_ansi_term_style("omit", None, false).unwrap(),
             (ansi_term::Style::new(), true, false, false)
         );
         // It doesn't make sense for omit to be combined with anything else, but it is not an error.
         assert_eq!(
-            parse_ansi_term_style("omit syntax italic white hidden", None, false),
+            parse_ansi_term_style("omit syntax italic white hidden", None, false).unwrap(),
             (
                 ansi_term::Style {
                     background: Some(ansi_term::Color::Fixed(
@@ -462,12 +459,12 @@ mod tests {
     #[test]
     fn test_parse_ansi_term_style_with_special_raw_attribute() {
         assert_eq!(
-            parse_ansi_term_style("raw", None, false),
+            parse_ansi_term_style("raw", None, false).unwrap(),
             (ansi_term::Style::new(), false, true, false)
         );
         // It doesn't make sense for raw to be combined with anything else, but it is not an error.
         assert_eq!(
-            parse_ansi_term_style("raw syntax italic white hidden", None, false),
+            parse_ansi_term_style("raw syntax italic white hidden", None, false).unwrap(),
             (
                 ansi_term::Style {
                     background: Some(ansi_term::Color::Fixed(
@@ -530,7 +527,7 @@ mod tests {
     #[test]
     fn test_decoration_style_from_str_empty_string() {
         assert_eq!(
-            DecorationStyle::from_str("", true),
+            DecorationStyle::from_str("", true).unwrap(),
             DecorationStyle::NoDecoration,
         )
     }
@@ -538,7 +535,7 @@ mod tests {
     #[test]
     fn test_decoration_style_from_str() {
         assert_eq!(
-            DecorationStyle::from_str("ol red box bold green ul", true),
+            DecorationStyle::from_str("ol red box bold green ul", true).unwrap(),
             DecorationStyle::BoxWithUnderOverline(ansi_term::Style {
                 foreground: Some(ansi_term::Color::Fixed(1)),
                 background: Some(ansi_term::Color::Fixed(2)),
@@ -556,7 +553,8 @@ mod tests {
             Some("ol red box bold green ul"),
             true,
             false,
-        );
+        )
+        .unwrap();
         let red_green_bold = ansi_term::Style {
             foreground: Some(ansi_term::Color::Fixed(1)),
             background: Some(ansi_term::Color::Fixed(2)),
@@ -575,7 +573,7 @@ mod tests {
 
     #[test]
     fn test_style_from_str_raw_with_box() {
-        let actual_style = Style::from_str("raw", None, Some("box"), true, false);
+        let actual_style = Style::from_str("raw", None, Some("box"), true, false).unwrap();
         let empty_ansi_term_style = ansi_term::Style::new();
         assert_eq!(
             actual_style,
@@ -590,7 +588,8 @@ mod tests {
 
     #[test]
     fn test_style_from_str_decoration_style_only() {
-        let actual_style = Style::from_str("", None, Some("ol red box bold green ul"), true, false);
+        let actual_style =
+            Style::from_str("", None, Some("ol red box bold green ul"), true, false).unwrap();
         let red_green_bold = ansi_term::Style {
             foreground: Some(ansi_term::Color::Fixed(1)),
             background: Some(ansi_term::Color::Fixed(2)),
@@ -614,7 +613,8 @@ mod tests {
             Some("ol red box bold green ul"),
             true,
             false,
-        );
+        )
+        .unwrap();
         let expected_decoration_style = DecorationStyle::BoxWithUnderOverline(ansi_term::Style {
             foreground: Some(ansi_term::Color::Fixed(1)),
             background: Some(ansi_term::Color::Fixed(2)),
@@ -638,7 +638,8 @@ mod tests {
             Some("box"),
             true,
             false,
-        );
+        )
+        .unwrap();
         let empty_ansi_term_style = ansi_term::Style::new();
         assert_eq!(
             actual_style,
@@ -662,7 +663,7 @@ mod tests {
         });
         let actual_style = Style::from_str_with_handling_of_special_decoration_attributes_and_respecting_deprecated_foreground_color_arg(
                 "", None, Some("ol red box bold green ul"), None, true, false
-            );
+            ).unwrap();
         assert_eq!(
             actual_style,
             Style {
@@ -682,7 +683,7 @@ mod tests {
             None,
             true,
             false,
-        );
+        ).unwrap();
         let empty_ansi_term_style = ansi_term::Style::new();
         assert_eq!(
             actual_style,
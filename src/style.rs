@@ -263,7 +263,9 @@ pub mod tests {
     #[test]
     fn test_parse_git_style_string_and_ansi_code_iterator() {
         for (git_style_string, git_output) in &*GIT_STYLE_STRING_EXAMPLES {
-            assert!(Style::from_git_str(git_style_string).is_applied_to(git_output));
+            assert!(Style::from_git_str(git_style_string)
+                .unwrap()
+                .is_applied_to(git_output));
         }
     }
 
@@ -271,7 +273,9 @@ pub mod tests {
     fn test_is_applied_to_negative_assertion() {
         let style_string_from_24 = "bold #aabbcc ul 19 strike";
         let git_output_from_25 = "\x1b[1;4;9;38;5;19;48;2;170;187;204m+\x1b[m\x1b[1;4;9;38;5;19;48;2;170;187;204mtext\x1b[m\n";
-        assert!(!Style::from_git_str(style_string_from_24).is_applied_to(git_output_from_25));
+        assert!(!Style::from_git_str(style_string_from_24)
+            .unwrap()
+            .is_applied_to(git_output_from_25));
     }
 
     #[test]
@@ -0,0 +1,242 @@
+use std::str::FromStr;
+
+use git2::{Oid, Repository};
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::git_config_entry::GitRemoteRepo;
+use crate::model::SubmoduleHeader;
+
+lazy_static! {
+    static ref SUBMODULE_HEADER_REGEX: Regex =
+        Regex::new(r"^Submodule (.+) ([0-9a-f]+)\.\.\.?([0-9a-f]+)(?: \((.+)\))?:?$").unwrap();
+}
+
+/// Given input like
+/// "Submodule x/y 1234567...89abcde (rewind):"
+/// Return the submodule path, the commits before and after the change, and the note ("rewind").
+/// Return None for other lines beginning "Submodule", e.g. "Submodule x/y contains modified
+/// content".
+pub fn parse_submodule_header_line(line: &str) -> Option<SubmoduleHeader> {
+    let captures = SUBMODULE_HEADER_REGEX.captures(line)?;
+    Some(SubmoduleHeader {
+        path: captures[1].to_string(),
+        minus_commit: captures[2].to_string(),
+        plus_commit: captures[3].to_string(),
+        note: captures.get(4).map(|note| note.as_str().to_string()),
+    })
+}
+
+/// Given input like
+/// "  > Add feature"
+/// Return whether the commit was added ('>') rather than removed ('<'), and its summary.
+pub fn parse_submodule_log_line(line: &str) -> Option<(bool, &str)> {
+    if let Some(summary) = line.strip_prefix("  > ") {
+        Some((true, summary))
+    } else {
+        line.strip_prefix("  < ").map(|summary| (false, summary))
+    }
+}
+
+/// The commits that git diff --submodule=log lists under the header of a submodule, read from the
+/// submodule's repository. git lists only their summaries, so a listed commit is identified as the
+/// first unclaimed commit with its summary. Commits are read as their lines are listed, and no
+/// more are read on each side than have been listed.
+#[derive(Default)]
+pub struct SubmoduleCommits {
+    repo: Option<Repository>,
+    plus_walk: FirstParentWalk,
+    minus_walk: FirstParentWalk,
+    // (is_plus, summary, commit id)
+    commits: Vec<(bool, String, String)>,
+    pub github_repo: Option<String>,
+}
+
+/// The first-parent history of a commit, up to the first commit in the history of another.
+#[derive(Default)]
+struct FirstParentWalk {
+    next_oid: Option<Oid>,
+    hidden_oid: Option<Oid>,
+    n_listed: usize,
+    n_read: usize,
+}
+
+impl SubmoduleCommits {
+    /// Read the commits of the submodule at `path` in the superproject `repo` between the
+    /// commits of `header`.
+    pub fn from_superproject(repo: &Repository, header: &SubmoduleHeader) -> Self {
+        repo.find_submodule(&header.path)
+            .and_then(|submodule| submodule.open())
+            .map(|submodule_repo| Self::new(submodule_repo, header))
+            .unwrap_or_default()
+    }
+
+    /// Read the commits on the first-parent history of each of the commits of `header` that are
+    /// not in the history of the other. If either commit is not in `repo`, as the "0000000" of a
+    /// new or deleted submodule is not, no commits are read.
+    pub fn new(repo: Repository, header: &SubmoduleHeader) -> Self {
+        let github_repo = repo
+            .find_remote("origin")
+            .ok()
+            .and_then(|remote| GitRemoteRepo::from_str(remote.url()?).ok())
+            .map(|GitRemoteRepo::GitHubRepo(github_repo)| github_repo);
+        let find = |commit: &str| -> Option<Oid> { Some(repo.revparse_single(commit).ok()?.id()) };
+        match (find(&header.minus_commit), find(&header.plus_commit)) {
+            (Some(minus_oid), Some(plus_oid)) => Self {
+                repo: Some(repo),
+                plus_walk: FirstParentWalk::new(plus_oid, minus_oid),
+                minus_walk: FirstParentWalk::new(minus_oid, plus_oid),
+                commits: Vec::new(),
+                github_repo,
+            },
+            _ => Self {
+                github_repo,
+                ..Self::default()
+            },
+        }
+    }
+
+    /// Return the id of the first unclaimed commit with `summary` that was added (`is_plus`) or
+    /// removed, and claim it.
+    pub fn take_commit_id(&mut self, is_plus: bool, summary: &str) -> Option<String> {
+        let walk = if is_plus {
+            &mut self.plus_walk
+        } else {
+            &mut self.minus_walk
+        };
+        walk.n_listed += 1;
+        if let Some(repo) = &self.repo {
+            while walk.n_read < walk.n_listed {
+                match walk.next_commit(repo) {
+                    Some((oid, commit_summary)) => {
+                        self.commits
+                            .push((is_plus, commit_summary, oid.to_string()))
+                    }
+                    None => break,
+                }
+            }
+        }
+        let i = self
+            .commits
+            .iter()
+            .position(|commit| commit.0 == is_plus && commit.1 == summary)?;
+        Some(self.commits.remove(i).2)
+    }
+}
+
+impl FirstParentWalk {
+    fn new(oid: Oid, hidden_oid: Oid) -> Self {
+        Self {
+            next_oid: Some(oid),
+            hidden_oid: Some(hidden_oid),
+            ..Self::default()
+        }
+    }
+
+    /// Return the id and summary of the next commit, or None if there are no more.
+    fn next_commit(&mut self, repo: &Repository) -> Option<(Oid, String)> {
+        let oid = self.next_oid.take()?;
+        if let Some(hidden_oid) = self.hidden_oid {
+            if oid == hidden_oid || repo.graph_descendant_of(hidden_oid, oid).ok()? {
+                return None;
+            }
+        }
+        let commit = repo.find_commit(oid).ok()?;
+        self.next_oid = commit.parent_id(0).ok();
+        self.n_read += 1;
+        Some((oid, commit.summary().unwrap_or("").to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use git2::Signature;
+
+    use super::*;
+    use crate::git_blob::tests::make_git_repo;
+
+    #[test]
+    fn test_parse_submodule_header_line() {
+        assert_eq!(
+            parse_submodule_header_line("Submodule x/y 1234567..89abcde:"),
+            Some(SubmoduleHeader {
+                path: "x/y".to_string(),
+                minus_commit: "1234567".to_string(),
+                plus_commit: "89abcde".to_string(),
+                note: None,
+            })
+        );
+        assert_eq!(
+            parse_submodule_header_line("Submodule x 0000000...89abcde (new submodule)")
+                .and_then(|header| header.note),
+            Some("new submodule".to_string())
+        );
+        assert_eq!(
+            parse_submodule_header_line("Submodule x 1234567...89abcde (rewind):")
+                .and_then(|header| header.note),
+            Some("rewind".to_string())
+        );
+        assert_eq!(
+            parse_submodule_header_line("Submodule x/y contains untracked content"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_submodule_log_line() {
+        assert_eq!(parse_submodule_log_line("  > Add a"), Some((true, "Add a")));
+        assert_eq!(
+            parse_submodule_log_line("  < Add b"),
+            Some((false, "Add b"))
+        );
+        assert_eq!(parse_submodule_log_line("diff --git a/x b/x"), None);
+    }
+
+    #[test]
+    fn test_submodule_commits() {
+        let repo = make_git_repo("delta__test_submodule_commits");
+        let signature = Signature::now("a", "a@example.com").unwrap();
+        let tree = repo
+            .find_tree(repo.treebuilder(None).unwrap().write().unwrap())
+            .unwrap();
+        let commit = |summary: &str, parent: Option<Oid>| {
+            let parents: Vec<_> = parent
+                .map(|p| repo.find_commit(p).unwrap())
+                .into_iter()
+                .collect();
+            let parents: Vec<_> = parents.iter().collect();
+            repo.commit(None, &signature, &signature, summary, &tree, &parents)
+                .unwrap()
+        };
+        let base = commit("Base", None);
+        let old = commit("Same summary", Some(base));
+        let new = commit("Same summary", Some(commit("Other", Some(base))));
+        let header = SubmoduleHeader {
+            path: "x".to_string(),
+            minus_commit: old.to_string()[..7].to_string(),
+            plus_commit: new.to_string()[..7].to_string(),
+            note: None,
+        };
+
+        let mut commits = SubmoduleCommits::new(repo.open(), &header);
+        assert_eq!(
+            commits.take_commit_id(true, "Same summary"),
+            Some(new.to_string())
+        );
+        assert_eq!(commits.take_commit_id(true, "Same summary"), None);
+        assert!(commits.take_commit_id(true, "Other").is_some());
+        assert_eq!(
+            commits.take_commit_id(false, "Same summary"),
+            Some(old.to_string())
+        );
+        assert_eq!(commits.take_commit_id(false, "Base"), None);
+
+        // The commits of a new submodule are not read.
+        let header = SubmoduleHeader {
+            minus_commit: "0000000".to_string(),
+            ..header
+        };
+        let mut commits = SubmoduleCommits::new(repo.open(), &header);
+        assert_eq!(commits.take_commit_id(true, "Same summary"), None);
+    }
+}
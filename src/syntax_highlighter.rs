@@ -0,0 +1,262 @@
+use syntect::highlighting::{
+    HighlightIterator, HighlightState, Highlighter, Style as SyntectStyle, Theme,
+};
+use syntect::parsing::{ParseState, ScopeStack, SyntaxReference, SyntaxSet};
+
+use crate::highlight_cache::{self, HighlightCache};
+
+/// This is syntect::easy::HighlightLines, except that its parse and highlight state can be
+/// obtained and restored. This allows highlighting to start in the middle of a file, from the
+/// state reached by parsing the lines preceding it.
+pub struct HighlightLines<'a> {
+    highlighter: Highlighter<'a>,
+    parse_state: ParseState,
+    highlight_state: HighlightState,
+    // Under --highlight-cache, the cache, and the key of the state reached by the lines highlighted
+    // so far.
+    cache: Option<HighlightCacheState<'a>>,
+    // Lines whose highlighting was found in the cache, and which have therefore not been parsed
+    // yet. They are parsed if a subsequent line is not found in the cache.
+    unparsed_lines: Vec<String>,
+}
+
+struct HighlightCacheState<'a> {
+    cache: &'a HighlightCache,
+    syntax: &'a SyntaxReference,
+    theme: &'a Theme,
+    state_key: u64,
+}
+
+impl<'a> HighlightLines<'a> {
+    pub fn new(syntax: &SyntaxReference, theme: &'a Theme) -> Self {
+        let highlighter = Highlighter::new(theme);
+        let highlight_state = HighlightState::new(&highlighter, ScopeStack::new());
+        Self {
+            highlighter,
+            parse_state: ParseState::new(syntax),
+            highlight_state,
+            cache: None,
+            unparsed_lines: Vec::new(),
+        }
+    }
+
+    /// Return a highlighter that looks up the highlighting of lines in `cache`, if it is given,
+    /// and stores it there.
+    pub fn new_with_cache(
+        syntax: &'a SyntaxReference,
+        theme: &'a Theme,
+        cache: Option<&'a HighlightCache>,
+    ) -> Self {
+        let mut highlighter = Self::new(syntax, theme);
+        highlighter.cache = cache.map(|cache| HighlightCacheState {
+            cache,
+            syntax,
+            theme,
+            state_key: highlight_cache::state_key(
+                syntax,
+                theme,
+                &highlighter.parse_state,
+                &highlighter.highlight_state,
+            ),
+        });
+        highlighter
+    }
+
+    pub fn from_state(theme: &'a Theme, state: (ParseState, HighlightState)) -> Self {
+        let (parse_state, highlight_state) = state;
+        Self {
+            highlighter: Highlighter::new(theme),
+            parse_state,
+            highlight_state,
+            cache: None,
+            unparsed_lines: Vec::new(),
+        }
+    }
+
+    /// Return the parse and highlight state. This must not be called on a highlighter with a
+    /// cache, since the lines found in the cache have not been parsed.
+    pub fn state(&self) -> (ParseState, HighlightState) {
+        (self.parse_state.clone(), self.highlight_state.clone())
+    }
+
+    /// Highlight a line of a file, advancing the parse and highlight state.
+    pub fn highlight<'b>(
+        &mut self,
+        line: &'b str,
+        syntax_set: &SyntaxSet,
+    ) -> Vec<(SyntectStyle, &'b str)> {
+        if let Some(cache_state) = self.cache.as_mut() {
+            let cached_line = cache_state
+                .cache
+                .get(highlight_cache::line_key(cache_state.state_key, line));
+            if let Some((sections, next_state_key)) =
+                cached_line.and_then(|(styles, next_state_key)| {
+                    Some((get_sections(line, &styles)?, next_state_key))
+                })
+            {
+                self.unparsed_lines.push(line.to_string());
+                cache_state.state_key = next_state_key;
+                return sections;
+            }
+        }
+        for unparsed_line in std::mem::take(&mut self.unparsed_lines) {
+            self.parse_and_highlight(&unparsed_line, syntax_set);
+        }
+        let sections = self.parse_and_highlight(line, syntax_set);
+        if let Some(cache_state) = self.cache.as_mut() {
+            let next_state_key = highlight_cache::state_key(
+                cache_state.syntax,
+                cache_state.theme,
+                &self.parse_state,
+                &self.highlight_state,
+            );
+            cache_state.cache.insert(
+                highlight_cache::line_key(cache_state.state_key, line),
+                (
+                    sections
+                        .iter()
+                        .map(|(style, section)| (*style, section.len()))
+                        .collect(),
+                    next_state_key,
+                ),
+            );
+            cache_state.state_key = next_state_key;
+        }
+        sections
+    }
+
+    fn parse_and_highlight<'b>(
+        &mut self,
+        line: &'b str,
+        syntax_set: &SyntaxSet,
+    ) -> Vec<(SyntectStyle, &'b str)> {
+        let ops = self.parse_state.parse_line(line, syntax_set);
+        HighlightIterator::new(&mut self.highlight_state, &ops[..], line, &self.highlighter)
+            .collect()
+    }
+}
+
+/// Divide `line` into sections with the given styles and lengths. Return None if the lengths do
+/// not divide the line, as they would not if the cached entry was for a different line.
+fn get_sections<'b>(
+    line: &'b str,
+    styles: &[(SyntectStyle, usize)],
+) -> Option<Vec<(SyntectStyle, &'b str)>> {
+    let mut sections = Vec::new();
+    let mut rest = line;
+    for (style, len) in styles {
+        if *len > rest.len() || !rest.is_char_boundary(*len) {
+            return None;
+        }
+        let (section, tail) = rest.split_at(*len);
+        sections.push((*style, section));
+        rest = tail;
+    }
+    if rest.is_empty() {
+        Some(sections)
+    } else {
+        None
+    }
+}
+
+/// The syntax-highlighting state of a complete file (the pre- or post-image of a diff). This is
+/// used to highlight hunks as if the whole file had been highlighted: e.g. code inside a multiline
+/// string or comment that began before the hunk is highlighted as string or comment.
+pub struct FileSyntaxState<'a> {
+    lines: Vec<String>,
+    theme: &'a Theme,
+    highlighter: HighlightLines<'a>,
+    next_line_number: usize, // 1-based number of the next line to be parsed
+}
+
+impl<'a> FileSyntaxState<'a> {
+    pub fn new(contents: &str, syntax: &SyntaxReference, theme: &'a Theme) -> Self {
+        Self {
+            // Lines are terminated with newline, as for hunk lines; see Annotator::prepare.
+            lines: contents.lines().map(|line| format!("{}\n", line)).collect(),
+            theme,
+            highlighter: HighlightLines::new(syntax, theme),
+            next_line_number: 1,
+        }
+    }
+
+    /// Return a highlighter whose state is that reached by parsing the lines of the file that
+    /// precede `line_number`. Line numbers must be requested in non-decreasing order (as they
+    /// are by successive hunks of a diff); a lower line number yields the current state.
+    pub fn get_highlighter_at_line(
+        &mut self,
+        line_number: usize,
+        syntax_set: &SyntaxSet,
+    ) -> HighlightLines<'a> {
+        while self.next_line_number < line_number && self.next_line_number <= self.lines.len() {
+            self.highlighter
+                .highlight(&self.lines[self.next_line_number - 1], syntax_set);
+            self.next_line_number += 1;
+        }
+        HighlightLines::from_state(self.theme, self.highlighter.state())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::tests::integration_test_utils::integration_test_utils;
+    use crate::tests::test_utils::test_utils::TempDir;
+
+    #[test]
+    fn test_highlighter_at_line_continues_multiline_string() {
+        let config = integration_test_utils::make_config_from_args(&[]);
+        let theme = config.syntax_theme.as_ref().unwrap();
+        let syntax = config.syntax_set.find_syntax_by_extension("py").unwrap();
+        let contents = "def f():\n    \"\"\"\n    x = 1\n    \"\"\"\n    x = 1\n";
+
+        let mut file_syntax_state = FileSyntaxState::new(contents, syntax, theme);
+        let inside_string = file_syntax_state
+            .get_highlighter_at_line(3, &config.syntax_set)
+            .highlight("    x = 1\n", &config.syntax_set);
+        let outside_string = file_syntax_state
+            .get_highlighter_at_line(5, &config.syntax_set)
+            .highlight("    x = 1\n", &config.syntax_set);
+        let from_scratch =
+            HighlightLines::new(syntax, theme).highlight("    x = 1\n", &config.syntax_set);
+
+        assert_ne!(inside_string, from_scratch);
+        assert_eq!(outside_string, from_scratch);
+    }
+
+    #[test]
+    fn test_highlighter_with_cache() {
+        let config = integration_test_utils::make_config_from_args(&[]);
+        let theme = config.syntax_theme.as_ref().unwrap();
+        let syntax = config.syntax_set.find_syntax_by_extension("py").unwrap();
+        let dir = TempDir::new("delta__test_highlighter_with_cache");
+        let cache = HighlightCache::new(dir.path(), 1, 1_000_000);
+        let highlight = |lines: &[&str], cache| {
+            let mut highlighter = HighlightLines::new_with_cache(syntax, theme, cache);
+            lines
+                .iter()
+                .map(|line| {
+                    highlighter
+                        .highlight(line, &config.syntax_set)
+                        .into_iter()
+                        .map(|(style, s)| (style, s.to_string()))
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let lines = ["\"\"\"\n", "x = 1\n", "\"\"\"\n", "x = 1\n"];
+        let expected = highlight(&lines, None);
+        assert_eq!(highlight(&lines, Some(&cache)), expected);
+        // Served from the cache.
+        assert_eq!(highlight(&lines, Some(&cache)), expected);
+        // The first two lines are served from the cache, and are then parsed so that the
+        // highlighting of the third continues from the correct state.
+        let other_lines = ["\"\"\"\n", "x = 1\n", "y = 2\n", "\"\"\"\n", "x = 1\n"];
+        assert_eq!(
+            highlight(&other_lines, Some(&cache)),
+            highlight(&other_lines, None)
+        );
+    }
+}
@@ -2,6 +2,7 @@
 pub mod ansi_test_utils {
     use ansi_term;
 
+    use crate::annotate::Annotator;
     use crate::ansi;
     use crate::config::Config;
     use crate::delta::State;
@@ -116,19 +117,18 @@ pub mod ansi_test_utils {
         config: &Config,
     ) -> String {
         let mut output_buffer = String::new();
-        let mut unused_writer = Vec::<u8>::new();
-        let mut painter = paint::Painter::new(&mut unused_writer, config);
+        let mut annotator = Annotator::new(config);
         let syntax_highlighted_style = Style {
             is_syntax_highlighted: true,
             ..Style::new()
         };
-        painter.set_syntax(Some(language_extension));
-        painter.set_highlighter();
+        annotator.set_syntax(Some(language_extension));
+        annotator.set_highlighter();
         let lines = vec![(format!(" {}", line), state.clone())];
-        let syntax_style_sections = paint::Painter::get_syntax_style_sections_for_lines(
+        let syntax_style_sections = Annotator::get_syntax_style_sections_for_lines(
             &lines,
             &state,
-            &mut painter.highlighter,
+            &mut annotator.highlighter,
             config,
         );
         let diff_style_sections = vec![vec![(syntax_highlighted_style, lines[0].0.as_str())]];
@@ -162,7 +162,8 @@ pub mod ansi_test_utils {
     ) -> bool {
         let line = output.lines().nth(line_number).unwrap();
         assert!(ansi::strip_ansi_codes(line).starts_with(expected_prefix));
-        let mut style = Style::from_str(expected_style, None, None, config.true_color, false);
+        let mut style =
+            Style::from_str(expected_style, None, None, config.true_color, false).unwrap();
         if _4_bit_color {
             style.ansi_term_style.foreground = style
                 .ansi_term_style
@@ -1,16 +1,19 @@
 #[cfg(test)]
 pub mod integration_test_utils {
+    use std::convert::TryFrom;
     use std::fs::File;
-    use std::io::{BufReader, Write};
+    use std::io::{BufRead, BufReader, Write};
     use std::path::Path;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
 
-    use bytelines::ByteLines;
     use itertools;
 
     use crate::ansi;
     use crate::cli;
     use crate::config;
     use crate::delta::delta;
+    use crate::git_blob::tests::{make_git_repo, TestRepo};
     use crate::git_config::GitConfig;
 
     pub fn make_options_from_args_and_git_config(
@@ -41,15 +44,59 @@ pub mod integration_test_utils {
         git_config_contents: Option<&[u8]>,
         git_config_path: Option<&str>,
     ) -> config::Config {
-        config::Config::from(make_options_from_args_and_git_config(
+        config::Config::try_from(make_options_from_args_and_git_config(
             args,
             git_config_contents,
             git_config_path,
         ))
+        .unwrap()
     }
 
     pub fn make_config_from_args(args: &[&str]) -> config::Config {
-        config::Config::from(make_options_from_args(args))
+        config::Config::try_from(make_options_from_args(args)).unwrap()
+    }
+
+    /// A diff made from a template by replacing {minus_blob_id} and {plus_blob_id} with the
+    /// abbreviated ids of blobs with the given contents in a temporary repository, and a config
+    /// that reads the files of the diff from the repository.
+    pub struct RepoDiff {
+        pub input: String,
+        pub config: config::Config,
+        _repo: TestRepo,
+    }
+
+    impl RepoDiff {
+        pub fn new(template: &str, minus: &[u8], plus: &[u8], args: &[&str]) -> Self {
+            static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+            let repo = make_git_repo(&format!(
+                "delta__test_repo_diff_{}",
+                NEXT_ID.fetch_add(1, Ordering::Relaxed)
+            ));
+            let blob_id =
+                |contents: &[u8]| repo.blob(contents).unwrap().to_string()[..7].to_string();
+            let input = template
+                .replace("{minus_blob_id}", &blob_id(minus))
+                .replace("{plus_blob_id}", &blob_id(plus));
+            let mut config = make_config_from_args(args);
+            config.repo = Some(Mutex::new(repo.open()));
+            Self {
+                input,
+                config,
+                _repo: repo,
+            }
+        }
+
+        pub fn run(&self) -> String {
+            run_delta(&self.input, &self.config)
+        }
+
+        /// Run delta as if the files of the diff could not be read from the repository.
+        pub fn run_without_repo(&mut self) -> String {
+            let repo = self.config.repo.take();
+            let output = self.run();
+            self.config.repo = repo;
+            output
+        }
     }
 
     fn make_git_config(contents: &[u8], path: &str) -> GitConfig {
@@ -75,7 +122,7 @@ pub mod integration_test_utils {
         let mut writer: Vec<u8> = Vec::new();
 
         delta(
-            ByteLines::new(BufReader::new(input.as_bytes())),
+            BufReader::new(input.as_bytes()).split(b'\n'),
             &mut writer,
             &config,
         )
@@ -1,8 +1,12 @@
 #[cfg(test)]
 mod tests {
+    use std::path::PathBuf;
+
     use crate::ansi::{self, strip_ansi_codes};
-    use crate::cli::InspectRawLines;
+    use crate::cli::{ImageProtocol, InspectRawLines};
     use crate::delta::State;
+    use crate::git_config_entry::GitConfigEntry;
+    use crate::image;
     use crate::style;
     use crate::tests::ansi_test_utils::ansi_test_utils;
     use crate::tests::integration_test_utils::integration_test_utils;
@@ -17,12 +21,11 @@ mod tests {
     }
 
     #[test]
-    #[ignore] // #128
     fn test_added_empty_file() {
         let config = integration_test_utils::make_config_from_args(&[]);
         let output = integration_test_utils::run_delta(ADDED_EMPTY_FILE, &config);
         let output = strip_ansi_codes(&output);
-        assert!(output.contains("\nadded: file\n"));
+        assert!(output.contains("\nadded: file (empty)\n"));
     }
 
     #[test]
@@ -42,7 +45,7 @@ mod tests {
         let output = strip_ansi_codes(&output);
         assert!(test_utils::contains_once(
             &output,
-            "\nrenamed: a.py ⟶   b.py\n"
+            "\nrenamed: a.py → b.py\n"
         ));
     }
 
@@ -53,7 +56,7 @@ mod tests {
         let output = strip_ansi_codes(&output);
         assert!(test_utils::contains_once(
             &output,
-            "\ncopied: first_file ⟶   copied_file\n"
+            "\ncopied: first_file → copied_file\n"
         ));
     }
 
@@ -65,7 +68,7 @@ mod tests {
         println!("{}", output);
         assert!(test_utils::contains_once(
             &output,
-            "\nrenamed: Casks/font-dejavusansmono-nerd-font.rb ⟶   Casks/font-dejavu-sans-mono-nerd-font.rb\n"));
+            "\nrenamed: Casks/{font-dejavusansmono-nerd-font.rb → font-dejavu-sans-mono-nerd-font.rb} (95% similar)\n"));
     }
 
     #[test]
@@ -215,6 +218,33 @@ mod tests {
         assert!(output.contains("\nSubmodule x/y/z contains untracked content\n"));
     }
 
+    #[test]
+    fn test_submodule_log() {
+        let config = integration_test_utils::make_config_from_args(&[]);
+        let output = integration_test_utils::run_delta(SUBMODULE_LOG_INPUT, &config);
+        let output = strip_ansi_codes(&output);
+        assert!(output.contains("\nsubmodule x/y: 1234567..89abcde\n"));
+        assert!(output.contains("\n  > Add feature\n  < Remove feature\n"));
+        assert!(output.contains("\nsubmodule z: 0000000..fedcba9 (new submodule)\n"));
+        assert!(output.contains("\nSubmodule z contains untracked content\n"));
+    }
+
+    #[test]
+    fn test_submodule_diff() {
+        let config = integration_test_utils::make_config_from_args(&[]);
+        let output = integration_test_utils::run_delta(SUBMODULE_DIFF_INPUT, &config);
+        let stripped_output = strip_ansi_codes(&output);
+        assert!(stripped_output.contains("\nsubmodule x/y: 1234567..89abcde\n"));
+        assert!(stripped_output.contains("\nadded: x/y/src/main.py\n"));
+        // The nested diff is displayed as it would be without the submodule header.
+        let (_, nested_diff) = SUBMODULE_DIFF_INPUT.split_once('\n').unwrap();
+        assert!(output.ends_with(&integration_test_utils::run_delta(nested_diff, &config)));
+        ansi_test_utils::assert_has_color_other_than_plus_color(
+            output.lines().nth(8).unwrap(),
+            &config,
+        );
+    }
+
     #[test]
     fn test_triple_dash_at_beginning_of_line_in_code() {
         let config = integration_test_utils::make_config_from_args(&[]);
@@ -1103,6 +1133,66 @@ impl<'a> Alignment<'a> {
         assert!(!output.contains("impl<'a> Alignment<'a> {"));
     }
 
+    #[test]
+    fn test_hunk_header_format() {
+        let config = integration_test_utils::make_config_from_args(&[
+            "--hunk-header-format",
+            "{path}:{plus_start} (-{minus_len} +{plus_len}) {fragment}",
+            "--hunk-header-file-style",
+            "red",
+            "--hunk-header-line-number-style",
+            "green",
+            "--hunk-header-plus-len-style",
+            "yellow",
+        ]);
+        let output = integration_test_utils::run_delta(GIT_DIFF_SINGLE_HUNK, &config);
+        let paint = |text: &str, style| {
+            style::Style::from_str(style, None, None, config.true_color, false)
+                .unwrap()
+                .paint(text)
+                .to_string()
+        };
+        assert!(output.contains(&paint("src/align.rs", "red")));
+        assert!(output.contains(&paint("71", "green")));
+        assert!(output.contains(&paint("11", "green")));
+        assert!(output.contains(&paint("8", "yellow")));
+        let output = strip_ansi_codes(&output);
+        assert!(output.contains("src/align.rs:71 (-11 +8) impl<'a> Alignment<'a> {"));
+        // The first line number is not emitted separately, since it is in the hunk header.
+        assert!(!output.contains("\n71\n"));
+    }
+
+    #[test]
+    fn test_hunk_header_format_with_hyperlinks() {
+        let mut config = integration_test_utils::make_config_from_args(&[
+            "--hunk-header-format",
+            "{minus_len} {fragment}",
+            "--hunk-header-minus-len-style",
+            "normal",
+            "--hyperlinks",
+        ]);
+        let output = integration_test_utils::run_delta(GIT_DIFF_SINGLE_HUNK, &config);
+        // Without a working directory, the placeholders are not hyperlinks.
+        assert!(!output.contains("\x1b]8;;"));
+        assert!(strip_ansi_codes(&output).contains("\n11 impl<'a> Alignment<'a> {"));
+
+        config.git_config_entries.insert(
+            "delta.__workdir__".to_string(),
+            GitConfigEntry::Path(PathBuf::from("/repo")),
+        );
+        let output = integration_test_utils::run_delta(GIT_DIFF_SINGLE_HUNK, &config);
+        assert!(output.contains("\x1b]8;;file:///repo/src/align.rs\x1b\\11\x1b]8;;\x1b\\"));
+    }
+
+    #[test]
+    fn test_hunk_header_format_default() {
+        let config = integration_test_utils::make_config_from_args(&[]);
+        let output = integration_test_utils::run_delta(GIT_DIFF_SINGLE_HUNK, &config);
+        let output = strip_ansi_codes(&output);
+        assert!(output.contains("\nimpl<'a> Alignment<'a> { │\n"));
+        assert!(output.contains("\n71\n"));
+    }
+
     #[test]
     fn test_hunk_header_style_empty_string() {
         _do_test_hunk_header_empty_style(&["--hunk-header-style", ""]);
@@ -1320,7 +1410,7 @@ impl<'a> Alignment<'a> { │
         let output = integration_test_utils::run_delta(example_diff, &config);
         let line = output.lines().nth(6).unwrap();
         if base_style_has_background_color {
-            let style = style::Style::from_str(base_style, None, None, true, false);
+            let style = style::Style::from_str(base_style, None, None, true, false).unwrap();
             assert_eq!(
                 line,
                 &style
@@ -1329,7 +1419,8 @@ impl<'a> Alignment<'a> { │
                     .to_string()
             );
         } else {
-            let style = style::Style::from_str(empty_line_marker_style, None, None, true, false);
+            let style =
+                style::Style::from_str(empty_line_marker_style, None, None, true, false).unwrap();
             assert_eq!(
                 line,
                 &style
@@ -1359,6 +1450,112 @@ impl<'a> Alignment<'a> { │
         );
     }
 
+    #[test]
+    fn test_whitespace_change_style() {
+        let whitespace_change_style = "bold yellow red ul";
+        let config = integration_test_utils::make_config_from_args(&[
+            "--whitespace-change-style",
+            whitespace_change_style,
+        ]);
+        let output = integration_test_utils::run_delta(DIFF_WITH_WHITESPACE_CHANGES, &config);
+        for (line_number, expected_prefix) in &[(5, "  x = 1"), (8, "    x = 1"), (9, "    y")] {
+            ansi_test_utils::assert_line_has_style(
+                &output,
+                *line_number,
+                expected_prefix,
+                whitespace_change_style,
+                &config,
+            );
+        }
+        for (line_number, expected_prefix) in &[(7, "z = 3"), (10, "z = 4")] {
+            ansi_test_utils::assert_line_does_not_have_style(
+                &output,
+                *line_number,
+                expected_prefix,
+                whitespace_change_style,
+                &config,
+            );
+        }
+    }
+
+    #[test]
+    fn test_whitespace_insensitive_emph() {
+        let plus_style = "bold yellow red ul";
+        let plus_emph_style = "bold green blue";
+        let args = &[
+            "--plus-style",
+            plus_style,
+            "--plus-emph-style",
+            plus_emph_style,
+        ];
+        let config = integration_test_utils::make_config_from_args(args);
+        let output = integration_test_utils::run_delta(DIFF_WITH_WHITESPACE_CHANGES, &config);
+        ansi_test_utils::assert_line_has_style(&output, 8, "", plus_emph_style, &config);
+
+        let config = integration_test_utils::make_config_from_args(
+            &[&args[..], &["--whitespace-insensitive-emph"]].concat(),
+        );
+        let output = integration_test_utils::run_delta(DIFF_WITH_WHITESPACE_CHANGES, &config);
+        ansi_test_utils::assert_line_has_style(&output, 8, "    x = 1", plus_style, &config);
+        ansi_test_utils::assert_line_has_style(&output, 9, "    y", plus_style, &config);
+        ansi_test_utils::assert_line_does_not_have_style(
+            &output,
+            9,
+            "    y",
+            plus_emph_style,
+            &config,
+        );
+    }
+
+    #[test]
+    fn test_show_whitespace_changes() {
+        let config = integration_test_utils::make_config_from_args(&[
+            "--show-whitespace-changes",
+            "--tabs",
+            "4",
+        ]);
+        let output = integration_test_utils::run_delta(DIFF_WITH_WHITESPACE_CHANGES, &config);
+        let output = strip_ansi_codes(&output);
+        let lines: Vec<&str> = output.lines().skip(5).collect();
+        assert_eq!(
+            lines,
+            vec![
+                "  x = 1",
+                "→   y = f(a,b)",
+                "z = 3",
+                "··  x = 1",
+                "·   y = f(a,·b)",
+                "z = 4",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_line_format_changes_are_marked() {
+        let config = integration_test_utils::make_config_from_args(&["--line-ending-note"]);
+        let output = integration_test_utils::run_delta(DIFF_WITH_LINE_FORMAT_CHANGES, &config);
+        let output = strip_ansi_codes(&output);
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[1], "a.txt (line endings: CRLF → LF)");
+        assert_eq!(
+            &lines[5..],
+            &["[BOM]x␍", "y␍", "z␍", "x", "y", "z  no newline ",]
+        );
+    }
+
+    #[test]
+    fn test_no_newline_marker_is_attached_to_unchanged_line() {
+        let config = integration_test_utils::make_config_from_args(&[]);
+        let output =
+            integration_test_utils::run_delta(DIFF_WITH_NO_NEWLINE_AT_END_OF_FILE, &config);
+        let output = strip_ansi_codes(&output);
+        let lines: Vec<&str> = output.lines().skip(5).collect();
+        assert_eq!(
+            lines,
+            vec!["x = 1", "y = 1", "y = 111", "z = 2  no newline "]
+        );
+    }
+
     #[test]
     fn test_added_empty_line_is_not_whitespace_error() {
         let plus_style = "bold yellow red ul";
@@ -1435,6 +1632,422 @@ impl<'a> Alignment<'a> { │
         ));
     }
 
+    #[test]
+    fn test_full_file_syntax_highlighting() {
+        let mut diff = integration_test_utils::RepoDiff::new(
+            GIT_DIFF_INSIDE_MULTILINE_STRING_TEMPLATE,
+            b"def f():\n    \"\"\"\n    x = 1\n    \"\"\"\n",
+            b"def f():\n    \"\"\"\n    x = 2\n    \"\"\"\n",
+            &["--full-file-syntax-highlighting"],
+        );
+        let output_without_repo = diff.run_without_repo();
+        let output = diff.run();
+
+        // Without access to the full files, each hunk is highlighted in isolation.
+        assert_eq!(
+            output_without_repo,
+            integration_test_utils::run_delta(
+                &diff.input,
+                &integration_test_utils::make_config_from_args(&[])
+            )
+        );
+        // With access to them, the hunk is highlighted as being inside a string.
+        assert_ne!(output, output_without_repo);
+        assert_eq!(
+            strip_ansi_codes(&output),
+            strip_ansi_codes(&output_without_repo)
+        );
+    }
+
+    #[test]
+    fn test_context_lines() {
+        let file_contents: String = (1..=12).map(|n| format!("line {}\n", n)).collect();
+        let mut diff = integration_test_utils::RepoDiff::new(
+            GIT_DIFF_TWO_HUNKS_TEMPLATE,
+            file_contents.as_bytes(),
+            b"",
+            &[
+                "--context-lines",
+                "1",
+                "--hunk-header-style",
+                "raw",
+                "--line-numbers",
+                "--line-numbers-left-format",
+                "{nm:>2}⋮",
+                "--line-numbers-right-format",
+                "{np:>2}│",
+            ],
+        );
+        let output_without_repo = diff.run_without_repo();
+        let output = diff.run();
+
+        // Without access to the file, the hunks are unchanged.
+        let output_without_repo = strip_ansi_codes(&output_without_repo);
+        assert!(!output_without_repo.contains("line 1\n"));
+        assert!(output_without_repo.contains(" 9⋮ 9│line 9\n"));
+        assert!(!output_without_repo.contains("line 10\n"));
+        assert_eq!(output_without_repo.matches("@@ -").count(), 2);
+
+        // With access to it, the hunks gain a line of context at either end, and are merged
+        // because the gap between them is no more than two lines.
+        let output = strip_ansi_codes(&output);
+        assert!(output.contains(" 1⋮ 1│line 1\n"));
+        assert!(output.contains(" 6⋮ 6│line 6\n"));
+        assert!(output.contains("10⋮10│line 10\n"));
+        assert!(!output.contains("line 11"));
+        assert_eq!(output.matches("@@ -").count(), 1);
+        assert!(output.contains("@@ -1,10 +1,10 @@"));
+    }
+
+    #[test]
+    fn test_hunk_header_enclosing_scope() {
+        let mut diff = integration_test_utils::RepoDiff::new(
+            GIT_DIFF_WITHOUT_CODE_FRAGMENT_TEMPLATE,
+            b"\
+mod foo {
+    impl Bar {
+        fn baz() {
+            let x = 1;
+        }
+    }
+}
+",
+            b"",
+            &["--hunk-header-enclosing-scope"],
+        );
+        let output_without_repo = diff.run_without_repo();
+        let output = diff.run();
+
+        assert!(!strip_ansi_codes(&output_without_repo).contains("fn baz"));
+        assert!(strip_ansi_codes(&output).contains("mod foo › impl Bar › fn baz"));
+    }
+
+    #[test]
+    fn test_hex_diff() {
+        let mut diff = integration_test_utils::RepoDiff::new(
+            GIT_DIFF_BINARY_FILES_TEMPLATE,
+            b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0dIHDR\x00\x00\x00\x10",
+            b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0dIHDR\x00\x00\x00\x20",
+            &["--hex-diff"],
+        );
+        let output = strip_ansi_codes(&diff.run());
+        assert!(output.contains("Binary files a/image.png and b/image.png differ\n"));
+        assert!(output.contains("binary: 20 → 20 bytes\n"));
+        assert!(output.contains(
+            "00000010  00 00 00 10              ....     │ 00 00 00 20              ...     \n"
+        ));
+        assert!(!output.contains("00000000  "));
+
+        diff.config.hex_diff_max_size = 10;
+        let output = strip_ansi_codes(&diff.run());
+        assert!(output.contains("binary: 20 → 20 bytes (too large for a hex diff)\n"));
+        assert!(!output.contains("00000010  "));
+    }
+
+    #[test]
+    fn test_image_thumbnails() {
+        let minus_png = image::tests::make_png(2, &[&[[255, 0, 0], [0, 0, 255]]]);
+        let plus_png = image::tests::make_png(1, &[&[[255, 0, 0]]]);
+        let summary = format!(
+            "image: 2×1 PNG, {} B → 1×1 PNG, {} B\n",
+            minus_png.len(),
+            plus_png.len()
+        );
+
+        let mut diff = integration_test_utils::RepoDiff::new(
+            GIT_DIFF_BINARY_FILES_TEMPLATE,
+            &minus_png,
+            &plus_png,
+            &[
+                "--image-thumbnails",
+                "--image-protocol",
+                "kitty",
+                "--image-thumbnail-height",
+                "2",
+            ],
+        );
+        diff.config.available_terminal_width = 80;
+        let output = diff.run();
+        assert!(output.contains(&summary));
+        assert!(output.contains(&format!(
+            "\n\n\x1b[2A\x1b7{}\x1b8\x1b[10C{}\x1b8\x1b[2B",
+            image::format_kitty_image(&minus_png, 8, 2),
+            image::format_kitty_image(&plus_png, 4, 2)
+        )));
+
+        diff.config.image_protocol = ImageProtocol::Sixel;
+        let output = diff.run();
+        assert!(output.contains("\x1bP0;1;0q\"1;1;2;1"));
+
+        diff.config.image_protocol = ImageProtocol::None;
+        let output = diff.run();
+        assert!(output.contains(&summary));
+        assert!(!output.contains("\x1b7"));
+    }
+
+    #[test]
+    fn test_file_info_in_file_headers() {
+        let config = integration_test_utils::make_config_from_args(&["--file-style", "raw"]);
+        let output = integration_test_utils::run_delta(GIT_DIFF_WITH_FILE_INFO, &config);
+        let output = strip_ansi_codes(&output);
+        assert!(output.contains("\nrenamed: a → b (94% similar, mode +x)\n"));
+        assert!(output.contains("\nrenamed: gone → empty\n"));
+        assert!(output.contains("\ns.sh (mode +x)\n"));
+        assert!(output.contains("\nadded: file (empty)\n"));
+        assert!(output.contains("\nremoved: run.sh (executable, empty)\n"));
+        assert!(!output.contains("mode 100"));
+        assert!(!output.contains("similarity index"));
+    }
+
+    #[test]
+    fn test_file_info_of_empty_file_in_last_commit() {
+        // See etc/examples/128-empty-file
+        let config = integration_test_utils::make_config_from_args(&["--file-style", "raw"]);
+        let input = "\
+commit 2c3b6e8
+Author: a <a@example.com>
+
+    Initial commit
+
+diff --git a/file b/file
+new file mode 100644
+index 0000000..e69de29
+";
+        let output = integration_test_utils::run_delta(input, &config);
+        let output = strip_ansi_codes(&output);
+        assert!(output.contains("\nadded: file (empty)\n"));
+    }
+
+    #[test]
+    fn test_symlink_targets_in_file_headers() {
+        let mut diff = integration_test_utils::RepoDiff::new(
+            "\
+diff --git a/link b/link
+index {minus_blob_id}..{plus_blob_id} 120000
+--- a/link
++++ b/link
+@@ -1 +1 @@
+-a
+\\ No newline at end of file
++s.sh
+\\ No newline at end of file
+diff --git a/new-link b/new-link
+new file mode 120000
+index 0000000..{plus_blob_id}
+--- /dev/null
++++ b/new-link
+@@ -0,0 +1 @@
++s.sh
+\\ No newline at end of file
+",
+            b"a",
+            b"s.sh",
+            &["--file-style", "raw"],
+        );
+        let output = strip_ansi_codes(&diff.run_without_repo());
+        assert!(output.contains("\nlink\n"));
+        assert!(output.contains("\nadded: new-link (symlink)\n"));
+
+        let output = strip_ansi_codes(&diff.run());
+        assert!(output.contains("\nlink (symlink a → s.sh)\n"));
+        assert!(output.contains("\nadded: new-link (symlink → s.sh)\n"));
+    }
+
+    #[test]
+    fn test_quoted_and_unusually_prefixed_paths() {
+        let config = integration_test_utils::make_config_from_args(&[]);
+        let output = integration_test_utils::run_delta(GIT_DIFF_WITH_UNUSUAL_PATHS, &config);
+        let output = strip_ansi_codes(&output);
+        assert!(output.contains("\ncafé\n"));
+        assert!(output.contains("\na/x\n"));
+        assert!(output.contains("\nrenamed: plain → new name (50% similar)\n"));
+        assert!(output.contains("\nadded: with space/f 1\n"));
+    }
+
+    #[test]
+    fn test_relative_paths() {
+        let mut config = integration_test_utils::make_config_from_args(&[
+            "--relative-paths",
+            "--hunk-header-format",
+            "{path}:{plus_start}",
+        ]);
+        config.current_dir_in_repo = Some(PathBuf::from("src/features"));
+        let output = integration_test_utils::run_delta(GIT_DIFF_SINGLE_HUNK, &config);
+        let output = strip_ansi_codes(&output);
+        assert!(output.contains("\n../align.rs\n"));
+        assert!(output.contains("\n../align.rs:71"));
+
+        let output = integration_test_utils::run_delta(RENAMED_FILE_INPUT, &config);
+        let output = strip_ansi_codes(&output);
+        assert!(output.contains("\nrenamed: ../../{a.py → b.py}\n"));
+    }
+
+    #[test]
+    fn test_file_rename_format() {
+        let input = "\
+diff --git a/src/features/navigate.rs b/src/plugins/navigate.rs
+similarity index 100%
+rename from src/features/navigate.rs
+rename to src/plugins/navigate.rs
+";
+        let config = integration_test_utils::make_config_from_args(&[]);
+        let output = integration_test_utils::run_delta(input, &config);
+        assert!(
+            strip_ansi_codes(&output).contains("\nrenamed: src/{features → plugins}/navigate.rs\n")
+        );
+        assert!(output.contains(&config.minus_emph_style.paint("features").to_string()));
+        assert!(output.contains(&config.plus_emph_style.paint("plugins").to_string()));
+
+        let config = integration_test_utils::make_config_from_args(&[
+            "--file-rename-format",
+            "{old} ⟶   {new}",
+        ]);
+        let output = integration_test_utils::run_delta(input, &config);
+        assert!(strip_ansi_codes(&output)
+            .contains("\nrenamed: src/features/navigate.rs ⟶   src/plugins/navigate.rs\n"));
+    }
+
+    #[test]
+    fn test_file_header_format() {
+        let input = "\
+diff --git a/src/lib.rs b/src/lib.rs
+index 3ec6c3f..f5f8c8e 100644
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1,2 +1,3 @@
+-mod a;
++mod b;
++mod c;
+ mod d;
+diff --git a/run b/run.py
+old mode 100644
+new mode 100755
+similarity index 90%
+rename from run
+rename to run.py
+index 7898192..93829c7
+--- a/run
++++ b/run.py
+@@ -1 +1 @@
+-print(1)
++print(2)
+";
+        let config = integration_test_utils::make_config_from_args(&[
+            "--file-header-format",
+            "{icon} {event} {path} [{old_path}] {language} +{added} -{removed} {mode} {notes}",
+            "--file-icons",
+            "rs=R py=P",
+            "--file-renamed-label",
+            "renamed:",
+            "--file-header-path-style",
+            "bold",
+        ]);
+        let output = integration_test_utils::run_delta(input, &config);
+        let plain_output = strip_ansi_codes(&output);
+        assert!(plain_output.contains("\nR  src/lib.rs [] Rust +2 -1  \n"));
+        assert!(plain_output
+            .contains("\nP renamed: run → run.py [run] Python +1 -1 mode +x 90% similar\n"));
+        assert!(output.contains(
+            &config
+                .file_header_path_style
+                .paint("src/lib.rs")
+                .to_string()
+        ));
+
+        let config = integration_test_utils::make_config_from_args(&[]);
+        let output = integration_test_utils::run_delta(input, &config);
+        let plain_output = strip_ansi_codes(&output);
+        assert!(plain_output.contains("\nsrc/lib.rs\n"));
+        assert!(plain_output.contains("\nrenamed: run → run.py (90% similar, mode +x)\n"));
+    }
+
+    #[test]
+    fn test_file_rename_format_with_change_of_directory_depth() {
+        let config = integration_test_utils::make_config_from_args(&[]);
+        for (minus_path, plus_path, expected) in &[
+            ("src/a.rs", "a.rs", "renamed: src/a.rs → a.rs"),
+            ("a.rs", "src/a.rs", "renamed: a.rs → src/a.rs"),
+        ] {
+            let input = format!(
+                "\
+diff --git a/{minus_path} b/{plus_path}
+similarity index 100%
+rename from {minus_path}
+rename to {plus_path}
+",
+                minus_path = minus_path,
+                plus_path = plus_path
+            );
+            let output = integration_test_utils::run_delta(&input, &config);
+            assert!(strip_ansi_codes(&output).contains(&format!("\n{}\n", expected)));
+        }
+    }
+
+    const GIT_DIFF_WITH_UNUSUAL_PATHS: &str = "\
+diff --git \"a/caf\\303\\251\" \"b/caf\\303\\251\"
+index 6178079..b51ec5b 100644
+--- \"a/caf\\303\\251\"
++++ \"b/caf\\303\\251\"
+@@ -1 +1,2 @@
+ b
++y
+diff --git a/x a/x
+index 7898192..93829c7 100644
+--- a/x
++++ a/x
+@@ -1 +1,2 @@
+ a
++x
+diff --git SRC/plain dest/new name
+similarity index 50%
+rename from plain
+rename to new name
+index f2ad6c7..9f853d3 100644
+--- SRC/plain
++++ dest/new name\t
+@@ -1 +1,2 @@
+ c
++z
+diff --git i/with space/f 1 w/with space/f 1
+new file mode 100644
+index 0000000..93829c7
+--- /dev/null
++++ w/with space/f 1\t
+@@ -0,0 +1 @@
++a
+";
+
+    const GIT_DIFF_WITH_FILE_INFO: &str = "\
+diff --git a/a b/b
+old mode 100644
+new mode 100755
+similarity index 94%
+rename from a
+rename to b
+index 0ff3bbb..fe94667
+--- a/a
++++ b/b
+@@ -17,4 +17,4 @@
+ 17
+ 18
+ 19
+-20
++21
+diff --git a/gone b/empty
+similarity index 100%
+rename from gone
+rename to empty
+diff --git a/s.sh b/s.sh
+old mode 100644
+new mode 100755
+diff --git a/file b/file
+new file mode 100644
+index 0000000..e69de29
+diff --git a/run.sh b/run.sh
+deleted file mode 100755
+index 0000000..e69de29
+";
+
     const GIT_DIFF_SINGLE_HUNK: &str = "\
 commit 94907c0f136f46dc46ffae2dc92dca9af7eb7c2e
 Author: Dan Davison <dandavison7@gmail.com>
@@ -1462,6 +2075,50 @@ index 8e37a9e..6ce4863 100644
                          parent: left,
 ";
 
+    const GIT_DIFF_INSIDE_MULTILINE_STRING_TEMPLATE: &str = r#"
+diff --git a/example.py b/example.py
+index {minus_blob_id}..{plus_blob_id} 100644
+--- a/example.py
++++ b/example.py
+@@ -3,2 +3,2 @@ def f():
+-    x = 1
++    x = 2
+     """
+"#;
+
+    const GIT_DIFF_WITHOUT_CODE_FRAGMENT_TEMPLATE: &str = "
+diff --git a/src/foo.rs b/src/foo.rs
+index {minus_blob_id}..1234567 100644
+--- a/src/foo.rs
++++ b/src/foo.rs
+@@ -4 +4 @@
+-            let x = 1;
++            let x = 2;
+";
+
+    const GIT_DIFF_BINARY_FILES_TEMPLATE: &str = "
+diff --git a/image.png b/image.png
+index {minus_blob_id}..{plus_blob_id} 100644
+Binary files a/image.png and b/image.png differ
+";
+
+    const GIT_DIFF_TWO_HUNKS_TEMPLATE: &str = "
+diff --git a/example.txt b/example.txt
+index {minus_blob_id}..1234567 100644
+--- a/example.txt
++++ b/example.txt
+@@ -2,3 +2,3 @@
+ line 2
+-line 3
++line three
+ line 4
+@@ -7,3 +7,3 @@
+ line 7
+-line 8
++line eight
+ line 9
+";
+
     const GIT_DIFF_SINGLE_HUNK_WITH_ANSI_ESCAPE_SEQUENCES: &str = "\
 [1;31mcommit 94907c0f136f46dc46ffae2dc92dca9af7eb7c2e[m
 Author: Dan Davison <dandavison7@gmail.com>
@@ -1689,6 +2346,31 @@ This is a regular file that contains:
  Some text here
 -Some text with a minus
 +Some text with a plus
+";
+
+    const SUBMODULE_LOG_INPUT: &str = "\
+commit 1c5cb1bc6ba1e5e28bd4b20b6ca8f5e0e1d31f9c
+Author: Dan Davison <dandavison7@gmail.com>
+Date:   Wed Feb 12 08:05:53 2020 -0600
+
+    Update submodules
+
+Submodule x/y 1234567...89abcde:
+  > Add feature
+  < Remove feature
+Submodule z 0000000...fedcba9 (new submodule)
+Submodule z contains untracked content
+";
+
+    const SUBMODULE_DIFF_INPUT: &str = "\
+Submodule x/y 1234567..89abcde:
+diff --git a/x/y/src/main.py b/x/y/src/main.py
+new file mode 100644
+index 0000000..6ce4863
+--- /dev/null
++++ b/x/y/src/main.py
+@@ -0,0 +1 @@
++x = 2
 ";
 
     const SUBMODULE_CONTAINS_UNTRACKED_CONTENT_INPUT: &str = "\
@@ -1957,6 +2639,48 @@ index 0000000..8d1c8b6
 +++ i/a
 @@ -0,0 +1 @@
 + 
+";
+
+    const DIFF_WITH_WHITESPACE_CHANGES: &str = "\
+diff --git a/a.py b/a.py
+index 1e0b1c5..4c5f8e4 100644
+--- a/a.py
++++ b/a.py
+@@ -1,3 +1,3 @@
+-  x = 1
+-\ty = f(a,b)
+-z = 3
++    x = 1
++    y = f(a, b)
++z = 4
+";
+
+    const DIFF_WITH_LINE_FORMAT_CHANGES: &str = "\
+diff --git a/a.txt b/a.txt
+index 1111111..2222222 100644
+--- a/a.txt
++++ b/a.txt
+@@ -1,3 +1,3 @@
+-\u{feff}x\r
+-y\r
+-z\r
++x
++y
++z
+\\ No newline at end of file
+";
+
+    const DIFF_WITH_NO_NEWLINE_AT_END_OF_FILE: &str = "\
+diff --git a/a.py b/a.py
+index 1111111..2222222 100644
+--- a/a.py
++++ b/a.py
+@@ -1,3 +1,3 @@
+ x = 1
+-y = 1
++y = 111
+ z = 2
+\\ No newline at end of file
 ";
 
     const DIFF_WITH_REMOVED_WHITESPACE_ERROR: &str = r"
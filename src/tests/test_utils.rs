@@ -1,5 +1,9 @@
 #[cfg(test)]
 pub mod test_utils {
+    use std::fs::{create_dir_all, remove_dir_all};
+    use std::path::{Path, PathBuf};
+    use std::process;
+
     /// Return true iff `s` contains exactly one occurrence of substring `t`.
     pub fn contains_once(s: &str, t: &str) -> bool {
         match (s.find(t), s.rfind(t)) {
@@ -8,6 +12,31 @@ pub mod test_utils {
         }
     }
 
+    /// A directory for a test, under the system's temporary directory. It is removed when dropped,
+    /// so that it is not left behind by a test that fails.
+    pub struct TempDir(PathBuf);
+
+    impl TempDir {
+        pub fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("{}.{}", name, process::id()));
+            if path.exists() {
+                remove_dir_all(&path).unwrap();
+            }
+            create_dir_all(&path).unwrap();
+            Self(path)
+        }
+
+        pub fn path(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = remove_dir_all(&self.0);
+        }
+    }
+
     #[allow(dead_code)]
     pub fn print_with_line_numbers(s: &str) {
         for (i, t) in s.lines().enumerate() {